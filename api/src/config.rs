@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+/// Settings read from the environment at startup.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// The SMTP server `POST /v1/emails/send` relays messages through.
+    pub relay_host: String,
+    pub relay_port: u16,
+    /// The UI's build output, served at `/` when set.
+    pub ui_dist: Option<PathBuf>,
+    /// The token the `X-Admin-Token` header must carry for destructive admin routes, which are
+    /// refused when it is not set.
+    pub admin_token: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            relay_host: "localhost".to_string(),
+            relay_port: 2525,
+            ui_dist: None,
+            admin_token: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            relay_host: std::env::var("SMTP_RELAY_HOST").unwrap_or(defaults.relay_host),
+            relay_port: std::env::var("SMTP_RELAY_PORT").map_or(defaults.relay_port, |port| {
+                port.parse().expect("SMTP_RELAY_PORT must be a valid u16")
+            }),
+            ui_dist: std::env::var_os("REMAIL_UI_DIST").map(PathBuf::from),
+            admin_token: std::env::var("REMAIL_ADMIN_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
+        }
+    }
+}
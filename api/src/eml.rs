@@ -0,0 +1,305 @@
+use remail_maild::address::parse_mailbox;
+use remail_maild::email::NewEmail;
+use remail_smtp::line::split_lines;
+use remail_smtp::mailbox::MailboxAddress;
+use remail_smtp::{MessageParser, MessageParserError, MessageParserEvent};
+use remail_types::Email;
+use std::fmt;
+
+/// The recipient used for imported messages that do not name one in their `To` header.
+const IMPORT_RECIPIENT: &str = "admin@local";
+
+#[derive(Debug)]
+pub enum ParseError {
+    Parser(MessageParserError),
+    MissingSender,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Parser(e) => write!(f, "unable to parse message: {e}"),
+            ParseError::MissingSender => write!(f, "message has no valid From header"),
+        }
+    }
+}
+
+/// Headers emitted ahead of all others, in this order, when the message carried them.
+const LEADING_HEADERS: [&str; 3] = ["Date", "Message-ID", "Received"];
+
+/// Rebuilds an RFC 5322 message from the stored envelope, headers and body.
+///
+/// `Date`, `Message-ID` and `Received` come first, followed by `From` and `To` taken from the
+/// envelope when the message did not carry them itself, then the remaining headers in the
+/// order they are stored.
+pub fn reconstruct(email: &Email) -> String {
+    let mut eml = header_section(email);
+    for line in email.body.lines() {
+        eml.push_str(line);
+        eml.push_str("\r\n");
+    }
+    eml
+}
+
+/// Like [`reconstruct`], with the body's bytes as received instead of its decoded text.
+pub fn reconstruct_with_raw_body(email: &Email, raw_body: &[u8]) -> Vec<u8> {
+    let mut eml = header_section(email).into_bytes();
+    eml.extend_from_slice(raw_body);
+    eml
+}
+
+/// The headers, as [`reconstruct`] orders them, followed by the blank line ending them.
+fn header_section(email: &Email) -> String {
+    let mut eml = String::new();
+
+    let is_leading = |key: &str| {
+        LEADING_HEADERS
+            .iter()
+            .any(|name| key.eq_ignore_ascii_case(name))
+    };
+    for name in LEADING_HEADERS {
+        for (key, value) in &email.headers {
+            if key.eq_ignore_ascii_case(name) {
+                push_header(&mut eml, key, value);
+            }
+        }
+    }
+
+    if !email.headers.contains("From") {
+        push_header(&mut eml, "From", &email.from);
+    }
+    if !email.headers.contains("To") {
+        push_header(&mut eml, "To", &email.to);
+    }
+    for (key, value) in &email.headers {
+        if !is_leading(key) {
+            push_header(&mut eml, key, value);
+        }
+    }
+
+    eml.push_str("\r\n");
+    eml
+}
+
+/// Parses a raw RFC 5322 message by replaying its header section through [`MessageParser`]
+/// inside a synthetic SMTP transaction.
+///
+/// The body is kept as bytes, like the SMTP handler keeps DATA, so 8-bit bodies are stored
+/// byte for byte. The envelope has a null sender, so the sender is taken from the `From`
+/// header. The recipient comes from the `To` header when it holds a valid address.
+pub fn parse(raw: &[u8]) -> Result<NewEmail, ParseError> {
+    let lines = split_lines(raw);
+
+    let mut transaction =
+        format!("HELO import\r\nMAIL FROM:<>\r\nRCPT TO:<{IMPORT_RECIPIENT}>\r\nDATA\r\n");
+    // Header lines are decoded lossily, as `NewEmail::from_raw_bytes` decodes them.
+    for line in lines.iter().take_while(|line| !line.is_empty()) {
+        let line = String::from_utf8_lossy(line);
+        if line.starts_with('.') {
+            transaction.push('.');
+        }
+        transaction.push_str(&line);
+        transaction.push_str("\r\n");
+    }
+    transaction.push_str(".\r\n");
+
+    let mut to = None;
+    let mut headers = None;
+    for event in MessageParser::new(transaction.as_bytes()) {
+        match event.map_err(|err| ParseError::Parser(err.kind))? {
+            MessageParserEvent::To(address) => to = Some(address),
+            MessageParserEvent::Done(message) => {
+                headers = Some(message.headers);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let (Some(to), Some(headers)) = (to, headers) else {
+        return Err(ParseError::Parser(MessageParserError::UnexpectedEnd));
+    };
+
+    let header_address = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .and_then(|(_, value)| parse_mailbox(value))
+            .map(MailboxAddress::from)
+    };
+    let from = header_address("From").ok_or(ParseError::MissingSender)?;
+    let to = header_address("To").unwrap_or(to);
+
+    Ok(NewEmail::from_raw_bytes(from, to, lines))
+}
+
+fn push_header(eml: &mut String, key: &str, value: &str) {
+    eml.push_str(key);
+    eml.push_str(": ");
+    // Emails received before headers were unfolded store folded values joined by `\n`, so
+    // fold those back onto continuation lines.
+    eml.push_str(&value.lines().collect::<Vec<_>>().join("\r\n "));
+    eml.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Email {
+        Email {
+            id: uuid::Uuid::new_v4(),
+            from: "sender@example.com".to_string(),
+            to: "recipient@example.com".to_string(),
+            to_addrs: Vec::new(),
+            cc: Vec::new(),
+            envelope_to: Vec::new(),
+            subject: Some("Hello".to_string()),
+            date: None,
+            message_id: None,
+            in_reply_to: None,
+            reply_to: None,
+            headers: vec![
+                ("From".to_string(), "sender@example.com".to_string()),
+                ("Subject".to_string(), "Hello".to_string()),
+                (
+                    "X-Folded".to_string(),
+                    "first part\nsecond part".to_string(),
+                ),
+            ]
+            .into(),
+            tags: Vec::new(),
+            body: "Line one\r\n.dotted line\r\n".to_string(),
+            preview: "Line one .dotted line".to_string(),
+            body_bytes: 24,
+            body_lines: 2,
+            attachments: Vec::new(),
+            dkim_result: None,
+            auth_results: Vec::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_reconstruct() {
+        assert_eq!(
+            reconstruct(&email()),
+            "To: recipient@example.com\r\n\
+             From: sender@example.com\r\n\
+             Subject: Hello\r\n\
+             X-Folded: first part\r\n second part\r\n\
+             \r\n\
+             Line one\r\n\
+             .dotted line\r\n"
+        );
+    }
+
+    #[test]
+    fn test_reconstruct_leading_headers() {
+        let mut email = email();
+        email.headers.extend([
+            ("Received".to_string(), "from mx.example.com".to_string()),
+            ("Message-ID".to_string(), "<1@example.com>".to_string()),
+            (
+                "Date".to_string(),
+                "Mon, 4 Aug 2025 12:00:00 +0000".to_string(),
+            ),
+        ]);
+
+        let eml = reconstruct(&email);
+        let keys: Vec<&str> = eml
+            .lines()
+            .take_while(|line| !line.is_empty())
+            .filter(|line| !line.starts_with(' '))
+            .map(|line| line.split_once(':').unwrap().0)
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "Date",
+                "Message-ID",
+                "Received",
+                "To",
+                "From",
+                "Subject",
+                "X-Folded"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reconstruct_reparses() {
+        let email = email();
+        let eml = reconstruct(&email);
+
+        let mut transaction = String::from(
+            "HELO example.com\r\nMAIL FROM: <sender@example.com>\r\nRCPT TO: <recipient@example.com>\r\nDATA\r\n",
+        );
+        for line in eml.lines() {
+            if line.starts_with('.') {
+                transaction.push('.');
+            }
+            transaction.push_str(line);
+            transaction.push_str("\r\n");
+        }
+        transaction.push_str(".\r\n");
+
+        let body = MessageParser::new(transaction.as_bytes())
+            .find_map(|event| match event {
+                Ok(MessageParserEvent::Body(body)) => Some(body),
+                _ => None,
+            })
+            .expect("parser should emit the body");
+
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for line in body.iter().take_while(|line| !line.is_empty()) {
+            if let Some(continuation) = line.strip_prefix(' ') {
+                let (_, value) = headers.last_mut().unwrap();
+                value.push('\n');
+                value.push_str(continuation);
+            } else {
+                let (key, value) = line.split_once(": ").unwrap();
+                headers.push((key.to_string(), value.to_string()));
+            }
+        }
+
+        let mut expected = vec![("To".to_string(), email.to.clone())];
+        expected.extend(email.headers);
+        assert_eq!(headers, expected);
+    }
+
+    #[test]
+    fn test_parse() {
+        let email = parse(include_bytes!("../fixtures/welcome.eml")).unwrap();
+
+        assert_eq!(email.from.as_str(), "alice@example.com");
+        assert_eq!(email.to.as_str(), "bob@example.org");
+        assert_eq!(email.subject, "Welcome to Remail");
+        assert_eq!(email.message_id.as_deref(), Some("<welcome-1@example.com>"));
+        assert!(email.body.contains("\r\n.signature\r\n"), "{}", email.body);
+    }
+
+    #[test]
+    fn test_parse_8bit_body() {
+        let email =
+            parse(b"From: alice@example.com\r\nSubject: Caf\xe9\r\n\r\n.caf\xe9\r\n").unwrap();
+
+        assert_eq!(email.subject, "Caf\u{fffd}");
+        assert_eq!(email.raw_body.as_deref(), Some(&b".caf\xe9\r\n"[..]));
+    }
+
+    #[test]
+    fn test_parse_without_to_header() {
+        let email = parse(b"From: alice@example.com\r\nSubject: Hi\r\n\r\nHello\r\n").unwrap();
+
+        assert_eq!(email.to.as_str(), IMPORT_RECIPIENT);
+    }
+
+    #[test]
+    fn test_parse_without_from_header() {
+        assert!(matches!(
+            parse(b"Subject: Hi\r\n\r\nHello\r\n"),
+            Err(ParseError::MissingSender)
+        ));
+    }
+}
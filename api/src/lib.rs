@@ -0,0 +1,2099 @@
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{FromRef, FromRequest, Multipart, Path, Query, Request, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use config::ServerConfig;
+use metrics_exporter_prometheus::PrometheusHandle;
+use pagination::Cursor;
+use remail_maild::address;
+use remail_maild::client::SmtpClient;
+use remail_maild::email::NewEmail;
+use remail_maild::persistor::{PersistResult, SmtpPersistor, SqlxPersistor};
+use remail_maild::preview;
+use remail_smtp::mailbox::MailboxAddress;
+use remail_types::{Email, EmailPage, Headers, RecipientCount};
+use serde::{Deserialize, Serialize};
+use sqlx::types::time::OffsetDateTime;
+use tokio::sync::broadcast;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+mod config;
+mod eml;
+mod live;
+mod mime;
+mod openapi;
+mod pagination;
+mod telemetry;
+mod ui;
+
+#[derive(Clone, FromRef)]
+struct AppState {
+    db: sqlx::Pool<sqlx::Postgres>,
+    persistor: SqlxPersistor,
+    config: ServerConfig,
+    metrics: PrometheusHandle,
+    new_emails: broadcast::Sender<Email>,
+}
+
+/// The `To` header's addresses, falling back to the envelope recipient `to`.
+fn to_addrs(headers: &[(String, String)], to: &str) -> Vec<String> {
+    let addresses = address::header_addresses(headers, "To");
+    if addresses.is_empty() {
+        vec![to.to_string()]
+    } else {
+        addresses
+    }
+}
+
+fn to_chrono(datetime: OffsetDateTime) -> chrono::DateTime<chrono::Utc> {
+    chrono::DateTime::from_timestamp(datetime.unix_timestamp(), datetime.nanosecond())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListEmailsParams {
+    /// Case-insensitive substring of the subject.
+    subject_contains: Option<String>,
+    to: Option<String>,
+    from: Option<String>,
+    /// Only emails carrying this tag.
+    tag: Option<String>,
+    /// Case-insensitive substring of the subject, sender, recipient or body.
+    q: Option<String>,
+}
+
+/// An `emails` row, as the queries building an [`Email`] select it.
+struct EmailRow {
+    id: Uuid,
+    from: String,
+    to: String,
+    envelope_to: Vec<String>,
+    subject: Option<String>,
+    date: Option<OffsetDateTime>,
+    message_id: Option<String>,
+    in_reply_to: Option<String>,
+    reply_to: Option<String>,
+    body: String,
+    preview: String,
+    body_bytes: i64,
+    body_lines: i64,
+    dkim_result: Option<String>,
+    auth_results: sqlx::types::Json<Vec<(String, String)>>,
+    created_at: OffsetDateTime,
+    updated_at: OffsetDateTime,
+}
+
+impl EmailRow {
+    fn into_email(self, headers: Headers, tags: Vec<String>) -> Email {
+        let attachments = mime::attachments(&headers, &self.body);
+        Email {
+            id: self.id,
+            from: self.from,
+            to_addrs: to_addrs(&headers, &self.to),
+            cc: address::header_addresses(&headers, "Cc"),
+            envelope_to: self.envelope_to,
+            to: self.to,
+            subject: self.subject,
+            date: self.date.map(to_chrono),
+            message_id: self.message_id,
+            in_reply_to: self.in_reply_to,
+            reply_to: self.reply_to,
+            headers,
+            tags,
+            body: self.body,
+            preview: self.preview,
+            body_bytes: self.body_bytes as u64,
+            body_lines: self.body_lines as u64,
+            attachments,
+            dkim_result: self.dkim_result,
+            auth_results: self.auth_results.0,
+            created_at: to_chrono(self.created_at),
+            updated_at: to_chrono(self.updated_at),
+        }
+    }
+}
+
+/// Builds the emails of `rows`, in the same order, looking up the headers and tags of all of
+/// them at once.
+async fn emails_from_rows(
+    db: &sqlx::Pool<sqlx::Postgres>,
+    rows: Vec<EmailRow>,
+) -> Result<Vec<Email>, sqlx::Error> {
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+    let email_ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+
+    let headers = telemetry::timed_query(
+        "email_headers",
+        sqlx::query!(
+            r#"
+            SELECT email_id, key, value
+            FROM email_headers
+            WHERE email_id = ANY($1)
+            ORDER BY email_id, ordinal
+            "#,
+            &email_ids
+        )
+        .fetch_all(db),
+    )
+    .await?;
+
+    let tags = telemetry::timed_query(
+        "email_tags",
+        sqlx::query!(
+            r#"
+            SELECT email_id, tag
+            FROM tags
+            WHERE email_id = ANY($1)
+            ORDER BY email_id, tag
+            "#,
+            &email_ids
+        )
+        .fetch_all(db),
+    )
+    .await?;
+
+    let mut headers_by_email: std::collections::HashMap<Uuid, Headers> =
+        std::collections::HashMap::new();
+
+    for header in headers {
+        headers_by_email
+            .entry(header.email_id)
+            .or_default()
+            .insert(header.key, header.value);
+    }
+
+    let mut tags_by_email: std::collections::HashMap<Uuid, Vec<String>> =
+        std::collections::HashMap::new();
+
+    for tag in tags {
+        tags_by_email.entry(tag.email_id).or_default().push(tag.tag);
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let headers = headers_by_email.remove(&row.id).unwrap_or_default();
+            let tags = tags_by_email.remove(&row.id).unwrap_or_default();
+            row.into_email(headers, tags)
+        })
+        .collect())
+}
+
+/// Lists the emails matching `params`, newest first, starting after `cursor` and returning at
+/// most `limit` emails when given.
+async fn list_emails(
+    db: &sqlx::Pool<sqlx::Postgres>,
+    params: &ListEmailsParams,
+    cursor: Option<Cursor>,
+    limit: Option<i64>,
+) -> Result<Vec<Email>, sqlx::Error> {
+    let rows = telemetry::timed_query(
+        "list_emails",
+        sqlx::query_as!(
+            EmailRow,
+            r#"
+            SELECT id, "from", "to", envelope_to, subject, "date", message_id, in_reply_to,
+                reply_to, body, preview, body_bytes, body_lines, dkim_result,
+                auth_results AS "auth_results: sqlx::types::Json<Vec<(String, String)>>",
+                created_at, updated_at
+            FROM emails
+            WHERE ($1::TEXT IS NULL OR strpos(lower(subject), lower($1)) > 0)
+                AND ($2::TEXT IS NULL OR "to" = $2)
+                AND ($3::TEXT IS NULL OR "from" = $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR (created_at, id) < ($4, $5::UUID))
+                AND ($7::TEXT IS NULL OR EXISTS (
+                    SELECT 1 FROM tags WHERE tags.email_id = emails.id AND tags.tag = $7
+                ))
+                AND ($8::TEXT IS NULL OR strpos(
+                    lower(concat_ws(' ', subject, "from", "to", body)), lower($8)
+                ) > 0)
+            ORDER BY created_at DESC, id DESC
+            LIMIT $6
+            "#,
+            params.subject_contains,
+            params.to,
+            params.from,
+            cursor.map(|cursor| cursor.created_at) as Option<chrono::DateTime<chrono::Utc>>,
+            cursor.map(|cursor| cursor.id),
+            limit,
+            params.tag,
+            params.q
+        )
+        .fetch_all(db),
+    )
+    .await?;
+
+    emails_from_rows(db, rows).await
+}
+
+async fn count_matching_emails(
+    db: &sqlx::Pool<sqlx::Postgres>,
+    params: &ListEmailsParams,
+) -> Result<u64, sqlx::Error> {
+    let count = telemetry::timed_query(
+        "count_emails",
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM emails
+            WHERE ($1::TEXT IS NULL OR strpos(lower(subject), lower($1)) > 0)
+                AND ($2::TEXT IS NULL OR "to" = $2)
+                AND ($3::TEXT IS NULL OR "from" = $3)
+                AND ($4::TEXT IS NULL OR EXISTS (
+                    SELECT 1 FROM tags WHERE tags.email_id = emails.id AND tags.tag = $4
+                ))
+                AND ($5::TEXT IS NULL OR strpos(
+                    lower(concat_ws(' ', subject, "from", "to", body)), lower($5)
+                ) > 0)
+            "#,
+            params.subject_contains,
+            params.to,
+            params.from,
+            params.tag,
+            params.q
+        )
+        .fetch_one(db),
+    )
+    .await?;
+
+    Ok(count as u64)
+}
+
+/// Every distinct recipient with its number of emails received since `since`, the most
+/// frequent first and ties ordered by address.
+async fn list_recipients(
+    db: &sqlx::Pool<sqlx::Postgres>,
+    since: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<Vec<RecipientCount>, sqlx::Error> {
+    let recipients = telemetry::timed_query(
+        "list_recipients",
+        sqlx::query!(
+            r#"
+            SELECT "to" AS address, COUNT(*) AS "count!"
+            FROM emails
+            WHERE ($1::TIMESTAMPTZ IS NULL OR created_at >= $1)
+            GROUP BY "to"
+            ORDER BY "count!" DESC, "to"
+            "#,
+            since as Option<chrono::DateTime<chrono::Utc>>
+        )
+        .fetch_all(db),
+    )
+    .await?
+    .into_iter()
+    .map(|recipient| RecipientCount {
+        address: recipient.address,
+        count: recipient.count as u64,
+    })
+    .collect();
+
+    Ok(recipients)
+}
+
+/// The exact bytes of the email's body, stored when they differ from its decoded text.
+async fn get_raw_body(
+    db: &sqlx::Pool<sqlx::Postgres>,
+    id: Uuid,
+) -> Result<Option<Vec<u8>>, sqlx::Error> {
+    let raw_body = sqlx::query_scalar!("SELECT raw_body FROM emails WHERE id = $1", id)
+        .fetch_optional(db)
+        .await?;
+    Ok(raw_body.flatten())
+}
+
+async fn get_email(
+    db: &sqlx::Pool<sqlx::Postgres>,
+    id: Uuid,
+) -> Result<Option<Email>, sqlx::Error> {
+    let row = telemetry::timed_query(
+        "get_email",
+        sqlx::query_as!(
+            EmailRow,
+            r#"
+            SELECT id, "from", "to", envelope_to, subject, "date", message_id, in_reply_to,
+                reply_to, body, preview, body_bytes, body_lines, dkim_result,
+                auth_results AS "auth_results: sqlx::types::Json<Vec<(String, String)>>",
+                created_at, updated_at
+            FROM emails
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(db),
+    )
+    .await?;
+
+    Ok(emails_from_rows(db, row.into_iter().collect()).await?.pop())
+}
+
+/// The emails in the same thread as `id`, oldest first by their Date header, or by arrival
+/// when they have none. `None` when there is no such email.
+///
+/// Each email's chain is its Message-ID, In-Reply-To and References, and emails whose chains
+/// share an id are in the same thread, even when the message they all reply to is missing.
+async fn get_thread(
+    db: &sqlx::Pool<sqlx::Postgres>,
+    id: Uuid,
+) -> Result<Option<Vec<Email>>, sqlx::Error> {
+    let rows = telemetry::timed_query(
+        "get_thread",
+        sqlx::query_as!(
+            EmailRow,
+            r#"
+            WITH RECURSIVE chains AS (
+                SELECT id, array_remove("references" || ARRAY[in_reply_to, message_id], NULL) AS chain
+                FROM emails
+            ), thread AS (
+                SELECT id, chain FROM chains WHERE id = $1
+                UNION
+                SELECT chains.id, chains.chain
+                FROM chains JOIN thread ON chains.chain && thread.chain
+            )
+            SELECT id, "from", "to", envelope_to, subject, "date", message_id, in_reply_to,
+                reply_to, body, preview, body_bytes, body_lines, dkim_result,
+                auth_results AS "auth_results: sqlx::types::Json<Vec<(String, String)>>",
+                created_at, updated_at
+            FROM emails
+            WHERE id IN (SELECT id FROM thread)
+            ORDER BY COALESCE("date", created_at), created_at, id
+            "#,
+            id
+        )
+        .fetch_all(db),
+    )
+    .await?;
+    if rows.is_empty() {
+        return Ok(None);
+    }
+
+    emails_from_rows(db, rows).await.map(Some)
+}
+
+async fn delete_emails(db: &sqlx::Pool<sqlx::Postgres>, ids: &[Uuid]) -> Result<u64, sqlx::Error> {
+    // Headers and tags are removed along with their email by the `ON DELETE CASCADE` foreign key.
+    let result = telemetry::timed_query(
+        "delete_emails",
+        sqlx::query!("DELETE FROM emails WHERE id = ANY($1)", ids).execute(db),
+    )
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Tags `id` with `tag`, returning whether the email exists. Adding a tag twice is a no-op.
+async fn add_tag(
+    db: &sqlx::Pool<sqlx::Postgres>,
+    id: Uuid,
+    tag: &str,
+) -> Result<bool, sqlx::Error> {
+    let exists = telemetry::timed_query(
+        "add_tag",
+        sqlx::query_scalar!(
+            r#"
+            WITH inserted AS (
+                INSERT INTO tags (email_id, tag)
+                SELECT id, $2 FROM emails WHERE id = $1
+                ON CONFLICT DO NOTHING
+            )
+            SELECT EXISTS (SELECT 1 FROM emails WHERE id = $1) AS "exists!"
+            "#,
+            id,
+            tag
+        )
+        .fetch_one(db),
+    )
+    .await?;
+
+    Ok(exists)
+}
+
+/// Removes `tag` from `id`, returning whether the email carried it.
+async fn remove_tag(
+    db: &sqlx::Pool<sqlx::Postgres>,
+    id: Uuid,
+    tag: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = telemetry::timed_query(
+        "remove_tag",
+        sqlx::query!("DELETE FROM tags WHERE email_id = $1 AND tag = $2", id, tag).execute(db),
+    )
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct BulkDeleteRequest {
+    ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct DeleteResponse {
+    deleted: u64,
+}
+
+fn internal_server_error() -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error").into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/emails",
+    params(ListEmailsParams),
+    responses((status = 200, description = "Every matching email, newest first", body = [Email]))
+)]
+async fn handle_list_emails(
+    State(db): State<sqlx::Pool<sqlx::Postgres>>,
+    Query(params): Query<ListEmailsParams>,
+) -> Response {
+    match list_emails(&db, &params, None, None).await {
+        Ok(emails) => {
+            telemetry::record_emails_listed(emails.len());
+            Json(emails).into_response()
+        }
+        Err(e) => {
+            eprintln!("Error fetching emails: {e}");
+            internal_server_error()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RecipientsParams {
+    /// Only count emails received at or after this time, in RFC 3339.
+    since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+async fn handle_list_recipients(
+    State(db): State<sqlx::Pool<sqlx::Postgres>>,
+    Query(params): Query<RecipientsParams>,
+) -> Response {
+    match list_recipients(&db, params.since).await {
+        Ok(recipients) => Json(recipients).into_response(),
+        Err(e) => {
+            eprintln!("Error fetching recipients: {e}");
+            internal_server_error()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct PageParams {
+    /// The `next_cursor` of the previous page.
+    cursor: Option<String>,
+    /// The page size, clamped to the server maximum.
+    limit: Option<i64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/emails/page",
+    params(ListEmailsParams, PageParams),
+    responses(
+        (status = 200, description = "One page of matching emails", body = EmailPage),
+        (status = 400, description = "The cursor is malformed"),
+    )
+)]
+async fn handle_list_email_page(
+    State(db): State<sqlx::Pool<sqlx::Postgres>>,
+    Query(params): Query<ListEmailsParams>,
+    Query(page): Query<PageParams>,
+) -> Response {
+    let cursor = match page.cursor.as_deref().map(str::parse).transpose() {
+        Ok(cursor) => cursor,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let limit = page
+        .limit
+        .unwrap_or(pagination::DEFAULT_PAGE_SIZE)
+        .clamp(1, pagination::MAX_PAGE_SIZE);
+
+    // One extra email is fetched to tell whether another page follows.
+    let mut emails = match list_emails(&db, &params, cursor, Some(limit + 1)).await {
+        Ok(emails) => emails,
+        Err(e) => {
+            eprintln!("Error fetching emails: {e}");
+            return internal_server_error();
+        }
+    };
+    let total = match count_matching_emails(&db, &params).await {
+        Ok(total) => total,
+        Err(e) => {
+            eprintln!("Error counting emails: {e}");
+            return internal_server_error();
+        }
+    };
+
+    let next_cursor = if emails.len() > limit as usize {
+        emails.truncate(limit as usize);
+        emails.last().map(|email| {
+            Cursor {
+                created_at: email.created_at,
+                id: email.id,
+            }
+            .to_string()
+        })
+    } else {
+        None
+    };
+
+    telemetry::record_emails_listed(emails.len());
+    Json(EmailPage {
+        emails,
+        next_cursor,
+        total,
+    })
+    .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/emails/{id}/eml",
+    params(("id" = Uuid, Path, description = "The email id")),
+    responses(
+        (status = 200, description = "The email as an .eml file", body = String, content_type = "message/rfc822"),
+        (status = 404, description = "No such email"),
+    )
+)]
+async fn handle_download_eml(
+    State(db): State<sqlx::Pool<sqlx::Postgres>>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    let email = match get_email(&db, id).await {
+        Ok(Some(email)) => email,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Not Found").into_response(),
+        Err(e) => {
+            eprintln!("Error fetching email {id}: {e}");
+            return internal_server_error();
+        }
+    };
+
+    match get_raw_body(&db, id).await {
+        Ok(raw_body) => (
+            [
+                (header::CONTENT_TYPE, "message/rfc822".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{id}.eml\""),
+                ),
+            ],
+            match raw_body {
+                Some(raw_body) => eml::reconstruct_with_raw_body(&email, &raw_body),
+                None => eml::reconstruct(&email).into_bytes(),
+            },
+        )
+            .into_response(),
+        Err(e) => {
+            eprintln!("Error fetching the body of email {id}: {e}");
+            internal_server_error()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/emails/{id}",
+    params(("id" = Uuid, Path, description = "The email id")),
+    responses(
+        (status = 200, description = "The email", body = Email),
+        (status = 404, description = "No such email"),
+    )
+)]
+async fn handle_get_email(
+    State(db): State<sqlx::Pool<sqlx::Postgres>>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    match get_email(&db, id).await {
+        Ok(Some(email)) => Json(email).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Not Found").into_response(),
+        Err(e) => {
+            eprintln!("Error fetching email {id}: {e}");
+            internal_server_error()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/emails/{id}/thread",
+    params(("id" = Uuid, Path, description = "The email id")),
+    responses(
+        (status = 200, description = "The emails of the thread, oldest first", body = Vec<Email>),
+        (status = 404, description = "No such email"),
+    )
+)]
+async fn handle_get_thread(
+    State(db): State<sqlx::Pool<sqlx::Postgres>>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    match get_thread(&db, id).await {
+        Ok(Some(thread)) => Json(thread).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Not Found").into_response(),
+        Err(e) => {
+            eprintln!("Error fetching the thread of email {id}: {e}");
+            internal_server_error()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/emails/{id}/attachments/{index}",
+    params(
+        ("id" = Uuid, Path, description = "The email id"),
+        ("index" = usize, Path, description = "The position in the email's attachments"),
+    ),
+    responses(
+        (status = 200, description = "The decoded attachment", body = Vec<u8>, content_type = "application/octet-stream"),
+        (status = 404, description = "No such email or attachment"),
+    )
+)]
+async fn handle_download_attachment(
+    State(db): State<sqlx::Pool<sqlx::Postgres>>,
+    Path((id, index)): Path<(Uuid, usize)>,
+) -> Response {
+    let email = match get_email(&db, id).await {
+        Ok(Some(email)) => email,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Not Found").into_response(),
+        Err(e) => {
+            eprintln!("Error fetching email {id}: {e}");
+            return internal_server_error();
+        }
+    };
+
+    let Some((attachment, content)) = mime::attachment(&email.headers, &email.body, index) else {
+        return (StatusCode::NOT_FOUND, "Not Found").into_response();
+    };
+    let filename = attachment
+        .filename
+        .unwrap_or_else(|| format!("attachment-{}.bin", index + 1))
+        .replace('"', "");
+    (
+        [
+            (header::CONTENT_TYPE, attachment.content_type),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        content,
+    )
+        .into_response()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/emails/{id}",
+    params(("id" = Uuid, Path, description = "The email id")),
+    responses(
+        (status = 204, description = "The email was deleted"),
+        (status = 404, description = "No such email"),
+    )
+)]
+async fn handle_delete_email(
+    State(db): State<sqlx::Pool<sqlx::Postgres>>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    match delete_emails(&db, &[id]).await {
+        Ok(0) => (StatusCode::NOT_FOUND, "Not Found").into_response(),
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => {
+            eprintln!("Error deleting email {id}: {e}");
+            internal_server_error()
+        }
+    }
+}
+
+/// Deletes every email, returning how many there were.
+async fn clear_emails(db: &sqlx::Pool<sqlx::Postgres>) -> Result<u64, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    // Keep emails from arriving between the count and the truncation.
+    sqlx::query!("LOCK TABLE emails IN ACCESS EXCLUSIVE MODE")
+        .execute(&mut *tx)
+        .await?;
+    let deleted = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM emails"#)
+        .fetch_one(&mut *tx)
+        .await?;
+    sqlx::query!("TRUNCATE emails, email_headers, tags")
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(deleted as u64)
+}
+
+/// The header carrying [`ServerConfig::admin_token`].
+const ADMIN_TOKEN_HEADER: &str = "X-Admin-Token";
+
+#[utoipa::path(
+    delete,
+    path = "/v1/emails",
+    params(("X-Admin-Token" = String, Header, description = "The configured admin token")),
+    responses(
+        (status = 200, description = "How many emails were deleted", body = DeleteResponse),
+        (status = 403, description = "The admin token is missing, wrong or not configured"),
+    )
+)]
+async fn handle_clear_emails(
+    State(db): State<sqlx::Pool<sqlx::Postgres>>,
+    State(config): State<ServerConfig>,
+    headers: header::HeaderMap,
+) -> Response {
+    let token = headers
+        .get(ADMIN_TOKEN_HEADER)
+        .and_then(|token| token.to_str().ok());
+    if config.admin_token.is_none() || token != config.admin_token.as_deref() {
+        return (StatusCode::FORBIDDEN, "Forbidden").into_response();
+    }
+
+    match telemetry::timed_query("clear_emails", clear_emails(&db)).await {
+        Ok(deleted) => Json(DeleteResponse { deleted }).into_response(),
+        Err(e) => {
+            eprintln!("Error clearing emails: {e}");
+            internal_server_error()
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/emails/bulk-delete",
+    request_body = BulkDeleteRequest,
+    responses((status = 200, description = "How many emails were deleted", body = DeleteResponse))
+)]
+async fn handle_bulk_delete(
+    State(db): State<sqlx::Pool<sqlx::Postgres>>,
+    Json(request): Json<BulkDeleteRequest>,
+) -> Response {
+    match delete_emails(&db, &request.ids).await {
+        Ok(deleted) => Json(DeleteResponse { deleted }).into_response(),
+        Err(e) => {
+            eprintln!("Error deleting emails: {e}");
+            internal_server_error()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct AddTagRequest {
+    tag: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/emails/{id}/tags",
+    params(("id" = Uuid, Path, description = "The email id")),
+    request_body = AddTagRequest,
+    responses(
+        (status = 200, description = "The tagged email", body = Email),
+        (status = 400, description = "The tag is empty"),
+        (status = 404, description = "No such email"),
+    )
+)]
+async fn handle_add_tag(
+    State(db): State<sqlx::Pool<sqlx::Postgres>>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<AddTagRequest>,
+) -> Response {
+    let tag = request.tag.trim();
+    if tag.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Tag must not be empty").into_response();
+    }
+
+    match add_tag(&db, id, tag).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::NOT_FOUND, "Not Found").into_response(),
+        Err(e) => {
+            eprintln!("Error tagging email {id}: {e}");
+            return internal_server_error();
+        }
+    }
+
+    match get_email(&db, id).await {
+        Ok(Some(email)) => Json(email).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Not Found").into_response(),
+        Err(e) => {
+            eprintln!("Error fetching email {id}: {e}");
+            internal_server_error()
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/emails/{id}/tags/{tag}",
+    params(("id" = Uuid, Path, description = "The email id"), ("tag" = String, Path, description = "The tag to remove")),
+    responses(
+        (status = 204, description = "The tag was removed"),
+        (status = 404, description = "No such email or tag"),
+    )
+)]
+async fn handle_remove_tag(
+    State(db): State<sqlx::Pool<sqlx::Postgres>>,
+    Path((id, tag)): Path<(Uuid, String)>,
+) -> Response {
+    match remove_tag(&db, id, &tag).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, "Not Found").into_response(),
+        Err(e) => {
+            eprintln!("Error untagging email {id}: {e}");
+            internal_server_error()
+        }
+    }
+}
+
+/// Reads the uploaded message, either as the raw request body or as the first part of a
+/// multipart form.
+async fn read_upload(request: Request) -> Result<Bytes, Response> {
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    if content_type.starts_with("message/rfc822") {
+        Bytes::from_request(request, &())
+            .await
+            .map_err(IntoResponse::into_response)
+    } else if content_type.starts_with("multipart/form-data") {
+        let mut multipart = Multipart::from_request(request, &())
+            .await
+            .map_err(IntoResponse::into_response)?;
+        match multipart.next_field().await {
+            Ok(Some(field)) => field.bytes().await.map_err(IntoResponse::into_response),
+            Ok(None) => Err((StatusCode::BAD_REQUEST, "Missing file").into_response()),
+            Err(e) => Err(e.into_response()),
+        }
+    } else {
+        Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Expected message/rfc822 or multipart/form-data",
+        )
+            .into_response())
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/emails/import",
+    request_body(content = String, content_type = "message/rfc822", description = "The raw message, or a multipart form holding it"),
+    responses(
+        (status = 201, description = "The imported email", body = Email),
+        (status = 400, description = "The message could not be parsed"),
+        (status = 409, description = "The email already exists"),
+    )
+)]
+async fn handle_import_eml(
+    State(db): State<sqlx::Pool<sqlx::Postgres>>,
+    State(persistor): State<SqlxPersistor>,
+    request: Request,
+) -> Response {
+    let raw = match read_upload(request).await {
+        Ok(raw) => raw,
+        Err(response) => return response,
+    };
+
+    let email = match eml::parse(&raw) {
+        Ok(email) => email,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    let id = match persistor.persist_email(&email).await {
+        Ok(PersistResult::Inserted(id)) => id,
+        Ok(PersistResult::Duplicate) => {
+            return (StatusCode::CONFLICT, "Email already exists").into_response();
+        }
+        Err(e) => {
+            eprintln!("Error importing email: {e}");
+            return internal_server_error();
+        }
+    };
+
+    match get_email(&db, id).await {
+        Ok(Some(email)) => (StatusCode::CREATED, Json(email)).into_response(),
+        Ok(None) => internal_server_error(),
+        Err(e) => {
+            eprintln!("Error fetching email {id}: {e}");
+            internal_server_error()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct SendEmailRequest {
+    from: String,
+    to: Vec<String>,
+    subject: String,
+    body: String,
+}
+
+/// Builds the message, addressed to every recipient in `envelope_to`.
+fn build_outgoing(request: SendEmailRequest) -> Result<NewEmail, String> {
+    let parse = |address: &str| {
+        address
+            .parse::<MailboxAddress>()
+            .map_err(|e| format!("invalid address {address}: {e}"))
+    };
+    let from = parse(&request.from)?;
+    let to = request
+        .to
+        .iter()
+        .map(|address| parse(address))
+        .collect::<Result<Vec<_>, _>>()?;
+    let Some(first) = to.first().cloned() else {
+        return Err("at least one recipient is required".to_string());
+    };
+
+    let date = chrono::Utc::now().fixed_offset();
+    let headers = Headers::from(vec![
+        ("Date".to_string(), date.to_rfc2822()),
+        ("From".to_string(), from.to_string()),
+        (
+            "To".to_string(),
+            to.iter()
+                .map(MailboxAddress::as_str)
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        ("Subject".to_string(), request.subject.clone()),
+    ]);
+    let body: String = request
+        .body
+        .lines()
+        .map(|line| format!("{line}\r\n"))
+        .collect();
+    let preview = preview::preview(&headers, &body);
+
+    Ok(NewEmail {
+        from,
+        to: first,
+        envelope_to: to,
+        subject: request.subject,
+        date: Some(date),
+        message_id: None,
+        in_reply_to: None,
+        references: Vec::new(),
+        cc: Vec::new(),
+        reply_to: None,
+        headers,
+        body_bytes: body.len() as u64,
+        body_lines: body.lines().count() as u64,
+        body,
+        raw_body: None,
+        received_bytes: 0,
+        preview,
+        dkim_result: None,
+        auth_results: Vec::new(),
+        helo_domain: None,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/emails/send",
+    request_body = SendEmailRequest,
+    responses(
+        (status = 202, description = "The relay accepted the email for every recipient"),
+        (status = 400, description = "An address is invalid or no recipient was given"),
+        (status = 502, description = "The relay rejected the email, which was sent to no one"),
+    )
+)]
+async fn handle_send_email(
+    State(config): State<ServerConfig>,
+    Json(request): Json<SendEmailRequest>,
+) -> Response {
+    let email = match build_outgoing(request) {
+        Ok(email) => email,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    // One transaction for all recipients, so a rejected recipient fails the whole send
+    // instead of leaving the earlier ones delivered.
+    let result = match SmtpClient::connect(&config.relay_host, config.relay_port).await {
+        Ok(client) => client.send_to(&email, &email.envelope_to).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        eprintln!("Error relaying email from {}: {e}", email.from);
+        return (StatusCode::BAD_GATEWAY, e.to_string()).into_response();
+    }
+
+    StatusCode::ACCEPTED.into_response()
+}
+
+async fn handle_metrics(State(metrics): State<PrometheusHandle>) -> String {
+    metrics.render()
+}
+
+fn router(state: AppState) -> Router {
+    let cors = CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(|origin, _request_head| {
+            let origin_str = origin.to_str().unwrap_or("");
+            origin_str.starts_with("http://localhost:")
+        }))
+        .allow_methods(Any)
+        .allow_headers(Any);
+
+    let v1 = Router::new()
+        .route(
+            "/v1/emails",
+            axum::routing::get(handle_list_emails).delete(handle_clear_emails),
+        )
+        .route(
+            "/v1/emails/page",
+            axum::routing::get(handle_list_email_page),
+        )
+        .route("/v1/recipients", axum::routing::get(handle_list_recipients))
+        .route("/v1/emails/stream", axum::routing::get(live::handle_stream))
+        .route("/v1/emails/import", axum::routing::post(handle_import_eml))
+        .route("/v1/emails/send", axum::routing::post(handle_send_email))
+        .route(
+            "/v1/emails/bulk-delete",
+            axum::routing::post(handle_bulk_delete),
+        )
+        .route(
+            "/v1/emails/{id}",
+            axum::routing::get(handle_get_email).delete(handle_delete_email),
+        )
+        .route(
+            "/v1/emails/{id}/thread",
+            axum::routing::get(handle_get_thread),
+        )
+        .route(
+            "/v1/emails/{id}/attachments/{index}",
+            axum::routing::get(handle_download_attachment),
+        )
+        .route("/v1/emails/{id}/tags", axum::routing::post(handle_add_tag))
+        .route(
+            "/v1/emails/{id}/tags/{tag}",
+            axum::routing::delete(handle_remove_tag),
+        )
+        .route(
+            "/v1/emails/{id}/eml",
+            axum::routing::get(handle_download_eml),
+        )
+        .route(
+            "/v1/emails/{id}/raw",
+            axum::routing::get(handle_download_eml),
+        );
+
+    let ui_dist = state.config.ui_dist.clone();
+    let app = Router::new()
+        .route("/readyz", axum::routing::get(|| async { "OK" }))
+        .route("/livez", axum::routing::get(|| async { "OK" }))
+        .route("/metrics", axum::routing::get(handle_metrics))
+        .route("/openapi.json", axum::routing::get(openapi::handle_openapi))
+        // The UI reaches the API under `/api` when both are served from the same origin.
+        .nest("/api", v1.clone())
+        .merge(v1)
+        .route_layer(axum::middleware::from_fn(telemetry::track_requests))
+        .layer(cors)
+        .with_state(state);
+
+    match ui_dist {
+        Some(dist) => app.fallback_service(ui::router(&dist)),
+        None => app,
+    }
+}
+
+/// Applies any pending migrations, so the API works even when it starts before maild.
+async fn prepare_database(db: &sqlx::Pool<sqlx::Postgres>) -> Result<(), sqlx::Error> {
+    remail_maild::MIGRATOR.run(db).await?;
+    Ok(())
+}
+
+/// The API's routes for `db` with the default configuration, for serving the API from tests
+/// and other binaries. Live updates are not published.
+pub fn app(db: sqlx::Pool<sqlx::Postgres>) -> Router {
+    router(AppState {
+        persistor: SqlxPersistor::new(db.clone()),
+        config: ServerConfig::default(),
+        db,
+        metrics: telemetry::install(),
+        new_emails: broadcast::channel(1).0,
+    })
+}
+
+/// Runs the API configured from the environment until the server fails.
+pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+    let pg_pool = sqlx::postgres::PgPoolOptions::new()
+        .max_connections(5)
+        .connect(&db_url)
+        .await?;
+    prepare_database(&pg_pool).await?;
+
+    let (new_emails, _) = broadcast::channel(64);
+    tokio::spawn(live::listen_for_new_emails(
+        pg_pool.clone(),
+        new_emails.clone(),
+    ));
+
+    let app = router(AppState {
+        persistor: SqlxPersistor::new(pg_pool.clone()),
+        config: ServerConfig::from_env(),
+        db: pg_pool,
+        metrics: telemetry::install(),
+        new_emails,
+    });
+
+    let port: u16 = std::env::var("PORT")
+        .unwrap_or_else(|_| "3000".to_string())
+        .parse()
+        .expect("PORT must be a valid u16");
+
+    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{port}"))
+        .await
+        .expect("Failed to bind TCP listener");
+
+    println!("Listening on http://0.0.0.0:{port}");
+    axum::serve(listener, app)
+        .await
+        .expect("Failed to start server");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use email_address::EmailAddress;
+    use http_body_util::BodyExt;
+    use remail_maild::bounce::BounceRules;
+    use tower::ServiceExt;
+
+    async fn send(app: &Router, request: Request<Body>) -> (StatusCode, String) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    async fn get(app: &Router, uri: &str) -> (StatusCode, String) {
+        send(app, Request::get(uri).body(Body::empty()).unwrap()).await
+    }
+
+    fn app_with_config(db: sqlx::Pool<sqlx::Postgres>, config: ServerConfig) -> Router {
+        router(AppState {
+            persistor: SqlxPersistor::new(db.clone()),
+            config,
+            db,
+            metrics: telemetry::install(),
+            new_emails: broadcast::channel(1).0,
+        })
+    }
+
+    async fn insert_email(db: &sqlx::Pool<sqlx::Postgres>, subject: &str) -> Uuid {
+        sqlx::query_scalar(
+            r#"INSERT INTO emails ("from", "to", subject, body) VALUES ($1, $2, $3, $4) RETURNING id"#,
+        )
+        .bind("sender@example.com")
+        .bind("recipient@example.com")
+        .bind(subject)
+        .bind("Hello, world!\r\n")
+        .fetch_one(db)
+        .await
+        .unwrap()
+    }
+
+    async fn insert_email_to(db: &sqlx::Pool<sqlx::Postgres>, to: &str) -> Uuid {
+        sqlx::query_scalar(
+            r#"INSERT INTO emails ("from", "to", subject, body) VALUES ($1, $2, $3, $4) RETURNING id"#,
+        )
+        .bind("sender@example.com")
+        .bind(to)
+        .bind("Hello")
+        .bind("Hello, world!\r\n")
+        .fetch_one(db)
+        .await
+        .unwrap()
+    }
+
+    async fn count_emails(db: &sqlx::Pool<sqlx::Postgres>) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM emails")
+            .fetch_one(db)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_metrics_after_list(db: sqlx::Pool<sqlx::Postgres>) {
+        insert_email(&db, "Hello").await;
+        let app = app(db);
+
+        let (status, _) = get(&app, "/v1/emails").await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, metrics) = get(&app, "/metrics").await;
+        assert_eq!(status, StatusCode::OK);
+        let listed = emails_listed(&metrics);
+        assert!(listed >= 1.0, "{metrics}");
+        assert!(
+            metrics.contains(r#"remail_api_requests_total{route="/v1/emails"}"#),
+            "{metrics}"
+        );
+        assert!(
+            metrics.contains("remail_db_query_duration_seconds_bucket"),
+            "{metrics}"
+        );
+
+        // Every list request adds the emails it returned, so the counter keeps growing. Other
+        // tests share the recorder, so it may have grown by more.
+        get(&app, "/v1/emails").await;
+        let (_, metrics) = get(&app, "/metrics").await;
+        assert!(emails_listed(&metrics) >= listed + 1.0, "{metrics}");
+    }
+
+    /// The value of `remail_emails_listed_total` in rendered `metrics`.
+    fn emails_listed(metrics: &str) -> f64 {
+        metrics
+            .lines()
+            .find_map(|line| line.strip_prefix("remail_emails_listed_total "))
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_else(|| panic!("remail_emails_listed_total missing: {metrics}"))
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_openapi_spec(db: sqlx::Pool<sqlx::Postgres>) {
+        let app = app(db);
+
+        let (status, body) = get(&app, "/openapi.json").await;
+        assert_eq!(status, StatusCode::OK);
+        let spec: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert!(spec["paths"]["/v1/emails"]["get"].is_object(), "{body}");
+        assert!(
+            spec["paths"]["/v1/emails/{id}"]["delete"].is_object(),
+            "{body}"
+        );
+        assert!(spec["components"]["schemas"]["Email"].is_object(), "{body}");
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_delete_email(db: sqlx::Pool<sqlx::Postgres>) {
+        let id = insert_email(&db, "Hello").await;
+        let app = app(db.clone());
+
+        let request = || {
+            Request::delete(format!("/v1/emails/{id}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let (status, _) = send(&app, request()).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(count_emails(&db).await, 0);
+
+        let (status, _) = send(&app, request()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_bulk_delete(db: sqlx::Pool<sqlx::Postgres>) {
+        let first = insert_email(&db, "First").await;
+        let second = insert_email(&db, "Second").await;
+        insert_email(&db, "Kept").await;
+        let app = app(db.clone());
+
+        let request = Request::post("/v1/emails/bulk-delete")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                serde_json::json!({ "ids": [first, second] }).to_string(),
+            ))
+            .unwrap();
+        let (status, body) = send(&app, request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, r#"{"deleted":2}"#);
+        assert_eq!(count_emails(&db).await, 1);
+    }
+
+    fn clear_request(token: Option<&str>) -> Request<Body> {
+        let mut request = Request::delete("/v1/emails");
+        if let Some(token) = token {
+            request = request.header(ADMIN_TOKEN_HEADER, token);
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    fn admin_config() -> ServerConfig {
+        ServerConfig {
+            admin_token: Some("secret".to_string()),
+            ..ServerConfig::default()
+        }
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_clear_emails(db: sqlx::Pool<sqlx::Postgres>) {
+        let id = insert_email(&db, "First").await;
+        insert_email(&db, "Second").await;
+        let app = app_with_config(db.clone(), admin_config());
+        let (status, _) = send(&app, add_tag_request(id, "important")).await;
+        assert_eq!(status, StatusCode::OK);
+        sqlx::query(
+            "INSERT INTO email_headers (email_id, key, value, ordinal) VALUES ($1, 'Subject', 'First', 0)",
+        )
+        .bind(id)
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let (status, body) = send(&app, clear_request(Some("secret"))).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body, r#"{"deleted":2}"#);
+        assert_eq!(count_emails(&db).await, 0);
+        for table in ["email_headers", "tags"] {
+            let rows: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table}"))
+                .fetch_one(&db)
+                .await
+                .unwrap();
+            assert_eq!(rows, 0, "{table}");
+        }
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_clear_emails_requires_admin_token(db: sqlx::Pool<sqlx::Postgres>) {
+        insert_email(&db, "Kept").await;
+
+        let configured = app_with_config(db.clone(), admin_config());
+        for token in [None, Some("wrong"), Some("")] {
+            let (status, _) = send(&configured, clear_request(token)).await;
+            assert_eq!(status, StatusCode::FORBIDDEN, "{token:?}");
+        }
+        // Without a configured token, nobody may clear the emails.
+        let (status, _) = send(&app(db.clone()), clear_request(Some("secret"))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        assert_eq!(count_emails(&db).await, 1);
+    }
+
+    const WELCOME_EML: &[u8] = include_bytes!("../fixtures/welcome.eml");
+
+    fn add_tag_request(id: Uuid, tag: &str) -> Request<Body> {
+        Request::post(format!("/v1/emails/{id}/tags"))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::json!({ "tag": tag }).to_string()))
+            .unwrap()
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_add_and_remove_tags(db: sqlx::Pool<sqlx::Postgres>) {
+        let id = insert_email(&db, "Hello").await;
+        let app = app(db);
+
+        for tag in ["signup", "flaky", "signup"] {
+            let (status, body) = send(&app, add_tag_request(id, tag)).await;
+            assert_eq!(status, StatusCode::OK, "{body}");
+        }
+        let (_, body) = get(&app, "/v1/emails").await;
+        let emails: Vec<Email> = serde_json::from_str(&body).unwrap();
+        assert_eq!(emails[0].tags, ["flaky", "signup"]);
+
+        let remove = || {
+            Request::delete(format!("/v1/emails/{id}/tags/flaky"))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let (status, _) = send(&app, remove()).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(&app, remove()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (_, body) = get(&app, "/v1/emails").await;
+        let emails: Vec<Email> = serde_json::from_str(&body).unwrap();
+        assert_eq!(emails[0].tags, ["signup"]);
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_add_tag_rejects_missing_email_and_empty_tag(db: sqlx::Pool<sqlx::Postgres>) {
+        let id = insert_email(&db, "Hello").await;
+        let app = app(db);
+
+        let (status, _) = send(&app, add_tag_request(Uuid::new_v4(), "signup")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = send(&app, add_tag_request(id, "  ")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_filter_by_tag(db: sqlx::Pool<sqlx::Postgres>) {
+        let tagged = insert_email(&db, "Tagged").await;
+        insert_email(&db, "Untagged").await;
+        let app = app(db);
+        send(&app, add_tag_request(tagged, "signup")).await;
+
+        let (_, body) = get(&app, "/v1/emails?tag=signup").await;
+        let emails: Vec<Email> = serde_json::from_str(&body).unwrap();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].id, tagged);
+
+        let (_, body) = get(&app, "/v1/emails/page?tag=signup").await;
+        let page: EmailPage = serde_json::from_str(&body).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.emails[0].id, tagged);
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_search_emails(db: sqlx::Pool<sqlx::Postgres>) {
+        insert_email(&db, "Welcome aboard").await;
+        insert_email_to(&db, "Carol@example.com").await;
+        insert_email(&db, "Unrelated").await;
+        let app = app(db);
+
+        for (query, expected) in [("welcome", 1), ("carol%40", 1), ("hello%2C+world", 3)] {
+            let (status, body) = get(&app, &format!("/v1/emails?q={query}")).await;
+            assert_eq!(status, StatusCode::OK, "{body}");
+            let emails: Vec<Email> = serde_json::from_str(&body).unwrap();
+            assert_eq!(emails.len(), expected, "{query}");
+
+            let (_, body) = get(&app, &format!("/v1/emails/page?q={query}")).await;
+            let page: EmailPage = serde_json::from_str(&body).unwrap();
+            assert_eq!(page.total, expected as u64, "{query}");
+        }
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_list_recipients(db: sqlx::Pool<sqlx::Postgres>) {
+        insert_email_to(&db, "alice@example.com").await;
+        insert_email_to(&db, "bob@example.com").await;
+        insert_email_to(&db, "bob@example.com").await;
+        let app = app(db);
+
+        let (status, body) = get(&app, "/v1/recipients").await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let recipients: Vec<RecipientCount> = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            recipients,
+            [
+                RecipientCount {
+                    address: "bob@example.com".to_string(),
+                    count: 2,
+                },
+                RecipientCount {
+                    address: "alice@example.com".to_string(),
+                    count: 1,
+                },
+            ]
+        );
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_list_recipients_since(db: sqlx::Pool<sqlx::Postgres>) {
+        insert_email_to(&db, "alice@example.com").await;
+        let app = app(db);
+
+        for (since, expected) in [("2000-01-01T00:00:00Z", 1), ("2999-01-01T00:00:00Z", 0)] {
+            let (status, body) = get(&app, &format!("/v1/recipients?since={since}")).await;
+            assert_eq!(status, StatusCode::OK, "{body}");
+            let recipients: Vec<RecipientCount> = serde_json::from_str(&body).unwrap();
+            assert_eq!(recipients.len(), expected, "{since}");
+        }
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_get_email_keeps_header_order(db: sqlx::Pool<sqlx::Postgres>) {
+        let app = app(db);
+        let request = Request::post("/v1/emails/import")
+            .header(header::CONTENT_TYPE, "message/rfc822")
+            .body(Body::from(WELCOME_EML))
+            .unwrap();
+        let (_, body) = send(&app, request).await;
+        let imported: Email = serde_json::from_str(&body).unwrap();
+
+        let (status, body) = get(&app, &format!("/v1/emails/{}", imported.id)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let email: Email = serde_json::from_str(&body).unwrap();
+        let names: Vec<&str> = email.headers.iter().map(|(key, _)| key.as_str()).collect();
+        let original: Vec<&str> = std::str::from_utf8(WELCOME_EML)
+            .unwrap()
+            .lines()
+            .take_while(|line| !line.is_empty())
+            .filter(|line| !line.starts_with([' ', '\t']))
+            .filter_map(|line| line.split_once(':').map(|(name, _)| name))
+            .collect();
+        assert_eq!(names, original);
+
+        let (status, _) = get(&app, &format!("/v1/emails/{}", Uuid::new_v4())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_migration_orders_headers_without_ordinal(db: sqlx::Pool<sqlx::Postgres>) {
+        const HEADER_ORDINAL: i64 = 20250830120000;
+        let (before, after): (Vec<_>, Vec<_>) = remail_maild::MIGRATOR
+            .iter()
+            .partition(|migration| migration.version < HEADER_ORDINAL);
+        for migration in before {
+            sqlx::raw_sql(&migration.sql).execute(&db).await.unwrap();
+        }
+        // Stored before header positions were recorded.
+        let id = insert_email(&db, "Hello").await;
+        for key in ["Subject", "Date", "Received"] {
+            sqlx::query("INSERT INTO email_headers (email_id, key, value) VALUES ($1, $2, '')")
+                .bind(id)
+                .bind(key)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        for migration in after {
+            sqlx::raw_sql(&migration.sql).execute(&db).await.unwrap();
+        }
+
+        // They keep the alphabetical order they were shown in.
+        let headers: Vec<(String, i32)> =
+            sqlx::query_as("SELECT key, ordinal FROM email_headers ORDER BY ordinal")
+                .fetch_all(&db)
+                .await
+                .unwrap();
+        assert_eq!(
+            headers,
+            [
+                ("Date".to_string(), 0),
+                ("Received".to_string(), 1),
+                ("Subject".to_string(), 2),
+            ]
+        );
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_list_emails_keeps_header_order(db: sqlx::Pool<sqlx::Postgres>) {
+        let lines = [
+            "Received: from relay.example.org by mx.example.com",
+            "Subject: Order",
+            "Received: from mail.example.org by relay.example.org",
+            "Date: Mon, 4 Aug 2025 12:00:00 +0000",
+        ];
+        let mut raw: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        raw.extend([String::new(), "Body".to_string()]);
+        let email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("alice@example.com"),
+            EmailAddress::new_unchecked("bob@example.org"),
+            raw,
+        );
+        SqlxPersistor::new(db.clone())
+            .persist_email(&email)
+            .await
+            .unwrap();
+
+        let (status, body) = get(&app(db), "/v1/emails").await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let emails: Vec<Email> = serde_json::from_str(&body).unwrap();
+        let headers: Vec<String> = emails[0]
+            .headers
+            .iter()
+            .map(|(key, value)| format!("{key}: {value}"))
+            .collect();
+        assert_eq!(headers[..lines.len()], lines);
+        // The message had no Message-ID, so one was generated.
+        let message_id = emails[0].message_id.as_deref().unwrap();
+        assert_eq!(
+            headers[lines.len()..],
+            [format!("Message-ID: {message_id}")]
+        );
+        assert!(message_id.ends_with("@localhost>"), "{message_id}");
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_download_attachment(db: sqlx::Pool<sqlx::Postgres>) {
+        let app = app(db);
+        let eml = "From: alice@example.com\r\n\
+            To: bob@example.org\r\n\
+            Subject: Report\r\n\
+            Content-Type: multipart/mixed; boundary=b1\r\n\
+            \r\n\
+            --b1\r\n\
+            Content-Type: text/plain\r\n\
+            \r\n\
+            See attached.\r\n\
+            --b1\r\n\
+            Content-Type: text/csv\r\n\
+            Content-Disposition: attachment; filename=\"report.csv\"\r\n\
+            Content-Transfer-Encoding: base64\r\n\
+            \r\n\
+            YSxiCjEsMgo=\r\n\
+            --b1--\r\n";
+        let request = Request::post("/v1/emails/import")
+            .header(header::CONTENT_TYPE, "message/rfc822")
+            .body(Body::from(eml))
+            .unwrap();
+        let (status, body) = send(&app, request).await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        let email: Email = serde_json::from_str(&body).unwrap();
+        assert_eq!(email.attachments.len(), 1);
+        assert_eq!(email.attachments[0].filename.as_deref(), Some("report.csv"));
+        assert_eq!(email.attachments[0].size, 8);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get(format!("/v1/emails/{}/attachments/0", email.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/csv");
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"report.csv\""
+        );
+        let content = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&content[..], b"a,b\n1,2\n");
+
+        let (status, _) = get(&app, &format!("/v1/emails/{}/attachments/1", email.id)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_recipient_lists(db: sqlx::Pool<sqlx::Postgres>) {
+        let app = app(db);
+        let eml = "From: alice@example.com\r\n\
+            To: Bob <bob@example.org>, \"Doe, Jane\" <jane@example.org>\r\n\
+            Cc: carol@example.org,\r\n \
+            Dave <dave@example.org>\r\n\
+            Subject: Team sync\r\n\
+            \r\n\
+            Agenda attached.\r\n";
+        let request = Request::post("/v1/emails/import")
+            .header(header::CONTENT_TYPE, "message/rfc822")
+            .body(Body::from(eml))
+            .unwrap();
+        let (status, body) = send(&app, request).await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+
+        let (_, body) = get(&app, "/v1/emails").await;
+        let emails: Vec<Email> = serde_json::from_str(&body).unwrap();
+        assert_eq!(emails[0].to_addrs, ["bob@example.org", "jane@example.org"]);
+        assert_eq!(emails[0].cc, ["carol@example.org", "dave@example.org"]);
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_import_eml(db: sqlx::Pool<sqlx::Postgres>) {
+        let app = app(db.clone());
+
+        let request = || {
+            Request::post("/v1/emails/import")
+                .header(header::CONTENT_TYPE, "message/rfc822")
+                .body(Body::from(WELCOME_EML))
+                .unwrap()
+        };
+        let (status, body) = send(&app, request()).await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+
+        let email: Email = serde_json::from_str(&body).unwrap();
+        assert_eq!(email.from, "alice@example.com");
+        assert_eq!(email.to, "bob@example.org");
+        assert_eq!(email.subject.as_deref(), Some("Welcome to Remail"));
+        assert_eq!(
+            email.date.map(|date| date.to_rfc3339()).as_deref(),
+            Some("2025-08-04T12:00:00+00:00")
+        );
+        assert_eq!(email.message_id.as_deref(), Some("<welcome-1@example.com>"));
+        assert_eq!(email.body_bytes, email.body.len() as u64);
+        assert_eq!(email.body_lines, email.body.lines().count() as u64);
+        assert!(get_email(&db, email.id).await.unwrap().is_some());
+
+        let (status, _) = send(&app, request()).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(count_emails(&db).await, 1);
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_envelope_recipient_kept_apart_from_headers(db: sqlx::Pool<sqlx::Postgres>) {
+        let mut email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("alice@example.com"),
+            EmailAddress::new_unchecked("hidden@example.org"),
+            vec![
+                "To: team@example.org".to_string(),
+                "Subject: Minutes".to_string(),
+                String::new(),
+                "Notes attached.".to_string(),
+            ],
+        );
+        // Delivered as a BCC: the envelope recipient is not among the header recipients.
+        email.envelope_to = vec![EmailAddress::new_unchecked("hidden@example.org").into()];
+        let PersistResult::Inserted(id) = SqlxPersistor::new(db.clone())
+            .persist_email(&email)
+            .await
+            .unwrap()
+        else {
+            panic!("email was not inserted");
+        };
+
+        let (status, body) = get(&app(db), &format!("/v1/emails/{id}")).await;
+        assert_eq!(status, StatusCode::OK);
+        let email: Email = serde_json::from_str(&body).unwrap();
+        assert_eq!(email.to_addrs, ["team@example.org"]);
+        assert_eq!(email.envelope_to, ["hidden@example.org"]);
+    }
+
+    async fn persist_message(db: &sqlx::Pool<sqlx::Postgres>, lines: &[&str]) -> Uuid {
+        let mut raw: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        raw.extend([String::new(), "Body".to_string()]);
+        let email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("alice@example.com"),
+            EmailAddress::new_unchecked("bob@example.org"),
+            raw,
+        );
+        let PersistResult::Inserted(id) = SqlxPersistor::new(db.clone())
+            .persist_email(&email)
+            .await
+            .unwrap()
+        else {
+            panic!("email was not inserted");
+        };
+        id
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_get_thread(db: sqlx::Pool<sqlx::Postgres>) {
+        // The reply arrives first, but was written after the message it replies to.
+        let reply = persist_message(
+            &db,
+            &[
+                "Subject: Re: Plans",
+                "Date: Mon, 4 Aug 2025 13:00:00 +0000",
+                "Message-ID: <reply@example.com>",
+                "In-Reply-To: <original@example.com>",
+                "References: <original@example.com>",
+            ],
+        )
+        .await;
+        let original = persist_message(
+            &db,
+            &[
+                "Subject: Plans",
+                "Date: Mon, 4 Aug 2025 12:00:00 +0000",
+                "Message-ID: <original@example.com>",
+            ],
+        )
+        .await;
+        persist_message(&db, &["Subject: Unrelated"]).await;
+        let app = app(db);
+
+        for id in [original, reply] {
+            let (status, body) = get(&app, &format!("/v1/emails/{id}/thread")).await;
+            assert_eq!(status, StatusCode::OK, "{body}");
+            let thread: Vec<Email> = serde_json::from_str(&body).unwrap();
+            let ids: Vec<Uuid> = thread.iter().map(|email| email.id).collect();
+            assert_eq!(ids, [original, reply]);
+            // Each email comes with its own headers.
+            let message_ids: Vec<Option<&str>> = thread
+                .iter()
+                .map(|email| email.headers.get("Message-ID"))
+                .collect();
+            assert_eq!(
+                message_ids,
+                [Some("<original@example.com>"), Some("<reply@example.com>")]
+            );
+        }
+
+        let (status, _) = get(&app, &format!("/v1/emails/{}/thread", Uuid::new_v4())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_import_eml_multipart(db: sqlx::Pool<sqlx::Postgres>) {
+        let app = app(db.clone());
+
+        let mut body = b"--boundary\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"welcome.eml\"\r\n\
+            Content-Type: message/rfc822\r\n\r\n"
+            .to_vec();
+        body.extend_from_slice(WELCOME_EML);
+        body.extend_from_slice(b"\r\n--boundary--\r\n");
+
+        let request = Request::post("/v1/emails/import")
+            .header(
+                header::CONTENT_TYPE,
+                "multipart/form-data; boundary=boundary",
+            )
+            .body(Body::from(body))
+            .unwrap();
+        let (status, body) = send(&app, request).await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        assert_eq!(count_emails(&db).await, 1);
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_import_eml_unsupported_content_type(db: sqlx::Pool<sqlx::Postgres>) {
+        let request = Request::post("/v1/emails/import")
+            .header(header::CONTENT_TYPE, "text/plain")
+            .body(Body::from(WELCOME_EML))
+            .unwrap();
+        let (status, _) = send(&app(db), request).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_list_email_page(db: sqlx::Pool<sqlx::Postgres>) {
+        for i in 0..5 {
+            insert_email(&db, &format!("Email {i}")).await;
+        }
+        let app = app(db);
+
+        let mut subjects = Vec::new();
+        let mut uri = "/v1/emails/page?limit=2".to_string();
+        loop {
+            let (status, body) = get(&app, &uri).await;
+            assert_eq!(status, StatusCode::OK, "{body}");
+            let page: EmailPage = serde_json::from_str(&body).unwrap();
+            assert_eq!(page.total, 5);
+            assert!(page.emails.len() <= 2);
+            subjects.extend(page.emails.into_iter().filter_map(|email| email.subject));
+
+            match page.next_cursor {
+                Some(cursor) => uri = format!("/v1/emails/page?limit=2&cursor={cursor}"),
+                None => break,
+            }
+        }
+
+        subjects.sort();
+        assert_eq!(
+            subjects,
+            ["Email 0", "Email 1", "Email 2", "Email 3", "Email 4"]
+        );
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_list_email_page_invalid_cursor(db: sqlx::Pool<sqlx::Postgres>) {
+        let (status, _) = get(&app(db), "/v1/emails/page?cursor=bogus").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_raw_round_trip(db: sqlx::Pool<sqlx::Postgres>) {
+        let app = app(db);
+
+        let request = Request::post("/v1/emails/import")
+            .header(header::CONTENT_TYPE, "message/rfc822")
+            .body(Body::from(WELCOME_EML))
+            .unwrap();
+        let (status, body) = send(&app, request).await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        let email: Email = serde_json::from_str(&body).unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get(format!("/v1/emails/{}/raw", email.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "message/rfc822");
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            format!("attachment; filename=\"{}.eml\"", email.id).as_str()
+        );
+        let raw = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(raw.starts_with(b"Date: "), "{raw:?}");
+
+        let original = eml::parse(WELCOME_EML).unwrap();
+        let exported = eml::parse(&raw).unwrap();
+        let sorted = |mut headers: Vec<(String, String)>| {
+            headers.sort();
+            headers
+        };
+        assert_eq!(
+            sorted(exported.headers.into_vec()),
+            sorted(original.headers.into_vec())
+        );
+        assert_eq!(exported.body, original.body);
+        assert_eq!(exported.from, original.from);
+        assert_eq!(exported.to, original.to);
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_raw_keeps_transfer_encoding(db: sqlx::Pool<sqlx::Postgres>) {
+        let email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("alice@example.com"),
+            EmailAddress::new_unchecked("bob@example.org"),
+            vec![
+                "Content-Type: text/plain; charset=iso-8859-1".to_string(),
+                "Content-Transfer-Encoding: quoted-printable".to_string(),
+                String::new(),
+                "Gr=FC=DFe, 1 + 1 =3D =".to_string(),
+                "2".to_string(),
+            ],
+        );
+        let PersistResult::Inserted(id) = SqlxPersistor::new(db.clone())
+            .persist_email(&email)
+            .await
+            .unwrap()
+        else {
+            panic!("email not inserted");
+        };
+        let app = app(db);
+
+        let (status, body) = get(&app, &format!("/v1/emails/{id}")).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let stored: Email = serde_json::from_str(&body).unwrap();
+        assert_eq!(stored.body, "Grüße, 1 + 1 = 2\r\n");
+
+        let (status, raw) = get(&app, &format!("/v1/emails/{id}/raw")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            raw.ends_with("\r\n\r\nGr=FC=DFe, 1 + 1 =3D =\r\n2\r\n"),
+            "{raw}"
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingPersistor(std::sync::Arc<std::sync::Mutex<Vec<NewEmail>>>);
+
+    impl SmtpPersistor for RecordingPersistor {
+        async fn persist_email(&self, email: &NewEmail) -> Result<PersistResult, sqlx::Error> {
+            self.0.lock().unwrap().push(email.clone());
+            Ok(PersistResult::Inserted(Uuid::new_v4()))
+        }
+    }
+
+    /// Runs an SMTP server on a loopback port, recording every message it accepts and
+    /// rejecting recipients as `bounce_rules` say.
+    async fn loopback_relay(bounce_rules: BounceRules) -> (ServerConfig, RecordingPersistor) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = ServerConfig {
+            relay_host: "127.0.0.1".to_string(),
+            relay_port: listener.local_addr().unwrap().port(),
+            ..Default::default()
+        };
+        let persistor = RecordingPersistor::default();
+        let server_persistor = persistor.clone();
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                let (read_stream, write_stream) = socket.into_split();
+                remail_maild::handler::SmtpHandler::new(write_stream, server_persistor.clone())
+                    .with_bounce_rules(bounce_rules.clone())
+                    .handle(read_stream)
+                    .await;
+            }
+        });
+        (config, persistor)
+    }
+
+    fn send_request(body: serde_json::Value) -> Request<Body> {
+        Request::post("/v1/emails/send")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_send_email(db: sqlx::Pool<sqlx::Postgres>) {
+        let (config, relay) = loopback_relay(BounceRules::default()).await;
+        let app = app_with_config(db, config);
+
+        let (status, body) = send(
+            &app,
+            send_request(serde_json::json!({
+                "from": "app@example.com",
+                "to": ["first@example.com", "second@example.com"],
+                "subject": "Hello",
+                "body": "Hi there\n.dotted",
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::ACCEPTED, "{body}");
+
+        let relayed = relay.0.lock().unwrap().clone();
+        assert_eq!(relayed.len(), 1);
+        let email = &relayed[0];
+        let recipients: Vec<&str> = email.envelope_to.iter().map(|to| to.as_str()).collect();
+        assert_eq!(recipients, ["first@example.com", "second@example.com"]);
+        assert_eq!(email.from.as_str(), "app@example.com");
+        assert_eq!(email.subject, "Hello");
+        assert_eq!(email.body, "Hi there\r\n.dotted\r\n");
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_send_email_rejected_recipient(db: sqlx::Pool<sqlx::Postgres>) {
+        let (config, relay) = loopback_relay(BounceRules::default().bounce("second@*")).await;
+        let app = app_with_config(db, config);
+
+        let (status, _) = send(
+            &app,
+            send_request(serde_json::json!({
+                "from": "app@example.com",
+                "to": ["first@example.com", "second@example.com"],
+                "subject": "Hello",
+                "body": "Hi",
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        // The first recipient was not sent the email either, so retrying sends no duplicate.
+        assert!(relay.0.lock().unwrap().is_empty());
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_send_email_invalid_address(db: sqlx::Pool<sqlx::Postgres>) {
+        let (status, _) = send(
+            &app(db),
+            send_request(serde_json::json!({
+                "from": "app@example.com",
+                "to": ["not an address"],
+                "subject": "Hello",
+                "body": "Hi",
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+    async fn test_send_email_relay_unavailable(db: sqlx::Pool<sqlx::Postgres>) {
+        // Bind and immediately release a port so nothing is listening on it.
+        let port = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let app = app_with_config(
+            db,
+            ServerConfig {
+                relay_host: "127.0.0.1".to_string(),
+                relay_port: port,
+                ..Default::default()
+            },
+        );
+
+        let (status, _) = send(
+            &app,
+            send_request(serde_json::json!({
+                "from": "app@example.com",
+                "to": ["first@example.com"],
+                "subject": "Hello",
+                "body": "Hi",
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_prepare_database(db: sqlx::Pool<sqlx::Postgres>) {
+        prepare_database(&db).await.unwrap();
+        // Running again on an up-to-date schema is a no-op.
+        prepare_database(&db).await.unwrap();
+
+        assert_eq!(count_emails(&db).await, 0);
+    }
+}
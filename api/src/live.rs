@@ -0,0 +1,67 @@
+use axum::extract::State;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use remail_types::Email;
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_stream::Stream;
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::BroadcastStream;
+use uuid::Uuid;
+
+/// Postgres channel the `emails` insert trigger notifies with the new email's id.
+const NEW_EMAIL_CHANNEL: &str = "new_email";
+
+/// Forwards every email inserted into the database, by any process, to `sender`, reconnecting
+/// whenever the listener connection drops.
+pub async fn listen_for_new_emails(
+    db: sqlx::Pool<sqlx::Postgres>,
+    sender: broadcast::Sender<Email>,
+) {
+    loop {
+        if let Err(e) = forward_new_emails(&db, &sender).await {
+            eprintln!("Error listening for new emails: {e}");
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+async fn forward_new_emails(
+    db: &sqlx::Pool<sqlx::Postgres>,
+    sender: &broadcast::Sender<Email>,
+) -> Result<(), sqlx::Error> {
+    let mut listener = sqlx::postgres::PgListener::connect_with(db).await?;
+    listener.listen(NEW_EMAIL_CHANNEL).await?;
+
+    loop {
+        let notification = listener.recv().await?;
+        let Ok(id) = Uuid::parse_str(notification.payload()) else {
+            eprintln!(
+                "Ignoring malformed notification: {}",
+                notification.payload()
+            );
+            continue;
+        };
+
+        if let Some(email) = crate::get_email(db, id).await? {
+            // Sending only fails when nobody is subscribed, which is fine.
+            let _ = sender.send(email);
+        }
+    }
+}
+
+pub async fn handle_stream(
+    State(sender): State<broadcast::Sender<Email>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let events = BroadcastStream::new(sender.subscribe()).filter_map(|email| {
+        // A lagging subscriber skips the emails it missed rather than closing the stream.
+        let email = email.ok()?;
+        Event::default()
+            .event("email")
+            .json_data(email)
+            .ok()
+            .map(Ok)
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
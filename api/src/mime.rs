@@ -0,0 +1,274 @@
+//! Just enough MIME to find the attachments in a stored message body.
+
+use base64::Engine;
+use remail_types::Attachment;
+
+/// A leaf part of a multipart message.
+#[derive(Debug, Clone, PartialEq)]
+struct Part {
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Part {
+    fn header(&self, name: &str) -> Option<&str> {
+        header(&self.headers, name)
+    }
+
+    fn content_type(&self) -> String {
+        self.header("Content-Type")
+            .and_then(|value| value.split(';').next())
+            .map(|media_type| media_type.trim().to_lowercase())
+            .unwrap_or_else(|| "text/plain".to_string())
+    }
+
+    /// Whether the part is a file rather than a body to display: it is marked as an
+    /// attachment, carries a file name, or is non-text content referenced by `Content-ID`.
+    fn is_attachment(&self) -> bool {
+        let disposition = self.header("Content-Disposition").unwrap_or_default();
+        disposition
+            .trim_start()
+            .to_lowercase()
+            .starts_with("attachment")
+            || self.filename().is_some()
+            || (self.header("Content-ID").is_some() && !self.content_type().starts_with("text/"))
+    }
+
+    fn filename(&self) -> Option<String> {
+        self.header("Content-Disposition")
+            .and_then(|value| param(value, "filename"))
+            .or_else(|| {
+                self.header("Content-Type")
+                    .and_then(|value| param(value, "name"))
+            })
+            .filter(|name| !name.is_empty())
+    }
+
+    fn content(&self) -> Vec<u8> {
+        let encoding = self
+            .header("Content-Transfer-Encoding")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        if encoding == "base64" {
+            let encoded: String = self.body.split_whitespace().collect();
+            if let Ok(decoded) = base64::engine::general_purpose::STANDARD.decode(encoded) {
+                return decoded;
+            }
+        }
+        self.body.as_bytes().to_vec()
+    }
+
+    fn metadata(&self) -> Attachment {
+        Attachment {
+            filename: self.filename(),
+            content_type: self.content_type(),
+            size: self.content().len() as u64,
+            content_id: self.header("Content-ID").map(|id| {
+                id.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            }),
+        }
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// The value of the `name` parameter in a header such as `Content-Type: text/plain; name=a.txt`.
+fn param(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// Splits a part's raw text into its headers and body.
+fn parse_part(raw: &str) -> Part {
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut lines = raw.split("\r\n");
+    for line in lines.by_ref() {
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t'])
+            && let Some((_, value)) = headers.last_mut()
+        {
+            value.push(' ');
+            value.push_str(line.trim());
+        } else if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    Part {
+        headers,
+        body: lines.collect::<Vec<_>>().join("\r\n"),
+    }
+}
+
+/// The leaf parts of a body with the given `Content-Type`, descending into nested multiparts.
+fn leaf_parts(content_type: &str, body: &str) -> Vec<Part> {
+    let is_multipart = content_type
+        .trim_start()
+        .to_lowercase()
+        .starts_with("multipart/");
+    let Some(boundary) = param(content_type, "boundary").filter(|_| is_multipart) else {
+        return Vec::new();
+    };
+    let delimiter = format!("--{boundary}");
+    let closing = format!("--{boundary}--");
+
+    let mut raw_parts: Vec<Vec<&str>> = Vec::new();
+    let mut current: Option<Vec<&str>> = None;
+    for line in body.split("\r\n") {
+        if line.trim_end() == closing {
+            raw_parts.extend(current.take());
+            break;
+        } else if line.trim_end() == delimiter {
+            raw_parts.extend(current.replace(Vec::new()));
+        } else if let Some(lines) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+
+    raw_parts
+        .into_iter()
+        .map(|lines| parse_part(&lines.join("\r\n")))
+        .flat_map(|part| match part.header("Content-Type") {
+            Some(content_type)
+                if content_type
+                    .trim_start()
+                    .to_lowercase()
+                    .starts_with("multipart/") =>
+            {
+                leaf_parts(content_type, &part.body)
+            }
+            _ => vec![part],
+        })
+        .collect()
+}
+
+fn attachment_parts(headers: &[(String, String)], body: &str) -> Vec<Part> {
+    let Some(content_type) = header(headers, "Content-Type") else {
+        return Vec::new();
+    };
+    leaf_parts(content_type, body)
+        .into_iter()
+        .filter(Part::is_attachment)
+        .collect()
+}
+
+/// The attachments of an email, in the order they appear in the message.
+pub fn attachments(headers: &[(String, String)], body: &str) -> Vec<Attachment> {
+    attachment_parts(headers, body)
+        .iter()
+        .map(Part::metadata)
+        .collect()
+}
+
+/// The attachment at `index`, see [`attachments`], with its decoded content.
+pub fn attachment(
+    headers: &[(String, String)],
+    body: &str,
+    index: usize,
+) -> Option<(Attachment, Vec<u8>)> {
+    let part = attachment_parts(headers, body).into_iter().nth(index)?;
+    Some((part.metadata(), part.content()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = "This is a multi-part message.\r\n\
+        --outer\r\n\
+        Content-Type: multipart/alternative; boundary=\"inner\"\r\n\
+        \r\n\
+        --inner\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        Hello\r\n\
+        --inner\r\n\
+        Content-Type: text/html\r\n\
+        \r\n\
+        <p>Hello <img src=\"cid:logo@example.com\"></p>\r\n\
+        --inner--\r\n\
+        --outer\r\n\
+        Content-Type: image/png\r\n\
+        Content-ID: <logo@example.com>\r\n\
+        Content-Transfer-Encoding: base64\r\n\
+        \r\n\
+        iVBORw0K\r\n\
+        GgoAAAAN\r\n\
+        --outer\r\n\
+        Content-Type: application/pdf;\r\n\
+        \tname=\"invoice.pdf\"\r\n\
+        Content-Disposition: attachment; filename=\"invoice.pdf\"\r\n\
+        \r\n\
+        %PDF-1.4\r\n\
+        --outer\r\n\
+        Content-Type: application/octet-stream\r\n\
+        Content-Disposition: attachment\r\n\
+        \r\n\
+        raw\r\n\
+        --outer--\r\n";
+
+    fn headers() -> Vec<(String, String)> {
+        vec![(
+            "Content-Type".to_string(),
+            "multipart/mixed; boundary=outer".to_string(),
+        )]
+    }
+
+    #[test]
+    fn test_attachments() {
+        assert_eq!(
+            attachments(&headers(), BODY),
+            [
+                Attachment {
+                    filename: None,
+                    content_type: "image/png".to_string(),
+                    size: 12,
+                    content_id: Some("logo@example.com".to_string()),
+                },
+                Attachment {
+                    filename: Some("invoice.pdf".to_string()),
+                    content_type: "application/pdf".to_string(),
+                    size: 8,
+                    content_id: None,
+                },
+                Attachment {
+                    filename: None,
+                    content_type: "application/octet-stream".to_string(),
+                    size: 3,
+                    content_id: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attachment_content_is_decoded() {
+        let (attachment, content) = attachment(&headers(), BODY, 0).unwrap();
+
+        assert_eq!(attachment.content_type, "image/png");
+        assert_eq!(&content[..4], b"\x89PNG");
+        assert!(super::attachment(&headers(), BODY, 3).is_none());
+    }
+
+    #[test]
+    fn test_single_part_email_has_no_attachments() {
+        let headers = vec![("Content-Type".to_string(), "text/plain".to_string())];
+
+        assert!(attachments(&headers, "Hello\r\n").is_empty());
+        assert!(attachments(&[], "Hello\r\n").is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+use axum::Json;
+use remail_types::{Attachment, Email, EmailPage, Headers};
+use utoipa::OpenApi;
+
+/// The OpenAPI description of the `/v1/emails` routes.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "remail",
+        description = "Browse, tag, import and send captured emails."
+    ),
+    paths(
+        crate::handle_list_emails,
+        crate::handle_list_email_page,
+        crate::handle_get_email,
+        crate::handle_get_thread,
+        crate::handle_delete_email,
+        crate::handle_bulk_delete,
+        crate::handle_clear_emails,
+        crate::handle_add_tag,
+        crate::handle_remove_tag,
+        crate::handle_download_eml,
+        crate::handle_download_attachment,
+        crate::handle_import_eml,
+        crate::handle_send_email,
+    ),
+    components(schemas(
+        Email,
+        Attachment,
+        EmailPage,
+        Headers,
+        crate::BulkDeleteRequest,
+        crate::DeleteResponse,
+        crate::AddTagRequest,
+        crate::SendEmailRequest,
+    ))
+)]
+struct ApiDoc;
+
+pub async fn handle_openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
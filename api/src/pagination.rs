@@ -0,0 +1,57 @@
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 200;
+
+/// Points just past the last email of a page. Pages are ordered by `(created_at, id)`
+/// descending, so emails received after the cursor was issued do not shift later pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub id: Uuid,
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.created_at.timestamp_micros(), self.id)
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid cursor: {s}");
+        let (micros, id) = s.split_once('_').ok_or_else(invalid)?;
+        let micros = micros.parse().map_err(|_| invalid())?;
+
+        Ok(Self {
+            created_at: chrono::DateTime::from_timestamp_micros(micros).ok_or_else(invalid)?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor {
+            created_at: chrono::DateTime::from_timestamp_micros(1_754_308_800_123_456).unwrap(),
+            id: Uuid::new_v4(),
+        };
+
+        assert_eq!(cursor.to_string().parse(), Ok(cursor));
+    }
+
+    #[test]
+    fn test_cursor_invalid() {
+        assert!("".parse::<Cursor>().is_err());
+        assert!("abc_def".parse::<Cursor>().is_err());
+        assert!(format!("12_{}", "not-a-uuid").parse::<Cursor>().is_err());
+    }
+}
@@ -0,0 +1,53 @@
+use axum::{extract::MatchedPath, extract::Request, middleware::Next, response::Response};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+const DB_QUERY_DURATION: &str = "remail_db_query_duration_seconds";
+
+/// Installs the global Prometheus recorder, returning the handle used to render `/metrics`.
+///
+/// The recorder can only be installed once per process, so later calls reuse the first handle.
+pub fn install() -> PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+    HANDLE
+        .get_or_init(|| {
+            PrometheusBuilder::new()
+                .set_buckets_for_metric(
+                    Matcher::Full(DB_QUERY_DURATION.to_string()),
+                    &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5],
+                )
+                .expect("buckets must not be empty")
+                .install_recorder()
+                .expect("Failed to install Prometheus recorder")
+        })
+        .clone()
+}
+
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    metrics::counter!("remail_api_requests_total", "route" => route).increment(1);
+
+    next.run(request).await
+}
+
+/// Runs a database query, recording how long it took under the `query` label.
+pub async fn timed_query<T>(query: &'static str, future: impl Future<Output = T>) -> T {
+    let start = Instant::now();
+    let result = future.await;
+    metrics::histogram!(DB_QUERY_DURATION, "query" => query).record(start.elapsed().as_secs_f64());
+    result
+}
+
+/// Counts the emails returned by list requests, summed over every request rather than the
+/// number stored.
+pub fn record_emails_listed(count: usize) {
+    metrics::counter!("remail_emails_listed_total").increment(count as u64);
+}
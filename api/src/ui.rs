@@ -0,0 +1,118 @@
+use axum::{
+    Router,
+    extract::Request,
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::path::Path;
+use tower_http::services::{ServeDir, ServeFile};
+
+/// Dioxus puts fingerprinted build output under `/assets`, so those files never change.
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+const REVALIDATE: &str = "no-cache";
+
+/// Serves the UI's dist directory, falling back to `index.html` so client-side routes load the
+/// app.
+pub fn router(dist: &Path) -> Router {
+    let files = ServeDir::new(dist).fallback(ServeFile::new(dist.join("index.html")));
+
+    Router::new()
+        .fallback_service(files)
+        .layer(axum::middleware::from_fn(serve_ui))
+}
+
+async fn serve_ui(request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    // Unknown API paths are errors, not client-side routes.
+    if path == "/v1" || path.starts_with("/v1/") || path == "/api" || path.starts_with("/api/") {
+        return (StatusCode::NOT_FOUND, "Not Found").into_response();
+    }
+
+    let mut response = next.run(request).await;
+    if response.status().is_success() {
+        let cache_control = if path.starts_with("/assets/") {
+            IMMUTABLE
+        } else {
+            REVALIDATE
+        };
+        response.headers_mut().insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(cache_control),
+        );
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    fn dist() -> tempfile::TempDir {
+        let dist = tempfile::tempdir().unwrap();
+        std::fs::write(dist.path().join("index.html"), "<html>remail</html>").unwrap();
+        std::fs::create_dir(dist.path().join("assets")).unwrap();
+        std::fs::write(dist.path().join("assets/whatever.wasm"), b"\0asm").unwrap();
+        dist
+    }
+
+    async fn get(router: &Router, uri: &str) -> (StatusCode, header::HeaderMap, String) {
+        let response = router
+            .clone()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, headers, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    #[tokio::test]
+    async fn test_serves_index() {
+        let dist = dist();
+        let (status, headers, body) = get(&router(dist.path()), "/").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "<html>remail</html>");
+        assert_eq!(headers[header::CACHE_CONTROL], REVALIDATE);
+    }
+
+    #[tokio::test]
+    async fn test_spa_fallback() {
+        let dist = dist();
+        let (status, headers, body) = get(&router(dist.path()), "/some/spa/route").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "<html>remail</html>");
+        assert!(
+            headers[header::CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("text/html")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_serves_wasm_assets() {
+        let dist = dist();
+        let (status, headers, body) = get(&router(dist.path()), "/assets/whatever.wasm").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "\0asm");
+        assert_eq!(headers[header::CONTENT_TYPE], "application/wasm");
+        assert_eq!(headers[header::CACHE_CONTROL], IMMUTABLE);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_api_path() {
+        let dist = dist();
+        let (status, _, _) = get(&router(dist.path()), "/v1/unknown").await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,204 @@
+//! End-to-end testing of remail: messages are sent to a real maild listener over SMTP and read
+//! back through the API, with both servers sharing one database.
+//!
+//! [`TestServers::start`] takes the pool `#[sqlx::test(migrator = "remail_maild::MIGRATOR")]`
+//! hands each test, so tests get a database of their own and can run in parallel. There is no
+//! in-memory alternative: the API queries Postgres directly rather than through a persistor.
+
+use remail_maild::handler::SmtpHandler;
+use remail_maild::persistor::SqlxPersistor;
+use remail_maild::server::Server;
+use remail_types::Email;
+use std::io;
+use std::net::SocketAddr;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// maild and the API, each listening on an ephemeral local port. Both stop when dropped.
+pub struct TestServers {
+    /// maild's own accept loop, handing every connection to an [`SmtpHandler`] with its
+    /// default settings.
+    smtp: Server,
+    api_url: String,
+    http: reqwest::Client,
+    api_task: JoinHandle<()>,
+}
+
+impl TestServers {
+    pub async fn start(db: sqlx::Pool<sqlx::Postgres>) -> Self {
+        let persistor = SqlxPersistor::new(db.clone());
+        let smtp = Server::bind("127.0.0.1:0", None, move |write_stream, addr| {
+            SmtpHandler::new(write_stream, persistor.clone()).with_peer_addr(addr)
+        })
+        .await
+        .expect("failed to bind the SMTP listener");
+        let api_listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind the API listener");
+        let api_addr = api_listener.local_addr().unwrap();
+
+        let app = remail_api::app(db);
+        let api_task = tokio::spawn(async move {
+            axum::serve(api_listener, app)
+                .await
+                .expect("API server failed");
+        });
+
+        Self {
+            smtp,
+            api_url: format!("http://{api_addr}"),
+            http: reqwest::Client::new(),
+            api_task,
+        }
+    }
+
+    pub fn smtp_addr(&self) -> SocketAddr {
+        self.smtp.local_addr()
+    }
+
+    /// The API's base URL, such as `http://127.0.0.1:40123`.
+    pub fn api_url(&self) -> &str {
+        &self.api_url
+    }
+
+    /// Opens an SMTP connection to maild, having read its greeting.
+    pub async fn connect(&self) -> io::Result<SmtpSession> {
+        SmtpSession::connect(self.smtp_addr()).await
+    }
+
+    /// Every stored email, newest first, as listed by `GET /v1/emails`.
+    pub async fn emails(&self) -> Vec<Email> {
+        let response = self
+            .http
+            .get(format!("{}/v1/emails", self.api_url))
+            .send()
+            .await
+            .expect("failed to reach the API");
+        assert!(
+            response.status().is_success(),
+            "listing emails failed with {}",
+            response.status()
+        );
+        response.json().await.expect("invalid email list")
+    }
+}
+
+impl Drop for TestServers {
+    /// The SMTP server stops accepting connections as it is dropped along with this.
+    fn drop(&mut self) {
+        self.api_task.abort();
+    }
+}
+
+/// A reply from the server, with the text of each of its lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    pub code: u16,
+    pub lines: Vec<String>,
+}
+
+impl Reply {
+    pub fn is_positive(&self) -> bool {
+        (200..400).contains(&self.code)
+    }
+}
+
+/// A hand-rolled SMTP client, sending exactly the lines it is given.
+pub struct SmtpSession {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    greeting: Reply,
+}
+
+impl SmtpSession {
+    pub async fn connect(addr: SocketAddr) -> io::Result<Self> {
+        let (read_stream, writer) = TcpStream::connect(addr).await?.into_split();
+        let mut reader = BufReader::new(read_stream);
+        let greeting = read_reply(&mut reader).await?;
+        Ok(Self {
+            reader,
+            writer,
+            greeting,
+        })
+    }
+
+    /// The reply the server opened the connection with.
+    pub fn greeting(&self) -> &Reply {
+        &self.greeting
+    }
+
+    /// Sends `line`, which must not end with a line break, and reads the reply.
+    pub async fn command(&mut self, line: &str) -> io::Result<Reply> {
+        self.writer
+            .write_all(format!("{line}\r\n").as_bytes())
+            .await?;
+        self.read_reply().await
+    }
+
+    pub async fn read_reply(&mut self) -> io::Result<Reply> {
+        read_reply(&mut self.reader).await
+    }
+
+    /// Sends `DATA` followed by `message`, dot-stuffed and with its lines ending in CRLF, and
+    /// returns the reply to the message, or the reply refusing `DATA`.
+    pub async fn data(&mut self, message: &str) -> io::Result<Reply> {
+        let reply = self.command("DATA").await?;
+        if reply.code != 354 {
+            return Ok(reply);
+        }
+
+        let mut data = String::with_capacity(message.len() + 5);
+        for line in message.lines() {
+            if line.starts_with('.') {
+                data.push('.');
+            }
+            data.push_str(line);
+            data.push_str("\r\n");
+        }
+        data.push_str(".\r\n");
+        self.writer.write_all(data.as_bytes()).await?;
+        self.read_reply().await
+    }
+
+    /// Runs a whole transaction, returning the first reply that is not positive or, when all
+    /// of them are, the reply to the message.
+    pub async fn send_mail(&mut self, from: &str, to: &str, message: &str) -> io::Result<Reply> {
+        for command in [format!("MAIL FROM: <{from}>"), format!("RCPT TO: <{to}>")] {
+            let reply = self.command(&command).await?;
+            if !reply.is_positive() {
+                return Ok(reply);
+            }
+        }
+        self.data(message).await
+    }
+}
+
+/// Reads a reply, following its continuation lines.
+async fn read_reply(reader: &mut BufReader<OwnedReadHalf>) -> io::Result<Reply> {
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        let invalid =
+            || io::Error::new(io::ErrorKind::InvalidData, format!("invalid reply: {line}"));
+        let code = line
+            .get(..3)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(invalid)?;
+        let (last, text) = match line.get(3..4) {
+            Some("-") => (false, &line[4..]),
+            Some(" ") => (true, &line[4..]),
+            None => (true, ""),
+            Some(_) => return Err(invalid()),
+        };
+        lines.push(text.to_string());
+        if last {
+            return Ok(Reply { code, lines });
+        }
+    }
+}
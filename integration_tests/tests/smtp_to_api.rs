@@ -0,0 +1,202 @@
+use remail_integration_tests::TestServers;
+
+type Db = sqlx::Pool<sqlx::Postgres>;
+
+#[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+async fn test_basic_delivery(db: Db) {
+    let servers = TestServers::start(db).await;
+    let mut smtp = servers.connect().await.unwrap();
+    assert_eq!(smtp.greeting().code, 220);
+
+    let reply = smtp.command("EHLO client.example.com").await.unwrap();
+    assert_eq!(reply.code, 250);
+    assert!(reply.lines.iter().any(|line| line == "8BITMIME"));
+    assert!(reply.lines.iter().any(|line| line == "SMTPUTF8"));
+    assert!(reply.lines.iter().any(|line| line == "CHUNKING"));
+
+    let reply = smtp
+        .send_mail(
+            "sender@example.com",
+            "recipient@example.com",
+            "Subject: Hello\r\nTo: recipient@example.com\r\n\r\nHello, world!\r\n",
+        )
+        .await
+        .unwrap();
+    assert_eq!(reply.code, 250);
+
+    let emails = servers.emails().await;
+    assert_eq!(emails.len(), 1);
+    let email = &emails[0];
+    assert_eq!(email.from, "sender@example.com");
+    assert_eq!(email.to, "recipient@example.com");
+    assert_eq!(email.envelope_to, ["recipient@example.com"]);
+    assert_eq!(email.subject.as_deref(), Some("Hello"));
+    assert_eq!(email.body, "Hello, world!\r\n");
+}
+
+#[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+async fn test_multiple_recipients(db: Db) {
+    let servers = TestServers::start(db).await;
+    let mut smtp = servers.connect().await.unwrap();
+    smtp.command("EHLO client.example.com").await.unwrap();
+
+    assert_eq!(
+        smtp.command("MAIL FROM: <sender@example.com>")
+            .await
+            .unwrap()
+            .code,
+        250
+    );
+    assert_eq!(
+        smtp.command("RCPT TO: <alice@example.com>")
+            .await
+            .unwrap()
+            .code,
+        250
+    );
+    assert_eq!(
+        smtp.command("RCPT TO: <bob@example.com>")
+            .await
+            .unwrap()
+            .code,
+        250
+    );
+    let reply = smtp
+        .data("To: alice@example.com, bob@example.com\r\nCc: carol@example.com\r\n\r\nHi all\r\n")
+        .await
+        .unwrap();
+    assert_eq!(reply.code, 250);
+
+    let emails = servers.emails().await;
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].to, "alice@example.com");
+    assert_eq!(emails[0].to_addrs, ["alice@example.com", "bob@example.com"]);
+    assert_eq!(
+        emails[0].envelope_to,
+        ["alice@example.com", "bob@example.com"]
+    );
+    assert_eq!(emails[0].cc, ["carol@example.com"]);
+}
+
+#[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+async fn test_large_body(db: Db) {
+    let servers = TestServers::start(db).await;
+    let mut smtp = servers.connect().await.unwrap();
+    smtp.command("HELO client.example.com").await.unwrap();
+
+    // 1 MiB of body, including a line that needs dot-stuffing.
+    let line = format!("{}\r\n", "a".repeat(510));
+    let body = format!(".leading dot\r\n{}", line.repeat(2048));
+    let reply = smtp
+        .send_mail(
+            "sender@example.com",
+            "recipient@example.com",
+            &format!("Subject: Large\r\n\r\n{body}"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(reply.code, 250);
+
+    let emails = servers.emails().await;
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].body, body);
+    assert_eq!(emails[0].body_bytes, body.len() as u64);
+    assert_eq!(emails[0].body_lines, 2049);
+}
+
+#[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+async fn test_folded_headers(db: Db) {
+    let servers = TestServers::start(db).await;
+    let mut smtp = servers.connect().await.unwrap();
+    smtp.command("HELO client.example.com").await.unwrap();
+
+    let reply = smtp
+        .send_mail(
+            "sender@example.com",
+            "recipient@example.com",
+            "Subject: A folded\r\n subject\r\nX-Trace: one\r\n\ttwo\r\n\r\nBody\r\n",
+        )
+        .await
+        .unwrap();
+    assert_eq!(reply.code, 250);
+
+    let emails = servers.emails().await;
+    assert_eq!(emails[0].subject.as_deref(), Some("A folded subject"));
+    assert_eq!(emails[0].headers.get("X-Trace"), Some("one\ttwo"));
+}
+
+#[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+async fn test_internationalized_addresses(db: Db) {
+    let servers = TestServers::start(db).await;
+    let mut smtp = servers.connect().await.unwrap();
+    smtp.command("EHLO client.example.com").await.unwrap();
+
+    let reply = smtp
+        .command("MAIL FROM: <प्रेषक@उदाहरण.भारत> SMTPUTF8")
+        .await
+        .unwrap();
+    assert_eq!(reply.code, 250);
+    let reply = smtp.command("RCPT TO: <用户@例子.广告>").await.unwrap();
+    assert_eq!(reply.code, 250);
+    let reply = smtp
+        .data("Subject: Привет\r\nTo: 用户@例子.广告\r\n\r\nHello\r\n")
+        .await
+        .unwrap();
+    assert_eq!(reply.code, 250);
+
+    let emails = servers.emails().await;
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].from, "प्रेषक@उदाहरण.भारत");
+    assert_eq!(emails[0].to, "用户@例子.广告");
+    assert_eq!(emails[0].envelope_to, ["用户@例子.广告"]);
+    assert_eq!(emails[0].subject.as_deref(), Some("Привет"));
+}
+
+#[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+async fn test_rset(db: Db) {
+    let servers = TestServers::start(db).await;
+    let mut smtp = servers.connect().await.unwrap();
+    smtp.command("HELO client.example.com").await.unwrap();
+
+    smtp.command("MAIL FROM: <discarded@example.com>")
+        .await
+        .unwrap();
+    smtp.command("RCPT TO: <discarded@example.com>")
+        .await
+        .unwrap();
+    assert_eq!(smtp.command("RSET").await.unwrap().code, 250);
+
+    let reply = smtp
+        .send_mail(
+            "sender@example.com",
+            "recipient@example.com",
+            "Subject: After RSET\r\n\r\nBody\r\n",
+        )
+        .await
+        .unwrap();
+    assert_eq!(reply.code, 250);
+
+    let emails = servers.emails().await;
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].from, "sender@example.com");
+    assert_eq!(emails[0].to, "recipient@example.com");
+}
+
+#[sqlx::test(migrator = "remail_maild::MIGRATOR")]
+async fn test_quit(db: Db) {
+    let servers = TestServers::start(db).await;
+    let mut smtp = servers.connect().await.unwrap();
+    smtp.command("HELO client.example.com").await.unwrap();
+
+    smtp.command("MAIL FROM: <sender@example.com>")
+        .await
+        .unwrap();
+    smtp.command("RCPT TO: <recipient@example.com>")
+        .await
+        .unwrap();
+    assert_eq!(smtp.command("QUIT").await.unwrap().code, 221);
+    assert!(smtp.read_reply().await.is_err(), "connection not closed");
+
+    // The unfinished transaction is dropped.
+    assert!(servers.emails().await.is_empty());
+}
@@ -0,0 +1,31 @@
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use email_address::EmailAddress;
+use remail_maild::email::NewEmail;
+
+/// The same messages the SMTP parser is benchmarked with.
+#[path = "../../smtp/benches/inputs/mod.rs"]
+mod inputs;
+
+fn bench_new_email(c: &mut Criterion) {
+    let from = EmailAddress::new_unchecked("sender@example.com");
+    let to = EmailAddress::new_unchecked("recipient@example.com");
+
+    for group in inputs::groups() {
+        let mut benchmark = c.benchmark_group(format!("new_email/{}", group.name));
+        for (size, lines) in &group.inputs {
+            let bytes: usize = lines.iter().map(|line| line.len() + 2).sum();
+            benchmark.throughput(Throughput::Bytes(bytes as u64));
+            benchmark.bench_with_input(BenchmarkId::from_parameter(size), lines, |b, lines| {
+                b.iter_batched(
+                    || lines.clone(),
+                    |lines| NewEmail::from_raw_message(from.clone(), to.clone(), lines),
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+        benchmark.finish();
+    }
+}
+
+criterion_group!(benches, bench_new_email);
+criterion_main!(benches);
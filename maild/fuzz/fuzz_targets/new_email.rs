@@ -0,0 +1,20 @@
+#![no_main]
+
+use email_address::EmailAddress;
+use libfuzzer_sys::fuzz_target;
+use remail_maild::email::{MAX_LINE_BYTES, NewEmail};
+
+// Each input is split into lines on CRLF, as they arrive during DATA, so a lone CR or LF ends
+// up inside a line.
+fuzz_target!(|input: &[u8]| {
+    let input = String::from_utf8_lossy(input);
+    let lines = input.split("\r\n").map(str::to_string).collect();
+    let email = NewEmail::from_raw_message(
+        EmailAddress::new_unchecked("sender@example.com"),
+        EmailAddress::new_unchecked("recipient@example.com"),
+        lines,
+    );
+
+    assert!(email.subject.len() <= MAX_LINE_BYTES);
+    email.to_rfc5322();
+});
@@ -0,0 +1,3 @@
+-- The outcome of verifying the DKIM signatures of an email when it was received, such as
+-- "pass" or "fail (<reason>)". NULL when verification is turned off.
+ALTER TABLE emails ADD COLUMN dkim_result TEXT;
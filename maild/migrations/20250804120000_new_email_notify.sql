@@ -0,0 +1,12 @@
+-- Notify listeners (the API's live stream) of every new email. Notifications are only
+-- delivered on commit, so the email's headers are visible by the time they are received.
+CREATE OR REPLACE FUNCTION notify_new_email() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('new_email', NEW.id::text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER emails_notify_new_email
+    AFTER INSERT ON emails
+    FOR EACH ROW EXECUTE FUNCTION notify_new_email();
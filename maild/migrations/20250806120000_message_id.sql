@@ -0,0 +1,3 @@
+-- Emails without a Message-ID header store NULL, which never conflicts with the constraint.
+ALTER TABLE emails ADD COLUMN message_id TEXT;
+ALTER TABLE emails ADD CONSTRAINT emails_message_id_key UNIQUE (message_id);
@@ -0,0 +1,7 @@
+-- Free-form labels attached to emails, at most one of each per email.
+CREATE TABLE tags (
+    email_id UUID NOT NULL REFERENCES emails(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (email_id, tag)
+);
+CREATE INDEX idx_tags_tag ON tags(tag);
@@ -0,0 +1,2 @@
+-- Headers stored before this migration have no recorded position and sort after the rest.
+ALTER TABLE email_headers ADD COLUMN position INTEGER;
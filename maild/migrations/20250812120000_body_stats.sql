@@ -0,0 +1,9 @@
+-- The size of each email's body in bytes, line breaks included, and its number of lines.
+ALTER TABLE emails ADD COLUMN body_bytes BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE emails ADD COLUMN body_lines BIGINT NOT NULL DEFAULT 0;
+
+-- Backfill existing emails, counting a final line without a line break as a line too.
+UPDATE emails SET
+    body_bytes = octet_length(body),
+    body_lines = length(body) - length(replace(body, E'\n', ''))
+        + CASE WHEN body = '' OR right(body, 1) = E'\n' THEN 0 ELSE 1 END;
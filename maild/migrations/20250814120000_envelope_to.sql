@@ -0,0 +1,4 @@
+-- The RCPT TO recipient, kept apart from the header recipients so BCC deliveries are visible.
+-- Older rows are left NULL: imported emails stored a header address in "to", so it cannot be
+-- told apart from an envelope recipient.
+ALTER TABLE emails ADD COLUMN envelope_to TEXT;
@@ -0,0 +1,5 @@
+-- Header fields parsed when the email is received. Each is NULL when the header was missing or
+-- malformed, in which case only the raw header in email_headers is kept.
+ALTER TABLE emails ADD COLUMN "date" TIMESTAMPTZ;
+ALTER TABLE emails ADD COLUMN in_reply_to TEXT;
+ALTER TABLE emails ADD COLUMN reply_to TEXT;
@@ -0,0 +1,3 @@
+-- The exact bytes of a body that is not valid UTF-8, received over 8BITMIME. "body" then holds
+-- a lossy decoding for display and search. NULL for UTF-8 bodies, which "body" holds exactly.
+ALTER TABLE emails ADD COLUMN raw_body BYTEA;
@@ -0,0 +1,3 @@
+-- The [method, result] pairs of the authentication checks run on an email when it was
+-- received, such as [["dkim", "pass"], ["spf", "fail"]]. Empty when none ran.
+ALTER TABLE emails ADD COLUMN auth_results JSONB NOT NULL DEFAULT '[]';
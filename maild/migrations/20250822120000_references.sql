@@ -0,0 +1,3 @@
+-- The Message-IDs of the References header, oldest ancestor first, which together with
+-- message_id and in_reply_to link the emails of a thread.
+ALTER TABLE emails ADD COLUMN "references" TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,7 @@
+-- A short plain-text summary of the body for list views, taken from its text part or its
+-- HTML part without markup.
+ALTER TABLE emails ADD COLUMN preview TEXT NOT NULL DEFAULT '';
+
+-- Emails stored before previews existed get their body with whitespace collapsed, which is
+-- close enough for plain text ones.
+UPDATE emails SET preview = left(btrim(regexp_replace(body, '\s+', ' ', 'g')), 160);
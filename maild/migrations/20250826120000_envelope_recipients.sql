@@ -0,0 +1,6 @@
+-- A transaction may have several RCPT TO recipients, so envelope_to keeps all of them.
+ALTER TABLE emails
+    ALTER COLUMN envelope_to TYPE TEXT[]
+        USING CASE WHEN envelope_to IS NULL THEN '{}' ELSE ARRAY[envelope_to] END,
+    ALTER COLUMN envelope_to SET DEFAULT '{}',
+    ALTER COLUMN envelope_to SET NOT NULL;
@@ -0,0 +1,3 @@
+-- The size of the message as received over SMTP, headers and line endings included and
+-- dot-stuffing excluded, for quota checks. 0 for emails that did not arrive over SMTP.
+ALTER TABLE emails ADD COLUMN received_bytes BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,15 @@
+-- Headers are ordered by their ordinal, their index among the email's headers as received.
+ALTER TABLE email_headers RENAME COLUMN position TO ordinal;
+
+-- Headers stored before positions were recorded keep the order they were shown in until now,
+-- alphabetical by key, so every header has an ordinal.
+UPDATE email_headers
+SET ordinal = numbered.ordinal
+FROM (
+    SELECT ctid, ROW_NUMBER() OVER (PARTITION BY email_id ORDER BY key) - 1 AS ordinal
+    FROM email_headers
+    WHERE email_id IN (SELECT email_id FROM email_headers WHERE ordinal IS NULL)
+) AS numbered
+WHERE email_headers.ctid = numbered.ctid;
+
+ALTER TABLE email_headers ALTER COLUMN ordinal SET NOT NULL;
@@ -0,0 +1,196 @@
+use email_address::EmailAddress;
+use std::str::FromStr;
+
+/// Extracts the address from a mailbox such as `Name <user@example.com>`.
+pub fn parse_mailbox(value: &str) -> Option<EmailAddress> {
+    let address = match value.rsplit_once('<') {
+        Some((_, rest)) => rest.split_once('>')?.0,
+        None => value,
+    };
+    EmailAddress::from_str(address.trim()).ok()
+}
+
+/// The addresses in an address-list header such as `To` or `Cc`, skipping entries that are
+/// not valid mailboxes.
+///
+/// Commas inside quoted display names, like `"Doe, Jane" <jane@example.com>`, do not split
+/// the list.
+pub fn parse_mailbox_list(value: &str) -> Vec<String> {
+    parse_mailboxes(value)
+        .iter()
+        .map(|address| address.to_string())
+        .collect()
+}
+
+/// Like [`parse_mailbox_list`], keeping the parsed addresses.
+pub fn parse_mailboxes(value: &str) -> Vec<EmailAddress> {
+    parse_address_list(value)
+        .into_iter()
+        .map(|(_, address)| address)
+        .collect()
+}
+
+/// The mailboxes in an RFC 5322 address list, with their display names, skipping entries that
+/// are not valid mailboxes.
+///
+/// Groups such as `Team: a@example.com, b@example.com;` are flattened into their members, and
+/// commas, colons and semicolons inside quoted display names or angle brackets do not split
+/// the list.
+pub fn parse_address_list(value: &str) -> Vec<(Option<String>, EmailAddress)> {
+    let mut entries = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut angled = false;
+    for c in value.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            _ if quoted => {}
+            '<' => angled = true,
+            '>' => angled = false,
+            // The group's display name is not an address of its own.
+            ':' if !angled => {
+                current.clear();
+                continue;
+            }
+            ',' | ';' if !angled => {
+                entries.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    entries.push(current);
+
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let address = parse_mailbox(entry)?;
+            Some((display_name(entry), address))
+        })
+        .collect()
+}
+
+/// The display name of a mailbox such as `"Doe, Jane" <jane@example.com>`, unquoted.
+fn display_name(mailbox: &str) -> Option<String> {
+    let (name, _) = mailbox.rsplit_once('<')?;
+    let name = name.trim();
+    let name = match name
+        .strip_prefix('"')
+        .and_then(|name| name.strip_suffix('"'))
+    {
+        Some(quoted) => {
+            let mut name = String::new();
+            let mut escaped = false;
+            for c in quoted.chars() {
+                if c == '\\' && !escaped {
+                    escaped = true;
+                    continue;
+                }
+                escaped = false;
+                name.push(c);
+            }
+            name
+        }
+        None => name.to_string(),
+    };
+    (!name.is_empty()).then_some(name)
+}
+
+/// The addresses in every `name` header, in order.
+pub fn header_addresses(headers: &[(String, String)], name: &str) -> Vec<String> {
+    headers
+        .iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+        .flat_map(|(_, value)| parse_mailbox_list(value))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multiple_addresses() {
+        assert_eq!(
+            parse_mailbox_list("a@example.com, b@example.com,c@example.com"),
+            ["a@example.com", "b@example.com", "c@example.com"]
+        );
+    }
+
+    #[test]
+    fn test_display_names() {
+        assert_eq!(
+            parse_mailbox_list(
+                r#"Alice <alice@example.com>, "Doe, Jane" <jane@example.com>, "Say \"hi\", Bob" <bob@example.com>"#
+            ),
+            ["alice@example.com", "jane@example.com", "bob@example.com"]
+        );
+    }
+
+    #[test]
+    fn test_skips_invalid_and_empty_entries() {
+        assert_eq!(
+            parse_mailbox_list("undisclosed-recipients:;, , carol@example.com"),
+            ["carol@example.com"]
+        );
+        assert!(parse_mailbox_list("").is_empty());
+    }
+
+    #[test]
+    fn test_address_list() {
+        let address = |value| EmailAddress::new_unchecked(value);
+        let table = [
+            (
+                "a@example.com, b@example.com",
+                vec![
+                    (None, address("a@example.com")),
+                    (None, address("b@example.com")),
+                ],
+            ),
+            (
+                r#""Doe, Jane" <jane@example.com>, "Say \"hi\"" <bob@example.com>"#,
+                vec![
+                    (Some("Doe, Jane".to_string()), address("jane@example.com")),
+                    (Some("Say \"hi\"".to_string()), address("bob@example.com")),
+                ],
+            ),
+            (
+                "Team: Alice <alice@example.com>, bob@example.com;, carol@example.com",
+                vec![
+                    (Some("Alice".to_string()), address("alice@example.com")),
+                    (None, address("bob@example.com")),
+                    (None, address("carol@example.com")),
+                ],
+            ),
+            (
+                r#""Team: ops; dev": <ops@example.com>;"#,
+                vec![(None, address("ops@example.com"))],
+            ),
+            ("undisclosed-recipients:;", vec![]),
+        ];
+
+        for (value, expected) in table {
+            assert_eq!(parse_address_list(value), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn test_folded_header() {
+        let headers = vec![
+            (
+                "Cc".to_string(),
+                "Alice <alice@example.com>,\nbob@example.com".to_string(),
+            ),
+            ("cc".to_string(), "carol@example.com".to_string()),
+        ];
+
+        assert_eq!(
+            header_addresses(&headers, "Cc"),
+            ["alice@example.com", "bob@example.com", "carol@example.com"]
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! The DNS lookups DKIM, SPF and relaying need, behind [`DnsResolver`] so tests can answer
+//! them.
+
+use async_trait::async_trait;
+use hickory_resolver::TokioAsyncResolver;
+use hickory_resolver::error::{ResolveError, ResolveErrorKind};
+use std::fmt;
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsError {
+    /// The name does not exist or has no records of the type asked for.
+    NotFound,
+    /// The lookup itself failed, so trying again later may succeed.
+    Failed(String),
+    /// No answer arrived in time, so trying again later may succeed.
+    Timeout,
+}
+
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DnsError::NotFound => write!(f, "no records found"),
+            DnsError::Failed(reason) => write!(f, "lookup failed: {reason}"),
+            DnsError::Timeout => write!(f, "lookup timed out"),
+        }
+    }
+}
+
+impl std::error::Error for DnsError {}
+
+/// A mail server of a domain, from one of its MX records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MxRecord {
+    /// The record's preference; lower values are tried first.
+    pub priority: u16,
+    pub hostname: String,
+}
+
+#[async_trait]
+pub trait DnsResolver: Send + Sync {
+    /// The TXT records of `name`, each with its strings joined.
+    async fn txt(&self, name: &str) -> Result<Vec<String>, DnsError>;
+    /// The A and AAAA records of `name`.
+    async fn ips(&self, name: &str) -> Result<Vec<IpAddr>, DnsError>;
+    /// The MX records of `name`, most preferred first.
+    async fn mx(&self, name: &str) -> Result<Vec<MxRecord>, DnsError>;
+}
+
+/// Resolves through the system's DNS configuration.
+pub struct HickoryResolver {
+    resolver: TokioAsyncResolver,
+}
+
+impl HickoryResolver {
+    pub fn from_system_conf() -> Result<Self, ResolveError> {
+        Ok(Self {
+            resolver: TokioAsyncResolver::tokio_from_system_conf()?,
+        })
+    }
+}
+
+/// Makes `name` fully qualified, so the resolver does not try it under the search domains.
+fn fqdn(name: &str) -> String {
+    if name.ends_with('.') {
+        name.to_string()
+    } else {
+        format!("{name}.")
+    }
+}
+
+fn dns_error(error: ResolveError) -> DnsError {
+    match error.kind() {
+        ResolveErrorKind::NoRecordsFound { .. } => DnsError::NotFound,
+        ResolveErrorKind::Timeout => DnsError::Timeout,
+        _ => DnsError::Failed(error.to_string()),
+    }
+}
+
+#[async_trait]
+impl DnsResolver for HickoryResolver {
+    async fn txt(&self, name: &str) -> Result<Vec<String>, DnsError> {
+        let lookup = self
+            .resolver
+            .txt_lookup(fqdn(name))
+            .await
+            .map_err(dns_error)?;
+        Ok(lookup
+            .iter()
+            .map(|txt| {
+                txt.txt_data()
+                    .iter()
+                    .map(|part| String::from_utf8_lossy(part))
+                    .collect()
+            })
+            .collect())
+    }
+
+    async fn ips(&self, name: &str) -> Result<Vec<IpAddr>, DnsError> {
+        let lookup = self
+            .resolver
+            .lookup_ip(fqdn(name))
+            .await
+            .map_err(dns_error)?;
+        Ok(lookup.iter().collect())
+    }
+
+    async fn mx(&self, name: &str) -> Result<Vec<MxRecord>, DnsError> {
+        let lookup = self
+            .resolver
+            .mx_lookup(fqdn(name))
+            .await
+            .map_err(dns_error)?;
+        let mut records: Vec<_> = lookup
+            .iter()
+            .map(|mx| MxRecord {
+                priority: mx.preference(),
+                hostname: mx.exchange().to_utf8().trim_end_matches('.').to_string(),
+            })
+            .collect();
+        records.sort_by_key(|mx| mx.priority);
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    /// Answers from fixed records. Names without records are not found, and names marked as
+    /// failing fail every lookup.
+    #[derive(Default)]
+    pub(crate) struct StubResolver {
+        txt: HashMap<String, Vec<String>>,
+        ips: HashMap<String, Vec<IpAddr>>,
+        mx: HashMap<String, Vec<MxRecord>>,
+        failing: HashSet<String>,
+    }
+
+    impl StubResolver {
+        pub(crate) fn with_txt(mut self, name: &str, record: &str) -> Self {
+            self.txt
+                .entry(name.to_string())
+                .or_default()
+                .push(record.to_string());
+            self
+        }
+
+        #[cfg_attr(not(feature = "auth-analysis"), allow(dead_code))]
+        pub(crate) fn with_ip(mut self, name: &str, ip: &str) -> Self {
+            self.ips
+                .entry(name.to_string())
+                .or_default()
+                .push(ip.parse().unwrap());
+            self
+        }
+
+        /// Adds an MX record, keeping the records of `name` sorted as a resolver returns them.
+        #[cfg_attr(not(feature = "relay"), allow(dead_code))]
+        pub(crate) fn with_mx(mut self, name: &str, priority: u16, host: &str) -> Self {
+            let records = self.mx.entry(name.to_string()).or_default();
+            records.push(MxRecord {
+                priority,
+                hostname: host.to_string(),
+            });
+            records.sort_by_key(|mx| mx.priority);
+            self
+        }
+
+        pub(crate) fn with_failure(mut self, name: &str) -> Self {
+            self.failing.insert(name.to_string());
+            self
+        }
+
+        fn lookup<T: Clone>(
+            &self,
+            records: &HashMap<String, Vec<T>>,
+            name: &str,
+        ) -> Result<Vec<T>, DnsError> {
+            let name = name.trim_end_matches('.').to_ascii_lowercase();
+            if self.failing.contains(&name) {
+                return Err(DnsError::Failed("stubbed failure".to_string()));
+            }
+            records.get(&name).cloned().ok_or(DnsError::NotFound)
+        }
+    }
+
+    #[async_trait]
+    impl DnsResolver for StubResolver {
+        async fn txt(&self, name: &str) -> Result<Vec<String>, DnsError> {
+            self.lookup(&self.txt, name)
+        }
+
+        async fn ips(&self, name: &str) -> Result<Vec<IpAddr>, DnsError> {
+            self.lookup(&self.ips, name)
+        }
+
+        async fn mx(&self, name: &str) -> Result<Vec<MxRecord>, DnsError> {
+            self.lookup(&self.mx, name)
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Authentication checks on received messages, such as SPF. Their results annotate a message,
+//! in [`NewEmail::auth_results`] and an `Authentication-Results` header, and never cause it to
+//! be rejected.
+//!
+//! Checks plug into the handler as a [`MessageAnalyzer`], see
+//! [`SmtpHandler::with_analyzer`](crate::handler::SmtpHandler::with_analyzer). The DNS-based
+//! [`DnsAuthAnalyzer`] needs the `auth-analysis` feature. DKIM is verified by the handler's
+//! [`DkimVerifier`](crate::dkim::DkimVerifier), whose result is reported alongside.
+
+use crate::email::NewEmail;
+use async_trait::async_trait;
+use remail_smtp::mailbox::MailboxAddress;
+use std::net::IpAddr;
+
+pub mod dns;
+#[cfg(feature = "auth-analysis")]
+mod spf;
+
+/// The authserv-id naming remail in the `Authentication-Results` headers it adds.
+pub const AUTHSERV_ID: &str = "remail";
+
+/// The outcome of one authentication method, as RFC 8601 reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthResult {
+    /// The method, such as `dkim` or `spf`.
+    pub method: String,
+    /// The result keyword, such as `pass`, `fail` or `temperror`.
+    pub result: String,
+    /// Why the result is not a pass, for people reading the header.
+    pub reason: Option<String>,
+    /// What was checked, such as `("header.d", "example.com")`.
+    pub properties: Vec<(String, String)>,
+}
+
+impl AuthResult {
+    pub fn new(method: impl Into<String>, result: impl Into<String>) -> Self {
+        Self {
+            method: method.into(),
+            result: result.into(),
+            reason: None,
+            properties: Vec::new(),
+        }
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    pub fn with_property(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.properties.push((name.into(), value.into()));
+        self
+    }
+}
+
+impl std::fmt::Display for AuthResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.method, self.result)?;
+        if let Some(reason) = &self.reason {
+            let reason = reason.replace('\\', "\\\\").replace('"', "\\\"");
+            write!(f, " reason=\"{reason}\"")?;
+        }
+        for (name, value) in &self.properties {
+            write!(f, " {name}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The value of an `Authentication-Results` header reporting `results`.
+pub fn authentication_results(authserv_id: &str, results: &[AuthResult]) -> String {
+    let mut value = authserv_id.to_string();
+    if results.is_empty() {
+        value.push_str("; none");
+    }
+    for result in results {
+        value.push_str("; ");
+        value.push_str(&result.to_string());
+    }
+    value
+}
+
+/// What an analyzer knows about the SMTP session a message arrived in.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionInfo<'a> {
+    /// The client's address, unless the handler was not told it.
+    pub peer_ip: Option<IpAddr>,
+    pub helo_domain: Option<&'a str>,
+    pub mail_from: &'a MailboxAddress,
+}
+
+#[async_trait]
+pub trait MessageAnalyzer: Send + Sync {
+    async fn analyze(&self, email: &NewEmail, session: &SessionInfo<'_>) -> Vec<AuthResult>;
+}
+
+/// Evaluates SPF for the client's address and the MAIL FROM domain, looking records up through
+/// `resolver`.
+#[cfg(feature = "auth-analysis")]
+pub struct DnsAuthAnalyzer {
+    resolver: std::sync::Arc<dyn dns::DnsResolver>,
+}
+
+#[cfg(feature = "auth-analysis")]
+impl DnsAuthAnalyzer {
+    pub fn new(resolver: std::sync::Arc<dyn dns::DnsResolver>) -> Self {
+        Self { resolver }
+    }
+}
+
+#[cfg(feature = "auth-analysis")]
+#[async_trait]
+impl MessageAnalyzer for DnsAuthAnalyzer {
+    async fn analyze(&self, _email: &NewEmail, session: &SessionInfo<'_>) -> Vec<AuthResult> {
+        // SPF authorizes client addresses, so without one there is nothing to evaluate.
+        let Some(ip) = session.peer_ip else {
+            return Vec::new();
+        };
+        let domain = session.mail_from.domain();
+        let result = spf::check_host(self.resolver.as_ref(), ip, domain).await;
+        vec![AuthResult::new("spf", result.to_string()).with_property("smtp.mailfrom", domain)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authentication_results() {
+        let results = [
+            AuthResult::new("dkim", "pass")
+                .with_property("header.d", "example.com")
+                .with_property("header.s", "test"),
+            AuthResult::new("spf", "fail")
+                .with_reason("not \"authorized\"")
+                .with_property("smtp.mailfrom", "example.com"),
+        ];
+
+        assert_eq!(
+            authentication_results(AUTHSERV_ID, &results),
+            "remail; dkim=pass header.d=example.com header.s=test; spf=fail reason=\"not \\\"authorized\\\"\" smtp.mailfrom=example.com"
+        );
+        assert_eq!(authentication_results(AUTHSERV_ID, &[]), "remail; none");
+    }
+
+    #[cfg(feature = "auth-analysis")]
+    #[tokio::test]
+    async fn test_dns_auth_analyzer() {
+        use dns::tests::StubResolver;
+        use std::sync::Arc;
+
+        let resolver =
+            StubResolver::default().with_txt("example.com", "v=spf1 ip4:192.0.2.0/24 -all");
+        let analyzer = DnsAuthAnalyzer::new(Arc::new(resolver));
+        let from = MailboxAddress::parse("alice@example.com").unwrap();
+        let email = NewEmail::from_raw_message(
+            from.clone(),
+            MailboxAddress::parse("bob@example.org").unwrap(),
+            vec![
+                "Subject: Hello".to_string(),
+                String::new(),
+                "Hi".to_string(),
+            ],
+        );
+
+        let session = SessionInfo {
+            peer_ip: Some("192.0.2.25".parse().unwrap()),
+            helo_domain: Some("mail.example.com"),
+            mail_from: &from,
+        };
+        let results = analyzer.analyze(&email, &session).await;
+        assert_eq!(
+            authentication_results(AUTHSERV_ID, &results),
+            "remail; spf=pass smtp.mailfrom=example.com"
+        );
+
+        let session = SessionInfo {
+            peer_ip: Some("198.51.100.1".parse().unwrap()),
+            ..session
+        };
+        let results = analyzer.analyze(&email, &session).await;
+        assert_eq!(results[0].result, "fail");
+
+        let session = SessionInfo {
+            peer_ip: None,
+            ..session
+        };
+        let results = analyzer.analyze(&email, &session).await;
+        assert!(results.is_empty());
+    }
+}
@@ -0,0 +1,437 @@
+//! SPF evaluation, following the `check_host()` function of RFC 7208.
+//!
+//! Macros are not expanded, so records using them are a `permerror`, and the `ptr` mechanism,
+//! which RFC 7208 discourages, never matches.
+
+use super::dns::{DnsError, DnsResolver};
+use std::fmt;
+use std::future::Future;
+use std::net::IpAddr;
+use std::pin::Pin;
+
+/// The most mechanisms and modifiers causing DNS lookups a check may evaluate, including
+/// those of the records it includes.
+const MAX_LOOKUPS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpfResult {
+    None,
+    Neutral,
+    Pass,
+    Fail,
+    SoftFail,
+    TempError,
+    PermError,
+}
+
+impl fmt::Display for SpfResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = match self {
+            SpfResult::None => "none",
+            SpfResult::Neutral => "neutral",
+            SpfResult::Pass => "pass",
+            SpfResult::Fail => "fail",
+            SpfResult::SoftFail => "softfail",
+            SpfResult::TempError => "temperror",
+            SpfResult::PermError => "permerror",
+        };
+        write!(f, "{result}")
+    }
+}
+
+/// Whether `domain` authorizes `ip` to send its mail.
+pub async fn check_host(resolver: &dyn DnsResolver, ip: IpAddr, domain: &str) -> SpfResult {
+    let mut evaluator = Evaluator {
+        resolver,
+        ip: ip.to_canonical(),
+        lookups: 0,
+    };
+    evaluator.check(domain.to_ascii_lowercase()).await
+}
+
+struct Evaluator<'a> {
+    resolver: &'a dyn DnsResolver,
+    ip: IpAddr,
+    lookups: usize,
+}
+
+/// A mechanism's own result, or the result ending the whole check.
+enum Match {
+    Yes,
+    No,
+    Abort(SpfResult),
+}
+
+impl<'a> Evaluator<'a> {
+    /// Evaluates the record of `domain`. Boxed, as `include` and `redirect` recurse.
+    fn check(&mut self, domain: String) -> Pin<Box<dyn Future<Output = SpfResult> + Send + '_>> {
+        Box::pin(async move {
+            let record = match self.record(&domain).await {
+                Ok(record) => record,
+                Err(result) => return result,
+            };
+
+            let mut redirect = None;
+            for term in record.split_whitespace().skip(1) {
+                if term.contains('%') {
+                    return SpfResult::PermError;
+                }
+                if let Some((name, value)) = modifier(term) {
+                    if name.eq_ignore_ascii_case("redirect") {
+                        if redirect.is_some() {
+                            return SpfResult::PermError;
+                        }
+                        redirect = Some(value.to_ascii_lowercase());
+                    }
+                    // Other modifiers, such as exp, do not affect the result.
+                    continue;
+                }
+
+                let (qualifier, mechanism) = match term.as_bytes()[0] {
+                    b'+' => (SpfResult::Pass, &term[1..]),
+                    b'-' => (SpfResult::Fail, &term[1..]),
+                    b'~' => (SpfResult::SoftFail, &term[1..]),
+                    b'?' => (SpfResult::Neutral, &term[1..]),
+                    _ => (SpfResult::Pass, term),
+                };
+                match self.mechanism(&domain, mechanism).await {
+                    Match::Yes => return qualifier,
+                    Match::No => {}
+                    Match::Abort(result) => return result,
+                }
+            }
+
+            match redirect {
+                Some(target) => {
+                    if let Err(result) = self.count_lookup() {
+                        return result;
+                    }
+                    match self.check(target).await {
+                        SpfResult::None => SpfResult::PermError,
+                        result => result,
+                    }
+                }
+                None => SpfResult::Neutral,
+            }
+        })
+    }
+
+    /// The SPF record of `domain`, or the result when it has none or several.
+    async fn record(&self, domain: &str) -> Result<String, SpfResult> {
+        let records = match self.resolver.txt(domain).await {
+            Ok(records) => records,
+            Err(DnsError::NotFound) => return Err(SpfResult::None),
+            Err(DnsError::Failed(_) | DnsError::Timeout) => return Err(SpfResult::TempError),
+        };
+        let mut records = records.into_iter().filter(|record| {
+            let version = record.split_whitespace().next().unwrap_or_default();
+            version.eq_ignore_ascii_case("v=spf1")
+        });
+        match (records.next(), records.next()) {
+            (Some(record), None) => Ok(record),
+            (None, _) => Err(SpfResult::None),
+            (Some(_), Some(_)) => Err(SpfResult::PermError),
+        }
+    }
+
+    fn count_lookup(&mut self) -> Result<(), SpfResult> {
+        self.lookups += 1;
+        if self.lookups > MAX_LOOKUPS {
+            return Err(SpfResult::PermError);
+        }
+        Ok(())
+    }
+
+    async fn mechanism(&mut self, domain: &str, mechanism: &str) -> Match {
+        let end = mechanism.find([':', '/']).unwrap_or(mechanism.len());
+        let (name, argument) = mechanism.split_at(end);
+        let target = |argument: &str| match argument.strip_prefix(':') {
+            Some(target) => target.to_ascii_lowercase(),
+            None => domain.to_string(),
+        };
+
+        let name = name.to_ascii_lowercase();
+        if ["include", "a", "mx", "exists", "ptr"].contains(&name.as_str())
+            && let Err(result) = self.count_lookup()
+        {
+            return Match::Abort(result);
+        }
+
+        match name.as_str() {
+            "all" if argument.is_empty() => Match::Yes,
+            "include" => {
+                let Some(target) = argument.strip_prefix(':') else {
+                    return Match::Abort(SpfResult::PermError);
+                };
+                match self.check(target.to_ascii_lowercase()).await {
+                    SpfResult::Pass => Match::Yes,
+                    SpfResult::Fail | SpfResult::SoftFail | SpfResult::Neutral => Match::No,
+                    SpfResult::TempError => Match::Abort(SpfResult::TempError),
+                    SpfResult::None | SpfResult::PermError => Match::Abort(SpfResult::PermError),
+                }
+            }
+            "a" | "mx" => {
+                let (target, cidrs) = match argument.find('/') {
+                    Some(slash) => (target(&argument[..slash]), &argument[slash..]),
+                    None => (target(argument), ""),
+                };
+                let Some(cidrs) = DualCidr::parse(cidrs) else {
+                    return Match::Abort(SpfResult::PermError);
+                };
+                let hosts = if name == "a" {
+                    vec![target]
+                } else {
+                    match self.resolver.mx(&target).await {
+                        Ok(records) if records.len() > MAX_LOOKUPS => {
+                            return Match::Abort(SpfResult::PermError);
+                        }
+                        Ok(records) => records.into_iter().map(|mx| mx.hostname).collect(),
+                        Err(DnsError::NotFound) => Vec::new(),
+                        Err(DnsError::Failed(_) | DnsError::Timeout) => {
+                            return Match::Abort(SpfResult::TempError);
+                        }
+                    }
+                };
+                for host in hosts {
+                    match self.resolver.ips(&host).await {
+                        Ok(ips) if ips.iter().any(|&ip| cidrs.contains(ip, self.ip)) => {
+                            return Match::Yes;
+                        }
+                        Ok(_) | Err(DnsError::NotFound) => {}
+                        Err(DnsError::Failed(_) | DnsError::Timeout) => {
+                            return Match::Abort(SpfResult::TempError);
+                        }
+                    }
+                }
+                Match::No
+            }
+            "ip4" | "ip6" => {
+                let Some(network) = argument.strip_prefix(':') else {
+                    return Match::Abort(SpfResult::PermError);
+                };
+                let (address, prefix) = match network.split_once('/') {
+                    Some((address, prefix)) => (address, Some(prefix)),
+                    None => (network, None),
+                };
+                let Ok(address) = address.parse::<IpAddr>() else {
+                    return Match::Abort(SpfResult::PermError);
+                };
+                let max = if address.is_ipv4() { 32 } else { 128 };
+                let prefix = match prefix.map(str::parse::<u32>) {
+                    None => max,
+                    Some(Ok(prefix)) if prefix <= max => prefix,
+                    Some(_) => return Match::Abort(SpfResult::PermError),
+                };
+                if (name == "ip4") != address.is_ipv4() {
+                    return Match::Abort(SpfResult::PermError);
+                }
+                if in_network(self.ip, address, prefix) {
+                    Match::Yes
+                } else {
+                    Match::No
+                }
+            }
+            "exists" => {
+                let Some(target) = argument.strip_prefix(':') else {
+                    return Match::Abort(SpfResult::PermError);
+                };
+                match self.resolver.ips(target).await {
+                    Ok(ips) if ips.iter().any(IpAddr::is_ipv4) => Match::Yes,
+                    Ok(_) | Err(DnsError::NotFound) => Match::No,
+                    Err(DnsError::Failed(_) | DnsError::Timeout) => {
+                        Match::Abort(SpfResult::TempError)
+                    }
+                }
+            }
+            "ptr" => Match::No,
+            _ => Match::Abort(SpfResult::PermError),
+        }
+    }
+}
+
+/// Splits a modifier, such as `redirect=example.com`, into its name and value.
+fn modifier(term: &str) -> Option<(&str, &str)> {
+    let (name, value) = term.split_once('=')?;
+    let is_name = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    is_name.then_some((name, value))
+}
+
+/// The prefix lengths an `a` or `mx` mechanism compares IPv4 and IPv6 addresses with, as in
+/// `a:example.com/24//64`.
+struct DualCidr {
+    v4: u32,
+    v6: u32,
+}
+
+impl DualCidr {
+    fn parse(cidrs: &str) -> Option<Self> {
+        let parse = |prefix: &str, max: u32| prefix.parse().ok().filter(|&prefix| prefix <= max);
+        let (v4, v6) = match cidrs.strip_prefix("//") {
+            Some(v6) => (None, Some(v6)),
+            None => match cidrs.strip_prefix('/') {
+                Some(cidrs) => match cidrs.split_once("//") {
+                    Some((v4, v6)) => (Some(v4), Some(v6)),
+                    None => (Some(cidrs), None),
+                },
+                None if cidrs.is_empty() => (None, None),
+                None => return None,
+            },
+        };
+        Some(Self {
+            v4: v4.map_or(Some(32), |v4| parse(v4, 32))?,
+            v6: v6.map_or(Some(128), |v6| parse(v6, 128))?,
+        })
+    }
+
+    fn contains(&self, network: IpAddr, ip: IpAddr) -> bool {
+        let network = network.to_canonical();
+        let prefix = if network.is_ipv4() { self.v4 } else { self.v6 };
+        in_network(ip, network, prefix)
+    }
+}
+
+/// Whether `ip` is within the first `prefix` bits of `network`, of the same family.
+fn in_network(ip: IpAddr, network: IpAddr, prefix: u32) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::dns::tests::StubResolver;
+    use super::*;
+
+    async fn check(resolver: &StubResolver, ip: &str) -> SpfResult {
+        check_host(resolver, ip.parse().unwrap(), "example.com").await
+    }
+
+    #[tokio::test]
+    async fn test_mechanisms() {
+        let resolver = StubResolver::default()
+            .with_txt(
+                "example.com",
+                "v=spf1 ip4:192.0.2.0/24 ip6:2001:db8::/32 a:www.example.com/28 mx//64 \
+                 include:_spf.example.net exists:missing.example.com ~all",
+            )
+            .with_txt("example.com", "google-site-verification=abc")
+            .with_ip("www.example.com", "198.51.100.1")
+            .with_mx("example.com", 10, "mx1.example.com")
+            .with_ip("mx1.example.com", "2001:db9::1")
+            .with_txt("_spf.example.net", "v=spf1 ip4:203.0.113.7 -all");
+
+        let table = [
+            ("192.0.2.25", SpfResult::Pass),
+            ("::ffff:192.0.2.25", SpfResult::Pass),
+            ("2001:db8::1", SpfResult::Pass),
+            ("198.51.100.14", SpfResult::Pass),
+            ("2001:db9::ffff", SpfResult::Pass),
+            ("203.0.113.7", SpfResult::Pass),
+            ("203.0.113.8", SpfResult::SoftFail),
+            ("198.51.100.16", SpfResult::SoftFail),
+        ];
+        for (ip, expected) in table {
+            assert_eq!(check(&resolver, ip).await, expected, "{ip}");
+        }
+
+        let resolver = StubResolver::default()
+            .with_txt("example.com", "v=spf1 a ?ip4:10.0.0.1 -ip6:::1 ~all")
+            .with_ip("example.com", "192.0.2.1");
+        let table = [
+            ("192.0.2.1", SpfResult::Pass),
+            ("192.0.2.2", SpfResult::SoftFail),
+            ("10.0.0.1", SpfResult::Neutral),
+            ("::1", SpfResult::Fail),
+        ];
+        for (ip, expected) in table {
+            assert_eq!(check(&resolver, ip).await, expected, "{ip}");
+        }
+
+        let resolver = StubResolver::default()
+            .with_txt("example.com", "v=spf1 exists:allowed.example.com -all")
+            .with_ip("allowed.example.com", "127.0.0.2");
+        assert_eq!(check(&resolver, "192.0.2.1").await, SpfResult::Pass);
+    }
+
+    #[tokio::test]
+    async fn test_redirect() {
+        let resolver = StubResolver::default()
+            .with_txt(
+                "example.com",
+                "v=spf1 ip4:192.0.2.1 redirect=_spf.example.com",
+            )
+            .with_txt("_spf.example.com", "v=spf1 ip4:192.0.2.2 -all");
+
+        assert_eq!(check(&resolver, "192.0.2.1").await, SpfResult::Pass);
+        assert_eq!(check(&resolver, "192.0.2.2").await, SpfResult::Pass);
+        assert_eq!(check(&resolver, "192.0.2.3").await, SpfResult::Fail);
+
+        let resolver =
+            StubResolver::default().with_txt("example.com", "v=spf1 redirect=missing.example.com");
+        assert_eq!(check(&resolver, "192.0.2.1").await, SpfResult::PermError);
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let ip = "192.0.2.1";
+        assert_eq!(check(&StubResolver::default(), ip).await, SpfResult::None);
+        assert_eq!(
+            check(&StubResolver::default().with_failure("example.com"), ip).await,
+            SpfResult::TempError
+        );
+
+        let resolver = StubResolver::default()
+            .with_txt("example.com", "v=spf1 include:_spf.example.com -all")
+            .with_failure("_spf.example.com");
+        assert_eq!(check(&resolver, ip).await, SpfResult::TempError);
+
+        let table = [
+            vec!["v=spf1 -all", "v=spf1 +all"],
+            vec!["v=spf1 ip4:192.0.2.0/33 -all"],
+            vec!["v=spf1 ip4:2001:db8::1 -all"],
+            vec!["v=spf1 unknown:example.com -all"],
+            vec!["v=spf1 exists:%{i}.example.com -all"],
+            vec!["v=spf1 include:missing.example.com -all"],
+            vec!["v=spf1 redirect=a.example.com redirect=b.example.com"],
+            vec!["v=spf1 include:example.com -all"],
+        ];
+        for records in table {
+            let resolver = records
+                .iter()
+                .fold(StubResolver::default(), |resolver, record| {
+                    resolver.with_txt("example.com", record)
+                });
+            assert_eq!(
+                check(&resolver, ip).await,
+                SpfResult::PermError,
+                "{records:?}"
+            );
+        }
+
+        // Unknown modifiers are ignored, and a record without a match is neutral.
+        let resolver =
+            StubResolver::default().with_txt("example.com", "v=spf1 exp=explain.example.com");
+        assert_eq!(check(&resolver, ip).await, SpfResult::Neutral);
+    }
+
+    #[test]
+    fn test_in_network() {
+        let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+        assert!(in_network(ip("192.0.2.200"), ip("192.0.2.0"), 24));
+        assert!(!in_network(ip("192.0.3.1"), ip("192.0.2.0"), 24));
+        assert!(in_network(ip("10.1.2.3"), ip("192.0.2.0"), 0));
+        assert!(in_network(ip("2001:db8::1"), ip("2001:db8::"), 32));
+        assert!(!in_network(ip("2001:db8::1"), ip("192.0.2.0"), 0));
+    }
+}
@@ -0,0 +1,124 @@
+use crate::reply::SmtpReply;
+use remail_smtp::mailbox::MailboxAddress;
+
+/// Recipient patterns that are refused at RCPT time, so senders' bounce and retry handling
+/// can be exercised against remail.
+///
+/// Patterns are matched against the whole address, ignoring case, and `*` matches any run
+/// of characters, e.g. `bounce@*` or `*@defer.example.com`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BounceRules {
+    bounce: Vec<String>,
+    defer: Vec<String>,
+}
+
+impl BounceRules {
+    /// Permanently rejects recipients matching `pattern` with a 550 reply.
+    pub fn bounce(mut self, pattern: &str) -> Self {
+        self.bounce.push(pattern.to_lowercase());
+        self
+    }
+
+    /// Temporarily rejects recipients matching `pattern` with a 450 reply.
+    pub fn defer(mut self, pattern: &str) -> Self {
+        self.defer.push(pattern.to_lowercase());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounce.is_empty() && self.defer.is_empty()
+    }
+
+    /// The reply refusing `to`, if it matches a pattern. Bounces win over deferrals.
+    pub fn check(&self, to: &MailboxAddress) -> Option<SmtpReply> {
+        let to = to.as_str().to_lowercase();
+        if self.bounce.iter().any(|pattern| matches(pattern, &to)) {
+            Some(SmtpReply::NO_SUCH_USER)
+        } else if self.defer.iter().any(|pattern| matches(pattern, &to)) {
+            Some(SmtpReply::MAILBOX_UNAVAILABLE)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether `text` matches the glob `pattern`, where `*` matches any run of characters.
+fn matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` at all, so the pattern has to match exactly.
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(address: &str) -> MailboxAddress {
+        MailboxAddress::parse(address).unwrap()
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("bounce@*", "bounce@example.com"));
+        assert!(matches("*@defer.example.com", "anyone@defer.example.com"));
+        assert!(matches("*bounce*", "soft-bounce-1@example.com"));
+        assert!(matches("exact@example.com", "exact@example.com"));
+        assert!(matches("*", ""));
+
+        assert!(!matches("bounce@*", "nobounce@example.com"));
+        assert!(!matches("exact@example.com", "exact@example.com.au"));
+        assert!(!matches("a*b*c", "acb"));
+        // The suffix must not reuse characters already matched by the prefix.
+        assert!(!matches("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn test_check() {
+        let rules = BounceRules::default().bounce("bounce@*").defer("defer@*");
+
+        assert_eq!(
+            rules.check(&address("bounce@example.com")),
+            Some(SmtpReply::NO_SUCH_USER)
+        );
+        assert_eq!(
+            rules.check(&address("Defer@Example.com")),
+            Some(SmtpReply::MAILBOX_UNAVAILABLE)
+        );
+        assert_eq!(rules.check(&address("recipient@example.com")), None);
+    }
+
+    #[test]
+    fn test_bounce_wins_over_defer() {
+        let rules = BounceRules::default()
+            .defer("*@example.com")
+            .bounce("bounce@*");
+
+        assert_eq!(
+            rules.check(&address("bounce@example.com")),
+            Some(SmtpReply::NO_SUCH_USER)
+        );
+    }
+
+    #[test]
+    fn test_default_accepts_everyone() {
+        let rules = BounceRules::default();
+
+        assert!(rules.is_empty());
+        assert_eq!(rules.check(&address("bounce@example.com")), None);
+    }
+}
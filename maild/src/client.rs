@@ -0,0 +1,307 @@
+use crate::email::NewEmail;
+use remail_smtp::line::split_lines;
+use remail_smtp::mailbox::MailboxAddress;
+use std::fmt;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+#[derive(Debug)]
+pub enum SmtpClientError {
+    Io(std::io::Error),
+    /// The server answered a command with an unexpected reply code.
+    UnexpectedReply {
+        code: u16,
+        message: String,
+    },
+}
+
+impl fmt::Display for SmtpClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmtpClientError::Io(e) => write!(f, "I/O error: {e}"),
+            SmtpClientError::UnexpectedReply { code, message } => write!(f, "{code} {message}"),
+        }
+    }
+}
+
+impl std::error::Error for SmtpClientError {}
+
+impl From<std::io::Error> for SmtpClientError {
+    fn from(e: std::io::Error) -> Self {
+        SmtpClientError::Io(e)
+    }
+}
+
+/// A minimal SMTP client delivering one message per connection.
+pub struct SmtpClient<S> {
+    stream: BufReader<S>,
+}
+
+impl SmtpClient<TcpStream> {
+    pub async fn connect(host: &str, port: u16) -> Result<Self, SmtpClientError> {
+        let stream = TcpStream::connect((host, port)).await?;
+        Self::from_stream(stream).await
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> SmtpClient<S> {
+    /// Wraps an established connection, waiting for the server's greeting.
+    pub async fn from_stream(stream: S) -> Result<Self, SmtpClientError> {
+        let mut client = Self {
+            stream: BufReader::new(stream),
+        };
+        client.expect(220).await?;
+        Ok(client)
+    }
+
+    /// Sends `email` to its recipient and closes the connection.
+    pub async fn send(self, email: &NewEmail) -> Result<(), SmtpClientError> {
+        self.send_to(email, std::slice::from_ref(&email.to)).await
+    }
+
+    /// Sends `email` to every address in `recipients` in a single transaction and closes the
+    /// connection. Nothing is delivered unless the server accepts all of them.
+    pub async fn send_to(
+        mut self,
+        email: &NewEmail,
+        recipients: &[MailboxAddress],
+    ) -> Result<(), SmtpClientError> {
+        self.command("EHLO remail\r\n", 250).await?;
+        // RFC 6531: addresses that are not ASCII may only be sent in an SMTPUTF8 transaction.
+        let smtputf8 = if email.from.is_ascii() && recipients.iter().all(MailboxAddress::is_ascii) {
+            ""
+        } else {
+            " SMTPUTF8"
+        };
+        // RFC 6152: a message that is not 7-bit has to be declared, and is sent as it was
+        // received rather than lossily decoded.
+        let message = email.to_rfc5322_bytes();
+        let body = if message.is_ascii() {
+            ""
+        } else {
+            " BODY=8BITMIME"
+        };
+        self.command(
+            &format!("MAIL FROM:<{}>{body}{smtputf8}\r\n", email.from),
+            250,
+        )
+        .await?;
+        for recipient in recipients {
+            self.command(&format!("RCPT TO:<{recipient}>\r\n"), 250)
+                .await?;
+        }
+        self.command("DATA\r\n", 354).await?;
+
+        let mut data = Vec::with_capacity(message.len());
+        for line in split_lines(&message) {
+            // Section 4.5.2 of RFC 5321: lines starting with a dot get an extra one so they
+            // are not mistaken for the end of data.
+            if line.starts_with(b".") {
+                data.push(b'.');
+            }
+            data.extend_from_slice(&line);
+            data.extend_from_slice(b"\r\n");
+        }
+        data.extend_from_slice(b".\r\n");
+        self.stream.get_mut().write_all(&data).await?;
+        self.expect(250).await?;
+
+        // The message is accepted at this point, so a server that hangs up instead of
+        // answering QUIT is not an error.
+        let _ = self.command("QUIT\r\n", 221).await;
+        Ok(())
+    }
+
+    async fn command(&mut self, command: &str, expected: u16) -> Result<(), SmtpClientError> {
+        self.stream.get_mut().write_all(command.as_bytes()).await?;
+        self.expect(expected).await
+    }
+
+    /// Reads a possibly multi-line reply and checks its code.
+    async fn expect(&mut self, expected: u16) -> Result<(), SmtpClientError> {
+        let mut message = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+            let line = line.trim_end();
+            let code = line
+                .get(..3)
+                .and_then(|code| code.parse().ok())
+                .ok_or_else(|| SmtpClientError::UnexpectedReply {
+                    code: 0,
+                    message: line.to_string(),
+                })?;
+            message.push(line.get(4..).unwrap_or_default().to_string());
+
+            // `250-` continues a multi-line reply, `250 ` ends it.
+            if line.as_bytes().get(3) != Some(&b'-') {
+                if code == expected {
+                    return Ok(());
+                }
+                return Err(SmtpClientError::UnexpectedReply {
+                    code,
+                    message: message.join(" "),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::SmtpHandler;
+    use crate::persistor::tests::RecordingPersistor;
+    use crate::persistor::{PersistResult, SmtpPersistor, SqlxPersistor};
+    use email_address::EmailAddress;
+
+    fn email() -> NewEmail {
+        NewEmail {
+            from: EmailAddress::new_unchecked("sender@example.com").into(),
+            to: EmailAddress::new_unchecked("recipient@example.com").into(),
+            subject: "Hello".to_string(),
+            date: None,
+            message_id: None,
+            in_reply_to: None,
+            references: Vec::new(),
+            cc: Vec::new(),
+            reply_to: None,
+            headers: vec![("Subject".to_string(), "Hello".to_string())].into(),
+            body: "Hi!\r\n.dotted\r\n".to_string(),
+            raw_body: None,
+            body_bytes: 14,
+            body_lines: 2,
+            received_bytes: 0,
+            preview: "Hi! .dotted".to_string(),
+            dkim_result: None,
+            auth_results: Vec::new(),
+            helo_domain: None,
+            envelope_to: Vec::new(),
+        }
+    }
+
+    /// Sends `email` to an [`SmtpHandler`], returning what it received.
+    async fn send_to_handler(email: &NewEmail) -> Vec<NewEmail> {
+        let (client_stream, server_stream) = tokio::io::duplex(64 * 1024);
+        let (read_stream, write_stream) = tokio::io::split(server_stream);
+        let persistor = RecordingPersistor::default();
+        let server =
+            tokio::spawn(SmtpHandler::new(write_stream, persistor.clone()).handle(read_stream));
+
+        SmtpClient::from_stream(client_stream)
+            .await
+            .unwrap()
+            .send(email)
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        persistor.emails()
+    }
+
+    #[tokio::test]
+    async fn test_send_to_handler() {
+        assert_eq!(
+            send_to_handler(&email()).await,
+            vec![NewEmail {
+                helo_domain: Some("remail".to_string()),
+                envelope_to: vec![EmailAddress::new_unchecked("recipient@example.com").into()],
+                received_bytes: 32,
+                ..email()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_utf8_addresses() {
+        // The handler refuses these addresses unless the client declares SMTPUTF8.
+        let email = NewEmail {
+            from: "отправитель@пример.рф".parse().unwrap(),
+            to: "用户@例子.广告".parse().unwrap(),
+            ..email()
+        };
+        assert_eq!(
+            send_to_handler(&email).await,
+            vec![NewEmail {
+                helo_domain: Some("remail".to_string()),
+                envelope_to: vec![email.to.clone()],
+                received_bytes: 32,
+                ..email
+            }]
+        );
+    }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_replay_stored_email(db: sqlx::Pool<sqlx::Postgres>) {
+        let persistor = SqlxPersistor::new(db);
+        let PersistResult::Inserted(id) = persistor.persist_email(&email()).await.unwrap() else {
+            panic!("expected the email to be inserted");
+        };
+        let stored = persistor.load_email(id).await.unwrap().unwrap();
+        assert_eq!(stored.subject, "Hello");
+        assert_eq!(stored.body, "Hi!\r\n.dotted\r\n");
+        assert!(stored.message_id.is_some());
+
+        assert_eq!(
+            send_to_handler(&stored).await,
+            vec![NewEmail {
+                helo_domain: Some("remail".to_string()),
+                envelope_to: vec![EmailAddress::new_unchecked("recipient@example.com").into()],
+                received_bytes: stored.to_rfc5322_bytes().len(),
+                ..stored
+            }]
+        );
+    }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_replay_stored_8bit_email(db: sqlx::Pool<sqlx::Postgres>) {
+        let email = NewEmail::from_raw_bytes(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            vec![
+                b"Subject: Hello".to_vec(),
+                Vec::new(),
+                b"caf\xe9".to_vec(),
+                b".na\xefve".to_vec(),
+            ],
+        );
+        let persistor = SqlxPersistor::new(db);
+        let PersistResult::Inserted(id) = persistor.persist_email(&email).await.unwrap() else {
+            panic!("expected the email to be inserted");
+        };
+        let stored = persistor.load_email(id).await.unwrap().unwrap();
+
+        let received = send_to_handler(&stored).await;
+        assert_eq!(received.len(), 1);
+        assert_eq!(
+            received[0].raw_body.as_deref(),
+            Some(&b"caf\xe9\r\n.na\xefve\r\n"[..])
+        );
+        assert_eq!(received[0].to_rfc5322_bytes(), stored.to_rfc5322_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_reply() {
+        let (client_stream, mut server_stream) = tokio::io::duplex(1024);
+        server_stream
+            .write_all(b"220-smtp.example.com\r\n220 ready\r\n550 5.1.1 Mailbox unavailable\r\n")
+            .await
+            .unwrap();
+
+        let result = SmtpClient::from_stream(client_stream)
+            .await
+            .unwrap()
+            .send(&email())
+            .await;
+
+        match result {
+            Err(SmtpClientError::UnexpectedReply { code, message }) => {
+                assert_eq!(code, 550);
+                assert_eq!(message, "5.1.1 Mailbox unavailable");
+            }
+            other => panic!("expected an unexpected reply, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,366 @@
+use crate::auth::AuthResult;
+use crate::auth::dns::{DnsError, DnsResolver};
+use crate::email::NewEmail;
+use async_trait::async_trait;
+use mail_auth::common::parse::TxtRecordParser;
+use mail_auth::common::verify::{DomainKey, VerifySignature};
+use mail_auth::hickory_resolver::config::{ResolverConfig, ResolverOpts};
+use mail_auth::hickory_resolver::proto::op::ResponseCode;
+use mail_auth::{AuthenticatedMessage, MessageAuthenticator, Parameters, ResolverCache, Txt};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DkimResult {
+    Pass,
+    Fail(String),
+    None,
+}
+
+impl fmt::Display for DkimResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DkimResult::Pass => write!(f, "pass"),
+            DkimResult::Fail(reason) => write!(f, "fail ({reason})"),
+            DkimResult::None => write!(f, "none"),
+        }
+    }
+}
+
+impl DkimResult {
+    /// The result as `Authentication-Results` reports it, naming the signing domain and
+    /// selector of `email`'s signature when it has one.
+    pub fn to_auth_result(&self, email: &NewEmail) -> AuthResult {
+        let result = match self {
+            DkimResult::Pass => AuthResult::new("dkim", "pass"),
+            DkimResult::Fail(reason) => AuthResult::new("dkim", "fail").with_reason(reason),
+            DkimResult::None => AuthResult::new("dkim", "none"),
+        };
+        match email
+            .headers
+            .get_all("DKIM-Signature")
+            .find_map(DkimSignature::parse)
+        {
+            Some(signature) => result
+                .with_property("header.d", signature.domain)
+                .with_property("header.s", signature.selector),
+            None => result,
+        }
+    }
+}
+
+/// What the handler does with a message whose DKIM signature does not verify.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DkimPolicy {
+    /// Store the verification result but accept the message anyway.
+    Monitor,
+    /// Reject the message with a 550 reply.
+    Reject,
+}
+
+impl FromStr for DkimPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "monitor" => Ok(DkimPolicy::Monitor),
+            "reject" => Ok(DkimPolicy::Reject),
+            other => Err(format!("unknown DKIM policy: {other}")),
+        }
+    }
+}
+
+#[async_trait]
+pub trait DkimVerifier: Send + Sync {
+    /// Verifies the signatures of `raw_message`, the message exactly as it was received, which
+    /// `email` was parsed from. Signatures cover the original bytes, so they are checked
+    /// against those rather than a message rebuilt from `email`.
+    async fn verify(&self, email: &NewEmail, raw_message: &[u8]) -> DkimResult;
+}
+
+/// The tags of a `DKIM-Signature` header needed to locate the signing key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DkimSignature {
+    pub domain: String,
+    pub selector: String,
+    pub signature: String,
+}
+
+impl DkimSignature {
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut domain = None;
+        let mut selector = None;
+        let mut signature = None;
+
+        for tag in value.split(';') {
+            let Some((name, value)) = tag.split_once('=') else {
+                continue;
+            };
+            // Tag values may be folded over several lines, so all whitespace is dropped.
+            let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+            match name.trim() {
+                "d" => domain = Some(value),
+                "s" => selector = Some(value),
+                "b" => signature = Some(value),
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            domain: domain.filter(|d| !d.is_empty())?,
+            selector: selector.filter(|s| !s.is_empty())?,
+            signature: signature.filter(|b| !b.is_empty())?,
+        })
+    }
+
+    /// The DNS name holding the public key, `<selector>._domainkey.<domain>`.
+    pub fn key_name(&self) -> String {
+        format!("{}._domainkey.{}", self.selector, self.domain)
+    }
+}
+
+/// Verifies signatures with `mail-auth`, looking keys up through a [`DnsResolver`].
+pub struct MailAuthDkimVerifier {
+    resolver: Arc<dyn DnsResolver>,
+    /// Never queries a nameserver itself: the keys of a message's signatures are looked up
+    /// through `resolver` beforehand and handed to it in a [`KeyCache`].
+    authenticator: MessageAuthenticator,
+}
+
+impl MailAuthDkimVerifier {
+    pub fn new(resolver: Arc<dyn DnsResolver>) -> Result<Self, Box<dyn std::error::Error>> {
+        let config = ResolverConfig::from_parts(None, Vec::new(), Vec::new());
+        Ok(Self {
+            resolver,
+            authenticator: MessageAuthenticator::new(config, ResolverOpts::default())?,
+        })
+    }
+
+    /// The key record at `name`, or the error `mail-auth` reports for it.
+    async fn domain_key(&self, name: &str) -> Txt {
+        let records = match self.resolver.txt(name).await {
+            Ok(records) => records,
+            Err(DnsError::NotFound) => {
+                return Txt::Error(mail_auth::Error::DnsRecordNotFound(ResponseCode::NXDomain));
+            }
+            Err(e) => return Txt::Error(mail_auth::Error::DnsError(e.to_string())),
+        };
+        // As `mail-auth` does itself, the first record that parses is used.
+        let mut key = Txt::Error(mail_auth::Error::InvalidRecordType);
+        for record in records {
+            match DomainKey::parse(record.as_bytes()) {
+                Ok(parsed) => return Txt::DomainKey(Arc::new(parsed)),
+                Err(e) => key = Txt::Error(e),
+            }
+        }
+        key
+    }
+}
+
+/// The keys of one message's signatures, keyed by their fully qualified, lowercase name as
+/// `mail-auth` asks for them.
+struct KeyCache(HashMap<String, Txt>);
+
+impl ResolverCache<String, Txt> for KeyCache {
+    fn get<Q>(&self, name: &Q) -> Option<Txt>
+    where
+        String: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.0.get(name).cloned()
+    }
+
+    fn remove<Q>(&self, _name: &Q) -> Option<Txt>
+    where
+        String: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        None
+    }
+
+    fn insert(&self, _key: String, _value: Txt, _valid_until: Instant) {}
+}
+
+#[async_trait]
+impl DkimVerifier for MailAuthDkimVerifier {
+    async fn verify(&self, email: &NewEmail, raw_message: &[u8]) -> DkimResult {
+        // Malformed signatures are skipped, as though they were not there.
+        let signatures: Vec<DkimSignature> = email
+            .headers
+            .get_all("DKIM-Signature")
+            .filter_map(DkimSignature::parse)
+            .collect();
+
+        if signatures.is_empty() {
+            return DkimResult::None;
+        }
+
+        let Some(message) = AuthenticatedMessage::parse(raw_message) else {
+            return DkimResult::Fail("unable to parse message".to_string());
+        };
+
+        let mut keys = HashMap::new();
+        for signature in message
+            .dkim_headers
+            .iter()
+            .filter_map(|h| h.header.as_ref().ok())
+        {
+            if let Entry::Vacant(entry) = keys.entry(signature.domain_key().to_lowercase()) {
+                let key = self.domain_key(entry.key()).await;
+                entry.insert(key);
+            }
+        }
+        let cache = KeyCache(keys);
+
+        let outputs = self
+            .authenticator
+            .verify_dkim(Parameters::new(&message).with_txt_cache(&cache))
+            .await;
+        if outputs
+            .iter()
+            .any(|output| output.result() == &mail_auth::DkimResult::Pass)
+        {
+            return DkimResult::Pass;
+        }
+
+        let reason = outputs
+            .iter()
+            .find(|output| output.signature().is_some())
+            .map(|output| match output.result() {
+                mail_auth::DkimResult::Fail(err) => err.to_string(),
+                mail_auth::DkimResult::PermError(err) => format!("permanent error: {err}"),
+                mail_auth::DkimResult::TempError(err) => format!("temporary error: {err}"),
+                mail_auth::DkimResult::Neutral(err) => format!("neutral: {err}"),
+                mail_auth::DkimResult::Pass => "pass".to_string(),
+                mail_auth::DkimResult::None => "none".to_string(),
+            })
+            .unwrap_or_else(|| "no verifiable signature".to_string());
+        let keys = signatures
+            .iter()
+            .map(DkimSignature::key_name)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        DkimResult::Fail(format!("{keys}: {reason}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::dns::tests::StubResolver;
+    use email_address::EmailAddress;
+
+    const KEY_NAME: &str = "test._domainkey.example.com";
+
+    /// The message in `fixtures/dkim-signed.eml`, signed with the key in
+    /// `fixtures/dkim-key.txt`.
+    const SIGNED: &str = include_str!("../fixtures/dkim-signed.eml");
+
+    async fn verify(message: &str, resolver: StubResolver) -> DkimResult {
+        let raw_message = message.replace('\n', "\r\n");
+        let email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("alice@example.com"),
+            EmailAddress::new_unchecked("bob@example.org"),
+            message.lines().map(str::to_string).collect(),
+        );
+        MailAuthDkimVerifier::new(Arc::new(resolver))
+            .unwrap()
+            .verify(&email, raw_message.as_bytes())
+            .await
+    }
+
+    fn resolver() -> StubResolver {
+        StubResolver::default().with_txt(KEY_NAME, include_str!("../fixtures/dkim-key.txt").trim())
+    }
+
+    #[tokio::test]
+    async fn test_verify_signed_message() {
+        assert_eq!(verify(SIGNED, resolver()).await, DkimResult::Pass);
+    }
+
+    #[tokio::test]
+    async fn test_verify_tampered_message() {
+        for (from, to) in [
+            ("Second\tline", "Second line!"),
+            ("Subject: DKIM  test", "Subject: DKIM test!"),
+            ("From: Alice", "From: Mallory"),
+        ] {
+            let result = verify(&SIGNED.replace(from, to), resolver()).await;
+            assert!(
+                matches!(&result, DkimResult::Fail(reason) if reason.starts_with(KEY_NAME)),
+                "{to}: {result:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_key_lookup_failures() {
+        for resolver in [
+            StubResolver::default(),
+            StubResolver::default().with_failure(KEY_NAME),
+            StubResolver::default().with_txt(KEY_NAME, "v=DKIM1; k=rsa; p=bm90IGEga2V5"),
+        ] {
+            let result = verify(SIGNED, resolver).await;
+            assert!(matches!(result, DkimResult::Fail(_)), "{result:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_skips_malformed_signatures() {
+        let malformed = format!("DKIM-Signature: v=1; d=example.com; b=dGVzdA==\n{SIGNED}");
+        assert_eq!(verify(&malformed, resolver()).await, DkimResult::Pass);
+        let email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("alice@example.com"),
+            EmailAddress::new_unchecked("bob@example.org"),
+            malformed.lines().map(str::to_string).collect(),
+        );
+        assert_eq!(
+            DkimResult::Pass.to_auth_result(&email).properties,
+            [
+                ("header.d".to_string(), "example.com".to_string()),
+                ("header.s".to_string(), "test".to_string())
+            ]
+        );
+
+        let unsigned =
+            "DKIM-Signature: v=1; d=example.com; b=dGVzdA==\nFrom: alice@example.com\n\nHi\n";
+        assert_eq!(verify(unsigned, resolver()).await, DkimResult::None);
+    }
+
+    #[test]
+    fn test_parse_dkim_signature() {
+        let signature = DkimSignature::parse(
+            "v=1; a=rsa-sha256; c=relaxed/relaxed; d=example.com; s=selector1;\n h=from:to:subject; bh=abc=; b=dGVz\n dA==",
+        )
+        .unwrap();
+
+        assert_eq!(signature.domain, "example.com");
+        assert_eq!(signature.selector, "selector1");
+        assert_eq!(signature.signature, "dGVzdA==");
+        assert_eq!(signature.key_name(), "selector1._domainkey.example.com");
+    }
+
+    #[test]
+    fn test_parse_dkim_signature_missing_tags() {
+        assert_eq!(DkimSignature::parse("v=1; d=example.com; b=dGVzdA=="), None);
+        assert_eq!(
+            DkimSignature::parse("v=1; d=example.com; s=; b=dGVzdA=="),
+            None
+        );
+        assert_eq!(DkimSignature::parse(""), None);
+    }
+
+    #[test]
+    fn test_dkim_policy_from_str() {
+        assert_eq!("monitor".parse(), Ok(DkimPolicy::Monitor));
+        assert_eq!("REJECT".parse(), Ok(DkimPolicy::Reject));
+        assert!("bogus".parse::<DkimPolicy>().is_err());
+    }
+}
@@ -1,55 +1,682 @@
+use crate::address::parse_mailboxes;
+use crate::preview;
+use chrono::{DateTime, FixedOffset};
 use email_address::EmailAddress;
+use remail_smtp::headers::{HeaderLine, HeaderParser};
+use remail_smtp::mailbox::MailboxAddress;
+use remail_smtp::{encoded_word, transfer_encoding};
+use remail_types::Headers;
 use serde::Serialize;
+use std::fmt;
+use uuid::Uuid;
+
+/// The longest line RFC 5322 section 2.1.1 allows, excluding the line break.
+pub const MAX_LINE_BYTES: usize = 998;
+
+/// A Message-ID, such as `<1234@example.com>`, which identifies a message for deduplication
+/// and threading.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct MessageId(String);
+
+impl MessageId {
+    /// Accepts anything shaped like `<left@right>` with neither side empty. The check is loose
+    /// since senders disagree on what RFC 5322 allows on either side of the `@`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (left, right) = value
+            .strip_prefix('<')?
+            .strip_suffix('>')?
+            .split_once('@')?;
+        let valid = |part: &str| {
+            !part.is_empty() && !part.contains(|c: char| c.is_whitespace() || "<>".contains(c))
+        };
+        (valid(left) && valid(right)).then(|| Self(value.to_string()))
+    }
+
+    /// The valid Message-IDs in a list such as a References header, in order. Whatever lies
+    /// between them, such as comments, is skipped.
+    pub fn parse_list(value: &str) -> Vec<Self> {
+        value
+            .split('<')
+            .skip(1)
+            .filter_map(|part| Self::parse(&format!("<{}>", part.split('>').next()?)))
+            .collect()
+    }
+
+    /// A new Message-ID, `<id@hostname>`, for a message that arrived without one.
+    pub fn generate(id: Uuid, hostname: &str) -> Self {
+        Self(format!("<{id}@{hostname}>"))
+    }
+}
+
+impl std::ops::Deref for MessageId {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
 
 #[derive(Debug, Serialize, Clone, PartialEq)]
 pub struct NewEmail {
-    pub from: EmailAddress,
-    pub to: EmailAddress,
+    pub from: MailboxAddress,
+    pub to: MailboxAddress,
+    /// The recipients accepted by RCPT TO, which a BCC delivery leaves out of the headers.
+    /// Empty when the email did not arrive over SMTP.
+    pub envelope_to: Vec<MailboxAddress>,
+    /// The Subject header, cut to [`MAX_LINE_BYTES`]. The full value stays in `headers`.
     pub subject: String,
-    pub headers: Vec<(String, String)>,
+    /// The Date header, unless it is missing or malformed.
+    pub date: Option<DateTime<FixedOffset>>,
+    /// The Message-ID header, if the message carried a valid one. Stored emails always have
+    /// one, see [`NewEmail::set_message_id`].
+    pub message_id: Option<MessageId>,
+    /// The first valid Message-ID of the In-Reply-To header.
+    pub in_reply_to: Option<MessageId>,
+    /// The Message-IDs of the References header, oldest ancestor first.
+    pub references: Vec<MessageId>,
+    /// Every valid address in the Cc headers.
+    pub cc: Vec<EmailAddress>,
+    /// The first valid address in the Reply-To header.
+    pub reply_to: Option<EmailAddress>,
+    pub headers: Headers,
+    /// The body as text. A single-part text body is decoded from its Content-Transfer-Encoding
+    /// and charset; bytes that still do not decode are replaced, see `raw_body`.
     pub body: String,
+    /// The exact bytes of the body when `body` differs from them, because it was decoded or is
+    /// not valid UTF-8, as 8BITMIME allows.
+    pub raw_body: Option<Vec<u8>>,
+    /// The size of the received body in bytes, line breaks included.
+    pub body_bytes: u64,
+    /// The number of lines in `body`.
+    pub body_lines: u64,
+    /// The size of the message as received during DATA or BDAT, headers and line endings
+    /// included and dot-stuffing excluded. 0 when the email did not arrive over SMTP.
+    pub received_bytes: usize,
+    /// A short plain-text summary of the body for list views, see [`crate::preview`].
+    pub preview: String,
+    pub dkim_result: Option<String>,
+    /// The `(method, result)` pair of each check a [`MessageAnalyzer`] ran, such as
+    /// `("dkim", "pass")`.
+    ///
+    /// [`MessageAnalyzer`]: crate::auth::MessageAnalyzer
+    pub auth_results: Vec<(String, String)>,
+    /// The hostname the client announced in HELO/EHLO.
+    pub helo_domain: Option<String>,
 }
 
 impl NewEmail {
-    pub fn from_raw_message(from: EmailAddress, to: EmailAddress, body_lines: Vec<String>) -> Self {
-        let mut headers = Vec::new();
-        let mut body = String::new();
-        let mut parsing_headers = true;
-        for line in body_lines {
-            if parsing_headers {
-                if line.is_empty() {
-                    parsing_headers = false;
-                    continue;
-                }
+    pub fn from_raw_message(
+        from: impl Into<MailboxAddress>,
+        to: impl Into<MailboxAddress>,
+        body_lines: Vec<String>,
+    ) -> Self {
+        let body_lines = body_lines.into_iter().map(String::into_bytes).collect();
+        Self::from_raw_bytes(from, to, body_lines)
+    }
 
-                if let Some((key, value)) = line.split_once(':') {
-                    headers.push((key.trim().to_string(), value.trim().to_string()));
-                } else {
-                    // If the line doesn't contain a colon, treat it as a continuation of the previous header
-                    if let Some(last_header) = headers.last_mut() {
-                        last_header.1.push_str(&format!("\n{line}"));
-                    } else {
-                        // If there are no headers yet, just push the line as a header
-                        headers.push((line.to_string(), String::new()));
+    /// Like [`NewEmail::from_raw_message`], for lines that may not be valid UTF-8. Header lines
+    /// are decoded lossily; the body's bytes are kept in `raw_body` when they do not decode.
+    pub fn from_raw_bytes(
+        from: impl Into<MailboxAddress>,
+        to: impl Into<MailboxAddress>,
+        body_lines: Vec<Vec<u8>>,
+    ) -> Self {
+        let mut parser = HeaderParser::new();
+        let mut headers = Headers::new();
+        let mut body = Vec::new();
+        for line in body_lines {
+            if !parser.is_done() {
+                match parser.push_line(&String::from_utf8_lossy(&line)) {
+                    HeaderLine::Header(header) => {
+                        headers.extend(header);
+                        continue;
+                    }
+                    HeaderLine::End(header) => {
+                        headers.extend(header);
+                        // Without a blank line after the headers, this line starts the body.
+                        if line.is_empty() {
+                            continue;
+                        }
                     }
                 }
-            } else {
-                body.push_str(&line);
-                body.push_str("\r\n");
             }
+            body.extend_from_slice(&line);
+            body.extend_from_slice(b"\r\n");
         }
+        headers.extend(parser.finish());
+        let body_bytes = body.len() as u64;
+        let text = decode_body(&headers, &body);
+        let raw_body = (text.as_bytes() != body).then_some(body);
+        let body = text;
+        let preview = preview::preview(&headers, &body);
 
-        let subject = headers
-            .iter()
-            .find(|(key, _)| key.eq_ignore_ascii_case("Subject"))
-            .map_or(String::new(), |(_, value)| value.clone());
+        // The header keeps its raw form, while the subject is decoded for display.
+        let subject = encoded_word::decode(headers.get("Subject").unwrap_or_default());
+        let subject = subject[..subject.floor_char_boundary(MAX_LINE_BYTES)].to_string();
 
         Self {
-            from,
-            to,
+            from: from.into(),
+            to: to.into(),
+            envelope_to: Vec::new(),
             subject,
+            date: headers.get("Date").and_then(parse_date),
+            message_id: headers.get("Message-ID").and_then(MessageId::parse),
+            in_reply_to: headers
+                .get("In-Reply-To")
+                .and_then(|value| MessageId::parse_list(value).into_iter().next()),
+            references: headers
+                .get("References")
+                .map(MessageId::parse_list)
+                .unwrap_or_default(),
+            cc: headers.get_all("Cc").flat_map(parse_mailboxes).collect(),
+            reply_to: headers
+                .get("Reply-To")
+                .and_then(|value| parse_mailboxes(value).into_iter().next()),
             headers,
+            body_bytes,
+            received_bytes: 0,
+            body_lines: body.lines().count() as u64,
+            preview,
             body,
+            raw_body,
+            dkim_result: None,
+            auth_results: Vec::new(),
+            helo_domain: None,
+        }
+    }
+
+    /// Sets the Message-ID, replacing the header so the new value is stored and reconstructed
+    /// with the rest.
+    pub fn set_message_id(&mut self, message_id: MessageId) {
+        self.headers.remove("Message-ID");
+        self.headers.insert("Message-ID", message_id.to_string());
+        self.message_id = Some(message_id);
+    }
+
+    /// Renders the message back into RFC 5322 form, folding multi-line header values. Bytes of
+    /// the body that are not valid UTF-8 are replaced.
+    pub fn to_rfc5322(&self) -> String {
+        String::from_utf8_lossy(&self.to_rfc5322_bytes()).into_owned()
+    }
+
+    /// Like [`NewEmail::to_rfc5322`], with the body's exact bytes.
+    pub fn to_rfc5322_bytes(&self) -> Vec<u8> {
+        let mut raw = self.header_section().into_bytes();
+        match &self.raw_body {
+            Some(body) => raw.extend_from_slice(body),
+            None => raw.extend_from_slice(self.body.as_bytes()),
+        }
+        raw
+    }
+
+    /// The headers followed by the blank line separating them from the body.
+    fn header_section(&self) -> String {
+        let mut raw = String::new();
+        for (key, value) in &self.headers {
+            raw.push_str(key);
+            raw.push_str(": ");
+            raw.push_str(&value.replace('\n', "\r\n "));
+            raw.push_str("\r\n");
         }
+        raw.push_str("\r\n");
+        raw
+    }
+}
+
+/// The text of a message body. A body whose Content-Type is `text/*`, or missing, is decoded
+/// from its Content-Transfer-Encoding and charset; other bodies, such as multipart ones whose
+/// parts carry their own encodings, are kept as they are.
+fn decode_body(headers: &Headers, body: &[u8]) -> String {
+    let content_type = headers.get("Content-Type").unwrap_or_default();
+    let mut params = content_type.split(';');
+    let media_type = params.next().unwrap_or_default().trim();
+    if !media_type.is_empty()
+        && !media_type
+            .get(..5)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("text/"))
+    {
+        return String::from_utf8_lossy(body).into_owned();
+    }
+
+    let encoding = headers.get("Content-Transfer-Encoding").unwrap_or_default();
+    let decoded = transfer_encoding::decode(encoding, body);
+    let charset = params.find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim().eq_ignore_ascii_case("charset").then_some(value)
+    });
+    match charset {
+        Some(charset) => transfer_encoding::decode_charset(charset, &decoded),
+        None => String::from_utf8_lossy(&decoded).into_owned(),
+    }
+}
+
+/// Parses a Date header as RFC 5322 section 3.3 describes, obsolete forms such as two-digit
+/// years and named time zones included.
+///
+/// A day of the week that does not match the date is ignored rather than rejecting the date,
+/// since some senders get it wrong.
+fn parse_date(value: &str) -> Option<DateTime<FixedOffset>> {
+    let value = value.trim();
+    DateTime::parse_from_rfc2822(value).ok().or_else(|| {
+        let (_, date) = value.split_once(',')?;
+        DateTime::parse_from_rfc2822(date.trim()).ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folded_header_with_colon() {
+        let email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            vec![
+                "Received: from mail.example.com".to_string(),
+                " by mx.example.org; Mon, 4 Aug 2025 12:00:01 +0000".to_string(),
+                "Subject: Hello".to_string(),
+                String::new(),
+                "Body".to_string(),
+            ],
+        );
+
+        assert_eq!(
+            email.headers,
+            Headers::from(vec![
+                (
+                    "Received".to_string(),
+                    "from mail.example.com by mx.example.org; Mon, 4 Aug 2025 12:00:01 +0000"
+                        .to_string()
+                ),
+                ("Subject".to_string(), "Hello".to_string()),
+            ])
+        );
+        assert_eq!(
+            email.to_rfc5322(),
+            "Received: from mail.example.com by mx.example.org; Mon, 4 Aug 2025 12:00:01 +0000\r\nSubject: Hello\r\n\r\nBody\r\n"
+        );
+    }
+
+    #[test]
+    fn test_body_stats() {
+        let email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            vec![
+                "Subject: Hello".to_string(),
+                String::new(),
+                "Hi Bob,".to_string(),
+                String::new(),
+                "Grüße".to_string(),
+            ],
+        );
+
+        assert_eq!(email.body, "Hi Bob,\r\n\r\nGrüße\r\n");
+        assert_eq!(email.body_bytes, email.body.len() as u64);
+        assert_eq!(email.body_bytes, 20);
+        assert_eq!(email.body_lines, 3);
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let table = [
+            (
+                "Mon, 4 Aug 2025 12:00:01 +0000",
+                Some("2025-08-04T12:00:01+00:00"),
+            ),
+            (
+                "Mon, 04 Aug 2025 12:00:01 -0700",
+                Some("2025-08-04T12:00:01-07:00"),
+            ),
+            (
+                "4 Aug 2025 12:00:01 +0200",
+                Some("2025-08-04T12:00:01+02:00"),
+            ),
+            (
+                "Mon, 4 Aug 2025 12:00 +0000",
+                Some("2025-08-04T12:00:00+00:00"),
+            ),
+            (
+                "Mon, 4 Aug 25 12:00:01 +0000",
+                Some("2025-08-04T12:00:01+00:00"),
+            ),
+            (
+                "Thu, 1 Jan 98 00:00:00 GMT",
+                Some("1998-01-01T00:00:00+00:00"),
+            ),
+            (
+                "Mon, 4 Aug 2025 12:00:01 EST",
+                Some("2025-08-04T12:00:01-05:00"),
+            ),
+            (
+                "Mon, 4 Aug 2025 12:00:01 PDT",
+                Some("2025-08-04T12:00:01-07:00"),
+            ),
+            (
+                "Mon, 4 Aug 2025 12:00:01 +0000 (UTC)",
+                Some("2025-08-04T12:00:01+00:00"),
+            ),
+            (
+                "Mon,  4 Aug 2025 12:00:01 -0000",
+                Some("2025-08-04T12:00:01+00:00"),
+            ),
+            (
+                "mon, 4 aug 2025 12:00:01 +0000",
+                Some("2025-08-04T12:00:01+00:00"),
+            ),
+            (
+                "Tue, 4 Aug 2025 12:00:01 +0000",
+                Some("2025-08-04T12:00:01+00:00"),
+            ),
+            (
+                " Mon, 4 Aug 2025 12:00:01 +0000 ",
+                Some("2025-08-04T12:00:01+00:00"),
+            ),
+            ("Mon, 4 Aug 2025 12:00:01", None),
+            ("2025-08-04T12:00:01Z", None),
+            ("yesterday", None),
+        ];
+
+        for (value, expected) in table {
+            assert_eq!(
+                parse_date(value).map(|date| date.to_rfc3339()).as_deref(),
+                expected,
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_message_id() {
+        let table = [
+            ("<reply@example.com>", Some("<reply@example.com>")),
+            (" <a.b+c@[127.0.0.1]> ", Some("<a.b+c@[127.0.0.1]>")),
+            ("<local@host@extra>", Some("<local@host@extra>")),
+            ("reply@example.com", None),
+            ("<reply.example.com>", None),
+            ("<@example.com>", None),
+            ("<reply@>", None),
+            ("<re ply@example.com>", None),
+            ("<<reply@example.com>>", None),
+            ("", None),
+        ];
+
+        for (value, expected) in table {
+            assert_eq!(MessageId::parse(value).as_deref(), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn test_parse_message_id_list() {
+        let ids = MessageId::parse_list(
+            "<root@example.com> (the original)\t<bad id@example.com>\n <reply@example.com>",
+        );
+        let ids: Vec<&str> = ids.iter().map(|id| &**id).collect();
+        assert_eq!(ids, ["<root@example.com>", "<reply@example.com>"]);
+        assert!(MessageId::parse_list("root@example.com").is_empty());
+    }
+
+    #[test]
+    fn test_provided_message_id_is_preserved() {
+        let email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            vec![
+                "Message-ID: <original@example.com>".to_string(),
+                String::new(),
+                "Body".to_string(),
+            ],
+        );
+
+        assert_eq!(email.message_id.as_deref(), Some("<original@example.com>"));
+        assert!(
+            email
+                .to_rfc5322()
+                .starts_with("Message-ID: <original@example.com>\r\n")
+        );
+    }
+
+    #[test]
+    fn test_generated_message_id() {
+        let mut email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            vec![
+                "Subject: Hi".to_string(),
+                "Message-ID: not an id".to_string(),
+                String::new(),
+                "Body".to_string(),
+            ],
+        );
+        assert_eq!(email.message_id, None);
+
+        email.set_message_id(MessageId::generate(
+            Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef),
+            "mx.example.com",
+        ));
+
+        let expected = "<01234567-89ab-cdef-0123-456789abcdef@mx.example.com>";
+        assert_eq!(email.message_id.as_deref(), Some(expected));
+        assert_eq!(
+            email.headers.get_all("Message-ID").collect::<Vec<_>>(),
+            [expected]
+        );
+        assert_eq!(
+            email.to_rfc5322(),
+            format!("Subject: Hi\r\nMessage-ID: {expected}\r\n\r\nBody\r\n")
+        );
+    }
+
+    #[test]
+    fn test_typed_headers() {
+        let email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            vec![
+                "Date: Mon, 4 Aug 2025 12:00:01 +0000".to_string(),
+                "Message-ID: <reply@example.com>".to_string(),
+                "In-Reply-To: <original@example.com> (Alice's message)".to_string(),
+                "References: <root@example.com> <original@example.com>".to_string(),
+                "Cc: Alice <alice@example.com>, not an address".to_string(),
+                "CC: bob@example.com".to_string(),
+                "Reply-To: \"Support, Team\" <support@example.com>".to_string(),
+                String::new(),
+                "Body".to_string(),
+            ],
+        );
+
+        assert_eq!(
+            email.date.map(|date| date.to_rfc3339()).as_deref(),
+            Some("2025-08-04T12:00:01+00:00")
+        );
+        assert_eq!(email.message_id.as_deref(), Some("<reply@example.com>"));
+        assert_eq!(email.in_reply_to.as_deref(), Some("<original@example.com>"));
+        assert_eq!(
+            email.references,
+            [
+                MessageId::parse("<root@example.com>").unwrap(),
+                MessageId::parse("<original@example.com>").unwrap(),
+            ]
+        );
+        assert_eq!(
+            email.cc,
+            [
+                EmailAddress::new_unchecked("alice@example.com"),
+                EmailAddress::new_unchecked("bob@example.com"),
+            ]
+        );
+        assert_eq!(
+            email.reply_to,
+            Some(EmailAddress::new_unchecked("support@example.com"))
+        );
+    }
+
+    #[test]
+    fn test_malformed_typed_headers() {
+        let email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            vec![
+                "Date: sometime last week".to_string(),
+                "Message-ID:   ".to_string(),
+                "Reply-To: nobody".to_string(),
+                String::new(),
+                "Body".to_string(),
+            ],
+        );
+
+        assert_eq!(email.date, None);
+        assert_eq!(email.message_id, None);
+        assert_eq!(email.reply_to, None);
+        assert!(email.cc.is_empty());
+        assert_eq!(email.headers.get("Date"), Some("sometime last week"));
+    }
+
+    #[test]
+    fn test_long_subject_is_truncated() {
+        let subject = format!("{}é", "a".repeat(MAX_LINE_BYTES - 1));
+        let email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            vec![format!("Subject: {subject}"), String::new()],
+        );
+
+        assert_eq!(email.subject, "a".repeat(MAX_LINE_BYTES - 1));
+        assert_eq!(email.headers.get("Subject"), Some(subject.as_str()));
+    }
+
+    #[test]
+    fn test_encoded_subject() {
+        let email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            vec![
+                "Subject: =?UTF-8?B?8J+OiSBXZWxjb21lIQ==?=".to_string(),
+                " =?ISO-8859-1?Q?_Andr=E9?=".to_string(),
+                String::new(),
+            ],
+        );
+
+        assert_eq!(email.subject, "🎉 Welcome! André");
+        assert_eq!(
+            email.headers.get("Subject"),
+            Some("=?UTF-8?B?8J+OiSBXZWxjb21lIQ==?= =?ISO-8859-1?Q?_Andr=E9?=")
+        );
+    }
+
+    fn body_with_headers(headers: &[&str], body: &[u8]) -> NewEmail {
+        let mut lines: Vec<Vec<u8>> = headers
+            .iter()
+            .map(|line| line.as_bytes().to_vec())
+            .collect();
+        lines.push(Vec::new());
+        lines.extend(body.split(|&byte| byte == b'\n').map(<[u8]>::to_vec));
+        NewEmail::from_raw_bytes(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            lines,
+        )
+    }
+
+    #[test]
+    fn test_quoted_printable_body() {
+        let raw = b"Gr=FC=DFe aus K=F6ln, a long line that has been wrapped with a soft =\nline break. 1 + 1 =3D 2";
+        let email = body_with_headers(
+            &[
+                "Content-Type: text/plain; charset=\"ISO-8859-1\"",
+                "Content-Transfer-Encoding: quoted-printable",
+            ],
+            raw,
+        );
+
+        assert_eq!(
+            email.body,
+            "Grüße aus Köln, a long line that has been wrapped with a soft line break. 1 + 1 = 2\r\n"
+        );
+        assert_eq!(
+            email.raw_body.as_deref(),
+            Some(&b"Gr=FC=DFe aus K=F6ln, a long line that has been wrapped with a soft =\r\nline break. 1 + 1 =3D 2\r\n"[..])
+        );
+        assert!(
+            email
+                .to_rfc5322_bytes()
+                .ends_with(b"\r\n\r\nGr=FC=DFe aus K=F6ln, a long line that has been wrapped with a soft =\r\nline break. 1 + 1 =3D 2\r\n")
+        );
+    }
+
+    #[test]
+    fn test_base64_body() {
+        // "こんにちは、世界" in Shift_JIS.
+        let email = body_with_headers(
+            &[
+                "Content-Type: text/html; charset=Shift_JIS",
+                "Content-Transfer-Encoding: BASE64",
+            ],
+            b"PHA+grGC8YLJgr+C\nzYFBkKKKRTwvcD4=",
+        );
+
+        assert_eq!(email.body, "<p>こんにちは、世界</p>");
+        assert_eq!(email.body_bytes, 36);
+    }
+
+    #[test]
+    fn test_undecodable_body() {
+        let email = body_with_headers(&["Content-Type: text/plain; charset=utf-8"], b"caf\xe9");
+
+        assert_eq!(email.body, "caf\u{fffd}\r\n");
+        assert_eq!(email.raw_body.as_deref(), Some(&b"caf\xe9\r\n"[..]));
+    }
+
+    #[test]
+    fn test_multipart_body_is_not_decoded() {
+        let body = b"--b1\nContent-Transfer-Encoding: base64\n\nSGk=\n--b1--";
+        let email = body_with_headers(
+            &[
+                "Content-Type: multipart/mixed; boundary=b1",
+                "Content-Transfer-Encoding: 7bit",
+            ],
+            body,
+        );
+
+        assert_eq!(
+            email.body,
+            "--b1\r\nContent-Transfer-Encoding: base64\r\n\r\nSGk=\r\n--b1--\r\n"
+        );
+        assert_eq!(email.raw_body, None);
+    }
+
+    #[test]
+    fn test_8bit_body() {
+        let email = NewEmail::from_raw_bytes(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            vec![
+                b"Subject: Gr\xfc\xdfe".to_vec(),
+                Vec::new(),
+                b"Gr\xfc\xdfe aus K\xf6ln".to_vec(),
+            ],
+        );
+
+        assert_eq!(email.subject, "Gr\u{fffd}\u{fffd}e");
+        assert_eq!(email.body, "Gr\u{fffd}\u{fffd}e aus K\u{fffd}ln\r\n");
+        assert_eq!(
+            email.raw_body.as_deref(),
+            Some(&b"Gr\xfc\xdfe aus K\xf6ln\r\n"[..])
+        );
+        assert_eq!(email.body_bytes, 16);
+        assert!(
+            email
+                .to_rfc5322_bytes()
+                .ends_with(b"\r\n\r\nGr\xfc\xdfe aus K\xf6ln\r\n")
+        );
     }
 }
@@ -0,0 +1,170 @@
+use async_trait::async_trait;
+use remail_smtp::mailbox::MailboxAddress;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::time::Instant;
+
+/// Number of tracked triplets above which those first seen long ago are dropped.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// How long a triplet is remembered once its delay is over, when pruning.
+const RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GreylistResult {
+    Allow,
+    /// The triplet is not trusted yet; the client may retry after the given duration.
+    Defer(Duration),
+    Block,
+}
+
+#[async_trait]
+pub trait Greylister: Send + Sync {
+    async fn check(
+        &self,
+        client_ip: IpAddr,
+        from: &MailboxAddress,
+        to: &MailboxAddress,
+    ) -> GreylistResult;
+}
+
+type Triplet = (IpAddr, String, String);
+
+/// Defers the first delivery attempt of every (client IP, sender, recipient) triplet and
+/// allows retries once `delay` has elapsed since that first attempt.
+///
+/// Once [`PRUNE_THRESHOLD`] triplets are tracked, those whose delay ended more than
+/// [`RETENTION`] ago are forgotten, and greylisted again on their next attempt.
+#[derive(Clone)]
+pub struct InMemoryGreylister {
+    first_seen: Arc<RwLock<HashMap<Triplet, Instant>>>,
+    delay: Duration,
+}
+
+impl Default for InMemoryGreylister {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5 * 60))
+    }
+}
+
+impl InMemoryGreylister {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            first_seen: Arc::new(RwLock::new(HashMap::new())),
+            delay,
+        }
+    }
+}
+
+#[async_trait]
+impl Greylister for InMemoryGreylister {
+    async fn check(
+        &self,
+        client_ip: IpAddr,
+        from: &MailboxAddress,
+        to: &MailboxAddress,
+    ) -> GreylistResult {
+        let triplet = (
+            client_ip,
+            from.as_str().to_lowercase(),
+            to.as_str().to_lowercase(),
+        );
+
+        let mut triplets = self.first_seen.write().await;
+        if triplets.len() >= PRUNE_THRESHOLD {
+            triplets.retain(|_, first_seen| first_seen.elapsed() < self.delay + RETENTION);
+        }
+        let first_seen = *triplets.entry(triplet).or_insert_with(Instant::now);
+        drop(triplets);
+
+        let elapsed = first_seen.elapsed();
+        if elapsed >= self.delay {
+            GreylistResult::Allow
+        } else {
+            GreylistResult::Defer(self.delay - elapsed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addresses() -> (MailboxAddress, MailboxAddress) {
+        (
+            MailboxAddress::parse("sender@example.com").unwrap(),
+            MailboxAddress::parse("recipient@example.com").unwrap(),
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_defers_first_attempt() {
+        let greylister = InMemoryGreylister::default();
+        let (from, to) = addresses();
+        let ip = IpAddr::from([127, 0, 0, 1]);
+
+        assert_eq!(
+            greylister.check(ip, &from, &to).await,
+            GreylistResult::Defer(Duration::from_secs(300))
+        );
+
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert_eq!(
+            greylister.check(ip, &from, &to).await,
+            GreylistResult::Defer(Duration::from_secs(240))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_allows_retry_after_delay() {
+        let greylister = InMemoryGreylister::new(Duration::from_secs(10));
+        let (from, to) = addresses();
+        let ip = IpAddr::from([127, 0, 0, 1]);
+
+        greylister.check(ip, &from, &to).await;
+        tokio::time::advance(Duration::from_secs(10)).await;
+
+        assert_eq!(
+            greylister.check(ip, &from, &to).await,
+            GreylistResult::Allow
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_triplets_are_independent() {
+        let greylister = InMemoryGreylister::new(Duration::from_secs(10));
+        let (from, to) = addresses();
+
+        greylister
+            .check(IpAddr::from([127, 0, 0, 1]), &from, &to)
+            .await;
+        tokio::time::advance(Duration::from_secs(10)).await;
+
+        assert_eq!(
+            greylister
+                .check(IpAddr::from([127, 0, 0, 2]), &from, &to)
+                .await,
+            GreylistResult::Defer(Duration::from_secs(10))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_prunes_old_triplets() {
+        let greylister = InMemoryGreylister::new(Duration::from_secs(10));
+        let (from, _) = addresses();
+        let ip = IpAddr::from([127, 0, 0, 1]);
+
+        for i in 0..PRUNE_THRESHOLD {
+            let to = MailboxAddress::parse(&format!("recipient{i}@example.com")).unwrap();
+            greylister.check(ip, &from, &to).await;
+        }
+        tokio::time::advance(Duration::from_secs(10) + RETENTION).await;
+
+        let (from, to) = addresses();
+        greylister.check(ip, &from, &to).await;
+        assert_eq!(greylister.first_seen.read().await.len(), 1);
+    }
+}
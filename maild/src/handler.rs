@@ -1,23 +1,127 @@
+use crate::auth::{AUTHSERV_ID, AuthResult, MessageAnalyzer, SessionInfo, authentication_results};
+use crate::bounce::BounceRules;
+use crate::dkim::{DkimPolicy, DkimResult, DkimVerifier};
 use crate::email::NewEmail;
-use crate::persistor::SmtpPersistor;
-use email_address::EmailAddress;
-use std::str::FromStr;
-use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use crate::greylist::{GreylistResult, Greylister};
+use crate::persistor::{PersistResult, SmtpPersistor};
+use crate::reply::SmtpReply;
+use crate::spam::{SpamClassification, SpamFilter};
+use remail_smtp::line::split_lines;
+use remail_smtp::mailbox::MailboxAddress;
+use remail_smtp::{
+    BodyType, HeaderGuard, HeaderLimits, MAX_LINE_LENGTH, MailFromParams, RcptToError,
+    parse_mail_from, parse_rcpt_to, strip_command,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use uuid::Uuid;
 
 enum SmtpState {
     Start,
     MailFrom,
     RcptTo,
-    Data,
+    /// A `BDAT` command was accepted, so the next `remaining` bytes are a chunk of the message
+    /// rather than commands. `is_last` when it was `BDAT <size> LAST`.
+    Bdat {
+        remaining: usize,
+        is_last: bool,
+    },
     End,
 }
 
+/// What became of a command, once its checks ran.
+enum Verdict {
+    Accepted,
+    /// The command was refused with the reply, but the session goes on.
+    Refused(SmtpReply),
+    /// The command was refused with the reply, and the connection is closed.
+    Closed(SmtpReply),
+}
+
+/// The commands listed by `HELP`, each with the line `HELP <command>` replies with.
+const COMMANDS: &[(&str, &str)] = &[
+    ("HELO", "HELO <domain>: introduce the client"),
+    ("EHLO", "EHLO <domain>: introduce the client"),
+    ("MAIL", "MAIL FROM:<address>: start a transaction"),
+    ("RCPT", "RCPT TO:<address>: add a recipient"),
+    (
+        "DATA",
+        "DATA: send the message, ending with a line holding only a dot",
+    ),
+    (
+        "BDAT",
+        "BDAT <size> [LAST]: send the next size bytes of the message",
+    ),
+    ("RSET", "RSET: abort the current transaction"),
+    ("NOOP", "NOOP: do nothing"),
+    ("QUIT", "QUIT: close the connection"),
+    ("HELP", "HELP [command]: list the commands or describe one"),
+];
+
+fn help(topic: &str) -> SmtpReply {
+    if topic.is_empty() {
+        let commands: Vec<&str> = COMMANDS.iter().map(|(command, _)| *command).collect();
+        return SmtpReply::multiline(214, ["Supported commands:".to_string(), commands.join(" ")]);
+    }
+    match COMMANDS
+        .iter()
+        .find(|(command, _)| command.eq_ignore_ascii_case(topic))
+    {
+        Some((_, description)) => SmtpReply::new(214, description),
+        None => SmtpReply::UNKNOWN_HELP_TOPIC,
+    }
+}
+
+/// The default ceiling on a message's size, counting each line with its CRLF.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 25 * 1024 * 1024;
+
+/// The most recipients a transaction may have, the minimum RFC 5321 section 4.5.3.1.8 asks
+/// servers to accept.
+const MAX_RECIPIENTS: usize = 100;
+
 pub struct SmtpHandler<P: SmtpPersistor, W: AsyncWrite + Unpin> {
+    /// Identifies this connection in logs and, when debugging, in the greeting.
+    connection_id: Uuid,
+    debug_connection_id: bool,
     persistor: P,
+    dkim_verifier: Option<Arc<dyn DkimVerifier>>,
+    dkim_policy: DkimPolicy,
+    analyzer: Option<Arc<dyn MessageAnalyzer>>,
+    greylister: Option<Arc<dyn Greylister>>,
+    bounce_rules: BounceRules,
+    peer_addr: Option<SocketAddr>,
+    spam_filter: Option<Arc<dyn SpamFilter>>,
+    spam_threshold: f32,
+    header_limits: HeaderLimits,
+    max_body_bytes: usize,
+    /// The longest command or message line accepted, in octets, not counting its line ending.
+    max_line_length: usize,
+    /// Whether `MAIL FROM` is refused until the connection is encrypted.
+    require_tls: bool,
+    tls_active: bool,
 
-    from: EmailAddress,
-    to: EmailAddress,
-    body: Vec<String>,
+    helo_domain: Option<String>,
+    /// The sender, once `MAIL FROM` was accepted.
+    from: Option<MailboxAddress>,
+    /// Whether the client declared `BODY=8BITMIME` for the current transaction.
+    eight_bit_mime: bool,
+    /// Whether the client declared `SMTPUTF8` for the current transaction, which allows
+    /// addresses that are not ASCII.
+    smtputf8: bool,
+    /// The recipients `RCPT TO` accepted, in the order they were given.
+    accepted_recipients: Vec<MailboxAddress>,
+    /// The recipients `RCPT TO` refused, kept to be logged with the delivery. Those that did
+    /// not parse are kept as they were given.
+    rejected_recipients: Vec<String>,
+    body: Vec<Vec<u8>>,
+    /// The chunks received so far by `BDAT`, or `None` when the message is not being sent in
+    /// chunks.
+    chunks: Option<Vec<u8>>,
+    /// The size of the message received so far, line endings included and dot-stuffing
+    /// excluded.
+    body_bytes: usize,
+    header_guard: HeaderGuard,
     write_stream: W,
     state: SmtpState,
 }
@@ -25,39 +129,226 @@ pub struct SmtpHandler<P: SmtpPersistor, W: AsyncWrite + Unpin> {
 impl<P: SmtpPersistor, W: AsyncWrite + Unpin> SmtpHandler<P, W> {
     pub fn new(write_stream: W, persistor: P) -> Self {
         Self {
+            connection_id: Uuid::new_v4(),
+            debug_connection_id: false,
             persistor,
+            dkim_verifier: None,
+            dkim_policy: DkimPolicy::Monitor,
+            analyzer: None,
+            greylister: None,
+            bounce_rules: BounceRules::default(),
+            peer_addr: None,
+            spam_filter: None,
+            spam_threshold: 0.0,
+            header_limits: HeaderLimits::default(),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_line_length: MAX_LINE_LENGTH,
+            require_tls: false,
+            tls_active: false,
 
-            from: EmailAddress::new_unchecked(""),
-            to: EmailAddress::new_unchecked(""),
+            helo_domain: None,
+            from: None,
+            eight_bit_mime: false,
+            smtputf8: false,
+            accepted_recipients: Vec::new(),
+            rejected_recipients: Vec::new(),
             body: Vec::new(),
+            chunks: None,
+            body_bytes: 0,
+            header_guard: HeaderGuard::new(HeaderLimits::default()),
             write_stream,
             state: SmtpState::Start,
         }
     }
 
+    /// Refuses mail on unencrypted connections with a 530. `EHLO` and `STARTTLS` are still
+    /// answered in cleartext.
+    pub fn with_require_tls(mut self, require_tls: bool) -> Self {
+        self.require_tls = require_tls;
+        self
+    }
+
+    /// Marks the connection as already encrypted, as when a TLS-terminating proxy forwards it.
+    pub fn with_tls_active(mut self, tls_active: bool) -> Self {
+        self.tls_active = tls_active;
+        self
+    }
+
+    pub fn connection_id(&self) -> Uuid {
+        self.connection_id
+    }
+
+    /// Appends the connection id to the 220 greeting, so a client's transcript can be matched
+    /// with the server's logs.
+    pub fn with_debug_connection_id(mut self, enabled: bool) -> Self {
+        self.debug_connection_id = enabled;
+        self
+    }
+
+    pub fn with_dkim_verifier(
+        mut self,
+        verifier: Arc<dyn DkimVerifier>,
+        policy: DkimPolicy,
+    ) -> Self {
+        self.dkim_verifier = Some(verifier);
+        self.dkim_policy = policy;
+        self
+    }
+
+    /// Runs every accepted message through `analyzer`, recording its results and adding an
+    /// `Authentication-Results` header, which also reports the DKIM verifier's result. Whatever
+    /// they are, the message is still accepted.
+    pub fn with_analyzer(mut self, analyzer: Arc<dyn MessageAnalyzer>) -> Self {
+        self.analyzer = Some(analyzer);
+        self
+    }
+
+    pub fn with_peer_addr(mut self, peer_addr: SocketAddr) -> Self {
+        self.peer_addr = Some(peer_addr);
+        self
+    }
+
+    /// Greylists transactions by client IP, sender and recipient. Requires the peer address
+    /// to be known, see [`SmtpHandler::with_peer_addr`].
+    pub fn with_greylister(mut self, greylister: Arc<dyn Greylister>) -> Self {
+        self.greylister = Some(greylister);
+        self
+    }
+
+    /// Refuses recipients matching `rules` at RCPT time, to simulate bounces and deferrals.
+    pub fn with_bounce_rules(mut self, rules: BounceRules) -> Self {
+        self.bounce_rules = rules;
+        self
+    }
+
+    /// Runs every accepted message through `spam_filter`, tagging it with an `X-Spam-Status`
+    /// header when it is classified as spam with a confidence above `threshold`.
+    pub fn with_spam_filter(mut self, spam_filter: Arc<dyn SpamFilter>, threshold: f32) -> Self {
+        self.spam_filter = Some(spam_filter);
+        self.spam_threshold = threshold;
+        self
+    }
+
+    /// Rejects messages whose header section exceeds `limits` with a 552 reply.
+    pub fn with_header_limits(mut self, limits: HeaderLimits) -> Self {
+        self.header_limits = limits;
+        self.header_guard = HeaderGuard::new(limits);
+        self
+    }
+
+    /// Rejects messages larger than `max_body_bytes` with a 552 reply, whether or not the
+    /// client declared a size up front.
+    pub fn with_max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Closes the connection with a 500 reply when a line is longer than `max_line_length`,
+    /// 998 octets by default as RFC 5321 section 4.5.3.1.6 requires.
+    pub fn with_max_line_length(mut self, max_line_length: usize) -> Self {
+        self.max_line_length = max_line_length;
+        self
+    }
+
+    fn greeting(&self) -> SmtpReply {
+        if self.debug_connection_id {
+            SmtpReply {
+                text: format!(
+                    "{} (connection {})",
+                    SmtpReply::SERVICE_READY.text,
+                    self.connection_id
+                )
+                .into(),
+                ..SmtpReply::SERVICE_READY
+            }
+        } else {
+            SmtpReply::SERVICE_READY
+        }
+    }
+
+    /// Logs to stderr, tagged with the connection the message is about.
+    fn log(&self, message: std::fmt::Arguments) {
+        eprintln!("[connection {}] {message}", self.connection_id);
+    }
+
     pub async fn handle(mut self, read_stream: impl AsyncRead + Unpin) {
-        if !self.write("220 smt.example.com ESMTP Remail\r\n").await {
+        // Nothing is read before the greeting is written, so commands a client pipelines ahead
+        // of it wait in `read_stream` and are answered in order afterwards.
+        if !self.write(self.greeting()).await {
+            self.log(format_args!(
+                "Closing the connection: the greeting could not be sent"
+            ));
             self.shutdown().await;
             return;
         }
 
-        let mut lines = BufReader::new(read_stream).lines();
+        let mut reader = BufReader::new(read_stream);
+        let mut buf = Vec::new();
 
         loop {
-            let line = lines.next_line().await;
-            match line {
-                Ok(Some(line)) => {
-                    let line = line.trim();
-                    if let Some(success) = self.handle_line(line).await {
+            if let SmtpState::Bdat { remaining, is_last } = self.state {
+                // A chunk is an exact number of bytes, line breaks and dots included. It is read
+                // as it arrives rather than into a buffer of the size the client announced.
+                let mut chunk = Vec::new();
+                let read = (&mut reader)
+                    .take(remaining as u64)
+                    .read_to_end(&mut chunk)
+                    .await
+                    .and_then(|read| {
+                        if read == remaining {
+                            Ok(())
+                        } else {
+                            Err(std::io::ErrorKind::UnexpectedEof.into())
+                        }
+                    });
+                if let Err(e) = read {
+                    self.log(format_args!("Error reading chunk: {e}"));
+                    self.shutdown().await;
+                    return;
+                }
+                if let Some(success) = self.handle_chunk(chunk, is_last).await {
+                    if !success {
+                        self.log(format_args!("Error handling chunk of {remaining} bytes"));
+                    }
+                    break;
+                }
+                continue;
+            }
+
+            buf.clear();
+            // Reading stops past the longest line allowed and its CRLF, so a line without end
+            // is never buffered whole.
+            let limit = self.max_line_length as u64 + 2;
+            match (&mut reader).take(limit).read_until(b'\n', &mut buf).await {
+                Ok(0) => break,
+                Ok(_) => {
+                    let line = buf.strip_suffix(b"\n").unwrap_or(&buf);
+                    let line = line.strip_suffix(b"\r").unwrap_or(line);
+                    let line_ending = buf.len() - line.len();
+                    if line.len() > self.max_line_length {
+                        self.log(format_args!("Closing the connection: line too long"));
+                        self.write(SmtpReply::LINE_TOO_LONG).await;
+                        break;
+                    }
+                    // Message lines are kept as bytes, since 8BITMIME allows them to be any
+                    // encoding, while commands are text.
+                    let result = if matches!(self.state, SmtpState::End) {
+                        self.handle_data_line(line, line_ending).await
+                    } else {
+                        self.handle_line(String::from_utf8_lossy(line).trim()).await
+                    };
+                    if let Some(success) = result {
                         if !success {
-                            eprintln!("Error handling line: {line}");
+                            self.log(format_args!(
+                                "Error handling line: {}",
+                                String::from_utf8_lossy(line)
+                            ));
                         }
                         break;
                     }
                 }
-                Ok(None) => break,
                 Err(e) => {
-                    eprintln!("Error reading line: {e}");
+                    self.log(format_args!("Error reading line: {e}"));
                     self.shutdown().await;
                     return;
                 }
@@ -67,162 +358,575 @@ impl<P: SmtpPersistor, W: AsyncWrite + Unpin> SmtpHandler<P, W> {
         self.shutdown().await;
     }
 
+    /// Runs a whole recorded session through an [`AsyncMessageParser`], so tests can check the
+    /// parser's events against the handler's checks. Commands go through the same checks as in
+    /// [`SmtpHandler::handle`], but there is no greeting, only refusals and the reply to each
+    /// message are written, and lines that are not UTF-8 end the session. Connections are
+    /// served by [`SmtpHandler::handle`].
+    #[cfg(test)]
+    async fn handle_parsed(mut self, read_stream: impl tokio::io::AsyncBufRead + Unpin) {
+        use futures_core::Stream;
+        use remail_smtp::{
+            AsyncMessageParser, MessageParserError, MessageParserEvent, ParserOptions, Spanned,
+        };
+        use std::future::poll_fn;
+        use std::pin::Pin;
+
+        let options = ParserOptions {
+            max_line_len: self.max_line_length,
+            ..ParserOptions::default()
+        };
+        let mut events = AsyncMessageParser::new_streaming(read_stream)
+            .with_options(options)
+            .with_header_limits(self.header_limits);
+
+        while let Some(event) = poll_fn(|cx| Pin::new(&mut events).poll_next(cx)).await {
+            let verdict = match event {
+                Ok(MessageParserEvent::From(_)) if self.tls_required() => {
+                    Verdict::Refused(SmtpReply::TLS_REQUIRED)
+                }
+                Ok(MessageParserEvent::From(_)) => match events.mail_from().cloned() {
+                    Some(params) => self.accept_sender(params),
+                    None => Verdict::Accepted,
+                },
+                // The sender was refused, so there is no transaction to add recipients to.
+                Ok(MessageParserEvent::To(_)) if self.from.is_none() => {
+                    Verdict::Refused(SmtpReply::BAD_SEQUENCE)
+                }
+                Ok(MessageParserEvent::To(to)) => self.accept_recipient(to).await,
+                // Without recipients the message is refused once it ends, so it is not kept.
+                Ok(MessageParserEvent::BodyLine(_)) if self.accepted_recipients.is_empty() => {
+                    Verdict::Accepted
+                }
+                Ok(MessageParserEvent::BodyLine(line)) => {
+                    // The parser drops line endings, so each line is counted with a CRLF.
+                    let received = line.len() + 2;
+                    if self.push_body_line(line.into_bytes(), received) {
+                        Verdict::Accepted
+                    } else {
+                        Verdict::Closed(SmtpReply::MESSAGE_TOO_BIG)
+                    }
+                }
+                Ok(MessageParserEvent::EndOfBody) if self.accepted_recipients.is_empty() => {
+                    self.reset_transaction();
+                    Verdict::Refused(SmtpReply::NO_VALID_RECIPIENTS)
+                }
+                Ok(MessageParserEvent::EndOfBody) => {
+                    if !self.deliver().await {
+                        break;
+                    }
+                    self.reset_transaction();
+                    Verdict::Accepted
+                }
+                Ok(MessageParserEvent::Header(..))
+                | Ok(MessageParserEvent::Body(_))
+                | Ok(MessageParserEvent::Done(_))
+                | Ok(MessageParserEvent::Unknown(_)) => Verdict::Accepted,
+                Err(Spanned {
+                    kind: MessageParserError::InvalidMailFrom(_),
+                    ..
+                }) => Verdict::Closed(SmtpReply::SYNTAX_ERROR),
+                Err(Spanned {
+                    kind: MessageParserError::InvalidRcptTo(e),
+                    ..
+                }) => self.reject_invalid_recipient(e.to_string(), &e),
+                Err(Spanned {
+                    kind: MessageParserError::HeadersTooLarge,
+                    ..
+                }) => Verdict::Closed(SmtpReply::HEADERS_TOO_LARGE),
+                Err(Spanned {
+                    kind: MessageParserError::LineTooLong(_),
+                    ..
+                }) => Verdict::Closed(SmtpReply::LINE_TOO_LONG),
+                Err(e) => {
+                    self.log(format_args!("Error parsing transaction: {e}"));
+                    Verdict::Closed(SmtpReply::UNRECOGNIZED_COMMAND)
+                }
+            };
+            match verdict {
+                Verdict::Accepted => {}
+                Verdict::Refused(reply) => {
+                    if !self.write(reply).await {
+                        break;
+                    }
+                }
+                Verdict::Closed(reply) => {
+                    self.write(reply).await;
+                    break;
+                }
+            }
+        }
+
+        self.shutdown().await;
+    }
+
     async fn shutdown(&mut self) {
         if let Err(e) = self.write_stream.shutdown().await {
-            eprintln!("Error shutting down stream: {e}");
+            self.log(format_args!("Error shutting down stream: {e}"));
         }
     }
 
-    async fn write(&mut self, response: &str) -> bool {
-        self.write_stream
-            .write(response.as_bytes())
-            .await
-            .map(|_| true)
-            .unwrap_or_else(|e| {
-                eprintln!("Error writing to stream: {e}");
+    async fn write(&mut self, reply: SmtpReply) -> bool {
+        match self.write_stream.write(reply.to_string().as_bytes()).await {
+            Ok(_) => true,
+            Err(e) => {
+                self.log(format_args!("Error writing to stream: {e}"));
                 false
-            })
+            }
+        }
+    }
+
+    /// The message as received, with the line endings the SMTP transaction carried it with.
+    fn raw_message(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity(self.body_bytes);
+        for line in &self.body {
+            raw.extend_from_slice(line);
+            raw.extend_from_slice(b"\r\n");
+        }
+        raw
+    }
+
+    /// Runs the received message through DKIM verification, the analyzer and the spam filter,
+    /// persists it and writes the final reply. Returns whether the message was accepted.
+    async fn deliver(&mut self) -> bool {
+        let (Some(from), Some(to)) = (self.from.clone(), self.accepted_recipients.first().cloned())
+        else {
+            self.write(SmtpReply::BAD_SEQUENCE).await;
+            return false;
+        };
+        if !self.rejected_recipients.is_empty() {
+            self.log(format_args!(
+                "Delivering without rejected recipients {}",
+                self.rejected_recipients.join(", ")
+            ));
+        }
+
+        // The bytes are stored as they are either way, but a client sending 8-bit data without
+        // declaring it would have it mangled by a strictly 7-bit server.
+        if !self.eight_bit_mime && self.body.iter().any(|line| !line.is_ascii()) {
+            self.log(format_args!(
+                "8-bit data received without BODY=8BITMIME from {from}"
+            ));
+        }
+        let mut email = NewEmail::from_raw_bytes(from.clone(), to.clone(), self.body.clone());
+        email.received_bytes = self.body_bytes;
+
+        let mut dkim_result = None;
+        if let Some(verifier) = self.dkim_verifier.clone() {
+            let result = verifier.verify(&email, &self.raw_message()).await;
+            if let DkimResult::Fail(reason) = &result {
+                self.log(format_args!("DKIM verification failed: {reason}"));
+                if self.dkim_policy == DkimPolicy::Reject {
+                    self.write(SmtpReply::DKIM_FAILED).await;
+                    return false;
+                }
+            }
+            email.dkim_result = Some(result.to_string());
+            dkim_result = Some(result);
+        }
+
+        email.helo_domain = self.helo_domain.clone();
+        email.envelope_to = self.accepted_recipients.clone();
+        if let Some(analyzer) = self.analyzer.clone() {
+            let session = SessionInfo {
+                peer_ip: self.peer_addr.map(|addr| addr.ip()),
+                helo_domain: self.helo_domain.as_deref(),
+                mail_from: &from,
+            };
+            let mut results: Vec<AuthResult> = dkim_result
+                .iter()
+                .map(|result| result.to_auth_result(&email))
+                .collect();
+            results.extend(analyzer.analyze(&email, &session).await);
+            email.headers.prepend(
+                "Authentication-Results",
+                authentication_results(AUTHSERV_ID, &results),
+            );
+            email.auth_results = results
+                .into_iter()
+                .map(|result| (result.method, result.result))
+                .collect();
+        }
+
+        if let Some(spam_filter) = self.spam_filter.clone()
+            && let SpamClassification::Spam { confidence } = spam_filter.classify(&email).await
+            && confidence > self.spam_threshold
+        {
+            email
+                .headers
+                .insert("X-Spam-Status", format!("Yes, score={confidence:.2}"));
+        }
+
+        match self.persistor.persist_email(&email).await {
+            Ok(PersistResult::Inserted(_)) => {}
+            Ok(PersistResult::Duplicate) => {
+                self.log(format_args!(
+                    "Skipping duplicate email {}",
+                    email.message_id.as_deref().unwrap_or_default()
+                ));
+            }
+            Err(e) => {
+                self.log(format_args!("Error saving email: {e}"));
+                self.write(SmtpReply::INTERNAL_ERROR).await;
+                return false;
+            }
+        }
+
+        self.write(SmtpReply::MESSAGE_ACCEPTED).await
+    }
+
+    /// Forgets the current transaction, keeping the greeting.
+    fn reset_transaction(&mut self) {
+        self.from = None;
+        self.eight_bit_mime = false;
+        self.smtputf8 = false;
+        self.accepted_recipients.clear();
+        self.rejected_recipients.clear();
+        self.body.clear();
+        self.chunks = None;
+        self.body_bytes = 0;
+        self.header_guard = HeaderGuard::new(self.header_limits);
+        if !matches!(self.state, SmtpState::Start) {
+            self.state = SmtpState::MailFrom;
+        }
+    }
+
+    /// Whether `MAIL FROM` must wait for the connection to be encrypted.
+    fn tls_required(&self) -> bool {
+        self.require_tls && !self.tls_active
+    }
+
+    /// Starts a transaction from `MAIL FROM`, unless its parameters are refused.
+    fn accept_sender(&mut self, params: MailFromParams) -> Verdict {
+        let MailFromParams {
+            address,
+            size,
+            body,
+            smtputf8,
+        } = params;
+        // Every stored email has a sender, so the null sender is refused too.
+        let Some(from) = address else {
+            return Verdict::Closed(SmtpReply::SYNTAX_ERROR);
+        };
+        if size.is_some_and(|size| size > self.max_body_bytes) {
+            // Refused before any of it is sent, as RFC 1870 allows. The client may start
+            // another transaction.
+            return Verdict::Refused(SmtpReply::MESSAGE_TOO_BIG);
+        }
+        if !smtputf8 && !from.is_ascii() {
+            return Verdict::Refused(SmtpReply::NON_ASCII_ADDRESS);
+        }
+        self.from = Some(from);
+        self.eight_bit_mime = body == Some(BodyType::EightBitMime);
+        self.smtputf8 = smtputf8;
+        Verdict::Accepted
+    }
+
+    /// Adds a recipient from `RCPT TO` to the transaction, unless it is refused.
+    async fn accept_recipient(&mut self, to: MailboxAddress) -> Verdict {
+        if self.accepted_recipients.len() >= MAX_RECIPIENTS {
+            return Verdict::Refused(SmtpReply::TOO_MANY_RECIPIENTS);
+        }
+        if !self.smtputf8 && !to.is_ascii() {
+            self.rejected_recipients.push(to.to_string());
+            return Verdict::Refused(SmtpReply::NON_ASCII_ADDRESS);
+        }
+        if let Some(reply) = self.bounce_rules.check(&to) {
+            // Only this recipient is refused; the message still goes to the others.
+            self.rejected_recipients.push(to.to_string());
+            return Verdict::Refused(reply);
+        }
+
+        // Recipients are only accepted once MAIL FROM named a sender.
+        if let (Some(greylister), Some(peer_addr), Some(from)) =
+            (self.greylister.clone(), self.peer_addr, self.from.clone())
+        {
+            match greylister.check(peer_addr.ip(), &from, &to).await {
+                GreylistResult::Allow => {}
+                GreylistResult::Defer(_) => return Verdict::Closed(SmtpReply::TRY_AGAIN_LATER),
+                GreylistResult::Block => return Verdict::Closed(SmtpReply::TRANSACTION_BLOCKED),
+            }
+        }
+
+        self.accepted_recipients.push(to);
+        Verdict::Accepted
+    }
+
+    /// Refuses a recipient whose `RCPT TO` did not parse, keeping `recipient` to be logged
+    /// with the delivery. A recipient that is not an address is refused as such, while a
+    /// malformed parameter is a syntax error.
+    fn reject_invalid_recipient(&mut self, recipient: String, error: &RcptToError) -> Verdict {
+        self.rejected_recipients.push(recipient);
+        match error {
+            RcptToError::MissingPath | RcptToError::InvalidAddress(_) => {
+                Verdict::Refused(SmtpReply::INVALID_RECIPIENT)
+            }
+            _ => Verdict::Refused(SmtpReply::SYNTAX_ERROR),
+        }
+    }
+
+    /// Adds a line of the message, `received` octets long as it was sent. Returns `false`,
+    /// dropping what was received so far, once the message grows past `max_body_bytes`.
+    fn push_body_line(&mut self, line: Vec<u8>, received: usize) -> bool {
+        self.body_bytes += received;
+        if self.body_bytes > self.max_body_bytes {
+            self.body = Vec::new();
+            return false;
+        }
+        self.body.push(line);
+        true
+    }
+
+    /// Writes the reply to a refused command. Returns `None` when the command was accepted,
+    /// otherwise what [`SmtpHandler::handle_line`] should.
+    async fn refuse(&mut self, verdict: Verdict) -> Option<Option<bool>> {
+        match verdict {
+            Verdict::Accepted => None,
+            Verdict::Refused(reply) => {
+                if !self.write(reply).await {
+                    return Some(Some(false));
+                }
+                Some(None)
+            }
+            Verdict::Closed(reply) => {
+                self.write(reply).await;
+                Some(Some(false))
+            }
+        }
+    }
+
+    /// Handles the commands accepted in every state but message input. Returns `None` when
+    /// `line` is some other command, otherwise what [`SmtpHandler::handle_line`] should.
+    async fn handle_session_command(&mut self, line: &str) -> Option<Option<bool>> {
+        let (verb, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let reply = match verb.to_uppercase().as_str() {
+            "HELP" => help(argument.trim()),
+            "NOOP" => SmtpReply::OK,
+            "RSET" => {
+                self.reset_transaction();
+                SmtpReply::OK
+            }
+            "QUIT" => {
+                self.write(SmtpReply::CLOSING).await;
+                return Some(Some(true));
+            }
+            // The upgrade itself is not implemented, so clients may carry on in cleartext.
+            "STARTTLS" if !self.tls_active => SmtpReply::TLS_NOT_AVAILABLE,
+            _ => return None,
+        };
+        if !self.write(reply).await {
+            return Some(Some(false));
+        }
+        Some(None)
     }
 
     async fn handle_line(&mut self, line: &str) -> Option<bool> {
+        if !matches!(self.state, SmtpState::End)
+            && let Some(result) = self.handle_session_command(line).await
+        {
+            return result;
+        }
+
         match self.state {
             SmtpState::Start => {
-                if line.len() < 4 {
-                    self.write("500 Unrecognized command\r\n").await;
-                    return Some(false);
-                }
-                let line = line[..4].to_uppercase();
-                if line == "HELO" || line == "EHLO" {
+                let (domain, reply) = match strip_command(line, "EHLO") {
+                    Some(domain) => (Some(domain), SmtpReply::EHLO),
+                    None => (strip_command(line, "HELO"), SmtpReply::HELLO),
+                };
+                if let Some(domain) = domain.map(str::trim) {
+                    self.helo_domain = Some(domain.to_string()).filter(|d| !d.is_empty());
                     self.state = SmtpState::MailFrom;
-                    if !self.write("250 Hello\r\n").await {
+                    if !self.write(reply).await {
                         return Some(false);
                     }
                 } else {
-                    self.write("500 Unrecognized command\r\n").await;
+                    self.write(SmtpReply::UNRECOGNIZED_COMMAND).await;
                     return Some(false);
                 }
             }
             SmtpState::MailFrom => {
-                if line.len() < 10 {
-                    self.write("500 Unrecognized command\r\n").await;
-                    return Some(false);
+                if self.tls_required() && strip_command(line, "MAIL FROM:").is_some() {
+                    if !self.write(SmtpReply::TLS_REQUIRED).await {
+                        return Some(false);
+                    }
+                    return None;
                 }
-                if line[..10].to_uppercase() == "MAIL FROM:" {
-                    let from = line[10..]
-                        .split_whitespace()
-                        .next()
-                        .unwrap_or("")
-                        .strip_prefix('<')
-                        .and_then(|s| s.strip_suffix('>'))
-                        .unwrap_or("")
-                        .to_string();
-
-                    match EmailAddress::from_str(&from) {
-                        Ok(email) => self.from = email,
-                        Err(_) => {
-                            self.write("501 Syntax error in parameters or arguments\r\n")
-                                .await;
-                            return Some(false);
-                        }
+
+                if let Some(argument) = strip_command(line, "MAIL FROM:") {
+                    let Ok(params) = parse_mail_from(argument) else {
+                        self.write(SmtpReply::SYNTAX_ERROR).await;
+                        return Some(false);
+                    };
+                    let verdict = self.accept_sender(params);
+                    if let Some(result) = self.refuse(verdict).await {
+                        return result;
                     }
 
-                    if !self.write("250 OK\r\n").await {
+                    if !self.write(SmtpReply::OK).await {
                         return Some(false);
                     }
 
                     self.state = SmtpState::RcptTo;
                 } else {
-                    self.write("503 Bad sequence of commands\r\n").await;
+                    self.write(SmtpReply::BAD_SEQUENCE).await;
                     return Some(false);
                 }
             }
             SmtpState::RcptTo => {
-                if line.len() < 8 {
-                    self.write("500 Unrecognized command\r\n").await;
-                    return Some(false);
+                if let Some(argument) = strip_command(line, "BDAT") {
+                    return self.handle_bdat(argument).await;
+                }
+                if self.chunks.is_some() {
+                    // Once a message is being sent in chunks, only more chunks may follow.
+                    if !self.write(SmtpReply::BAD_SEQUENCE).await {
+                        return Some(false);
+                    }
+                    return None;
                 }
-                if line[..8].to_uppercase() == "RCPT TO:" {
-                    let to = line[8..]
-                        .split_whitespace()
-                        .next()
-                        .unwrap_or("")
-                        .strip_prefix('<')
-                        .and_then(|s| s.strip_suffix('>'))
-                        .unwrap_or("")
-                        .to_string();
-                    match EmailAddress::from_str(&to) {
-                        Ok(email) => self.to = email,
-                        Err(_) => {
-                            self.write("501 Syntax error in parameters or arguments\r\n")
-                                .await;
+
+                if line.to_uppercase() == "DATA" {
+                    if self.accepted_recipients.is_empty() {
+                        if !self.write(SmtpReply::NO_VALID_RECIPIENTS).await {
                             return Some(false);
                         }
+                        return None;
                     }
 
-                    if !self.write("250 OK\r\n").await {
+                    if !self.write(SmtpReply::START_MAIL_INPUT).await {
                         return Some(false);
                     }
 
-                    self.state = SmtpState::Data;
-                } else {
-                    self.write("503 Bad sequence of commands\r\n").await;
-                    return Some(false);
+                    self.state = SmtpState::End;
+                    return None;
                 }
-            }
-            SmtpState::Data => {
-                if line.to_uppercase() == "DATA" {
-                    if !self
-                        .write("354 Start mail input; end with <CRLF>.<CRLF>\r\n")
-                        .await
-                    {
-                        return Some(false);
+
+                if let Some(argument) = strip_command(line, "RCPT TO:") {
+                    // A rejected recipient does not end the transaction; the client may try
+                    // another one before sending DATA.
+                    let verdict = match parse_rcpt_to(argument) {
+                        Ok(params) => self.accept_recipient(params.address).await,
+                        Err(e) => self.reject_invalid_recipient(argument.trim().to_string(), &e),
+                    };
+                    if let Some(result) = self.refuse(verdict).await {
+                        return result;
                     }
 
-                    self.state = SmtpState::End
+                    if !self.write(SmtpReply::OK).await {
+                        return Some(false);
+                    }
                 } else {
-                    self.write("503 Bad sequence of commands\r\n").await;
+                    self.write(SmtpReply::BAD_SEQUENCE).await;
                     return Some(false);
                 }
             }
-            SmtpState::End => {
-                if line == "." {
-                    let email = NewEmail::from_raw_message(
-                        self.from.clone(),
-                        self.to.clone(),
-                        self.body.clone(),
-                    );
-                    if let Err(e) = self.persistor.persist_email(&email).await {
-                        eprintln!("Error saving email: {e}");
-                        if !self.write("550 Internal server error\r\n").await {
-                            return Some(false);
-                        }
-                        return Some(false);
-                    }
+            SmtpState::Bdat { .. } => unreachable!("chunks are read by SmtpHandler::handle"),
+            SmtpState::End => return self.handle_data_line(line.as_bytes(), 2).await,
+        }
 
-                    if !self
-                        .write("250 OK: Message accepted for delivery\r\n")
-                        .await
-                    {
-                        return Some(false);
-                    }
+        None
+    }
 
-                    return Some(true);
-                }
+    /// Accepts a `BDAT <size> [LAST]` command, so [`SmtpHandler::handle`] reads its chunk.
+    ///
+    /// The client sends the chunk right after the command without waiting for a reply, so
+    /// when the command is refused the rest of the input cannot be told apart from commands
+    /// and the connection is closed.
+    async fn handle_bdat(&mut self, argument: &str) -> Option<bool> {
+        let mut words = argument.split_whitespace();
+        let size = words.next().and_then(|size| size.parse::<usize>().ok());
+        let is_last = match words.next() {
+            None => false,
+            Some(word) if word.eq_ignore_ascii_case("LAST") => true,
+            Some(_) => {
+                self.write(SmtpReply::SYNTAX_ERROR).await;
+                return Some(false);
+            }
+        };
+        let Some(size) = size.filter(|_| words.next().is_none()) else {
+            self.write(SmtpReply::SYNTAX_ERROR).await;
+            return Some(false);
+        };
 
-                let line_to_push = if let Some(line) = line.strip_prefix(".") {
-                    // Section 4.5.2 of RFC 5321 states that lines starting with a dot
-                    // should have the dot removed when they are part of the message body.
-                    // This is to avoid confusion with the end of data marker.
-                    // So we push the line without the leading dot.
-                    line.to_string()
-                } else {
-                    line.to_string()
-                };
+        if self.accepted_recipients.is_empty() {
+            self.write(SmtpReply::NO_VALID_RECIPIENTS).await;
+            return Some(false);
+        }
+        // `size` comes from the client, so the sum may not fit.
+        if self
+            .body_bytes
+            .checked_add(size)
+            .is_none_or(|total| total > self.max_body_bytes)
+        {
+            self.write(SmtpReply::MESSAGE_TOO_BIG).await;
+            return Some(false);
+        }
+
+        self.chunks.get_or_insert_with(Vec::new);
+        self.state = SmtpState::Bdat {
+            remaining: size,
+            is_last,
+        };
+        None
+    }
+
+    /// Handles a chunk sent with `BDAT`. The last one completes the message, which is then
+    /// delivered as if it had been sent with `DATA`.
+    async fn handle_chunk(&mut self, chunk: Vec<u8>, is_last: bool) -> Option<bool> {
+        self.body_bytes += chunk.len();
+        let chunks = self.chunks.get_or_insert_with(Vec::new);
+        chunks.extend_from_slice(&chunk);
 
-                self.body.push(line_to_push);
+        if !is_last {
+            self.state = SmtpState::RcptTo;
+            if !self.write(SmtpReply::chunk_received(chunk.len())).await {
+                return Some(false);
             }
+            return None;
+        }
+
+        let data = self.chunks.take().unwrap_or_default();
+        let lines = split_lines(&data);
+        for line in &lines {
+            if self
+                .header_guard
+                .check(&String::from_utf8_lossy(line))
+                .is_err()
+            {
+                self.write(SmtpReply::HEADERS_TOO_LARGE).await;
+                return Some(false);
+            }
+        }
+
+        self.body = lines;
+        Some(self.deliver().await)
+    }
+
+    /// Handles a line of message input, up to and including the final dot, without its line
+    /// ending of `line_ending` bytes.
+    async fn handle_data_line(&mut self, line: &[u8], line_ending: usize) -> Option<bool> {
+        if line == b"." {
+            return Some(self.deliver().await);
+        }
+
+        if self
+            .header_guard
+            .check(&String::from_utf8_lossy(line))
+            .is_err()
+        {
+            self.write(SmtpReply::HEADERS_TOO_LARGE).await;
+            return Some(false);
         }
 
+        // Section 4.5.2 of RFC 5321 states that lines starting with a dot should have the dot
+        // removed when they are part of the message body. This is to avoid confusion with the
+        // end of data marker.
+        let line_to_push = line.strip_prefix(b".").unwrap_or(line).to_vec();
+
+        let received = line_to_push.len() + line_ending;
+        if !self.push_body_line(line_to_push, received) {
+            self.write(SmtpReply::MESSAGE_TOO_BIG).await;
+            return Some(false);
+        }
         None
     }
 }
@@ -231,7 +935,10 @@ impl<P: SmtpPersistor, W: AsyncWrite + Unpin> SmtpHandler<P, W> {
 mod tests {
     use super::*;
     use crate::email::NewEmail;
+    use crate::greylist::InMemoryGreylister;
     use crate::persistor::SmtpPersistor;
+    use crate::persistor::tests::RecordingPersistor;
+    use email_address::EmailAddress;
 
     struct MockSmtpPersistor {
         expected: NewEmail,
@@ -244,20 +951,35 @@ mod tests {
     }
 
     impl SmtpPersistor for MockSmtpPersistor {
-        async fn persist_email(&self, email: &NewEmail) -> Result<(), sqlx::Error> {
+        async fn persist_email(&self, email: &NewEmail) -> Result<PersistResult, sqlx::Error> {
             assert_eq!(self.expected, *email);
-            Ok(())
+            Ok(PersistResult::Inserted(uuid::Uuid::nil()))
         }
     }
 
     #[tokio::test]
     async fn test_smtp_handler_simple_case() {
         let expected = NewEmail {
-            from: EmailAddress::new_unchecked("sender@example.com".to_string()),
-            to: EmailAddress::new_unchecked("recipient@example.com".to_string()),
+            from: EmailAddress::new_unchecked("sender@example.com").into(),
+            to: EmailAddress::new_unchecked("recipient@example.com").into(),
             subject: "Test Email".to_string(),
-            headers: vec![("Subject".to_string(), "Test Email".to_string())],
+            date: None,
+            message_id: None,
+            in_reply_to: None,
+            references: Vec::new(),
+            cc: Vec::new(),
+            reply_to: None,
+            headers: vec![("Subject".to_string(), "Test Email".to_string())].into(),
             body: "Hello, world!\r\n".to_string(),
+            raw_body: None,
+            body_bytes: 15,
+            body_lines: 1,
+            received_bytes: 38,
+            preview: "Hello, world!".to_string(),
+            dkim_result: None,
+            auth_results: Vec::new(),
+            helo_domain: Some("example.com".to_string()),
+            envelope_to: vec![EmailAddress::new_unchecked("recipient@example.com").into()],
         };
         let mock_persistor = MockSmtpPersistor::new(expected);
         let discard_stream = tokio::io::sink();
@@ -279,4 +1001,1443 @@ mod tests {
 
         let _ = handler.handle(read_stream).await;
     }
+
+    #[tokio::test]
+    async fn test_smtp_handler_counts_received_bytes() {
+        let mut expected = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            vec![
+                "Subject: Test Email".to_string(),
+                "".to_string(),
+                ".signature".to_string(),
+                "Hello".to_string(),
+            ],
+        );
+        expected.helo_domain = Some("example.com".to_string());
+        expected.envelope_to = vec![EmailAddress::new_unchecked("recipient@example.com").into()];
+        // Line endings count as sent, while the stuffed dot and the final dot do not.
+        expected.received_bytes = "Subject: Test Email\r\n\r\n.signature\r\nHello\n".len();
+
+        let message = [
+            "HELO example.com\r\n".as_bytes(),
+            "MAIL FROM: <sender@example.com>\r\n".as_bytes(),
+            "RCPT TO: <recipient@example.com>\r\n".as_bytes(),
+            "DATA\r\n".as_bytes(),
+            "Subject: Test Email\r\n".as_bytes(),
+            "\r\n".as_bytes(),
+            "..signature\r\n".as_bytes(),
+            "Hello\n".as_bytes(),
+            ".\r\n".as_bytes(),
+        ]
+        .concat();
+
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, MockSmtpPersistor::new(expected)),
+            message,
+        )
+        .await;
+        assert!(
+            responses.ends_with("250 OK: Message accepted for delivery\r\n"),
+            "{responses}"
+        );
+    }
+
+    /// Sends a Latin-1 message, as a client relying on 8BITMIME may, starting the transaction
+    /// with `mail_from`.
+    async fn deliver_8bit_message(mail_from: &[u8]) {
+        let expected = NewEmail {
+            from: EmailAddress::new_unchecked("sender@example.com").into(),
+            to: EmailAddress::new_unchecked("recipient@example.com").into(),
+            subject: "Caf\u{fffd}".to_string(),
+            date: None,
+            message_id: None,
+            in_reply_to: None,
+            references: Vec::new(),
+            cc: Vec::new(),
+            reply_to: None,
+            headers: vec![("Subject".to_string(), "Caf\u{fffd}".to_string())].into(),
+            body: "Gr\u{fffd}\u{fffd}e\r\n".to_string(),
+            raw_body: Some(b"Gr\xfc\xdfe\r\n".to_vec()),
+            body_bytes: 7,
+            body_lines: 1,
+            received_bytes: 24,
+            preview: "Gr\u{fffd}\u{fffd}e".to_string(),
+            dkim_result: None,
+            auth_results: Vec::new(),
+            helo_domain: Some("example.com".to_string()),
+            envelope_to: vec![EmailAddress::new_unchecked("recipient@example.com").into()],
+        };
+        let message = [
+            &b"EHLO example.com\r\n"[..],
+            mail_from,
+            b"RCPT TO: <recipient@example.com>\r\n",
+            b"DATA\r\n",
+            b"Subject: Caf\xe9\r\n",
+            b"\r\n",
+            b"Gr\xfc\xdfe\r\n",
+            b".\r\n",
+        ]
+        .concat();
+
+        // The mock checks what is persisted, and the final reply shows it was.
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, MockSmtpPersistor::new(expected)),
+            message,
+        )
+        .await;
+        assert!(
+            responses.ends_with("250 OK: Message accepted for delivery\r\n"),
+            "{responses}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_keeps_8bit_body() {
+        deliver_8bit_message(b"MAIL FROM: <sender@example.com> BODY=8BITMIME\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_keeps_undeclared_8bit_body() {
+        // High bytes are kept even when the client did not declare them.
+        deliver_8bit_message(b"MAIL FROM: <sender@example.com>\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_accepts_utf8_addresses_with_smtputf8() {
+        let table = [
+            ("用户@例子.广告", "收件人@例子.广告"),
+            ("отправитель@пример.рф", "получатель@пример.рф"),
+            ("प्रेषक@उदाहरण.भारत", "प्राप्तकर्ता@उदाहरण.भारत"),
+            ("αποστολέας@παράδειγμα.ελ", "παραλήπτης@παράδειγμα.ελ"),
+            ("مرسل@مثال.مصر", "josé@exemplo.pt"),
+        ];
+
+        for (from, to) in table {
+            let mut expected = NewEmail::from_raw_message(
+                from.parse::<MailboxAddress>().unwrap(),
+                to.parse::<MailboxAddress>().unwrap(),
+                vec![
+                    format!("To: {to}"),
+                    "".to_string(),
+                    "Hello, world!".to_string(),
+                ],
+            );
+            expected.helo_domain = Some("example.com".to_string());
+            expected.envelope_to = vec![to.parse().unwrap()];
+            expected.received_bytes = format!("To: {to}\r\n\r\nHello, world!\r\n").len();
+            let message = format!(
+                "EHLO example.com\r\nMAIL FROM: <{from}> SMTPUTF8\r\nRCPT TO: <{to}>\r\nDATA\r\nTo: {to}\r\n\r\nHello, world!\r\n.\r\n"
+            );
+
+            let output = run_handler(
+                |write_stream| SmtpHandler::new(write_stream, MockSmtpPersistor::new(expected)),
+                message.into_bytes(),
+            )
+            .await;
+            assert!(
+                output.ends_with("250 OK: Message accepted for delivery\r\n"),
+                "{from} -> {to}: {output}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_requires_smtputf8_for_utf8_addresses() {
+        let output = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, RejectingSmtpPersistor),
+            "EHLO example.com\r\nMAIL FROM: <用户@例子.广告>\r\nMAIL FROM: <sender@example.com>\r\nRCPT TO: <получатель@пример.рф>\r\nQUIT\r\n"
+                .as_bytes()
+                .to_vec(),
+        )
+        .await;
+        assert!(
+            output.ends_with(
+                "553 5.6.7 Non-ASCII addresses require SMTPUTF8\r\n250 OK\r\n553 5.6.7 Non-ASCII addresses require SMTPUTF8\r\n221 2.0.0 Bye\r\n"
+            ),
+            "{output}"
+        );
+    }
+
+    struct RejectingSmtpPersistor;
+
+    impl SmtpPersistor for RejectingSmtpPersistor {
+        async fn persist_email(&self, email: &NewEmail) -> Result<PersistResult, sqlx::Error> {
+            panic!("Unexpected email persisted: {email:?}");
+        }
+    }
+
+    struct StubDkimVerifier(DkimResult);
+
+    #[async_trait::async_trait]
+    impl DkimVerifier for StubDkimVerifier {
+        async fn verify(&self, _email: &NewEmail, _raw_message: &[u8]) -> DkimResult {
+            self.0.clone()
+        }
+    }
+
+    /// Passes every message, keeping the bytes it was asked to verify.
+    #[derive(Default)]
+    struct RecordingDkimVerifier(std::sync::Mutex<Vec<u8>>);
+
+    #[async_trait::async_trait]
+    impl DkimVerifier for RecordingDkimVerifier {
+        async fn verify(&self, _email: &NewEmail, raw_message: &[u8]) -> DkimResult {
+            *self.0.lock().unwrap() = raw_message.to_vec();
+            DkimResult::Pass
+        }
+    }
+
+    fn signed_message() -> Vec<u8> {
+        [
+            "HELO example.com\r\n".as_bytes(),
+            "MAIL FROM: <sender@example.com>\r\n".as_bytes(),
+            "RCPT TO: <recipient@example.com>\r\n".as_bytes(),
+            "DATA\r\n".as_bytes(),
+            "DKIM-Signature: v=1; d=example.com; s=sel; b=dGVzdA==\r\n".as_bytes(),
+            "Subject: Test Email\r\n".as_bytes(),
+            "\r\n".as_bytes(),
+            "Hello, world!\r\n".as_bytes(),
+            ".\r\n".as_bytes(),
+        ]
+        .concat()
+    }
+
+    async fn run_handler<P: SmtpPersistor>(
+        handler: impl FnOnce(tokio::io::DuplexStream) -> SmtpHandler<P, tokio::io::DuplexStream>,
+        message: Vec<u8>,
+    ) -> String {
+        let (write_stream, mut responses) = tokio::io::duplex(64 * 1024);
+        handler(write_stream)
+            .handle(std::io::Cursor::new(message))
+            .await;
+
+        let mut output = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut responses, &mut output)
+            .await
+            .unwrap();
+        output
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_requires_tls() {
+        let message =
+            b"EHLO example.com\r\nSTARTTLS\r\nMAIL FROM: <sender@example.com>\r\nQUIT\r\n";
+
+        let cleartext = run_handler(
+            |write_stream| {
+                SmtpHandler::new(write_stream, RejectingSmtpPersistor).with_require_tls(true)
+            },
+            message.to_vec(),
+        )
+        .await;
+        assert!(
+            cleartext.ends_with(
+                "250-Hello\r\n250-8BITMIME\r\n250-SMTPUTF8\r\n250 CHUNKING\r\n454 4.7.0 TLS not available due to temporary reason\r\n530 Must issue a STARTTLS command first\r\n221 2.0.0 Bye\r\n"
+            ),
+            "{cleartext}"
+        );
+
+        let encrypted = run_handler(
+            |write_stream| {
+                SmtpHandler::new(write_stream, RejectingSmtpPersistor)
+                    .with_require_tls(true)
+                    .with_tls_active(true)
+            },
+            b"EHLO example.com\r\nMAIL FROM: <sender@example.com>\r\nQUIT\r\n".to_vec(),
+        )
+        .await;
+        assert!(
+            encrypted.ends_with("250 CHUNKING\r\n250 OK\r\n221 2.0.0 Bye\r\n"),
+            "{encrypted}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_answers_commands_sent_before_greeting() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        tokio::io::AsyncWriteExt::write_all(&mut client, b"EHLO example.com\r\n")
+            .await
+            .unwrap();
+        drop(client);
+        let (write_stream, mut responses) = tokio::io::duplex(1024);
+
+        SmtpHandler::new(write_stream, RejectingSmtpPersistor)
+            .handle(server)
+            .await;
+
+        let mut output = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut responses, &mut output)
+            .await
+            .unwrap();
+        assert!(
+            output.starts_with(
+                "220 smt.example.com ESMTP Remail\r\n250-Hello\r\n250-8BITMIME\r\n250-SMTPUTF8\r\n250 CHUNKING\r\n"
+            ),
+            "{output}"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_smtp_handler_greylists_first_attempt() {
+        let greylister = Arc::new(InMemoryGreylister::default());
+        let peer_addr: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+
+        let output = run_handler(
+            |write_stream| {
+                SmtpHandler::new(write_stream, RejectingSmtpPersistor)
+                    .with_peer_addr(peer_addr)
+                    .with_greylister(greylister.clone())
+            },
+            signed_message(),
+        )
+        .await;
+        assert!(
+            output.ends_with("451 4.7.1 Please try again later\r\n"),
+            "{output}"
+        );
+
+        tokio::time::advance(std::time::Duration::from_secs(5 * 60)).await;
+
+        let mut expected = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            vec![
+                "DKIM-Signature: v=1; d=example.com; s=sel; b=dGVzdA==".to_string(),
+                "Subject: Test Email".to_string(),
+                "".to_string(),
+                "Hello, world!".to_string(),
+            ],
+        );
+        expected.helo_domain = Some("example.com".to_string());
+        expected.envelope_to = vec![EmailAddress::new_unchecked("recipient@example.com").into()];
+        expected.received_bytes = 93;
+        let output = run_handler(
+            |write_stream| {
+                SmtpHandler::new(write_stream, MockSmtpPersistor::new(expected))
+                    .with_peer_addr(peer_addr)
+                    .with_greylister(greylister.clone())
+            },
+            signed_message(),
+        )
+        .await;
+        assert!(
+            output.ends_with("250 OK: Message accepted for delivery\r\n"),
+            "{output}"
+        );
+    }
+
+    struct StubSpamFilter(SpamClassification);
+
+    #[async_trait::async_trait]
+    impl SpamFilter for StubSpamFilter {
+        async fn classify(&self, _email: &NewEmail) -> SpamClassification {
+            self.0
+        }
+    }
+
+    fn spam_email(status: Option<&str>) -> NewEmail {
+        let mut email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            vec![
+                "DKIM-Signature: v=1; d=example.com; s=sel; b=dGVzdA==".to_string(),
+                "Subject: Test Email".to_string(),
+                "".to_string(),
+                "Hello, world!".to_string(),
+            ],
+        );
+        email.helo_domain = Some("example.com".to_string());
+        email.envelope_to = vec![EmailAddress::new_unchecked("recipient@example.com").into()];
+        email.received_bytes = 93;
+        if let Some(status) = status {
+            email.headers.insert("X-Spam-Status", status);
+        }
+        email
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_tags_spam() {
+        let spam_filter = Arc::new(StubSpamFilter(SpamClassification::Spam { confidence: 0.8 }));
+        let handler = SmtpHandler::new(
+            tokio::io::sink(),
+            MockSmtpPersistor::new(spam_email(Some("Yes, score=0.80"))),
+        )
+        .with_spam_filter(spam_filter, 0.5);
+
+        handler.handle(std::io::Cursor::new(signed_message())).await;
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_ignores_spam_below_threshold() {
+        let spam_filter = Arc::new(StubSpamFilter(SpamClassification::Spam { confidence: 0.4 }));
+        let handler = SmtpHandler::new(tokio::io::sink(), MockSmtpPersistor::new(spam_email(None)))
+            .with_spam_filter(spam_filter, 0.5);
+
+        handler.handle(std::io::Cursor::new(signed_message())).await;
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_records_dkim_result() {
+        let expected = NewEmail {
+            from: EmailAddress::new_unchecked("sender@example.com").into(),
+            to: EmailAddress::new_unchecked("recipient@example.com").into(),
+            subject: "Test Email".to_string(),
+            date: None,
+            message_id: None,
+            in_reply_to: None,
+            references: Vec::new(),
+            cc: Vec::new(),
+            reply_to: None,
+            headers: vec![
+                (
+                    "DKIM-Signature".to_string(),
+                    "v=1; d=example.com; s=sel; b=dGVzdA==".to_string(),
+                ),
+                ("Subject".to_string(), "Test Email".to_string()),
+            ]
+            .into(),
+            body: "Hello, world!\r\n".to_string(),
+            raw_body: None,
+            body_bytes: 15,
+            body_lines: 1,
+            received_bytes: 93,
+            preview: "Hello, world!".to_string(),
+            dkim_result: Some("fail (bad signature)".to_string()),
+            auth_results: Vec::new(),
+            helo_domain: Some("example.com".to_string()),
+            envelope_to: vec![EmailAddress::new_unchecked("recipient@example.com").into()],
+        };
+        let verifier = Arc::new(StubDkimVerifier(DkimResult::Fail(
+            "bad signature".to_string(),
+        )));
+        let handler = SmtpHandler::new(tokio::io::sink(), MockSmtpPersistor::new(expected))
+            .with_dkim_verifier(verifier, DkimPolicy::Monitor);
+
+        handler.handle(std::io::Cursor::new(signed_message())).await;
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_rejects_dkim_failure() {
+        let verifier = Arc::new(StubDkimVerifier(DkimResult::Fail(
+            "bad signature".to_string(),
+        )));
+        let handler = SmtpHandler::new(tokio::io::sink(), RejectingSmtpPersistor)
+            .with_dkim_verifier(verifier, DkimPolicy::Reject);
+
+        handler.handle(std::io::Cursor::new(signed_message())).await;
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_verifies_dkim_over_received_bytes() {
+        let verifier = Arc::new(RecordingDkimVerifier::default());
+        let message = [
+            "HELO example.com\r\n".as_bytes(),
+            "MAIL FROM: <sender@example.com>\r\n".as_bytes(),
+            "RCPT TO: <recipient@example.com>\r\n".as_bytes(),
+            "DATA\r\n".as_bytes(),
+            "DKIM-Signature: v=1; d=example.com;\r\n".as_bytes(),
+            "\ts=sel; b=dGVzdA==\r\n".as_bytes(),
+            "subject:  Test Email\r\n".as_bytes(),
+            "\r\n".as_bytes(),
+            "..signature\r\n".as_bytes(),
+            ".\r\n".as_bytes(),
+        ]
+        .concat();
+
+        run_handler(
+            |write_stream| {
+                SmtpHandler::new(write_stream, DuplicateSmtpPersistor)
+                    .with_dkim_verifier(verifier.clone(), DkimPolicy::Reject)
+            },
+            message,
+        )
+        .await;
+
+        // Folding, header case and spacing are kept as sent; only the dot-stuffing is undone.
+        assert_eq!(
+            String::from_utf8_lossy(&verifier.0.lock().unwrap()),
+            "DKIM-Signature: v=1; d=example.com;\r\n\ts=sel; b=dGVzdA==\r\nsubject:  Test Email\r\n\r\n.signature\r\n"
+        );
+    }
+
+    struct StubAnalyzer;
+
+    #[async_trait::async_trait]
+    impl MessageAnalyzer for StubAnalyzer {
+        async fn analyze(&self, _email: &NewEmail, session: &SessionInfo<'_>) -> Vec<AuthResult> {
+            let spf = if session.peer_ip.is_some() {
+                "pass"
+            } else {
+                "none"
+            };
+            vec![
+                AuthResult::new("spf", spf)
+                    .with_property("smtp.mailfrom", session.mail_from.domain()),
+            ]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_records_auth_results() {
+        let mut expected = spam_email(None);
+        expected.headers.prepend(
+            "Authentication-Results",
+            "remail; dkim=fail reason=\"bad signature\" header.d=example.com header.s=sel; spf=pass smtp.mailfrom=example.com",
+        );
+        expected.dkim_result = Some("fail (bad signature)".to_string());
+        expected.auth_results = vec![
+            ("dkim".to_string(), "fail".to_string()),
+            ("spf".to_string(), "pass".to_string()),
+        ];
+        // A failing check only annotates the message, which is still stored.
+        let verifier = Arc::new(StubDkimVerifier(DkimResult::Fail(
+            "bad signature".to_string(),
+        )));
+        let handler = SmtpHandler::new(tokio::io::sink(), MockSmtpPersistor::new(expected))
+            .with_peer_addr("127.0.0.1:4000".parse().unwrap())
+            .with_dkim_verifier(verifier, DkimPolicy::Monitor)
+            .with_analyzer(Arc::new(StubAnalyzer));
+
+        handler.handle(std::io::Cursor::new(signed_message())).await;
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_rejects_data_without_recipients() {
+        let message = [
+            "HELO example.com\r\n".as_bytes(),
+            "MAIL FROM: <sender@example.com>\r\n".as_bytes(),
+            "RCPT TO: <not an address>\r\n".as_bytes(),
+            "DATA\r\n".as_bytes(),
+        ]
+        .concat();
+
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, RejectingSmtpPersistor),
+            message,
+        )
+        .await;
+
+        assert!(responses.contains("550 5.1.3 Invalid recipient address\r\n"));
+        assert!(responses.ends_with("503 5.5.1 No valid recipients\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_tracks_invalid_recipient() {
+        let (write_stream, _responses) = tokio::io::duplex(1024);
+        let mut handler = SmtpHandler::new(write_stream, RejectingSmtpPersistor);
+
+        for line in [
+            "HELO example.com",
+            "MAIL FROM: <sender@example.com>",
+            "RCPT TO: <not an address>",
+            "RCPT TO: <recipient@example.com>",
+        ] {
+            assert_eq!(handler.handle_line(line).await, None, "{line}");
+        }
+
+        assert_eq!(handler.rejected_recipients, ["<not an address>"]);
+        assert_eq!(handler.accepted_recipients.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_parsed_refuses_invalid_recipient() {
+        let message = concat!(
+            "HELO example.com\r\n",
+            "MAIL FROM: <sender@example.com>\r\n",
+            "RCPT TO: <not an address>\r\n",
+            "RCPT TO: <recipient@example.com>\r\n",
+            "DATA\r\n",
+            "Hello, world!\r\n",
+            ".\r\n",
+        );
+        let (write_stream, responses) = tokio::io::duplex(1024);
+
+        let output = run_parsed(
+            SmtpHandler::new(write_stream, RecordingPersistor::default()),
+            responses,
+            message,
+        )
+        .await;
+
+        assert_eq!(
+            output,
+            "550 5.1.3 Invalid recipient address\r\n250 OK: Message accepted for delivery\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_accepts_chunked_message() {
+        let expected = NewEmail {
+            from: EmailAddress::new_unchecked("sender@example.com").into(),
+            to: EmailAddress::new_unchecked("recipient@example.com").into(),
+            subject: "Chunked".to_string(),
+            date: None,
+            message_id: None,
+            in_reply_to: None,
+            references: Vec::new(),
+            cc: Vec::new(),
+            reply_to: None,
+            headers: vec![("Subject".to_string(), "Chunked".to_string())].into(),
+            body: "Hello, world!\r\n.signature\r\n".to_string(),
+            raw_body: None,
+            body_bytes: 27,
+            body_lines: 2,
+            received_bytes: 47,
+            preview: "Hello, world! .signature".to_string(),
+            dkim_result: None,
+            auth_results: Vec::new(),
+            helo_domain: Some("example.com".to_string()),
+            envelope_to: vec![EmailAddress::new_unchecked("recipient@example.com").into()],
+        };
+
+        // The chunks split lines anywhere, and a leading dot is not stuffed.
+        let chunks = [
+            "Subject: Chu",
+            "nked\r\n\r\nHello, wor",
+            "ld!\r\n.signature\r\n",
+        ];
+        let mut message = String::from(
+            "EHLO example.com\r\nMAIL FROM: <sender@example.com>\r\nRCPT TO: <recipient@example.com>\r\n",
+        );
+        for (i, chunk) in chunks.iter().enumerate() {
+            let last = if i == chunks.len() - 1 { " LAST" } else { "" };
+            message.push_str(&format!("BDAT {}{last}\r\n{chunk}", chunk.len()));
+        }
+
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, MockSmtpPersistor::new(expected)),
+            message.into_bytes(),
+        )
+        .await;
+
+        assert!(
+            responses.ends_with(
+                "250 OK\r\n250 OK\r\n250 12 octets received\r\n250 18 octets received\r\n250 OK: Message accepted for delivery\r\n"
+            ),
+            "{responses}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_rejects_data_after_bdat() {
+        let message = "HELO example.com\r\nMAIL FROM: <sender@example.com>\r\nRCPT TO: <recipient@example.com>\r\nBDAT 5\r\nHelloDATA\r\nRCPT TO: <other@example.com>\r\nQUIT\r\n";
+
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, RejectingSmtpPersistor),
+            message.as_bytes().to_vec(),
+        )
+        .await;
+
+        assert!(
+            responses.ends_with(
+                "250 5 octets received\r\n503 Bad sequence of commands\r\n503 Bad sequence of commands\r\n221 2.0.0 Bye\r\n"
+            ),
+            "{responses}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_rejects_bdat_without_recipients() {
+        let table = [
+            (
+                "RCPT TO: <not an address>\r\nBDAT 5 LAST\r\nHello",
+                "503 5.5.1 No valid recipients\r\n",
+            ),
+            (
+                "RCPT TO: <recipient@example.com>\r\nBDAT five\r\nHello",
+                "501 Syntax error in parameters or arguments\r\n",
+            ),
+            (
+                "RCPT TO: <recipient@example.com>\r\nBDAT 5 FIRST\r\nHello",
+                "501 Syntax error in parameters or arguments\r\n",
+            ),
+        ];
+
+        for (commands, expected) in table {
+            let message =
+                format!("HELO example.com\r\nMAIL FROM: <sender@example.com>\r\n{commands}");
+            let responses = run_handler(
+                |write_stream| SmtpHandler::new(write_stream, RejectingSmtpPersistor),
+                message.into_bytes(),
+            )
+            .await;
+
+            assert!(responses.ends_with(expected), "{commands}: {responses}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_accepts_dsn_parameters() {
+        let message = [
+            "HELO example.com\r\n".as_bytes(),
+            "MAIL FROM: <sender@example.com>\r\n".as_bytes(),
+            "RCPT TO: <recipient@example.com> NOTIFY=BOGUS\r\n".as_bytes(),
+            "RCPT TO: <recipient@example.com> NOTIFY=SUCCESS,FAILURE ORCPT=rfc822;recipient@example.com\r\n"
+                .as_bytes(),
+            "DATA\r\n".as_bytes(),
+        ]
+        .concat();
+
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, RejectingSmtpPersistor),
+            message,
+        )
+        .await;
+
+        assert!(
+            responses.contains("501 Syntax error in parameters or arguments\r\n250 OK\r\n"),
+            "{responses}"
+        );
+        assert!(responses.ends_with("354 Start mail input; end with <CRLF>.<CRLF>\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_greeting_includes_connection_id_when_debugging() {
+        let mut connection_id = None;
+        let responses = run_handler(
+            |write_stream| {
+                let handler = SmtpHandler::new(write_stream, RejectingSmtpPersistor)
+                    .with_debug_connection_id(true);
+                connection_id = Some(handler.connection_id());
+                handler
+            },
+            Vec::new(),
+        )
+        .await;
+
+        assert_eq!(
+            responses,
+            format!(
+                "220 smt.example.com ESMTP Remail (connection {})\r\n",
+                connection_id.unwrap()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_greeting_hides_connection_id_by_default() {
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, RejectingSmtpPersistor),
+            Vec::new(),
+        )
+        .await;
+
+        assert_eq!(responses, "220 smt.example.com ESMTP Remail\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_help_lists_commands() {
+        let message = [
+            "HELO example.com\r\n".as_bytes(),
+            "MAIL FROM: <sender@example.com>\r\n".as_bytes(),
+            "HELP\r\n".as_bytes(),
+            "RCPT TO: <recipient@example.com>\r\n".as_bytes(),
+            "DATA\r\n".as_bytes(),
+            "Subject: Test Email\r\n".as_bytes(),
+            "\r\n".as_bytes(),
+            "Hello, world!\r\n".as_bytes(),
+            ".\r\n".as_bytes(),
+        ]
+        .concat();
+
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, DuplicateSmtpPersistor),
+            message,
+        )
+        .await;
+
+        let listing = responses
+            .lines()
+            .find(|line| line.starts_with("214 "))
+            .unwrap_or_else(|| panic!("no final 214 line in {responses}"));
+        assert!(
+            responses.contains("214-Supported commands:\r\n"),
+            "{responses}"
+        );
+        for verb in [
+            "HELO", "EHLO", "MAIL", "RCPT", "DATA", "RSET", "NOOP", "QUIT",
+        ] {
+            assert!(listing.contains(verb), "{verb} missing from {listing}");
+        }
+        // HELP left the transaction alone, so the message still went through.
+        assert!(responses.ends_with("250 OK: Message accepted for delivery\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_help_topics() {
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, RejectingSmtpPersistor),
+            b"HELP data\r\nHELP BOGUS\r\n".to_vec(),
+        )
+        .await;
+
+        assert!(
+            responses
+                .contains("214 DATA: send the message, ending with a line holding only a dot\r\n"),
+            "{responses}"
+        );
+        assert!(responses.ends_with("504 5.5.4 HELP topic not recognized\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_rset_noop_and_quit() {
+        let message = [
+            "HELO example.com\r\n".as_bytes(),
+            "MAIL FROM: <sender@example.com>\r\n".as_bytes(),
+            "RCPT TO: <recipient@example.com>\r\n".as_bytes(),
+            "RSET\r\n".as_bytes(),
+            "NOOP\r\n".as_bytes(),
+            // The reset dropped the recipient, so there is nothing to send to.
+            "MAIL FROM: <sender@example.com>\r\n".as_bytes(),
+            "DATA\r\n".as_bytes(),
+            "QUIT\r\n".as_bytes(),
+            "NOOP\r\n".as_bytes(),
+        ]
+        .concat();
+
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, RejectingSmtpPersistor),
+            message,
+        )
+        .await;
+
+        assert_eq!(
+            responses,
+            "220 smt.example.com ESMTP Remail\r\n\
+             250 Hello\r\n\
+             250 OK\r\n\
+             250 OK\r\n\
+             250 OK\r\n\
+             250 OK\r\n\
+             250 OK\r\n\
+             503 5.5.1 No valid recipients\r\n\
+             221 2.0.0 Bye\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_rejects_multibyte_commands() {
+        for (message, reply) in [
+            ("HÉLO foo\r\n", "500 Unrecognized command\r\n"),
+            ("HEL€\r\n", "500 Unrecognized command\r\n"),
+            (
+                "HELO example.com\r\nMAIL FROM€\r\n",
+                "503 Bad sequence of commands\r\n",
+            ),
+            (
+                "HELO example.com\r\nMAIL FROM: <sender@example.com>\r\nRCPT T€\r\n",
+                "503 Bad sequence of commands\r\n",
+            ),
+        ] {
+            let responses = run_handler(
+                |write_stream| SmtpHandler::new(write_stream, RejectingSmtpPersistor),
+                message.as_bytes().to_vec(),
+            )
+            .await;
+
+            assert!(responses.ends_with(reply), "{message}: {responses}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_survives_random_input() {
+        let prefixes = [
+            "HELO",
+            "EHLO",
+            "MAIL FROM:",
+            "RCPT TO:",
+            "DATA",
+            "HELP",
+            ".",
+        ];
+        let chars = ['a', ' ', ':', '<', '>', '@', 'é', 'É', '€', '😀', '\u{301}'];
+        let mut rng = fastrand::Rng::with_seed(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..200 {
+            let mut message: Vec<u8> = (0..rng.usize(..8))
+                .flat_map(|_| {
+                    let prefix = prefixes[rng.usize(..prefixes.len())];
+                    let suffix: String = (0..rng.usize(..12))
+                        .map(|_| chars[rng.usize(..chars.len())])
+                        .collect();
+                    format!("{prefix}{suffix}\r\n").into_bytes()
+                })
+                .collect();
+            // Raw bytes, often not valid UTF-8 at all.
+            message.extend((0..rng.usize(..16)).map(|_| rng.u8(..)));
+
+            run_handler(
+                |write_stream| SmtpHandler::new(write_stream, DuplicateSmtpPersistor),
+                message.clone(),
+            )
+            .await;
+
+            let (write_stream, _responses) = tokio::io::duplex(64 * 1024);
+            SmtpHandler::new(write_stream, DuplicateSmtpPersistor)
+                .handle_parsed(std::io::Cursor::new(message))
+                .await;
+        }
+    }
+
+    async fn run_bounce_rules(recipient: &str) -> String {
+        let message = [
+            "HELO example.com\r\n".to_string(),
+            "MAIL FROM: <sender@example.com>\r\n".to_string(),
+            format!("RCPT TO: <{recipient}>\r\n"),
+            "DATA\r\n".to_string(),
+        ]
+        .concat();
+
+        run_handler(
+            |write_stream| {
+                SmtpHandler::new(write_stream, RejectingSmtpPersistor)
+                    .with_bounce_rules(BounceRules::default().bounce("bounce@*").defer("defer@*"))
+            },
+            message.into_bytes(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_bounces_matching_recipient() {
+        let responses = run_bounce_rules("bounce@example.com").await;
+
+        assert!(
+            responses.contains("550 No such user here\r\n"),
+            "{responses}"
+        );
+        assert!(responses.ends_with("503 5.5.1 No valid recipients\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_defers_matching_recipient() {
+        let responses = run_bounce_rules("defer@example.com").await;
+
+        assert!(
+            responses.contains("450 4.2.1 Mailbox unavailable, try again later\r\n"),
+            "{responses}"
+        );
+        assert!(responses.ends_with("503 5.5.1 No valid recipients\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_accepts_recipient_after_bounce() {
+        let message = [
+            "HELO example.com\r\n".as_bytes(),
+            "MAIL FROM: <sender@example.com>\r\n".as_bytes(),
+            "RCPT TO: <bounce@example.com>\r\n".as_bytes(),
+            "RCPT TO: <recipient@example.com>\r\n".as_bytes(),
+            "DATA\r\n".as_bytes(),
+            "Subject: Test Email\r\n".as_bytes(),
+            "\r\n".as_bytes(),
+            "Hello, world!\r\n".as_bytes(),
+            ".\r\n".as_bytes(),
+        ]
+        .concat();
+
+        let responses = run_handler(
+            |write_stream| {
+                SmtpHandler::new(write_stream, DuplicateSmtpPersistor)
+                    .with_bounce_rules(BounceRules::default().bounce("bounce@*"))
+            },
+            message,
+        )
+        .await;
+
+        assert!(
+            responses.contains("550 No such user here\r\n"),
+            "{responses}"
+        );
+        assert!(responses.ends_with("250 OK: Message accepted for delivery\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_delivers_to_accepted_recipients() {
+        let mut expected = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("alice@example.com"),
+            vec![
+                "Subject: Test Email".to_string(),
+                "".to_string(),
+                "Hello, world!".to_string(),
+            ],
+        );
+        expected.helo_domain = Some("example.com".to_string());
+        expected.envelope_to = vec![
+            EmailAddress::new_unchecked("alice@example.com").into(),
+            EmailAddress::new_unchecked("bob@example.com").into(),
+        ];
+        expected.received_bytes = 38;
+        let message = [
+            "HELO example.com\r\n".as_bytes(),
+            "MAIL FROM: <sender@example.com>\r\n".as_bytes(),
+            "RCPT TO: <alice@example.com>\r\n".as_bytes(),
+            "RCPT TO: <bounce@example.com>\r\n".as_bytes(),
+            "RCPT TO: <bob@example.com>\r\n".as_bytes(),
+            "DATA\r\n".as_bytes(),
+            "Subject: Test Email\r\n".as_bytes(),
+            "\r\n".as_bytes(),
+            "Hello, world!\r\n".as_bytes(),
+            ".\r\n".as_bytes(),
+        ]
+        .concat();
+
+        let responses = run_handler(
+            |write_stream| {
+                SmtpHandler::new(write_stream, MockSmtpPersistor::new(expected))
+                    .with_bounce_rules(BounceRules::default().bounce("bounce@*"))
+            },
+            message,
+        )
+        .await;
+
+        let replies: Vec<&str> = responses.lines().skip(3).take(3).collect();
+        assert_eq!(
+            replies,
+            ["250 OK", "550 No such user here", "250 OK"],
+            "{responses}"
+        );
+        assert!(responses.ends_with("250 OK: Message accepted for delivery\r\n"));
+    }
+
+    struct DuplicateSmtpPersistor;
+
+    impl SmtpPersistor for DuplicateSmtpPersistor {
+        async fn persist_email(&self, _email: &NewEmail) -> Result<PersistResult, sqlx::Error> {
+            Ok(PersistResult::Duplicate)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_accepts_duplicate() {
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, DuplicateSmtpPersistor),
+            signed_message(),
+        )
+        .await;
+
+        assert!(responses.ends_with("250 OK: Message accepted for delivery\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_handle_parsed() {
+        let expected = NewEmail {
+            from: EmailAddress::new_unchecked("sender@example.com").into(),
+            to: EmailAddress::new_unchecked("recipient@example.com").into(),
+            subject: "Test Email".to_string(),
+            date: None,
+            message_id: None,
+            in_reply_to: None,
+            references: Vec::new(),
+            cc: Vec::new(),
+            reply_to: None,
+            headers: vec![
+                (
+                    "DKIM-Signature".to_string(),
+                    "v=1; d=example.com; s=sel; b=dGVzdA==".to_string(),
+                ),
+                ("Subject".to_string(), "Test Email".to_string()),
+            ]
+            .into(),
+            body: "Hello, world!\r\n".to_string(),
+            raw_body: None,
+            body_bytes: 15,
+            body_lines: 1,
+            received_bytes: 93,
+            preview: "Hello, world!".to_string(),
+            dkim_result: None,
+            auth_results: Vec::new(),
+            helo_domain: None,
+            envelope_to: vec![EmailAddress::new_unchecked("recipient@example.com").into()],
+        };
+        let (write_stream, mut responses) = tokio::io::duplex(1024);
+
+        SmtpHandler::new(write_stream, MockSmtpPersistor::new(expected))
+            .handle_parsed(std::io::Cursor::new(signed_message()))
+            .await;
+
+        let mut output = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut responses, &mut output)
+            .await
+            .unwrap();
+        assert_eq!(output, "250 OK: Message accepted for delivery\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_parsed_rejects_null_sender() {
+        let message = "HELO example.com\r\nMAIL FROM: <>\r\nRCPT TO: <recipient@example.com>\r\nDATA\r\nHello, world!\r\n.\r\n";
+        let (write_stream, mut responses) = tokio::io::duplex(1024);
+
+        SmtpHandler::new(write_stream, RejectingSmtpPersistor)
+            .handle_parsed(std::io::Cursor::new(message))
+            .await;
+
+        let mut output = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut responses, &mut output)
+            .await
+            .unwrap();
+        assert_eq!(output, "501 Syntax error in parameters or arguments\r\n");
+    }
+
+    fn message_with_headers(headers: &str) -> Vec<u8> {
+        format!(
+            "HELO example.com\r\nMAIL FROM: <sender@example.com>\r\nRCPT TO: <recipient@example.com>\r\nDATA\r\n{headers}\r\nHello, world!\r\n.\r\n"
+        )
+        .into_bytes()
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_rejects_too_many_headers() {
+        let headers: String = (0..200)
+            .map(|i| format!("X-Header-{i}: value\r\n"))
+            .collect();
+
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, RejectingSmtpPersistor),
+            message_with_headers(&headers),
+        )
+        .await;
+
+        assert!(responses.ends_with("552 5.3.4 Message header section too large\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_rejects_large_header() {
+        // Folded, so that no single line exceeds the line length limit.
+        let headers = format!(
+            "X-Large: a\r\n{}",
+            format!(" {}\r\n", "a".repeat(900)).repeat(200)
+        );
+
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, RejectingSmtpPersistor),
+            message_with_headers(&headers),
+        )
+        .await;
+
+        assert!(responses.ends_with("552 5.3.4 Message header section too large\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_line_length_limit() {
+        let line = "a".repeat(998);
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, DuplicateSmtpPersistor),
+            message_with_headers(&format!("X-Long: {}\r\n", &line[8..])),
+        )
+        .await;
+        assert!(
+            responses.ends_with("250 OK: Message accepted for delivery\r\n"),
+            "{responses}"
+        );
+
+        for message in [
+            message_with_headers(&format!("X-Long: {}\r\n", "a".repeat(992))),
+            format!("HELO {}\r\n", "a".repeat(1000)).into_bytes(),
+        ] {
+            let responses = run_handler(
+                |write_stream| SmtpHandler::new(write_stream, RejectingSmtpPersistor),
+                message,
+            )
+            .await;
+            assert!(
+                responses.ends_with("500 5.5.0 Line too long\r\n"),
+                "{responses}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_parsed_line_length_limit() {
+        let message = message_with_headers(&format!("X-Long: {}\r\n", "a".repeat(992)));
+        let (write_stream, mut responses) = tokio::io::duplex(1024);
+
+        SmtpHandler::new(write_stream, RejectingSmtpPersistor)
+            .handle_parsed(std::io::Cursor::new(message))
+            .await;
+
+        let mut output = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut responses, &mut output)
+            .await
+            .unwrap();
+        assert_eq!(output, "500 5.5.0 Line too long\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_parsed_rejects_too_many_headers() {
+        let headers: String = (0..200)
+            .map(|i| format!("X-Header-{i}: value\r\n"))
+            .collect();
+        let (write_stream, mut responses) = tokio::io::duplex(1024);
+
+        SmtpHandler::new(write_stream, RejectingSmtpPersistor)
+            .handle_parsed(std::io::Cursor::new(message_with_headers(&headers)))
+            .await;
+
+        let mut output = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut responses, &mut output)
+            .await
+            .unwrap();
+        assert_eq!(output, "552 5.3.4 Message header section too large\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_rejects_oversize_body() {
+        let body = "a".repeat(998) + "\r\n";
+        let message = format!(
+            "HELO example.com\r\nMAIL FROM: <sender@example.com>\r\nRCPT TO: <recipient@example.com>\r\nDATA\r\nSubject: Big\r\n\r\n{}.\r\n",
+            body.repeat(20)
+        );
+
+        let responses = run_handler(
+            |write_stream| {
+                SmtpHandler::new(write_stream, RejectingSmtpPersistor).with_max_body_bytes(10_000)
+            },
+            message.into_bytes(),
+        )
+        .await;
+
+        assert!(
+            responses.ends_with("552 5.3.4 Message too big\r\n"),
+            "{responses}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_rejects_huge_chunk_size() {
+        let message = format!(
+            "EHLO example.com\r\nMAIL FROM: <sender@example.com>\r\nRCPT TO: <recipient@example.com>\r\nBDAT 4\r\nabcdBDAT {} LAST\r\n",
+            usize::MAX
+        );
+
+        let responses = run_handler(
+            |write_stream| SmtpHandler::new(write_stream, RejectingSmtpPersistor),
+            message.into_bytes(),
+        )
+        .await;
+
+        assert!(
+            responses.ends_with("250 4 octets received\r\n552 5.3.4 Message too big\r\n"),
+            "{responses}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_rejects_declared_oversize_message() {
+        let message = "HELO example.com\r\nMAIL FROM: <sender@example.com> SIZE=10001\r\nMAIL FROM: <sender@example.com> SIZE=10000\r\n";
+
+        let responses = run_handler(
+            |write_stream| {
+                SmtpHandler::new(write_stream, RejectingSmtpPersistor).with_max_body_bytes(10_000)
+            },
+            message.as_bytes().to_vec(),
+        )
+        .await;
+
+        assert!(
+            responses.ends_with("552 5.3.4 Message too big\r\n250 OK\r\n"),
+            "{responses}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_rejects_invalid_mail_from_parameters() {
+        for command in [
+            "MAIL FROM: <>",
+            "MAIL FROM: <sender@example.com> BODY=BINARYMIME",
+            "MAIL FROM: <sender@example.com> SIZE=big",
+        ] {
+            let responses = run_handler(
+                |write_stream| SmtpHandler::new(write_stream, RejectingSmtpPersistor),
+                format!("HELO example.com\r\n{command}\r\n").into_bytes(),
+            )
+            .await;
+
+            assert!(
+                responses.ends_with("501 Syntax error in parameters or arguments\r\n"),
+                "{command}: {responses}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_parsed_rejects_oversize_body() {
+        let message = message_with_headers("Subject: Big\r\n");
+        let (write_stream, mut responses) = tokio::io::duplex(1024);
+
+        SmtpHandler::new(write_stream, RejectingSmtpPersistor)
+            .with_max_body_bytes(8)
+            .handle_parsed(std::io::Cursor::new(message))
+            .await;
+
+        let mut output = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut responses, &mut output)
+            .await
+            .unwrap();
+        assert_eq!(output, "552 5.3.4 Message too big\r\n");
+    }
+
+    async fn run_parsed<P: SmtpPersistor>(
+        handler: SmtpHandler<P, tokio::io::DuplexStream>,
+        responses: tokio::io::DuplexStream,
+        message: &str,
+    ) -> String {
+        let mut responses = responses;
+        handler
+            .handle_parsed(std::io::Cursor::new(message.to_string()))
+            .await;
+
+        let mut output = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut responses, &mut output)
+            .await
+            .unwrap();
+        output
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_parsed_delivers_each_transaction() {
+        let message = concat!(
+            "HELO example.com\r\n",
+            "MAIL FROM: <first@example.com>\r\n",
+            "RCPT TO: <recipient@example.com>\r\n",
+            "DATA\r\n",
+            "Subject: First\r\n",
+            "\r\n",
+            "One\r\n",
+            ".\r\n",
+            "MAIL FROM: <second@example.com>\r\n",
+            "RCPT TO: <other@example.com>\r\n",
+            "DATA\r\n",
+            "Subject: Second\r\n",
+            "\r\n",
+            "Two\r\n",
+            ".\r\n",
+        );
+        let persistor = RecordingPersistor::default();
+        let (write_stream, responses) = tokio::io::duplex(1024);
+
+        let output = run_parsed(
+            SmtpHandler::new(write_stream, persistor.clone()),
+            responses,
+            message,
+        )
+        .await;
+
+        assert_eq!(
+            output,
+            "250 OK: Message accepted for delivery\r\n".repeat(2),
+        );
+        let emails = persistor.emails();
+        let envelopes: Vec<_> = emails
+            .iter()
+            .map(|email| {
+                (
+                    email.from.to_string(),
+                    email.subject.as_str(),
+                    email.body.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            envelopes,
+            [
+                ("first@example.com".to_string(), "First", "One\r\n"),
+                ("second@example.com".to_string(), "Second", "Two\r\n"),
+            ]
+        );
+        // The second message only goes to its own recipient.
+        assert_eq!(
+            emails[1].envelope_to,
+            vec![EmailAddress::new_unchecked("other@example.com").into()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_parsed_bounces_matching_recipient() {
+        let message = concat!(
+            "HELO example.com\r\n",
+            "MAIL FROM: <sender@example.com>\r\n",
+            "RCPT TO: <bounce@example.com>\r\n",
+            "RCPT TO: <recipient@example.com>\r\n",
+            "DATA\r\n",
+            "Subject: Test Email\r\n",
+            "\r\n",
+            "Hello, world!\r\n",
+            ".\r\n",
+        );
+        let persistor = RecordingPersistor::default();
+        let (write_stream, responses) = tokio::io::duplex(1024);
+
+        let output = run_parsed(
+            SmtpHandler::new(write_stream, persistor.clone())
+                .with_bounce_rules(BounceRules::default().bounce("bounce@*")),
+            responses,
+            message,
+        )
+        .await;
+
+        assert_eq!(
+            output,
+            "550 No such user here\r\n250 OK: Message accepted for delivery\r\n"
+        );
+        assert_eq!(
+            persistor.emails()[0].envelope_to,
+            vec![EmailAddress::new_unchecked("recipient@example.com").into()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_parsed_refuses_message_without_recipients() {
+        let message = concat!(
+            "HELO example.com\r\n",
+            "MAIL FROM: <sender@example.com>\r\n",
+            "RCPT TO: <bounce@example.com>\r\n",
+            "DATA\r\n",
+            "Hello, world!\r\n",
+            ".\r\n",
+        );
+        let persistor = RecordingPersistor::default();
+        let (write_stream, responses) = tokio::io::duplex(1024);
+
+        let output = run_parsed(
+            SmtpHandler::new(write_stream, persistor.clone())
+                .with_bounce_rules(BounceRules::default().bounce("bounce@*")),
+            responses,
+            message,
+        )
+        .await;
+
+        assert_eq!(
+            output,
+            "550 No such user here\r\n503 5.5.1 No valid recipients\r\n"
+        );
+        assert!(persistor.emails().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_smtp_handler_parsed_refuses_non_ascii_address_without_smtputf8() {
+        let message = concat!(
+            "HELO example.com\r\n",
+            "MAIL FROM: <sender@example.com>\r\n",
+            "RCPT TO: <josé@exemplo.pt>\r\n",
+            "RCPT TO: <recipient@example.com>\r\n",
+            "DATA\r\n",
+            "Hello, world!\r\n",
+            ".\r\n",
+        );
+        let (write_stream, responses) = tokio::io::duplex(1024);
+
+        let output = run_parsed(
+            SmtpHandler::new(write_stream, RecordingPersistor::default()),
+            responses,
+            message,
+        )
+        .await;
+
+        assert_eq!(
+            output,
+            "553 5.6.7 Non-ASCII addresses require SMTPUTF8\r\n250 OK: Message accepted for delivery\r\n"
+        );
+    }
 }
@@ -0,0 +1,19 @@
+pub mod address;
+pub mod auth;
+pub mod bounce;
+pub mod client;
+pub mod dkim;
+pub mod email;
+pub mod greylist;
+pub mod handler;
+pub mod persistor;
+pub mod preview;
+pub mod rate_limit;
+#[cfg(feature = "relay")]
+pub mod relay;
+pub mod reply;
+pub mod server;
+pub mod spam;
+
+/// The database schema, shared by maild and the API.
+pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations");
@@ -1,78 +1,314 @@
-use crate::handler::SmtpHandler;
-use crate::persistor::SqlxPersistor;
-use std::collections::HashMap;
-use std::net::SocketAddr;
+#[cfg(feature = "auth-analysis")]
+use remail_maild::auth::DnsAuthAnalyzer;
+use remail_maild::auth::MessageAnalyzer;
+use remail_maild::auth::dns::HickoryResolver;
+use remail_maild::bounce::BounceRules;
+use remail_maild::client::SmtpClient;
+use remail_maild::dkim::{DkimPolicy, DkimVerifier, MailAuthDkimVerifier};
+use remail_maild::greylist::{Greylister, InMemoryGreylister};
+use remail_maild::handler::{DEFAULT_MAX_BODY_BYTES, SmtpHandler};
+use remail_maild::persistor::SqlxPersistor;
+use remail_maild::rate_limit::RateLimiter;
+use remail_maild::server::Server;
+use remail_maild::spam::{HeaderBasedSpamFilter, SpamFilter};
+use remail_smtp::{HeaderLimits, MAX_LINE_LENGTH};
 use std::sync::Arc;
-use tokio::net::TcpListener;
+use std::time::Duration;
 use tokio::signal;
-use tokio::sync::RwLock;
-use tokio::task::JoinHandle;
+use uuid::Uuid;
 
-mod email;
-mod handler;
-mod persistor;
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    /// Apply migrations, then accept SMTP connections until interrupted.
+    Serve,
+    /// Apply migrations and exit.
+    Migrate,
+    /// Send a stored email to another SMTP server, such as one under test.
+    Replay { id: Uuid, host: String, port: u16 },
+}
+
+impl Command {
+    fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut command = Command::Serve;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--migrate" => command = Command::Migrate,
+                "--replay" => {
+                    let usage = || "usage: --replay <email id> <host:port>".to_string();
+                    let id = args.next().ok_or_else(usage)?;
+                    let id = id
+                        .parse()
+                        .map_err(|e| format!("invalid email id {id}: {e}"))?;
+                    let target = args.next().ok_or_else(usage)?;
+                    let (host, port) = target
+                        .rsplit_once(':')
+                        .and_then(|(host, port)| Some((host.to_string(), port.parse().ok()?)))
+                        .ok_or_else(|| format!("invalid host:port {target}"))?;
+                    command = Command::Replay { id, host, port };
+                }
+                other => return Err(format!("unknown argument: {other}")),
+            }
+        }
+        Ok(command)
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let command = Command::from_args(std::env::args().skip(1))?;
     let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    sqlx::migrate!("./migrations");
 
     let pg_pool = sqlx::postgres::PgPoolOptions::new()
         .max_connections(5)
         .connect(&db_url)
         .await?;
-    let persistor = SqlxPersistor::new(pg_pool.clone());
+
+    run(command, pg_pool).await
+}
+
+async fn run(
+    command: Command,
+    pg_pool: sqlx::Pool<sqlx::Postgres>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    remail_maild::MIGRATOR.run(&pg_pool).await?;
+    match command {
+        Command::Serve => serve(pg_pool).await,
+        Command::Migrate => {
+            println!("Migrations applied");
+            Ok(())
+        }
+        Command::Replay { id, host, port } => replay(pg_pool, id, &host, port).await,
+    }
+}
+
+async fn replay(
+    pg_pool: sqlx::Pool<sqlx::Postgres>,
+    id: Uuid,
+    host: &str,
+    port: u16,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let email = SqlxPersistor::new(pg_pool)
+        .load_email(id)
+        .await?
+        .ok_or_else(|| format!("no email with id {id}"))?;
+    SmtpClient::connect(host, port).await?.send(&email).await?;
+    println!("Replayed {id} to {host}:{port}");
+    Ok(())
+}
+
+async fn serve(pg_pool: sqlx::Pool<sqlx::Postgres>) -> Result<(), Box<dyn std::error::Error>> {
+    let persistor = SqlxPersistor::new(pg_pool);
 
     let port: u16 = std::env::var("SMTP_PORT")
         .unwrap_or_else(|_| "2525".to_string())
         .parse()
         .expect("SMTP_PORT must be a valid u16");
+    // Containers need 0.0.0.0 for other services to reach the server.
+    let host = std::env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string());
 
-    let listener = TcpListener::bind(format!("localhost:{port}")).await?;
-    let active_connections = Arc::new(RwLock::new(HashMap::<SocketAddr, JoinHandle<()>>::new()));
-
-    println!("Listening on localhost:{port}");
-    println!("Press Ctrl+C to stop the server");
+    // Verification looks keys up over DNS for every message, so it only runs when asked for.
+    let dkim = match std::env::var("DKIM_POLICY").as_deref() {
+        Err(_) | Ok("off") => None,
+        Ok(policy) => {
+            let policy: DkimPolicy = policy
+                .parse()
+                .expect("DKIM_POLICY must be one of off, monitor or reject");
+            let verifier = HickoryResolver::from_system_conf()
+                .map_err(Into::into)
+                .and_then(|resolver| MailAuthDkimVerifier::new(Arc::new(resolver)));
+            match verifier {
+                Ok(verifier) => Some((Arc::new(verifier) as Arc<dyn DkimVerifier>, policy)),
+                Err(e) => {
+                    eprintln!("DKIM verification disabled, failed to create resolver: {e}");
+                    None
+                }
+            }
+        }
+    };
 
-    let active_connections_clone = active_connections.clone();
+    let greylister: Option<Arc<dyn Greylister>> =
+        std::env::var("GREYLIST_DELAY_SECS").ok().map(|delay| {
+            let delay = delay
+                .parse()
+                .expect("GREYLIST_DELAY_SECS must be a valid number of seconds");
+            Arc::new(InMemoryGreylister::new(Duration::from_secs(delay))) as Arc<dyn Greylister>
+        });
 
-    let accept_task = tokio::spawn(async move {
-        loop {
-            match listener.accept().await {
-                Ok((socket, addr)) => {
-                    println!("Accepted connection from {addr}");
-                    let (read_stream, write_stream) = socket.into_split();
-                    let handler = SmtpHandler::new(write_stream, persistor.clone());
+    let rate_limiter = std::env::var("RATE_LIMIT_PER_MINUTE").ok().map(|limit| {
+        let limit = limit
+            .parse()
+            .expect("RATE_LIMIT_PER_MINUTE must be a valid u32");
+        RateLimiter::per_minute(limit)
+    });
 
-                    let active_connections_clone_clone = active_connections_clone.clone();
-                    let handle = tokio::spawn(async move {
-                        handler.handle(read_stream).await;
-                        println!("Connection from {addr} closed");
-                        active_connections_clone_clone.write().await.remove(&addr);
-                    });
+    let spam_filter = std::env::var("SPAM_THRESHOLD").ok().map(|threshold| {
+        let threshold: f32 = threshold
+            .parse()
+            .expect("SPAM_THRESHOLD must be a valid number");
+        (
+            Arc::new(HeaderBasedSpamFilter) as Arc<dyn SpamFilter>,
+            threshold,
+        )
+    });
 
-                    active_connections_clone.write().await.insert(addr, handle);
-                }
-                Err(e) => {
-                    eprintln!("Failed to accept connection: {e}");
-                }
+    #[cfg(feature = "auth-analysis")]
+    let analyzer: Option<Arc<dyn MessageAnalyzer>> = match std::env::var("AUTH_ANALYSIS").as_deref()
+    {
+        Ok("off") => None,
+        _ => match HickoryResolver::from_system_conf() {
+            Ok(resolver) => Some(Arc::new(DnsAuthAnalyzer::new(Arc::new(resolver)))),
+            Err(e) => {
+                eprintln!("Authentication analysis disabled, failed to create resolver: {e}");
+                None
             }
+        },
+    };
+    #[cfg(not(feature = "auth-analysis"))]
+    let analyzer: Option<Arc<dyn MessageAnalyzer>> = None;
+
+    let header_limits = {
+        let defaults = HeaderLimits::default();
+        HeaderLimits {
+            max_count: std::env::var("MAX_HEADER_COUNT").map_or(defaults.max_count, |count| {
+                count
+                    .parse()
+                    .expect("MAX_HEADER_COUNT must be a valid number")
+            }),
+            max_bytes: std::env::var("MAX_HEADER_BYTES").map_or(defaults.max_bytes, |bytes| {
+                bytes
+                    .parse()
+                    .expect("MAX_HEADER_BYTES must be a valid number")
+            }),
         }
+    };
+
+    let max_body_bytes = std::env::var("MAX_BODY_BYTES").map_or(DEFAULT_MAX_BODY_BYTES, |bytes| {
+        bytes
+            .parse()
+            .expect("MAX_BODY_BYTES must be a valid number")
     });
 
+    let max_line_length = std::env::var("MAX_LINE_LENGTH").map_or(MAX_LINE_LENGTH, |length| {
+        length
+            .parse()
+            .expect("MAX_LINE_LENGTH must be a valid number")
+    });
+
+    let bounce_rules = bounce_rules_from_env();
+
+    let debug_connection_id = std::env::var("DEBUG_CONNECTION_ID").is_ok_and(|value| {
+        value
+            .parse()
+            .expect("DEBUG_CONNECTION_ID must be true or false")
+    });
+
+    let require_tls = std::env::var("REQUIRE_TLS")
+        .is_ok_and(|value| value.parse().expect("REQUIRE_TLS must be true or false"));
+
+    let server = Server::bind(
+        format!("{host}:{port}"),
+        rate_limiter,
+        move |write_stream, addr| {
+            let mut handler = SmtpHandler::new(write_stream, persistor.clone())
+                .with_peer_addr(addr)
+                .with_header_limits(header_limits)
+                .with_max_body_bytes(max_body_bytes)
+                .with_max_line_length(max_line_length)
+                .with_bounce_rules(bounce_rules.clone())
+                .with_debug_connection_id(debug_connection_id)
+                .with_require_tls(require_tls);
+            if let Some((verifier, policy)) = &dkim {
+                handler = handler.with_dkim_verifier(verifier.clone(), *policy);
+            }
+            if let Some(analyzer) = &analyzer {
+                handler = handler.with_analyzer(analyzer.clone());
+            }
+            if let Some(greylister) = &greylister {
+                handler = handler.with_greylister(greylister.clone());
+            }
+            if let Some((spam_filter, threshold)) = &spam_filter {
+                handler = handler.with_spam_filter(spam_filter.clone(), *threshold);
+            }
+            handler
+        },
+    )
+    .await?;
+
+    println!("Listening on {host}:{port}");
+    println!("Press Ctrl+C to stop the server");
+
     signal::ctrl_c().await?;
     println!("\nShutting down server...");
 
-    accept_task.abort();
-
-    let mut connections = active_connections.write().await;
-    for handle in connections.values_mut() {
-        handle
-            .await
-            .map_err(|e| eprintln!("Error joining task: {e:?}"))
-            .ok();
-    }
+    server.shutdown().await;
 
     println!("Server shutdown complete");
     Ok(())
 }
+
+/// Reads the comma-separated recipient patterns in `BOUNCE_PATTERNS` and `DEFER_PATTERNS`.
+fn bounce_rules_from_env() -> BounceRules {
+    let patterns = |name| {
+        std::env::var(name)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    let mut rules = BounceRules::default();
+    for pattern in patterns("BOUNCE_PATTERNS") {
+        rules = rules.bounce(&pattern);
+    }
+    for pattern in patterns("DEFER_PATTERNS") {
+        rules = rules.defer(&pattern);
+    }
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_from_args() {
+        assert_eq!(Command::from_args(Vec::new()), Ok(Command::Serve));
+        assert_eq!(
+            Command::from_args(vec!["--migrate".to_string()]),
+            Ok(Command::Migrate)
+        );
+        assert!(Command::from_args(vec!["--bogus".to_string()]).is_err());
+
+        const ID: &str = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        let args = |args: &[&str]| Command::from_args(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(
+            args(&["--replay", ID, "localhost:2526"]),
+            Ok(Command::Replay {
+                id: ID.parse().unwrap(),
+                host: "localhost".to_string(),
+                port: 2526,
+            })
+        );
+        assert!(args(&["--replay", "not-an-id", "localhost:2526"]).is_err());
+        assert!(args(&["--replay", ID, "localhost"]).is_err());
+        assert!(args(&["--replay"]).is_err());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_migrate_returns_without_serving(db: sqlx::Pool<sqlx::Postgres>) {
+        // Serving only returns on Ctrl+C, so finishing at all means the listener never started.
+        tokio::time::timeout(Duration::from_secs(30), run(Command::Migrate, db.clone()))
+            .await
+            .expect("migrate should not start the server")
+            .unwrap();
+
+        let applied: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert!(applied > 0);
+    }
+}
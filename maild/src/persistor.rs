@@ -1,54 +1,209 @@
-use crate::email::NewEmail;
+use crate::email::{MessageId, NewEmail};
+use chrono::{DateTime, FixedOffset};
+use remail_smtp::line::split_lines;
+use remail_smtp::mailbox::MailboxAddress;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PersistResult {
+    Inserted(Uuid),
+    /// An email with the same Message-ID was already stored, so nothing was written.
+    Duplicate,
+}
 
 pub trait SmtpPersistor {
-    async fn persist_email(&self, email: &NewEmail) -> Result<(), sqlx::Error>;
+    // The future is `Send` so generic code, such as `server::Server`, can spawn the handlers
+    // using a persistor. Implementations can still be written as `async fn`.
+    fn persist_email(
+        &self,
+        email: &NewEmail,
+    ) -> impl Future<Output = Result<PersistResult, sqlx::Error>> + Send;
 }
 
+/// The hostname in the Message-IDs generated for emails that arrive without one.
+pub const DEFAULT_HOSTNAME: &str = "localhost";
+
 #[derive(Clone)]
 pub struct SqlxPersistor {
     db: sqlx::Pool<sqlx::Postgres>,
+    hostname: String,
 }
 
 impl SqlxPersistor {
     pub fn new(db: sqlx::Pool<sqlx::Postgres>) -> Self {
-        Self { db }
+        Self {
+            db,
+            hostname: DEFAULT_HOSTNAME.to_string(),
+        }
+    }
+
+    pub fn with_hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = hostname.into();
+        self
+    }
+
+    /// The stored email with the given id, rebuilt from its headers and body as they were
+    /// received, so it can be sent again. `None` if there is no such email.
+    pub async fn load_email(&self, id: Uuid) -> Result<Option<NewEmail>, sqlx::Error> {
+        let Some(email) = sqlx::query!(
+            r#"SELECT "from", "to", envelope_to, body, raw_body, received_bytes FROM emails WHERE id = $1"#,
+            id
+        )
+        .fetch_optional(&self.db)
+        .await?
+        else {
+            return Ok(None);
+        };
+        let headers = sqlx::query!(
+            "SELECT key, value FROM email_headers WHERE email_id = $1 ORDER BY ordinal",
+            id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut raw = Vec::new();
+        for header in headers {
+            raw.extend_from_slice(header.key.as_bytes());
+            raw.extend_from_slice(b": ");
+            raw.extend_from_slice(header.value.replace('\n', "\r\n ").as_bytes());
+            raw.extend_from_slice(b"\r\n");
+        }
+        raw.extend_from_slice(b"\r\n");
+        raw.extend(email.raw_body.unwrap_or_else(|| email.body.into_bytes()));
+
+        let lines = split_lines(&raw);
+
+        let address = |value: &str| {
+            value
+                .parse::<MailboxAddress>()
+                .map_err(|e| sqlx::Error::Decode(Box::new(e)))
+        };
+        let mut loaded =
+            NewEmail::from_raw_bytes(address(&email.from)?, address(&email.to)?, lines);
+        loaded.envelope_to = email
+            .envelope_to
+            .iter()
+            .map(|to| address(to))
+            .collect::<Result<_, _>>()?;
+        loaded.received_bytes = email.received_bytes as usize;
+        Ok(Some(loaded))
     }
 }
 
 impl SmtpPersistor for SqlxPersistor {
-    async fn persist_email(&self, email: &NewEmail) -> Result<(), sqlx::Error> {
+    async fn persist_email(&self, email: &NewEmail) -> Result<PersistResult, sqlx::Error> {
+        // Every stored email gets a Message-ID, so it can be deduplicated and threaded.
+        let generated;
+        let email = match email.message_id {
+            Some(_) => email,
+            None => {
+                let mut email = email.clone();
+                email.set_message_id(MessageId::generate(Uuid::new_v4(), &self.hostname));
+                generated = email;
+                &generated
+            }
+        };
+        let message_id = email.message_id.as_deref();
+
+        if let Some(message_id) = message_id {
+            let existing = sqlx::query!("SELECT id FROM emails WHERE message_id = $1", message_id)
+                .fetch_optional(&self.db)
+                .await?;
+            if existing.is_some() {
+                return Ok(PersistResult::Duplicate);
+            }
+        }
+
+        let envelope_to: Vec<String> = email.envelope_to.iter().map(|to| to.to_string()).collect();
+        let references: Vec<String> = email.references.iter().map(|id| id.to_string()).collect();
         let mut tx = self.db.begin().await?;
 
-        let email_id = sqlx::query!(
-            r#"INSERT INTO emails ("from", "to", subject, body) VALUES ($1, $2, $3, $4) RETURNING id"#,
+        let inserted = sqlx::query!(
+            r#"INSERT INTO emails ("from", "to", envelope_to, subject, body, preview, body_bytes, body_lines, dkim_result, message_id, "date", in_reply_to, "references", reply_to, raw_body, auth_results, received_bytes) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17) RETURNING id"#,
             email.from.to_string(),
             email.to.to_string(),
+            &envelope_to,
             email.subject,
-            email.body
+            email.body,
+            email.preview,
+            email.body_bytes as i64,
+            email.body_lines as i64,
+            email.dkim_result,
+            message_id,
+            // Bound as chrono rather than the `time` type the macro expects for TIMESTAMPTZ.
+            email.date as Option<DateTime<FixedOffset>>,
+            email.in_reply_to.as_deref(),
+            &references,
+            email.reply_to.as_ref().map(|reply_to| reply_to.to_string()),
+            email.raw_body.as_deref(),
+            serde_json::json!(email.auth_results),
+            email.received_bytes as i64
         )
         .fetch_one(&mut *tx)
-        .await?
-        .id;
+        .await;
+
+        let email_id = match inserted {
+            Ok(row) => row.id,
+            // A concurrent delivery of the same message won the race since the check above.
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                return Ok(PersistResult::Duplicate);
+            }
+            Err(e) => return Err(e),
+        };
 
         if !email.headers.is_empty() {
             let mut query =
-                String::from("INSERT INTO email_headers (email_id, key, value) VALUES ");
+                String::from("INSERT INTO email_headers (email_id, key, value, ordinal) VALUES ");
 
             for (i, _) in email.headers.iter().enumerate() {
                 if i > 0 {
                     query.push_str(", ");
                 }
-                query.push_str(&format!("(${}, ${}, ${})", i * 3 + 1, i * 3 + 2, i * 3 + 3));
+                query.push_str(&format!(
+                    "(${}, ${}, ${}, ${})",
+                    i * 4 + 1,
+                    i * 4 + 2,
+                    i * 4 + 3,
+                    i * 4 + 4
+                ));
             }
 
             let mut query_builder = sqlx::query(&query);
-            for (key, value) in &email.headers {
-                query_builder = query_builder.bind(email_id).bind(key).bind(value);
+            for (ordinal, (key, value)) in email.headers.iter().enumerate() {
+                query_builder = query_builder
+                    .bind(email_id)
+                    .bind(key)
+                    .bind(value)
+                    .bind(ordinal as i32);
             }
             query_builder.execute(&mut *tx).await?;
         }
 
         tx.commit().await?;
-        Ok(())
+        Ok(PersistResult::Inserted(email_id))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Accepts every email, keeping them in a list its clones share.
+    #[derive(Clone, Default)]
+    pub(crate) struct RecordingPersistor(Arc<Mutex<Vec<NewEmail>>>);
+
+    impl RecordingPersistor {
+        /// The emails persisted so far, in order.
+        pub(crate) fn emails(&self) -> Vec<NewEmail> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    impl SmtpPersistor for RecordingPersistor {
+        async fn persist_email(&self, email: &NewEmail) -> Result<PersistResult, sqlx::Error> {
+            self.0.lock().unwrap().push(email.clone());
+            Ok(PersistResult::Inserted(Uuid::nil()))
+        }
     }
 }
@@ -0,0 +1,371 @@
+//! Short plain-text previews of message bodies, for list views that cannot show raw MIME or
+//! HTML.
+//!
+//! The preview comes from the first `text/plain` part, or from the first `text/html` part
+//! stripped of its markup when there is none, with runs of whitespace collapsed.
+
+use remail_smtp::{headers, transfer_encoding};
+use remail_types::Headers;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The most characters a preview holds before it is cut, counting each emoji or other
+/// user-perceived character as one.
+pub const PREVIEW_CHARS: usize = 160;
+
+/// The preview of a body with the given headers. `body` is the body as stored: a single-part
+/// text body is already decoded, while the parts of a multipart body are decoded here.
+pub fn preview(headers: &Headers, body: &str) -> String {
+    let content_type = headers.get("Content-Type").unwrap_or("text/plain");
+    let parts = if is_multipart(content_type) {
+        leaf_parts(content_type, body)
+    } else {
+        vec![(media_type(content_type), body.to_string())]
+    };
+
+    let text = match parts
+        .iter()
+        .find(|(media_type, _)| media_type == "text/plain")
+    {
+        Some((_, text)) => text.clone(),
+        None => parts
+            .iter()
+            .find(|(media_type, _)| media_type == "text/html")
+            .map(|(_, html)| strip_html(html))
+            .unwrap_or_default(),
+    };
+    truncate(&collapse_whitespace(&text), PREVIEW_CHARS)
+}
+
+/// The value of the `name` parameter in a header such as `Content-Type: text/plain; charset=utf-8`.
+fn param<'a>(value: &'a str, name: &str) -> Option<&'a str> {
+    value.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+fn media_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+fn is_multipart(content_type: &str) -> bool {
+    media_type(content_type).starts_with("multipart/")
+}
+
+/// The media type and decoded text of each leaf part of a multipart body, descending into
+/// nested multiparts and leaving out attachments.
+fn leaf_parts(content_type: &str, body: &str) -> Vec<(String, String)> {
+    let Some(boundary) = param(content_type, "boundary") else {
+        return Vec::new();
+    };
+    let delimiter = format!("--{boundary}");
+    let closing = format!("--{boundary}--");
+
+    let mut raw_parts: Vec<Vec<&str>> = Vec::new();
+    let mut current: Option<Vec<&str>> = None;
+    for line in body.lines() {
+        if line.trim_end() == closing {
+            raw_parts.extend(current.take());
+            break;
+        } else if line.trim_end() == delimiter {
+            raw_parts.extend(current.replace(Vec::new()));
+        } else if let Some(lines) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+    raw_parts.extend(current);
+
+    let mut parts = Vec::new();
+    for lines in raw_parts {
+        let part_headers = Headers::from(headers::parse(lines.iter().copied()));
+        let part_body = lines
+            .iter()
+            .skip_while(|line| !line.is_empty())
+            .skip(1)
+            .copied()
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        let content_type = part_headers.get("Content-Type").unwrap_or("text/plain");
+        let disposition = part_headers.get("Content-Disposition").unwrap_or_default();
+        if is_multipart(content_type) {
+            parts.extend(leaf_parts(content_type, &part_body));
+        } else if !disposition
+            .trim_start()
+            .to_lowercase()
+            .starts_with("attachment")
+        {
+            let encoding = part_headers
+                .get("Content-Transfer-Encoding")
+                .unwrap_or_default();
+            let decoded = transfer_encoding::decode(encoding, part_body.as_bytes());
+            let text = match param(content_type, "charset") {
+                Some(charset) => transfer_encoding::decode_charset(charset, &decoded),
+                None => String::from_utf8_lossy(&decoded).into_owned(),
+            };
+            parts.push((media_type(content_type), text));
+        }
+    }
+    parts
+}
+
+/// Elements whose content is never shown, so none of it belongs in a preview.
+const HIDDEN_ELEMENTS: &[&str] = &["head", "script", "style", "template", "title"];
+
+/// Elements that start a new line when rendered, so their text is not run into the text
+/// around them.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dt",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "li",
+    "p",
+    "pre",
+    "table",
+    "td",
+    "th",
+    "tr",
+];
+
+/// The text of an HTML document: tags and comments are removed, as is everything inside the
+/// [`HIDDEN_ELEMENTS`], and character references are decoded.
+pub fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&decode_entities(&rest[..start]));
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.split_once("-->").map_or("", |(_, after)| after);
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            // A `<` that opens no tag is text.
+            text.push_str(&decode_entities(rest));
+            return text;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        if !tag.starts_with('/') && HIDDEN_ELEMENTS.contains(&name.as_str()) {
+            rest = after_closing_tag(rest, &name);
+        } else if BLOCK_ELEMENTS.contains(&name.as_str()) {
+            text.push(' ');
+        }
+    }
+    text.push_str(&decode_entities(rest));
+    text
+}
+
+/// What follows the closing tag of the element `name` in `html`, or nothing when it is not
+/// closed.
+fn after_closing_tag<'a>(html: &'a str, name: &str) -> &'a str {
+    let closing = format!("</{name}");
+    let Some(start) = html.to_ascii_lowercase().find(&closing) else {
+        return "";
+    };
+    let rest = &html[start..];
+    rest.find('>').map_or("", |end| &rest[end + 1..])
+}
+
+/// Decodes character references, such as `&amp;`, `&#233;` and `&#x1F389;`. Unknown ones are
+/// kept as they are.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 32)
+            .and_then(|end| Some((decode_entity(&rest[1..end + 1])?, end + 2)));
+        match reference {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        "copy" => Some('©'),
+        "reg" => Some('®'),
+        "trade" => Some('™'),
+        "hellip" => Some('…'),
+        "mdash" => Some('—'),
+        "ndash" => Some('–'),
+        "lsquo" => Some('‘'),
+        "rsquo" => Some('’'),
+        "ldquo" => Some('“'),
+        "rdquo" => Some('”'),
+        "zwnj" => Some('\u{200c}'),
+        _ => None,
+    }
+}
+
+/// Joins the words of `text` with single spaces.
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cuts `text` to its first `max` user-perceived characters, ending it with an ellipsis when
+/// anything was cut. Emoji made of several code points are kept whole.
+pub fn truncate(text: &str, max: usize) -> String {
+    match text.grapheme_indices(true).nth(max) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> Headers {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_strip_html() {
+        let table = [
+            ("<p>Hello, <b>world</b>!</p>", " Hello, world! "),
+            ("Fish &amp; chips &lt;3", "Fish & chips <3"),
+            ("caf&#233; &#x1F389; &eacute;", "café 🎉 &eacute;"),
+            ("a&nbsp;b", "a\u{a0}b"),
+            ("AT&T & co", "AT&T & co"),
+            (
+                "<html><head><title>Ignored</title><style>p { color: red; }</style></head><body>Shown</body></html>",
+                "Shown",
+            ),
+            ("<SCRIPT type=\"x\">alert('<p>')</SCRIPT>After", "After"),
+            ("Before<!-- <p>hidden</p> -->After", "BeforeAfter"),
+            ("line<br/>break", "line break"),
+            ("1 < 2", "1 < 2"),
+            ("<style>unclosed", ""),
+        ];
+
+        for (html, expected) in table {
+            assert_eq!(strip_html(html), expected, "{html}");
+        }
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("exactly 10", 10), "exactly 10");
+        assert_eq!(truncate("one two three", 8), "one two…");
+        assert_eq!(truncate("Grüße aus Köln", 5), "Grüße…");
+        // A family emoji is several code points joined together, and a flag is two.
+        assert_eq!(truncate("👨‍👩‍👧‍👦👨‍👩‍👧‍👦👨‍👩‍👧‍👦", 2), "👨‍👩‍👧‍👦👨‍👩‍👧‍👦…");
+        assert_eq!(truncate("🇵🇹🇧🇷", 1), "🇵🇹…");
+        assert_eq!(truncate("e\u{301}e\u{301}", 1), "e\u{301}…");
+    }
+
+    #[test]
+    fn test_preview_of_single_part_bodies() {
+        assert_eq!(
+            preview(&Headers::new(), "Hello,\r\n\r\n  world!\r\n"),
+            "Hello, world!"
+        );
+        assert_eq!(
+            preview(
+                &headers(&[("Content-Type", "text/html; charset=utf-8")]),
+                "<h1>Welcome</h1><p>Thanks &amp; enjoy</p>"
+            ),
+            "Welcome Thanks & enjoy"
+        );
+        assert_eq!(
+            preview(&headers(&[("Content-Type", "image/png")]), "iVBORw0KGgo="),
+            ""
+        );
+
+        let long = "word ".repeat(100);
+        let preview = preview(&Headers::new(), &long);
+        assert_eq!(preview.chars().count(), PREVIEW_CHARS);
+        assert!(preview.ends_with("word…"), "{preview}");
+    }
+
+    #[test]
+    fn test_preview_of_multipart_bodies() {
+        let alternative = "--inner\r\n\
+            Content-Type: text/html; charset=utf-8\r\n\
+            Content-Transfer-Encoding: base64\r\n\
+            \r\n\
+            PHA+Q2Fmw6kgJmFtcDsgY28uPC9wPg==\r\n\
+            --inner--\r\n";
+        let body = format!(
+            "This is a multipart message.\r\n\
+            --outer\r\n\
+            Content-Type: multipart/alternative; boundary=inner\r\n\
+            \r\n\
+            {alternative}\
+            --outer\r\n\
+            Content-Type: text/plain\r\n\
+            Content-Disposition: attachment; filename=notes.txt\r\n\
+            \r\n\
+            Not the body\r\n\
+            --outer--\r\n"
+        );
+        let headers = headers(&[("Content-Type", "multipart/mixed; boundary=\"outer\"")]);
+        assert_eq!(preview(&headers, &body), "Café & co.");
+
+        let body = body.replace(
+            "--inner--",
+            "--inner\r\n\
+            Content-Type: text/plain; charset=iso-8859-1\r\n\
+            Content-Transfer-Encoding: quoted-printable\r\n\
+            \r\n\
+            Caf=E9 and co.\r\n\
+            --inner--",
+        );
+        assert_eq!(preview(&headers, &body), "Café and co.");
+    }
+}
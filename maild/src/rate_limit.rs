@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use tokio::time::Instant;
+
+/// Number of tracked peers above which buckets that have refilled completely are dropped.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token bucket rate limiter keyed on the peer IP.
+///
+/// Every peer starts with `capacity` tokens, each connection takes one, and tokens are
+/// refilled continuously at `capacity` per minute.
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+    capacity: f64,
+    refill_per_sec: f64,
+}
+
+impl RateLimiter {
+    pub fn per_minute(connections: u32) -> Self {
+        let capacity = f64::from(connections);
+        Self {
+            buckets: Mutex::new(HashMap::new()),
+            capacity,
+            refill_per_sec: capacity / 60.0,
+        }
+    }
+
+    /// Takes a token for `ip`, returning whether the connection is allowed.
+    pub fn check(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| {
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens + elapsed * self.refill_per_sec < self.capacity
+            });
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: self.capacity,
+            last_refill: now,
+        });
+
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttles_rapid_connections() {
+        let limiter = RateLimiter::per_minute(3);
+        let peer = IpAddr::from([192, 0, 2, 1]);
+
+        let allowed: Vec<bool> = (0..5).map(|_| limiter.check(peer)).collect();
+        assert_eq!(allowed, vec![true, true, true, false, false]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_refills_over_time() {
+        let limiter = RateLimiter::per_minute(6);
+        let peer = IpAddr::from([192, 0, 2, 1]);
+
+        for _ in 0..6 {
+            assert!(limiter.check(peer));
+        }
+        assert!(!limiter.check(peer));
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(limiter.check(peer));
+        assert!(!limiter.check(peer));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_peers_are_limited_independently() {
+        let limiter = RateLimiter::per_minute(1);
+
+        assert!(limiter.check(IpAddr::from([192, 0, 2, 1])));
+        assert!(!limiter.check(IpAddr::from([192, 0, 2, 1])));
+        assert!(limiter.check(IpAddr::from([192, 0, 2, 2])));
+    }
+}
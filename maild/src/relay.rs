@@ -0,0 +1,246 @@
+//! Delivery of messages to the mail servers of their recipients' domains, found through MX
+//! records as RFC 5321 section 5 describes.
+
+use crate::auth::dns::{DnsError, DnsResolver, MxRecord};
+use crate::client::{SmtpClient, SmtpClientError};
+use crate::email::NewEmail;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::time::Instant;
+
+/// How long the MX records of a domain are reused before they are looked up again.
+const MX_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// The port mail servers accept relayed mail on.
+const SMTP_PORT: u16 = 25;
+
+/// The MX records looked up for each domain, with when they were looked up.
+type MxCache = Arc<RwLock<HashMap<String, (Vec<MxRecord>, Instant)>>>;
+
+#[derive(Debug)]
+pub enum RelayError {
+    /// The recipient's domain has no mail servers, or they could not be looked up.
+    Dns(DnsError),
+    /// Every mail server failed; this is the error of the last one tried.
+    Smtp(SmtpClientError),
+}
+
+impl fmt::Display for RelayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelayError::Dns(e) => write!(f, "MX lookup failed: {e}"),
+            RelayError::Smtp(e) => write!(f, "delivery failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for RelayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RelayError::Dns(e) => Some(e),
+            RelayError::Smtp(e) => Some(e),
+        }
+    }
+}
+
+/// Relays messages to the MX hosts of their recipient's domain, in order of preference.
+#[derive(Clone)]
+pub struct MxRelay {
+    resolver: Arc<dyn DnsResolver>,
+    cache: MxCache,
+    port: u16,
+}
+
+impl MxRelay {
+    pub fn new(resolver: Arc<dyn DnsResolver>) -> Self {
+        Self {
+            resolver,
+            cache: MxCache::default(),
+            port: SMTP_PORT,
+        }
+    }
+
+    /// Connects to the MX hosts on `port` instead of 25.
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// The MX records of `domain`, lowest priority value first. Records are cached for five
+    /// minutes; failed lookups are not.
+    pub async fn lookup_mx(&self, domain: &str) -> Result<Vec<MxRecord>, DnsError> {
+        let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+        if let Some((records, looked_up)) = self.cache.read().await.get(&domain)
+            && looked_up.elapsed() < MX_CACHE_TTL
+        {
+            return Ok(records.clone());
+        }
+
+        let mut records = self.resolver.mx(&domain).await?;
+        if records.is_empty() {
+            return Err(DnsError::NotFound);
+        }
+        records.sort_by_key(|mx| mx.priority);
+        self.cache
+            .write()
+            .await
+            .insert(domain, (records.clone(), Instant::now()));
+        Ok(records)
+    }
+
+    /// Sends `email` to the first MX host of its recipient's domain that accepts a connection.
+    ///
+    /// Only failing to connect moves on to the next host; a host that rejects the message
+    /// fails the delivery.
+    pub async fn deliver(&self, email: &NewEmail) -> Result<(), RelayError> {
+        let records = self
+            .lookup_mx(email.to.domain())
+            .await
+            .map_err(RelayError::Dns)?;
+
+        let mut last_error = None;
+        for record in records {
+            match SmtpClient::connect(&record.hostname, self.port).await {
+                Ok(client) => return client.send(email).await.map_err(RelayError::Smtp),
+                Err(e) => {
+                    eprintln!("Could not connect to MX {}: {e}", record.hostname);
+                    last_error = Some(e);
+                }
+            }
+        }
+        // `lookup_mx` never returns an empty list, so at least one host was tried.
+        Err(RelayError::Smtp(last_error.expect("no MX host was tried")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::dns::tests::StubResolver;
+    use crate::handler::SmtpHandler;
+    use crate::persistor::tests::RecordingPersistor;
+    use crate::server::Server;
+    use async_trait::async_trait;
+    use email_address::EmailAddress;
+    use std::net::IpAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts the MX lookups reaching a [`StubResolver`].
+    struct CountingResolver {
+        inner: StubResolver,
+        mx_lookups: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl DnsResolver for CountingResolver {
+        async fn txt(&self, name: &str) -> Result<Vec<String>, DnsError> {
+            self.inner.txt(name).await
+        }
+
+        async fn ips(&self, name: &str) -> Result<Vec<IpAddr>, DnsError> {
+            self.inner.ips(name).await
+        }
+
+        async fn mx(&self, name: &str) -> Result<Vec<MxRecord>, DnsError> {
+            self.mx_lookups.fetch_add(1, Ordering::SeqCst);
+            self.inner.mx(name).await
+        }
+    }
+
+    fn email() -> NewEmail {
+        NewEmail::from_raw_message(
+            EmailAddress::new_unchecked("sender@example.com"),
+            EmailAddress::new_unchecked("recipient@example.org"),
+            vec![
+                "Subject: Relayed".to_string(),
+                String::new(),
+                "Hello".to_string(),
+            ],
+        )
+    }
+
+    #[tokio::test]
+    async fn test_lookup_mx_sorts_by_priority() {
+        let resolver = StubResolver::default()
+            .with_mx("example.org", 20, "backup.example.org")
+            .with_mx("example.org", 10, "mx.example.org");
+        let relay = MxRelay::new(Arc::new(resolver));
+
+        assert_eq!(
+            relay.lookup_mx("Example.org.").await,
+            Ok(vec![
+                MxRecord {
+                    priority: 10,
+                    hostname: "mx.example.org".to_string()
+                },
+                MxRecord {
+                    priority: 20,
+                    hostname: "backup.example.org".to_string()
+                },
+            ])
+        );
+        assert_eq!(
+            relay.lookup_mx("example.net").await,
+            Err(DnsError::NotFound)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lookup_mx_is_cached() {
+        let resolver = Arc::new(CountingResolver {
+            inner: StubResolver::default().with_mx("example.org", 10, "mx.example.org"),
+            mx_lookups: AtomicUsize::new(0),
+        });
+        let relay = MxRelay::new(resolver.clone());
+
+        relay.lookup_mx("example.org").await.unwrap();
+        relay.lookup_mx("example.org").await.unwrap();
+        assert_eq!(resolver.mx_lookups.load(Ordering::SeqCst), 1);
+
+        tokio::time::advance(MX_CACHE_TTL).await;
+        relay.lookup_mx("example.org").await.unwrap();
+        assert_eq!(resolver.mx_lookups.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_deliver_falls_back_to_next_mx() {
+        let persistor = RecordingPersistor::default();
+        let server = {
+            let persistor = persistor.clone();
+            Server::bind("127.0.0.1:0", None, move |write_stream, addr| {
+                SmtpHandler::new(write_stream, persistor.clone()).with_peer_addr(addr)
+            })
+            .await
+            .unwrap()
+        };
+
+        // Nothing listens on 127.0.0.2, so the preferred host refuses the connection.
+        let resolver = StubResolver::default()
+            .with_mx("example.org", 10, "127.0.0.2")
+            .with_mx("example.org", 20, "127.0.0.1");
+        let relay = MxRelay::new(Arc::new(resolver)).with_port(server.local_addr().port());
+
+        relay.deliver(&email()).await.unwrap();
+        server.shutdown().await;
+
+        let emails = persistor.emails();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].to.as_str(), "recipient@example.org");
+        assert_eq!(emails[0].subject, "Relayed");
+    }
+
+    #[tokio::test]
+    async fn test_deliver_without_mx_records() {
+        let relay = MxRelay::new(Arc::new(
+            StubResolver::default().with_failure("example.org"),
+        ));
+
+        assert!(matches!(
+            relay.deliver(&email()).await,
+            Err(RelayError::Dns(DnsError::Failed(_)))
+        ));
+    }
+}
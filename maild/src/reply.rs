@@ -0,0 +1,117 @@
+use std::borrow::Cow;
+use std::fmt;
+
+/// A reply to an SMTP command, written as `{code} {text}\r\n`.
+///
+/// A multi-line reply keeps its lines in `text` separated by `\n`; every line but the last
+/// is written as a `{code}-{line}` continuation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmtpReply {
+    pub code: u16,
+    pub text: Cow<'static, str>,
+}
+
+impl SmtpReply {
+    pub const fn new(code: u16, text: &'static str) -> Self {
+        Self {
+            code,
+            text: Cow::Borrowed(text),
+        }
+    }
+
+    pub fn multiline<I>(code: u16, lines: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let lines: Vec<String> = lines.into_iter().map(Into::into).collect();
+        Self {
+            code,
+            text: Cow::Owned(lines.join("\n")),
+        }
+    }
+
+    /// The reply to a `BDAT` chunk other than the last, as RFC 3030 section 4.2 suggests.
+    pub fn chunk_received(size: usize) -> Self {
+        Self {
+            code: 250,
+            text: Cow::Owned(format!("{size} octets received")),
+        }
+    }
+
+    pub const SERVICE_READY: Self = Self::new(220, "smt.example.com ESMTP Remail");
+    pub const CLOSING: Self = Self::new(221, "2.0.0 Bye");
+    pub const HELLO: Self = Self::new(250, "Hello");
+    /// The reply to `EHLO`, listing the supported extensions after the greeting.
+    pub const EHLO: Self = Self::new(250, "Hello\n8BITMIME\nSMTPUTF8\nCHUNKING");
+    pub const OK: Self = Self::new(250, "OK");
+    pub const MESSAGE_ACCEPTED: Self = Self::new(250, "OK: Message accepted for delivery");
+    pub const START_MAIL_INPUT: Self = Self::new(354, "Start mail input; end with <CRLF>.<CRLF>");
+    pub const MAILBOX_UNAVAILABLE: Self =
+        Self::new(450, "4.2.1 Mailbox unavailable, try again later");
+    pub const TRY_AGAIN_LATER: Self = Self::new(451, "4.7.1 Please try again later");
+    pub const TOO_MANY_RECIPIENTS: Self = Self::new(452, "4.5.3 Too many recipients");
+    pub const TLS_NOT_AVAILABLE: Self =
+        Self::new(454, "4.7.0 TLS not available due to temporary reason");
+    pub const UNRECOGNIZED_COMMAND: Self = Self::new(500, "Unrecognized command");
+    pub const SYNTAX_ERROR: Self = Self::new(501, "Syntax error in parameters or arguments");
+    pub const BAD_SEQUENCE: Self = Self::new(503, "Bad sequence of commands");
+    pub const UNKNOWN_HELP_TOPIC: Self = Self::new(504, "5.5.4 HELP topic not recognized");
+    pub const NO_VALID_RECIPIENTS: Self = Self::new(503, "5.5.1 No valid recipients");
+    pub const TLS_REQUIRED: Self = Self::new(530, "Must issue a STARTTLS command first");
+    pub const NO_SUCH_USER: Self = Self::new(550, "No such user here");
+    pub const INVALID_RECIPIENT: Self = Self::new(550, "5.1.3 Invalid recipient address");
+    pub const INTERNAL_ERROR: Self = Self::new(550, "Internal server error");
+    pub const DKIM_FAILED: Self = Self::new(550, "5.7.20 DKIM verification failed");
+    pub const HEADERS_TOO_LARGE: Self = Self::new(552, "5.3.4 Message header section too large");
+    pub const MESSAGE_TOO_BIG: Self = Self::new(552, "5.3.4 Message too big");
+    pub const LINE_TOO_LONG: Self = Self::new(500, "5.5.0 Line too long");
+    /// RFC 6531 section 3.7.4.1: an address that is not ASCII in a transaction that did not
+    /// declare `SMTPUTF8`.
+    pub const NON_ASCII_ADDRESS: Self =
+        Self::new(553, "5.6.7 Non-ASCII addresses require SMTPUTF8");
+    pub const TRANSACTION_BLOCKED: Self = Self::new(554, "5.7.1 Transaction blocked");
+}
+
+impl fmt::Display for SmtpReply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = self.text.split('\n').peekable();
+        while let Some(line) = lines.next() {
+            let separator = if lines.peek().is_some() { '-' } else { ' ' };
+            write!(f, "{}{separator}{line}\r\n", self.code)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_line() {
+        assert_eq!(SmtpReply::OK.to_string(), "250 OK\r\n");
+        assert_eq!(
+            SmtpReply::BAD_SEQUENCE.to_string(),
+            "503 Bad sequence of commands\r\n"
+        );
+    }
+
+    #[test]
+    fn test_multiline() {
+        let reply = SmtpReply::multiline(250, ["remail Hello", "PIPELINING", "SIZE 1000"]);
+
+        assert_eq!(
+            reply.to_string(),
+            "250-remail Hello\r\n250-PIPELINING\r\n250 SIZE 1000\r\n"
+        );
+    }
+
+    #[test]
+    fn test_multiline_with_one_line() {
+        assert_eq!(
+            SmtpReply::multiline(250, ["Hello"]).to_string(),
+            "250 Hello\r\n"
+        );
+    }
+}
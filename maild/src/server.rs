@@ -0,0 +1,194 @@
+//! maild's accept loop: every connection is handed to an [`SmtpHandler`] running on a task of
+//! its own.
+
+use crate::handler::SmtpHandler;
+use crate::persistor::SmtpPersistor;
+use crate::rate_limit::RateLimiter;
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+/// The tasks handling open connections, by the peer's address.
+type Connections = Arc<RwLock<HashMap<SocketAddr, JoinHandle<()>>>>;
+
+/// A listener accepting SMTP connections until [`Server::shutdown`] or until it is dropped.
+pub struct Server {
+    local_addr: SocketAddr,
+    accept_task: JoinHandle<()>,
+    connections: Connections,
+}
+
+impl Server {
+    /// Starts accepting connections on `addr`, which may have port 0 to pick an ephemeral
+    /// port.
+    ///
+    /// `new_handler` builds the handler for each connection, from the stream replies are
+    /// written to and the peer's address. Peers over `rate_limiter`'s limit are refused with
+    /// a 421 instead.
+    pub async fn bind<P, F>(
+        addr: impl ToSocketAddrs,
+        rate_limiter: Option<RateLimiter>,
+        new_handler: F,
+    ) -> io::Result<Self>
+    where
+        P: SmtpPersistor + Send + Sync + 'static,
+        F: Fn(OwnedWriteHalf, SocketAddr) -> SmtpHandler<P, OwnedWriteHalf> + Send + 'static,
+    {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let connections = Connections::default();
+        let accept_task = tokio::spawn(accept_loop(
+            listener,
+            rate_limiter,
+            connections.clone(),
+            new_handler,
+        ));
+
+        Ok(Self {
+            local_addr,
+            accept_task,
+            connections,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops accepting connections, then waits for the open ones to close.
+    pub async fn shutdown(self) {
+        self.accept_task.abort();
+
+        let mut connections = self.connections.write().await;
+        for (_, handle) in connections.drain() {
+            handle
+                .await
+                .map_err(|e| eprintln!("Error joining task: {e:?}"))
+                .ok();
+        }
+    }
+}
+
+impl Drop for Server {
+    /// Stops accepting connections. Unlike [`Server::shutdown`], open ones are left to close
+    /// on their own.
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
+async fn accept_loop<P, F>(
+    listener: TcpListener,
+    rate_limiter: Option<RateLimiter>,
+    connections: Connections,
+    new_handler: F,
+) where
+    P: SmtpPersistor + Send + Sync + 'static,
+    F: Fn(OwnedWriteHalf, SocketAddr) -> SmtpHandler<P, OwnedWriteHalf>,
+{
+    loop {
+        match listener.accept().await {
+            Ok((mut socket, addr)) => {
+                println!("Accepted connection from {addr}");
+
+                if let Some(rate_limiter) = &rate_limiter
+                    && !rate_limiter.check(addr.ip())
+                {
+                    println!("Rate limit exceeded for {addr}");
+                    tokio::spawn(async move {
+                        if let Err(e) = socket.write_all(b"421 Rate limit exceeded\r\n").await {
+                            eprintln!("Error writing to stream: {e}");
+                        }
+                        let _ = socket.shutdown().await;
+                    });
+                    continue;
+                }
+
+                let (read_stream, write_stream) = socket.into_split();
+                let handler = new_handler(write_stream, addr);
+                let connection_id = handler.connection_id();
+                println!("Connection from {addr} is {connection_id}");
+
+                let connections_clone = connections.clone();
+                let handle = tokio::spawn(async move {
+                    handler.handle(read_stream).await;
+                    println!("Connection {connection_id} from {addr} closed");
+                    connections_clone.write().await.remove(&addr);
+                });
+
+                connections.write().await.insert(addr, handle);
+            }
+            Err(e) => {
+                eprintln!("Failed to accept connection: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistor::tests::RecordingPersistor;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::TcpStream;
+
+    /// Starts a server on an ephemeral loopback port, storing emails in `persistor`.
+    async fn start(persistor: RecordingPersistor) -> Server {
+        Server::bind("127.0.0.1:0", None, move |write_stream, addr| {
+            SmtpHandler::new(write_stream, persistor.clone()).with_peer_addr(addr)
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_server_receives_email() {
+        let persistor = RecordingPersistor::default();
+        let server = start(persistor.clone()).await;
+
+        let stream = TcpStream::connect(server.local_addr()).await.unwrap();
+        let (read_stream, mut write_stream) = stream.into_split();
+        let mut replies = BufReader::new(read_stream).lines();
+        assert_eq!(
+            replies.next_line().await.unwrap().as_deref(),
+            Some("220 smt.example.com ESMTP Remail")
+        );
+
+        write_stream
+            .write_all(
+                b"HELO example.com\r\n\
+                  MAIL FROM: <sender@example.com>\r\n\
+                  RCPT TO: <recipient@example.com>\r\n\
+                  DATA\r\n\
+                  Subject: Over TCP\r\n\
+                  \r\n\
+                  Hello, world!\r\n\
+                  .\r\n",
+            )
+            .await
+            .unwrap();
+
+        let mut lines = Vec::new();
+        while let Some(line) = replies.next_line().await.unwrap() {
+            lines.push(line);
+        }
+        assert_eq!(
+            lines.last().map(String::as_str),
+            Some("250 OK: Message accepted for delivery")
+        );
+        server.shutdown().await;
+
+        let emails = persistor.emails();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].from.as_str(), "sender@example.com");
+        assert_eq!(emails[0].to.as_str(), "recipient@example.com");
+        assert_eq!(emails[0].subject, "Over TCP");
+        assert_eq!(emails[0].body, "Hello, world!\r\n");
+    }
+}
@@ -0,0 +1,143 @@
+use crate::email::NewEmail;
+use async_trait::async_trait;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpamClassification {
+    Ham,
+    Spam { confidence: f32 },
+    Uncertain { confidence: f32 },
+}
+
+#[async_trait]
+pub trait SpamFilter: Send + Sync {
+    async fn classify(&self, email: &NewEmail) -> SpamClassification;
+}
+
+const SPAM_PHRASES: &[&str] = &[
+    "act now",
+    "congratulations",
+    "free money",
+    "limited time offer",
+    "make money fast",
+    "you have won",
+    "you've won",
+    "urgent",
+    "winner",
+];
+
+const SPAM_PHRASE_SCORE: f32 = 0.5;
+const MISSING_DATE_SCORE: f32 = 0.3;
+const DOMAIN_MISMATCH_SCORE: f32 = 0.3;
+
+/// Scores messages on a few cheap header heuristics: a subject opening with a well-known spam
+/// phrase, a missing `Date` header, and a sender domain that does not match the HELO/EHLO
+/// hostname.
+#[derive(Debug, Default, Clone)]
+pub struct HeaderBasedSpamFilter;
+
+impl HeaderBasedSpamFilter {
+    fn score(&self, email: &NewEmail) -> f32 {
+        let mut score = 0.0;
+
+        let subject = email.subject.to_lowercase();
+        if SPAM_PHRASES
+            .iter()
+            .any(|phrase| subject.starts_with(phrase))
+        {
+            score += SPAM_PHRASE_SCORE;
+        }
+
+        if !email.headers.contains("Date") {
+            score += MISSING_DATE_SCORE;
+        }
+
+        if let Some(helo_domain) = &email.helo_domain {
+            let from_domain = email.from.domain().to_lowercase();
+            let helo_domain = helo_domain.to_lowercase();
+            if from_domain != helo_domain && !helo_domain.ends_with(&format!(".{from_domain}")) {
+                score += DOMAIN_MISMATCH_SCORE;
+            }
+        }
+
+        f32::min(score, 1.0)
+    }
+}
+
+#[async_trait]
+impl SpamFilter for HeaderBasedSpamFilter {
+    async fn classify(&self, email: &NewEmail) -> SpamClassification {
+        let confidence = self.score(email);
+        if confidence == 0.0 {
+            SpamClassification::Ham
+        } else if confidence >= SPAM_PHRASE_SCORE {
+            SpamClassification::Spam { confidence }
+        } else {
+            SpamClassification::Uncertain { confidence }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use email_address::EmailAddress;
+
+    fn email(from: &str, helo_domain: &str, headers: &[&str]) -> NewEmail {
+        let mut lines: Vec<String> = headers.iter().map(|line| line.to_string()).collect();
+        lines.push(String::new());
+        lines.push("Hello!".to_string());
+
+        let mut email = NewEmail::from_raw_message(
+            EmailAddress::new_unchecked(from),
+            EmailAddress::new_unchecked("recipient@example.com"),
+            lines,
+        );
+        email.helo_domain = Some(helo_domain.to_string());
+        email
+    }
+
+    #[tokio::test]
+    async fn test_classifies_ham() {
+        let email = email(
+            "newsletter@example.com",
+            "mail.example.com",
+            &[
+                "Subject: Your weekly digest",
+                "Date: Mon, 4 Aug 2025 10:00:00 +0000",
+            ],
+        );
+
+        assert_eq!(
+            HeaderBasedSpamFilter.classify(&email).await,
+            SpamClassification::Ham
+        );
+    }
+
+    #[tokio::test]
+    async fn test_classifies_spam() {
+        let email = email(
+            "prince@scam.example",
+            "mx.totally-legit.example",
+            &["Subject: CONGRATULATIONS you have been selected"],
+        );
+
+        assert_eq!(
+            HeaderBasedSpamFilter.classify(&email).await,
+            SpamClassification::Spam { confidence: 1.0 }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_classifies_uncertain() {
+        let email = email(
+            "sender@example.com",
+            "example.com",
+            &["Subject: Meeting notes"],
+        );
+
+        assert_eq!(
+            HeaderBasedSpamFilter.classify(&email).await,
+            SpamClassification::Uncertain { confidence: 0.3 }
+        );
+    }
+}
@@ -0,0 +1,72 @@
+//! The messages benchmarked by both the SMTP parser and maild's `NewEmail`, as the lines sent
+//! during DATA without their line endings or dot-stuffing.
+
+/// A kind of message, generated at each of the sizes it is benchmarked with.
+pub struct Group {
+    pub name: &'static str,
+    pub inputs: Vec<(usize, Vec<String>)>,
+}
+
+pub fn groups() -> Vec<Group> {
+    let group = |name, sizes: &[usize], message: fn(usize) -> Vec<String>| Group {
+        name,
+        inputs: sizes.iter().map(|&size| (size, message(size))).collect(),
+    };
+    vec![
+        group("minimal", &[1], |_| minimal()),
+        group("headers", &[10, 100, 1_000], headers),
+        group("body_bytes", &[1024, 64 * 1024, 1024 * 1024], body),
+        group("body_lines", &[1_000, 100_000, 500_000], body_lines),
+        group("recipients", &[1, 10, 50], recipients),
+        group("folded_headers", &[1, 10, 50], folded_headers),
+    ]
+}
+
+fn minimal() -> Vec<String> {
+    vec!["Hello, world!".to_string()]
+}
+
+fn headers(count: usize) -> Vec<String> {
+    let mut lines: Vec<String> = (0..count)
+        .map(|i| format!("X-Header-{i}: value number {i}"))
+        .collect();
+    lines.extend(["".to_string(), "Hello, world!".to_string()]);
+    lines
+}
+
+/// A message whose body is `bytes` long, in 78-byte lines including their CRLF.
+fn body(bytes: usize) -> Vec<String> {
+    let mut lines = vec!["Subject: Large".to_string(), String::new()];
+    lines.extend((0..bytes / 78).map(|_| "a".repeat(76)));
+    lines
+}
+
+/// A body of `count` short lines, which the parser once recursed into once per line.
+fn body_lines(count: usize) -> Vec<String> {
+    let mut lines = vec!["Subject: Long".to_string(), String::new()];
+    lines.extend((0..count).map(|_| "line".to_string()));
+    lines
+}
+
+fn recipients(count: usize) -> Vec<String> {
+    let addresses: Vec<String> = (0..count)
+        .map(|i| format!("Recipient {i} <recipient{i}@example.com>"))
+        .collect();
+    vec![
+        format!("To: {}", addresses.join(", ")),
+        format!("Cc: {}", addresses.join(", ")),
+        String::new(),
+        "Hello, everyone!".to_string(),
+    ]
+}
+
+/// `count` headers, each folded over 10 continuation lines.
+fn folded_headers(count: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for i in 0..count {
+        lines.push(format!("X-Folded-{i}: first line"));
+        lines.extend((0..10).map(|j| format!("\tcontinuation {j}")));
+    }
+    lines.extend(["".to_string(), "Hello, world!".to_string()]);
+    lines
+}
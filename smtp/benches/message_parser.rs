@@ -0,0 +1,49 @@
+use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
+use remail_smtp::{HeaderLimits, MessageParser};
+
+mod inputs;
+
+/// Raised limits, so the larger header sections are parsed instead of refused.
+const LIMITS: HeaderLimits = HeaderLimits {
+    max_count: usize::MAX,
+    max_bytes: usize::MAX,
+};
+
+/// A whole SMTP transaction delivering the message made of `lines`.
+fn transaction(lines: &[String]) -> String {
+    let mut input = String::from(
+        "HELO example.com\r\nMAIL FROM: <sender@example.com>\r\nRCPT TO: <recipient@example.com>\r\nDATA\r\n",
+    );
+    for line in lines {
+        if line.starts_with('.') {
+            input.push('.');
+        }
+        input.push_str(line);
+        input.push_str("\r\n");
+    }
+    input.push_str(".\r\n");
+    input
+}
+
+fn parse(input: &str) {
+    for event in MessageParser::new(input.as_bytes()).with_header_limits(LIMITS) {
+        black_box(event.expect("benchmark input must parse"));
+    }
+}
+
+fn bench_message_parser(c: &mut Criterion) {
+    for group in inputs::groups() {
+        let mut benchmark = c.benchmark_group(format!("message_parser/{}", group.name));
+        for (size, lines) in &group.inputs {
+            let input = transaction(lines);
+            benchmark.throughput(Throughput::Bytes(input.len() as u64));
+            benchmark.bench_with_input(BenchmarkId::from_parameter(size), &input, |b, input| {
+                b.iter(|| parse(input))
+            });
+        }
+        benchmark.finish();
+    }
+}
+
+criterion_group!(benches, bench_message_parser);
+criterion_main!(benches);
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use remail_smtp::MessageParser;
+
+// Malformed input must surface as `Err` events; any panic is a bug.
+fuzz_target!(|input: &[u8]| {
+    let _: Vec<_> = MessageParser::new(input).collect();
+});
@@ -0,0 +1,185 @@
+//! Decoding of RFC 2047 encoded words, such as `=?UTF-8?B?8J+OiSBXZWxjb21lIQ==?=`, which
+//! carry non-ASCII text in header values.
+//!
+//! Charsets are converted with [`transfer_encoding::decode_charset`]. Sequences that only look
+//! like encoded words are left as they are.
+
+use crate::transfer_encoding;
+
+/// Decodes every encoded word in a header value. Whitespace between two adjacent encoded
+/// words is dropped, as section 6.2 of RFC 2047 requires.
+pub fn decode(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        let (before, candidate) = rest.split_at(start);
+        match decode_word(candidate) {
+            Some((word, len)) => {
+                if !(after_word && before.chars().all(char::is_whitespace)) {
+                    decoded.push_str(before);
+                }
+                decoded.push_str(&word);
+                rest = &candidate[len..];
+                after_word = true;
+            }
+            None => {
+                decoded.push_str(before);
+                decoded.push_str("=?");
+                rest = &candidate[2..];
+                after_word = false;
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Decodes the encoded word at the start of `input`, returning it with the length it took up.
+fn decode_word(input: &str) -> Option<(String, usize)> {
+    let inner = input.strip_prefix("=?")?;
+    let (charset, inner) = inner.split_once('?')?;
+    let (encoding, inner) = inner.split_once('?')?;
+    let (text, _) = inner.split_once("?=")?;
+    if charset.is_empty()
+        || [charset, encoding, text]
+            .iter()
+            .any(|part| part.contains(char::is_whitespace))
+    {
+        return None;
+    }
+
+    let bytes = match encoding {
+        "B" | "b" => decode_base64(text)?,
+        "Q" | "q" => decode_q(text)?,
+        _ => return None,
+    };
+    let len = "=?".len() + charset.len() + 1 + encoding.len() + 1 + text.len() + "?=".len();
+    Some((decode_charset(charset, &bytes), len))
+}
+
+fn decode_charset(charset: &str, bytes: &[u8]) -> String {
+    // RFC 2231 allows a language after the charset, as in `UTF-8*en`.
+    let charset = charset.split('*').next().unwrap_or(charset);
+    transfer_encoding::decode_charset(charset, bytes)
+}
+
+/// The "Q" encoding: quoted-printable where `_` stands for a space.
+fn decode_q(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut input = text.bytes();
+    while let Some(byte) = input.next() {
+        match byte {
+            b'_' => bytes.push(b' '),
+            b'=' => {
+                let hex = [input.next()?, input.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    Some(bytes)
+}
+
+/// The "B" encoding: standard base64, with the padding optional.
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc_2047_examples() {
+        let table = [
+            ("=?ISO-8859-1?Q?a?=", "a"),
+            ("=?ISO-8859-1?Q?a?= b", "a b"),
+            ("=?ISO-8859-1?Q?a?= =?ISO-8859-1?Q?b?=", "ab"),
+            ("=?ISO-8859-1?Q?a?=  =?ISO-8859-1?Q?b?=", "ab"),
+            ("=?ISO-8859-1?Q?a?=\t  =?ISO-8859-1?Q?b?=", "ab"),
+            ("=?ISO-8859-1?Q?a_b?=", "a b"),
+            ("=?ISO-8859-1?Q?a?= =?ISO-8859-2?Q?_b?=", "a b"),
+            ("(=?ISO-8859-1?Q?a?=)", "(a)"),
+            ("(=?ISO-8859-1?Q?a?= b)", "(a b)"),
+            (
+                "=?US-ASCII?Q?Keith_Moore?= <moore@cs.utk.edu>",
+                "Keith Moore <moore@cs.utk.edu>",
+            ),
+            (
+                "=?ISO-8859-1?Q?Keld_J=F8rn_Simonsen?=",
+                "Keld Jørn Simonsen",
+            ),
+            ("=?ISO-8859-1?Q?Andr=E9?= Pirard", "André Pirard"),
+            (
+                "=?ISO-8859-1?B?SWYgeW91IGNhbiByZWFkIHRoaXMgeW8=?= =?ISO-8859-2?B?dSB1bmRlcnN0YW5kIHRoZSBleGFtcGxlLg==?=",
+                "If you can read this you understand the example.",
+            ),
+        ];
+
+        for (value, expected) in table {
+            assert_eq!(decode(value), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        let table = [
+            ("=?UTF-8?B?8J+OiSBXZWxjb21lIQ==?=", "🎉 Welcome!"),
+            ("=?utf-8?b?8J+OiSBXZWxjb21lIQ?=", "🎉 Welcome!"),
+            ("Re: =?UTF-8?Q?caf=C3=A9?= time", "Re: café time"),
+            ("=?UTF-8*en?Q?caf=C3=A9?=", "café"),
+            ("=?x-unknown?Q?caf=C3=A9?=", "café"),
+            ("=?Shift_JIS?B?grGC8YLJgr+CzQ==?=", "こんにちは"),
+            ("Plain subject", "Plain subject"),
+            ("", ""),
+        ];
+
+        for (value, expected) in table {
+            assert_eq!(decode(value), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn test_malformed_words_are_untouched() {
+        let table = [
+            "=?UTF-8?B?not base64!?=",
+            "=?UTF-8?B?bm90*YmFzZTY0?=",
+            "=?UTF-8?X?abc?=",
+            "=?UTF-8?Q?unterminated",
+            "=?UTF-8?Q?bad=ZZ?=",
+            "=?UTF-8?Q?short=A?=",
+            "=??Q?no_charset?=",
+            "=?",
+            "a =? b",
+        ];
+
+        for value in table {
+            assert_eq!(decode(value), value);
+        }
+        assert_eq!(
+            decode("=?UTF-8?X?abc?= =?UTF-8?Q?ok?="),
+            "=?UTF-8?X?abc?= ok"
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Parsing of a message's header section, shared by everything that reads one.
+//!
+//! Folded headers are unfolded as RFC 5322 section 2.2.3 describes: the line break is removed
+//! and the whitespace starting the continuation line is kept, so `Subject: a` followed by
+//! `\tb` reads as `a\tb`. The whitespace after the colon and at the end of the value is
+//! trimmed, as is any obsolete whitespace before the colon.
+
+/// What a line fed to a [`HeaderParser`] turned out to be.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderLine {
+    /// The line belongs to the header section. Carries the header it completed, if any.
+    Header(Option<(String, String)>),
+    /// The line ends the header section: it is either the blank line separating the headers
+    /// from the body or, in a message without one, the first line of the body. Carries the
+    /// last header, if any.
+    End(Option<(String, String)>),
+}
+
+/// Reads a header section one line at a time, line endings excluded.
+///
+/// A header is only complete once the next line shows it is not folded, so each line returns
+/// the header before it.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderParser {
+    header: Option<(String, String)>,
+    done: bool,
+}
+
+impl HeaderParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a line has ended the header section. Later lines are ignored.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub fn push_line(&mut self, line: &str) -> HeaderLine {
+        if self.done {
+            return HeaderLine::End(None);
+        }
+
+        if let Some((_, value)) = &mut self.header {
+            if line.starts_with([' ', '\t']) {
+                value.push_str(line);
+                return HeaderLine::Header(None);
+            }
+            if !line.is_empty() && !line.contains(':') {
+                // Not a header, so most likely a continuation that lost its indentation.
+                value.push(' ');
+                value.push_str(line);
+                return HeaderLine::Header(None);
+            }
+        }
+
+        match line.split_once(':') {
+            Some((name, value)) => {
+                let finished = self.finish();
+                self.header = Some((name.trim().to_string(), value.trim_start().to_string()));
+                HeaderLine::Header(finished)
+            }
+            None => {
+                self.done = true;
+                HeaderLine::End(self.finish())
+            }
+        }
+    }
+
+    /// Completes the header being read, for input that ends within the header section.
+    pub fn finish(&mut self) -> Option<(String, String)> {
+        let (name, mut value) = self.header.take()?;
+        value.truncate(value.trim_end().len());
+        Some((name, value))
+    }
+}
+
+/// Parses the header section at the start of `lines`.
+pub fn parse<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<(String, String)> {
+    let mut parser = HeaderParser::new();
+    let mut headers = Vec::new();
+    for line in lines {
+        match parser.push_line(line) {
+            HeaderLine::Header(header) => headers.extend(header),
+            HeaderLine::End(header) => {
+                headers.extend(header);
+                return headers;
+            }
+        }
+    }
+    headers.extend(parser.finish());
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(name: &str, value: &str) -> (String, String) {
+        (name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn test_parse() {
+        let table: [(&[&str], Vec<_>); 10] = [
+            (&["Subject: Hello", ""], vec![header("Subject", "Hello")]),
+            (
+                &["Subject: Hello", "To: a@example.com", ""],
+                vec![header("Subject", "Hello"), header("To", "a@example.com")],
+            ),
+            (
+                &[
+                    "Received: from mail.example.com",
+                    " by mx.example.org; Mon, 4 Aug 2025 12:00:01 +0000",
+                    "",
+                ],
+                vec![header(
+                    "Received",
+                    "from mail.example.com by mx.example.org; Mon, 4 Aug 2025 12:00:01 +0000",
+                )],
+            ),
+            (
+                &["Subject: tab", "\tfolded", ""],
+                vec![header("Subject", "tab\tfolded")],
+            ),
+            (
+                &["X-Empty:", "X-Blank:   ", ""],
+                vec![header("X-Empty", ""), header("X-Blank", "")],
+            ),
+            (
+                &["Subject : obsolete", ""],
+                vec![header("Subject", "obsolete")],
+            ),
+            (
+                &["Subject: missing", "indentation", ""],
+                vec![header("Subject", "missing indentation")],
+            ),
+            (
+                &["Subject: unterminated"],
+                vec![header("Subject", "unterminated")],
+            ),
+            (&["Hello, world!", "Subject: body"], Vec::new()),
+            (
+                &["Subject: Hello", "", "X-Body: not a header"],
+                vec![header("Subject", "Hello")],
+            ),
+        ];
+
+        for (lines, expected) in table {
+            assert_eq!(parse(lines.iter().copied()), expected, "{lines:?}");
+        }
+    }
+
+    #[test]
+    fn test_push_line() {
+        let mut parser = HeaderParser::new();
+
+        assert_eq!(parser.push_line("Subject: Hello"), HeaderLine::Header(None));
+        assert_eq!(parser.push_line(" world"), HeaderLine::Header(None));
+        assert_eq!(
+            parser.push_line("To: a@example.com"),
+            HeaderLine::Header(Some(header("Subject", "Hello world")))
+        );
+        assert_eq!(
+            parser.push_line(""),
+            HeaderLine::End(Some(header("To", "a@example.com")))
+        );
+        assert!(parser.is_done());
+        assert_eq!(parser.push_line("X-Late: ignored"), HeaderLine::End(None));
+    }
+}
@@ -1,17 +1,42 @@
-use email_address::EmailAddress;
-use std::io::{BufRead, BufReader, Lines};
+use headers::{HeaderLine, HeaderParser};
+use line::LimitedLineReader;
+use mailbox::MailboxAddress;
+use std::io::BufReader;
 use std::str::FromStr;
 
+pub mod encoded_word;
+pub mod headers;
+pub mod line;
+pub mod mailbox;
+#[cfg(feature = "tokio")]
+mod stream;
+pub mod transfer_encoding;
+pub use line::MAX_LINE_LENGTH;
+#[cfg(feature = "tokio")]
+pub use stream::AsyncMessageParser;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Message {}
+pub struct Message {
+    /// Every header of the message, in order, unfolded as [`headers`] describes.
+    pub headers: Vec<(String, String)>,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MessageParserEvent {
-    From(Option<EmailAddress>),
-    To(EmailAddress),
+    From(Option<MailboxAddress>),
+    To(MailboxAddress),
+    /// A header of the message, emitted once its last folded line has been read.
     Header(String, String),
     Body(Vec<String>),
+    /// A line of the message, emitted instead of [`MessageParserEvent::Body`] by a streaming
+    /// parser.
+    BodyLine(String),
+    /// The end of the message, after its last [`MessageParserEvent::BodyLine`].
+    EndOfBody,
     Done(Message),
+    /// A line that is not the command expected next, emitted by a permissive parser instead of
+    /// [`MessageParserError::UnrecognizedCommand`]. The parser's state is unchanged.
+    Unknown(String),
 }
 
 pub enum MessageParserState {
@@ -24,188 +49,859 @@ pub enum MessageParserState {
     Done,
 }
 
-pub struct MessageParser<R: std::io::Read> {
-    lines: Lines<BufReader<R>>,
-    state: MessageParserState,
+/// Bounds on the header section of a message, so a client cannot make the server buffer an
+/// unbounded number of headers or one gigantic header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderLimits {
+    pub max_count: usize,
+    /// The total size of the header section, including line endings.
+    pub max_bytes: usize,
+}
 
-    from: Option<EmailAddress>,
-    to: EmailAddress,
-    body: Vec<String>,
+impl Default for HeaderLimits {
+    fn default() -> Self {
+        Self {
+            max_count: 100,
+            max_bytes: 100 * 1024,
+        }
+    }
+}
+
+/// Tracks the header section of a message as its DATA lines arrive, enforcing
+/// [`HeaderLimits`].
+#[derive(Debug, Clone)]
+pub struct HeaderGuard {
+    limits: HeaderLimits,
+    count: usize,
+    bytes: usize,
+    finished: bool,
+}
+
+impl HeaderGuard {
+    pub fn new(limits: HeaderLimits) -> Self {
+        Self {
+            limits,
+            count: 0,
+            bytes: 0,
+            finished: false,
+        }
+    }
+
+    /// Records a line of DATA. Lines after the blank line ending the headers are not counted.
+    pub fn check(&mut self, line: &str) -> Result<(), MessageParserError> {
+        if self.finished {
+            return Ok(());
+        }
+        if line.is_empty() {
+            self.finished = true;
+            return Ok(());
+        }
+
+        if !line.starts_with([' ', '\t']) {
+            self.count += 1;
+        }
+        self.bytes += line.len() + 2;
+
+        if self.count > self.limits.max_count || self.bytes > self.limits.max_bytes {
+            return Err(MessageParserError::HeadersTooLarge);
+        }
+        Ok(())
+    }
+}
+
+/// Strips the case-insensitive `command` prefix from `line`, returning the rest.
+///
+/// Unlike slicing, this never panics when a multi-byte character straddles the end of the
+/// prefix.
+pub fn strip_command<'a>(line: &'a str, command: &str) -> Option<&'a str> {
+    let prefix = line.get(..command.len())?;
+    prefix
+        .eq_ignore_ascii_case(command)
+        .then(|| &line[command.len()..])
+}
+
+/// Splits the argument of a `MAIL FROM:` or `RCPT TO:` command into the address inside the
+/// angle brackets and the parameters after it, as `(name, value)` pairs.
+fn split_path(argument: &str) -> Option<(&str, impl Iterator<Item = (&str, Option<&str>)>)> {
+    let mut parts = argument.split_whitespace();
+    let address = parts.next()?.strip_prefix('<')?.strip_suffix('>')?;
+    let parameters = parts.map(|parameter| match parameter.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (parameter, None),
+    });
+    Some((address, parameters))
+}
+
+/// The body type declared by the `BODY=` parameter of RFC 6152.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyType {
+    SevenBit,
+    EightBitMime,
+}
+
+/// The argument of a `MAIL FROM:` command: the sender and the parameters of the SIZE,
+/// 8BITMIME and SMTPUTF8 extensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailFromParams {
+    /// `None` for the null sender, `<>`, which bounces are sent from.
+    pub address: Option<MailboxAddress>,
+    /// The message size the client declared, in bytes.
+    pub size: Option<usize>,
+    pub body: Option<BodyType>,
+    pub smtputf8: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MailFromError {
+    /// The argument does not start with a path in angle brackets.
+    MissingPath,
+    InvalidAddress(email_address::Error),
+    /// A parameter was given twice.
+    DuplicateParameter(String),
+    /// A `SIZE=` value that is not a number of bytes.
+    InvalidSize(String),
+    /// A `BODY=` value other than `7BIT` and `8BITMIME`.
+    InvalidBody(String),
+    /// `SMTPUTF8` was given a value, which it does not take.
+    InvalidSmtpUtf8(String),
+}
+
+impl std::fmt::Display for MailFromError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MailFromError::MissingPath => write!(f, "missing <address>"),
+            MailFromError::InvalidAddress(err) => write!(f, "invalid address: {err}"),
+            MailFromError::DuplicateParameter(name) => write!(f, "duplicate {name} parameter"),
+            MailFromError::InvalidSize(value) => write!(f, "invalid SIZE value: {value}"),
+            MailFromError::InvalidBody(value) => write!(f, "invalid BODY value: {value}"),
+            MailFromError::InvalidSmtpUtf8(value) => {
+                write!(f, "unexpected SMTPUTF8 value: {value}")
+            }
+        }
+    }
+}
+
+/// Parses the argument of a `MAIL FROM:` command, the part after the colon, such as
+/// `<user@example.com> SIZE=1024 BODY=8BITMIME`.
+///
+/// Parameter names are case-insensitive. Parameters other than `SIZE`, `BODY` and `SMTPUTF8`
+/// are ignored.
+pub fn parse_mail_from(argument: &str) -> Result<MailFromParams, MailFromError> {
+    let (address, parameters) = split_path(argument).ok_or(MailFromError::MissingPath)?;
+    let address = match address {
+        "" => None,
+        address => Some(MailboxAddress::parse(address).map_err(MailFromError::InvalidAddress)?),
+    };
+
+    let mut params = MailFromParams {
+        address,
+        size: None,
+        body: None,
+        smtputf8: false,
+    };
+    for (name, value) in parameters {
+        let duplicate = || MailFromError::DuplicateParameter(name.to_ascii_uppercase());
+        if name.eq_ignore_ascii_case("SMTPUTF8") {
+            if params.smtputf8 {
+                return Err(duplicate());
+            }
+            if let Some(value) = value {
+                return Err(MailFromError::InvalidSmtpUtf8(value.to_string()));
+            }
+            params.smtputf8 = true;
+            continue;
+        }
+
+        let value = value.unwrap_or("");
+        if name.eq_ignore_ascii_case("SIZE") {
+            if params.size.is_some() {
+                return Err(duplicate());
+            }
+            let size = value
+                .parse()
+                .map_err(|_| MailFromError::InvalidSize(value.to_string()))?;
+            params.size = Some(size);
+        } else if name.eq_ignore_ascii_case("BODY") {
+            if params.body.is_some() {
+                return Err(duplicate());
+            }
+            let body = match value.to_ascii_uppercase().as_str() {
+                "7BIT" => BodyType::SevenBit,
+                "8BITMIME" => BodyType::EightBitMime,
+                _ => return Err(MailFromError::InvalidBody(value.to_string())),
+            };
+            params.body = Some(body);
+        }
+    }
+
+    Ok(params)
+}
+
+/// A condition a delivery status notification is requested for, from the `NOTIFY=` parameter
+/// of RFC 3461.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DsnNotify {
+    Never,
+    Success,
+    Failure,
+    Delay,
+}
+
+impl FromStr for DsnNotify {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "NEVER" => Ok(DsnNotify::Never),
+            "SUCCESS" => Ok(DsnNotify::Success),
+            "FAILURE" => Ok(DsnNotify::Failure),
+            "DELAY" => Ok(DsnNotify::Delay),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The argument of a `RCPT TO:` command: the recipient and the DSN parameters of RFC 3461.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RcptToParams {
+    pub address: MailboxAddress,
+    /// Empty when `NOTIFY=` was not given.
+    pub notify: Vec<DsnNotify>,
+    /// The original recipient, as `addr-type;address`, such as `rfc822;user@example.com`.
+    pub orcpt: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RcptToError {
+    /// The argument does not start with a path in angle brackets.
+    MissingPath,
+    InvalidAddress(email_address::Error),
+    /// A parameter was given twice.
+    DuplicateParameter(String),
+    /// A `NOTIFY=` value that is not a list of `SUCCESS`, `FAILURE` and `DELAY`, or `NEVER`
+    /// alone.
+    InvalidNotify(String),
+    /// An `ORCPT=` value without an address type.
+    InvalidOrcpt(String),
+}
+
+impl std::error::Error for MailFromError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MailFromError::InvalidAddress(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for RcptToError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RcptToError::MissingPath => write!(f, "missing <address>"),
+            RcptToError::InvalidAddress(err) => write!(f, "invalid address: {err}"),
+            RcptToError::DuplicateParameter(name) => write!(f, "duplicate {name} parameter"),
+            RcptToError::InvalidNotify(value) => write!(f, "invalid NOTIFY value: {value}"),
+            RcptToError::InvalidOrcpt(value) => write!(f, "invalid ORCPT value: {value}"),
+        }
+    }
+}
+
+impl std::error::Error for RcptToError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RcptToError::InvalidAddress(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the argument of a `RCPT TO:` command, the part after the colon, such as
+/// `<user@example.com> NOTIFY=SUCCESS,FAILURE ORCPT=rfc822;user@example.com`.
+///
+/// Parameter names are case-insensitive. Parameters other than `NOTIFY` and `ORCPT` are
+/// ignored.
+pub fn parse_rcpt_to(argument: &str) -> Result<RcptToParams, RcptToError> {
+    let (address, parameters) = split_path(argument).ok_or(RcptToError::MissingPath)?;
+    let address = MailboxAddress::parse(address).map_err(RcptToError::InvalidAddress)?;
+
+    let mut notify: Option<Vec<DsnNotify>> = None;
+    let mut orcpt = None;
+    for (name, value) in parameters {
+        let value = value.unwrap_or("");
+        if name.eq_ignore_ascii_case("NOTIFY") {
+            if notify.is_some() {
+                return Err(RcptToError::DuplicateParameter("NOTIFY".to_string()));
+            }
+            let invalid = || RcptToError::InvalidNotify(value.to_string());
+            let conditions = value
+                .split(',')
+                .map(|condition| condition.parse().map_err(|_| invalid()))
+                .collect::<Result<Vec<DsnNotify>, _>>()?;
+            if conditions.len() > 1 && conditions.contains(&DsnNotify::Never) {
+                return Err(invalid());
+            }
+            notify = Some(conditions);
+        } else if name.eq_ignore_ascii_case("ORCPT") {
+            if orcpt.is_some() {
+                return Err(RcptToError::DuplicateParameter("ORCPT".to_string()));
+            }
+            match value.split_once(';') {
+                Some((address_type, address))
+                    if !address_type.is_empty() && !address.is_empty() =>
+                {
+                    orcpt = Some(value.to_string());
+                }
+                _ => return Err(RcptToError::InvalidOrcpt(value.to_string())),
+            }
+        }
+    }
+
+    Ok(RcptToParams {
+        address,
+        notify: notify.unwrap_or_default(),
+        orcpt,
+    })
+}
+
+/// How strictly a [`MessageParser`] or `AsyncMessageParser` holds the input to the RFCs.
+///
+/// The default is what the parser has always accepted: bare LF line endings, commands in any
+/// case and whitespace before a header's colon, but not a transaction without HELO or EHLO.
+/// [`ParserOptions::lenient`] salvages what it can, while [`ParserOptions::strict`] reports
+/// each of those as an error of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// Whether lines must end with CRLF. Lines ending with a bare LF fail with
+    /// [`MessageParserError::BareLineFeed`].
+    pub strict_crlf: bool,
+    /// Whether a transaction may start with `MAIL FROM`, skipping HELO and EHLO. Otherwise it
+    /// fails with [`MessageParserError::MissingHelo`].
+    pub allow_missing_helo: bool,
+    /// Whether commands are recognized in any case, such as `data`. Otherwise commands not in
+    /// upper case fail with [`MessageParserError::LowercaseCommand`].
+    pub allow_bare_commands_case: bool,
+    /// Whether a header name may be followed by whitespace before its colon, the obsolete
+    /// syntax of RFC 5322 section 4.5.3. Otherwise such headers fail with
+    /// [`MessageParserError::SpaceBeforeColon`].
+    pub allow_space_before_colon: bool,
+    /// The most headers a message may have, see [`HeaderLimits::max_count`].
+    pub max_header_count: usize,
+    /// The longest line accepted, in octets, not counting its line ending. Longer lines fail
+    /// with [`MessageParserError::LineTooLong`].
+    pub max_line_len: usize,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            strict_crlf: false,
+            allow_missing_helo: false,
+            allow_bare_commands_case: true,
+            allow_space_before_colon: true,
+            max_header_count: HeaderLimits::default().max_count,
+            max_line_len: MAX_LINE_LENGTH,
+        }
+    }
+}
+
+impl ParserOptions {
+    /// Accepts everything the parser can make sense of, for replaying logs.
+    pub fn lenient() -> Self {
+        Self {
+            allow_missing_helo: true,
+            ..Self::default()
+        }
+    }
+
+    /// Flags every deviation the parser can detect, for conformance testing.
+    pub fn strict() -> Self {
+        Self {
+            strict_crlf: true,
+            allow_missing_helo: false,
+            allow_bare_commands_case: false,
+            allow_space_before_colon: false,
+            ..Self::default()
+        }
+    }
+}
+
+pub struct MessageParser<R: std::io::Read> {
+    lines: LimitedLineReader<BufReader<R>>,
+    machine: StateMachine,
 }
 
 impl<R: std::io::Read> MessageParser<R> {
     pub fn new(reader: R) -> Self {
-        let lines = BufReader::new(reader).lines();
-
         Self {
-            lines,
-            state: MessageParserState::Start,
-            from: None,
-            to: EmailAddress::new_unchecked(""),
-            body: Vec::new(),
+            lines: LimitedLineReader::new(BufReader::new(reader), MAX_LINE_LENGTH),
+            machine: StateMachine::new(),
         }
     }
+
+    /// Parses as strictly as `options` asks. Its header count replaces the one set by
+    /// [`MessageParser::with_header_limits`].
+    pub fn with_options(mut self, options: ParserOptions) -> Self {
+        self.lines.configure(&options);
+        self.machine.configure(options);
+        self
+    }
+
+    /// A parser emitting each line of the message as a [`MessageParserEvent::BodyLine`] instead
+    /// of one [`MessageParserEvent::Body`], so the body is never held in memory.
+    pub fn new_streaming(reader: R) -> Self {
+        let mut parser = Self::new(reader);
+        parser.machine.streaming = true;
+        parser
+    }
+
+    /// A parser that hands commands it does not recognize, such as extensions, to the caller
+    /// as [`MessageParserEvent::Unknown`] instead of failing on them.
+    pub fn permissive(reader: R) -> Self {
+        let mut parser = Self::new(reader);
+        parser.machine.permissive = true;
+        parser
+    }
+
+    pub fn with_header_limits(mut self, limits: HeaderLimits) -> Self {
+        self.machine.headers = HeaderGuard::new(limits);
+        self
+    }
+
+    /// The sender of the transaction, once `MAIL FROM` named one. `None` for the null sender.
+    pub fn from(&self) -> Option<&MailboxAddress> {
+        self.machine.mail_from.as_ref()?.address.as_ref()
+    }
+
+    /// The argument of the transaction's `MAIL FROM`, with its SIZE, BODY and SMTPUTF8
+    /// parameters, once it was parsed.
+    pub fn mail_from(&self) -> Option<&MailFromParams> {
+        self.machine.mail_from.as_ref()
+    }
+
+    /// The recipients of the transaction, in the order `RCPT TO` named them.
+    pub fn to(&self) -> &[MailboxAddress] {
+        &self.machine.to
+    }
 }
 
 #[derive(Debug)]
 pub enum MessageParserError {
     IO(std::io::Error),
     UnrecognizedCommand(String),
-    InvalidFromEmailAddress(email_address::Error),
-    InvalidToEmailAddress(email_address::Error),
+    InvalidMailFrom(MailFromError),
+    InvalidRcptTo(RcptToError),
     UnexpectedEnd,
     UnexpectedDataAfterEnd,
+    /// The message exceeded its [`HeaderLimits`]. The rest of the input is ignored.
+    HeadersTooLarge,
+    /// A line of this many octets exceeded [`ParserOptions::max_line_len`]. The rest of the
+    /// input is ignored.
+    LineTooLong(usize),
+    /// A line ended with a bare LF under [`ParserOptions::strict_crlf`]. The rest of the input
+    /// is ignored.
+    BareLineFeed,
+    /// The transaction started with `MAIL FROM` without HELO or EHLO, see
+    /// [`ParserOptions::allow_missing_helo`].
+    MissingHelo,
+    /// A command not in upper case, see [`ParserOptions::allow_bare_commands_case`].
+    LowercaseCommand(String),
+    /// A header with whitespace between its name and colon, see
+    /// [`ParserOptions::allow_space_before_colon`]. The rest of the input is ignored.
+    SpaceBeforeColon(String),
 }
 
-impl<R: std::io::Read> Iterator for MessageParser<R> {
-    type Item = Result<MessageParserEvent, MessageParserError>;
+impl std::fmt::Display for MessageParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageParserError::IO(err) => write!(f, "failed to read the message: {err}"),
+            MessageParserError::UnrecognizedCommand(line) => {
+                write!(f, "unrecognized command: {line}")
+            }
+            MessageParserError::InvalidMailFrom(err) => {
+                write!(f, "invalid MAIL FROM argument: {err}")
+            }
+            MessageParserError::InvalidRcptTo(err) => {
+                write!(f, "invalid RCPT TO argument: {err}")
+            }
+            MessageParserError::UnexpectedEnd => write!(f, "the input ended mid-transaction"),
+            MessageParserError::UnexpectedDataAfterEnd => {
+                write!(f, "unexpected data after the end of the message")
+            }
+            MessageParserError::HeadersTooLarge => {
+                write!(f, "the message headers exceed the allowed size")
+            }
+            MessageParserError::LineTooLong(length) => {
+                write!(f, "a line of {length} octets exceeds the allowed length")
+            }
+            MessageParserError::BareLineFeed => write!(f, "a line ends with a bare LF"),
+            MessageParserError::MissingHelo => {
+                write!(f, "the transaction started without HELO or EHLO")
+            }
+            MessageParserError::LowercaseCommand(line) => {
+                write!(f, "command not in upper case: {line}")
+            }
+            MessageParserError::SpaceBeforeColon(name) => {
+                write!(f, "whitespace before the colon of header {name}")
+            }
+        }
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let line = self.lines.next();
+impl std::error::Error for MessageParserError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MessageParserError::IO(err) => Some(err),
+            MessageParserError::InvalidMailFrom(err) => Some(err),
+            MessageParserError::InvalidRcptTo(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// An error with where it happened in the input: the number of the line, counting from 1,
+/// and the byte offset of the line's start. Errors about the input ending point just past its
+/// last line.
+#[derive(Debug)]
+pub struct Spanned<E> {
+    pub line: usize,
+    pub offset: usize,
+    pub kind: E,
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for Spanned<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl<E: std::error::Error> std::error::Error for Spanned<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.kind.source()
+    }
+}
+
+/// The parsing logic shared by [`MessageParser`] and `AsyncMessageParser`, fed one line at a
+/// time.
+struct StateMachine {
+    state: MessageParserState,
+
+    mail_from: Option<MailFromParams>,
+    to: Vec<MailboxAddress>,
+    body: Vec<String>,
+    /// Whether DATA lines are emitted as they arrive rather than collected into `body`.
+    streaming: bool,
+    /// Whether unrecognized commands are emitted as events rather than errors.
+    permissive: bool,
+    options: ParserOptions,
+    headers: HeaderGuard,
+    header_parser: HeaderParser,
+    finished_headers: Vec<(String, String)>,
+    /// An event produced by the same line as the one just returned, yielded next.
+    queued: Option<Result<MessageParserEvent, MessageParserError>>,
+}
+
+impl StateMachine {
+    fn new() -> Self {
+        Self {
+            state: MessageParserState::Start,
+            mail_from: None,
+            to: Vec::new(),
+            body: Vec::new(),
+            streaming: false,
+            permissive: false,
+            options: ParserOptions::default(),
+            headers: HeaderGuard::new(HeaderLimits::default()),
+            header_parser: HeaderParser::new(),
+            finished_headers: Vec::new(),
+            queued: None,
+        }
+    }
+
+    fn configure(&mut self, options: ParserOptions) {
+        self.options = options;
+        self.headers = HeaderGuard::new(HeaderLimits {
+            max_count: options.max_header_count,
+            ..self.headers.limits
+        });
+    }
+
+    /// Strips the first of `commands` that starts `line`, like [`strip_command`], failing when
+    /// commands must be in upper case and this one is not.
+    fn command<'a>(
+        &self,
+        line: &'a str,
+        commands: &[&str],
+    ) -> Result<Option<&'a str>, MessageParserError> {
+        for command in commands {
+            if let Some(argument) = strip_command(line, command) {
+                if !self.options.allow_bare_commands_case && !line.starts_with(command) {
+                    return Err(MessageParserError::LowercaseCommand(line.to_string()));
+                }
+                return Ok(Some(argument));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Forgets the transaction whose message was just read, so another can follow it on the
+    /// same connection, returning the event that completes it.
+    fn start_transaction(&mut self) -> MessageParserEvent {
+        let message = Message {
+            headers: std::mem::take(&mut self.finished_headers),
+        };
+        self.mail_from = None;
+        self.to.clear();
+        self.body.clear();
+        self.headers = HeaderGuard::new(self.headers.limits);
+        self.header_parser = HeaderParser::new();
+        self.state = MessageParserState::Helo;
+        MessageParserEvent::Done(message)
+    }
+
+    /// Drops the message after an error that leaves the rest of the input meaningless.
+    fn abort(&mut self) {
+        self.body.clear();
+        self.state = MessageParserState::Done;
+    }
+
+    /// Takes the event queued by the last line, if any. Checked before reading another line.
+    fn take_queued(&mut self) -> Option<Result<MessageParserEvent, MessageParserError>> {
+        self.queued.take()
+    }
+
+    /// The result for a line that is not the command expected next.
+    fn unrecognized(&self, line: String) -> Result<MessageParserEvent, MessageParserError> {
+        if self.permissive {
+            Ok(MessageParserEvent::Unknown(line))
+        } else {
+            Err(MessageParserError::UnrecognizedCommand(line))
+        }
+    }
+
+    /// Records a completed header, returning its event.
+    fn finish_header(&mut self, header: Option<(String, String)>) -> Option<MessageParserEvent> {
+        let (name, value) = header?;
+        self.finished_headers.push((name.clone(), value.clone()));
+        Some(MessageParserEvent::Header(name, value))
+    }
+
+    /// Follows a DATA line through the header section, returning the event for a header the
+    /// line completes.
+    fn track_header(&mut self, line: &str) -> Option<MessageParserEvent> {
+        match self.header_parser.push_line(line) {
+            HeaderLine::Header(header) | HeaderLine::End(header) => self.finish_header(header),
+        }
+    }
+
+    /// Consumes the result of reading a line. A line that is too long or ends with a bare LF
+    /// under strict CRLF ends the transaction, since the parser cannot tell what it held.
+    fn read(
+        &mut self,
+        line: Result<String, MessageParserError>,
+    ) -> Option<Result<MessageParserEvent, MessageParserError>> {
         match line {
-            Some(Ok(line)) => {
-                match self.state {
-                    MessageParserState::Start => {
-                        if line.len() < 4 {
-                            return Some(Err(MessageParserError::UnrecognizedCommand(line)));
-                        }
-                        let line = line[..4].to_uppercase();
-                        if line == "HELO" || line == "EHLO" {
-                            self.state = MessageParserState::Helo;
-                            self.next()
-                        } else {
-                            Some(Err(MessageParserError::UnrecognizedCommand(line)))
-                        }
-                    }
-                    MessageParserState::Helo => {
-                        if line.len() < 10 {
-                            return Some(Err(MessageParserError::UnrecognizedCommand(line)));
-                        }
-                        if line[..10].to_uppercase() == "MAIL FROM:" {
-                            let from = line[10..]
-                                .split_whitespace()
-                                .next()
-                                .unwrap_or("")
-                                .strip_prefix('<')
-                                .and_then(|s| s.strip_suffix('>'))
-                                .unwrap_or("")
-                                .to_string();
-
-                            if from == "" {
-                                self.from = None;
-                                self.state = MessageParserState::MailFrom;
-                                return Some(Ok(MessageParserEvent::From(None)));
-                            }
-
-                            match EmailAddress::from_str(&from) {
-                                Ok(email) => {
-                                    self.from = Some(email.clone());
-                                    self.state = MessageParserState::MailFrom;
-                                    Some(Ok(MessageParserEvent::From(Some(email))))
-                                }
-                                Err(err) => {
-                                    Some(Err(MessageParserError::InvalidFromEmailAddress(err)))
-                                }
-                            }
-                        } else {
-                            // TODO: we should actually check if this is a command that exists
-                            // to return a BadSequenceOfCommands Error instead of always returning
-                            // a UnrecognizedCommand Error
-                            Some(Err(MessageParserError::UnrecognizedCommand(line)))
-                        }
+            Ok(line) => self.step(line),
+            Err(MessageParserError::LineTooLong(_) | MessageParserError::BareLineFeed)
+                if matches!(self.state, MessageParserState::Done) =>
+            {
+                None
+            }
+            Err(err @ (MessageParserError::LineTooLong(_) | MessageParserError::BareLineFeed)) => {
+                self.abort();
+                Some(Err(err))
+            }
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    /// Consumes a line, returning `None` when the line did not produce an event and the next
+    /// line should be read.
+    fn step(&mut self, line: String) -> Option<Result<MessageParserEvent, MessageParserError>> {
+        match self.state {
+            MessageParserState::Start => match self.command(&line, &["HELO", "EHLO"]) {
+                Ok(Some(_)) => {
+                    self.state = MessageParserState::Helo;
+                    None
+                }
+                Ok(None) if strip_command(&line, "MAIL FROM:").is_some() => {
+                    if self.options.allow_missing_helo {
+                        self.state = MessageParserState::Helo;
+                        self.step(line)
+                    } else if self.permissive {
+                        Some(self.unrecognized(line))
+                    } else {
+                        Some(Err(MessageParserError::MissingHelo))
                     }
-                    MessageParserState::MailFrom => {
-                        if line.len() < 8 {
-                            // TODO: we should actually check if this is a command that exists
-                            // to return a BadSequenceOfCommands Error instead of always returning
-                            // a UnrecognizedCommand Error
-                            return Some(Err(MessageParserError::UnrecognizedCommand(line)));
-                        }
-                        if line[..8].to_uppercase() == "RCPT TO:" {
-                            let to = line[8..]
-                                .split_whitespace()
-                                .next()
-                                .unwrap_or("")
-                                .strip_prefix('<')
-                                .and_then(|s| s.strip_suffix('>'))
-                                .unwrap_or("")
-                                .to_string();
-                            match EmailAddress::from_str(&to) {
-                                Ok(email) => {
-                                    self.to = email.clone();
-                                    self.state = MessageParserState::RcptTo;
-                                    Some(Ok(MessageParserEvent::To(email)))
-                                }
-                                Err(err) => {
-                                    Some(Err(MessageParserError::InvalidToEmailAddress(err)))
-                                }
-                            }
-                        } else {
-                            // TODO: we should actually check if this is a command that exists
-                            // to return a BadSequenceOfCommands Error instead of always returning
-                            // a UnrecognizedCommand Error
-                            Some(Err(MessageParserError::UnrecognizedCommand(line)))
-                        }
+                }
+                Ok(None) => Some(self.unrecognized(line)),
+                Err(err) => Some(Err(err)),
+            },
+            MessageParserState::Helo => match self.command(&line, &["MAIL FROM:"]) {
+                Ok(Some(argument)) => match parse_mail_from(argument) {
+                    Ok(params) => {
+                        let from = params.address.clone();
+                        self.mail_from = Some(params);
+                        self.state = MessageParserState::MailFrom;
+                        Some(Ok(MessageParserEvent::From(from)))
                     }
-                    MessageParserState::RcptTo => {
-                        if line.to_uppercase() == "DATA" {
-                            self.state = MessageParserState::Data;
-                            self.next()
-                        } else {
-                            // TODO: we should actually check if this is a command that exists
-                            // to return a BadSequenceOfCommands Error instead of always returning
-                            // a UnrecognizedCommand Error
-                            Some(Err(MessageParserError::UnrecognizedCommand(line)))
+                    Err(err) => Some(Err(MessageParserError::InvalidMailFrom(err))),
+                },
+                // TODO: we should actually check if this is a command that exists
+                // to return a BadSequenceOfCommands Error instead of always returning
+                // a UnrecognizedCommand Error
+                Ok(None) => Some(self.unrecognized(line)),
+                Err(err) => Some(Err(err)),
+            },
+            MessageParserState::RcptTo if line.to_uppercase() == "DATA" => {
+                if !self.options.allow_bare_commands_case && line != "DATA" {
+                    return Some(Err(MessageParserError::LowercaseCommand(line)));
+                }
+                self.state = MessageParserState::Data;
+                None
+            }
+            // Every recipient is named by a `RCPT TO` of its own, until DATA.
+            MessageParserState::MailFrom | MessageParserState::RcptTo => {
+                match self.command(&line, &["RCPT TO:"]) {
+                    Ok(Some(argument)) => match parse_rcpt_to(argument) {
+                        Ok(params) => {
+                            self.to.push(params.address.clone());
+                            self.state = MessageParserState::RcptTo;
+                            Some(Ok(MessageParserEvent::To(params.address)))
                         }
+                        Err(err) => Some(Err(MessageParserError::InvalidRcptTo(err))),
+                    },
+                    // TODO: we should actually check if this is a command that exists
+                    // to return a BadSequenceOfCommands Error instead of always returning
+                    // a UnrecognizedCommand Error
+                    Ok(None) => Some(self.unrecognized(line)),
+                    Err(err) => Some(Err(err)),
+                }
+            }
+            MessageParserState::Data => {
+                if line == "." {
+                    self.state = MessageParserState::End;
+                    let body = if self.streaming {
+                        Ok(MessageParserEvent::EndOfBody)
+                    } else {
+                        Ok(MessageParserEvent::Body(self.body.clone()))
+                    };
+                    // A message without a body may end right after its last header.
+                    let last = self.header_parser.finish();
+                    if let Some(header) = self.finish_header(last) {
+                        self.queued = Some(body);
+                        return Some(Ok(header));
                     }
-                    MessageParserState::Data => {
-                        if line == "." {
-                            self.state = MessageParserState::End;
-                            return Some(Ok(MessageParserEvent::Body(self.body.clone())));
-                        }
+                    return Some(body);
+                }
 
-                        let line_to_push = if let Some(line) = line.strip_prefix(".") {
-                            // Section 4.5.2 of RFC 5321 states that lines starting with a dot
-                            // should have the dot removed when they are part of the message body.
-                            // This is to avoid confusion with the end of data marker.
-                            // So we push the line without the leading dot.
-                            line.to_string()
-                        } else {
-                            line.to_string()
-                        };
-
-                        self.body.push(line_to_push);
-                        self.next()
-                    }
-                    MessageParserState::End => {
-                        Some(Err(MessageParserError::UnexpectedDataAfterEnd))
-                    }
-                    MessageParserState::Done => {
-                        Some(Err(MessageParserError::UnexpectedDataAfterEnd))
-                    }
+                if let Err(err) = self.headers.check(&line) {
+                    self.abort();
+                    return Some(Err(err));
+                }
+
+                let line_to_push = if let Some(line) = line.strip_prefix(".") {
+                    // Section 4.5.2 of RFC 5321 states that lines starting with a dot
+                    // should have the dot removed when they are part of the message body.
+                    // This is to avoid confusion with the end of data marker.
+                    // So we push the line without the leading dot.
+                    line.to_string()
+                } else {
+                    line.to_string()
+                };
+
+                if !self.options.allow_space_before_colon
+                    && !self.header_parser.is_done()
+                    && let Some(name) = name_before_space(&line_to_push)
+                {
+                    let name = name.to_string();
+                    self.abort();
+                    return Some(Err(MessageParserError::SpaceBeforeColon(name)));
                 }
+
+                let event = self.track_header(&line_to_push);
+                if self.streaming {
+                    let line = Ok(MessageParserEvent::BodyLine(line_to_push));
+                    return match event {
+                        Some(header) => {
+                            self.queued = Some(line);
+                            Some(Ok(header))
+                        }
+                        None => Some(line),
+                    };
+                }
+                self.body.push(line_to_push);
+                event.map(Ok)
             }
-            Some(Err(err)) => Some(Err(MessageParserError::IO(err))),
-            None => match self.state {
-                MessageParserState::Start => Some(Err(MessageParserError::UnexpectedEnd)),
-                MessageParserState::Helo => Some(Err(MessageParserError::UnexpectedEnd)),
-                MessageParserState::MailFrom => Some(Err(MessageParserError::UnexpectedEnd)),
-                MessageParserState::RcptTo => Some(Err(MessageParserError::UnexpectedEnd)),
-                MessageParserState::Data => Some(Err(MessageParserError::UnexpectedEnd)),
-                MessageParserState::End => Some(Ok(MessageParserEvent::Done(Message {}))),
-                MessageParserState::Done => None,
+            MessageParserState::End => match self.command(&line, &["MAIL FROM:"]) {
+                // A pipelined session may start another transaction right after the message.
+                Ok(Some(_)) => {
+                    let done = self.start_transaction();
+                    self.queued = self.step(line);
+                    Some(Ok(done))
+                }
+                Ok(None) => Some(Err(MessageParserError::UnexpectedDataAfterEnd)),
+                Err(err) => Some(Err(err)),
             },
+            // Only reached after the message was rejected, so the rest of it is skipped.
+            MessageParserState::Done => None,
+        }
+    }
+
+    /// Called once the input is exhausted.
+    fn finish(&mut self) -> Option<Result<MessageParserEvent, MessageParserError>> {
+        match self.state {
+            MessageParserState::Start
+            | MessageParserState::Helo
+            | MessageParserState::MailFrom
+            | MessageParserState::RcptTo
+            | MessageParserState::Data => {
+                // Reported once, so that collecting the events terminates.
+                self.state = MessageParserState::Done;
+                Some(Err(MessageParserError::UnexpectedEnd))
+            }
+            MessageParserState::End => {
+                self.state = MessageParserState::Done;
+                Some(Ok(MessageParserEvent::Done(Message {
+                    headers: self.finished_headers.clone(),
+                })))
+            }
+            MessageParserState::Done => None,
         }
     }
 }
 
+/// The name of the header starting `line` when whitespace separates it from its colon.
+fn name_before_space(line: &str) -> Option<&str> {
+    if line.starts_with([' ', '\t']) {
+        return None;
+    }
+    let (name, _) = line.split_once(':')?;
+    let trimmed = name.trim_end_matches([' ', '\t']);
+    (trimmed.len() < name.len()).then_some(trimmed)
+}
+
+impl<R: std::io::Read> Iterator for MessageParser<R> {
+    type Item = Result<MessageParserEvent, Spanned<MessageParserError>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.machine.take_queued().or_else(|| {
+            self.lines
+                .by_ref()
+                .find_map(|line| self.machine.read(line))
+                .or_else(|| self.machine.finish())
+        })?;
+        Some(event.map_err(|kind| self.lines.span(kind)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use email_address::EmailAddress;
 
     fn assert_event(
         expected: MessageParserEvent,
-        actual: Option<Result<MessageParserEvent, MessageParserError>>,
+        actual: Option<Result<MessageParserEvent, Spanned<MessageParserError>>>,
     ) {
         match actual {
             Some(Ok(event)) => assert_eq!(expected, event),
@@ -220,18 +916,211 @@ mod tests {
         let mut parser = MessageParser::new(input.as_bytes());
 
         assert_event(
-            MessageParserEvent::From(Some(EmailAddress::new_unchecked("test@example.com"))),
+            MessageParserEvent::From(Some(EmailAddress::new_unchecked("test@example.com").into())),
             parser.next(),
         );
         assert_event(
-            MessageParserEvent::To(EmailAddress::new_unchecked("test@example.com")),
+            MessageParserEvent::To(EmailAddress::new_unchecked("test@example.com").into()),
             parser.next(),
         );
         assert_event(
             MessageParserEvent::Body(vec!["Hello, world!".to_string()]),
             parser.next(),
         );
-        assert_event(MessageParserEvent::Done(Message {}), parser.next());
+        assert_event(
+            MessageParserEvent::Done(Message {
+                headers: Vec::new(),
+            }),
+            parser.next(),
+        );
+        assert_eq!(
+            parser.from().map(MailboxAddress::as_str),
+            Some("test@example.com")
+        );
+        assert_eq!(
+            parser
+                .to()
+                .iter()
+                .map(MailboxAddress::as_str)
+                .collect::<Vec<_>>(),
+            ["test@example.com"]
+        );
+    }
+
+    #[test]
+    fn test_several_recipients() {
+        let input = "HELO example.com\r\nMAIL FROM: <sender@example.com>\r\nRCPT TO: <alice@example.com>\r\nRCPT TO: <bob@example.com>\r\nDATA\r\nHello\r\n.\r\n";
+        let mut parser = MessageParser::new(input.as_bytes());
+
+        let events: Vec<_> = parser.by_ref().map(Result::unwrap).collect();
+        assert_eq!(
+            events[1..3],
+            [
+                MessageParserEvent::To(EmailAddress::new_unchecked("alice@example.com").into()),
+                MessageParserEvent::To(EmailAddress::new_unchecked("bob@example.com").into()),
+            ]
+        );
+        assert_eq!(
+            parser
+                .to()
+                .iter()
+                .map(MailboxAddress::as_str)
+                .collect::<Vec<_>>(),
+            ["alice@example.com", "bob@example.com"]
+        );
+    }
+
+    #[test]
+    fn test_pipelined_transactions() {
+        let input = "HELO example.com\r\nMAIL FROM: <alice@example.com> SIZE=100\r\nRCPT TO: <bob@example.com>\r\nDATA\r\nFirst\r\n.\r\nMAIL FROM: <carol@example.com>\r\nRCPT TO: <dave@example.com>\r\nDATA\r\nSecond\r\n.\r\n";
+        let mut parser = MessageParser::new(input.as_bytes());
+        let address = |address| EmailAddress::new_unchecked(address).into();
+        let done = MessageParserEvent::Done(Message {
+            headers: Vec::new(),
+        });
+
+        let first: Vec<_> = parser.by_ref().take(3).map(Result::unwrap).collect();
+        assert_eq!(
+            first,
+            [
+                MessageParserEvent::From(Some(address("alice@example.com"))),
+                MessageParserEvent::To(address("bob@example.com")),
+                MessageParserEvent::Body(vec!["First".to_string()]),
+            ]
+        );
+        assert_eq!(parser.mail_from().and_then(|params| params.size), Some(100));
+
+        let second: Vec<_> = parser.by_ref().map(Result::unwrap).collect();
+        assert_eq!(
+            second,
+            [
+                done.clone(),
+                MessageParserEvent::From(Some(address("carol@example.com"))),
+                MessageParserEvent::To(address("dave@example.com")),
+                MessageParserEvent::Body(vec!["Second".to_string()]),
+                done,
+            ]
+        );
+        assert_eq!(parser.mail_from().and_then(|params| params.size), None);
+        assert_eq!(parser.to(), [address("dave@example.com")]);
+    }
+
+    #[test]
+    fn test_aborted_session_has_no_addresses() {
+        let mut parser = MessageParser::new("HELO example.com\r\n".as_bytes());
+        assert!(parser.by_ref().all(|event| event.is_err()));
+        assert_eq!(parser.from(), None);
+        assert!(parser.to().is_empty());
+
+        let input = "HELO example.com\r\nMAIL FROM: <test@example.com>\r\nQUIT\r\n";
+        let mut parser = MessageParser::new(input.as_bytes());
+        parser.by_ref().for_each(drop);
+        assert_eq!(
+            parser.from().map(MailboxAddress::as_str),
+            Some("test@example.com")
+        );
+        assert!(parser.to().is_empty());
+    }
+
+    #[test]
+    fn test_streaming() {
+        let input = "HELO example.com\r\nMAIL FROM: <test@example.com>\r\nRCPT TO: <test@example.com>\r\nDATA\r\nSubject: Hello\r\n\r\nHello,\r\n..world!\r\n.\r\n";
+        let mut parser = MessageParser::new_streaming(input.as_bytes());
+        let line = |line: &str| MessageParserEvent::BodyLine(line.to_string());
+
+        let events: Vec<_> = parser.by_ref().map(Result::unwrap).collect();
+
+        assert_eq!(
+            events[2..],
+            [
+                line("Subject: Hello"),
+                MessageParserEvent::Header("Subject".to_string(), "Hello".to_string()),
+                line(""),
+                line("Hello,"),
+                line(".world!"),
+                MessageParserEvent::EndOfBody,
+                MessageParserEvent::Done(Message {
+                    headers: vec![("Subject".to_string(), "Hello".to_string())],
+                }),
+            ]
+        );
+        assert!(parser.machine.body.is_empty());
+    }
+
+    #[test]
+    fn test_long_body() {
+        let mut input = "HELO example.com\r\nMAIL FROM: <test@example.com>\r\nRCPT TO: <test@example.com>\r\nDATA\r\nSubject: Long\r\n\r\n".to_string();
+        input.push_str(&"line\r\n".repeat(500_000));
+        input.push_str(".\r\n");
+
+        let body = MessageParser::new(input.as_bytes()).find_map(|event| match event {
+            Ok(MessageParserEvent::Body(body)) => Some(body),
+            _ => None,
+        });
+
+        // The Subject header, the blank line ending the headers, then the 500,000 lines.
+        assert_eq!(body.map(|body| body.len()), Some(500_002));
+    }
+
+    fn message_with_line(line: &str) -> String {
+        format!(
+            "HELO example.com\r\nMAIL FROM: <test@example.com>\r\nRCPT TO: <test@example.com>\r\nDATA\r\n\r\n{line}\r\n.\r\n"
+        )
+    }
+
+    #[test]
+    fn test_line_length_limit() {
+        let line = "a".repeat(998);
+        let input = message_with_line(&line);
+        let body = MessageParser::new(input.as_bytes()).find_map(|event| match event {
+            Ok(MessageParserEvent::Body(body)) => Some(body),
+            _ => None,
+        });
+        assert_eq!(body, Some(vec![String::new(), line]));
+
+        let input = message_with_line(&"a".repeat(1000));
+        let events: Vec<_> = MessageParser::new(input.as_bytes()).collect();
+        assert!(
+            matches!(
+                events[..],
+                [
+                    Ok(_),
+                    Ok(_),
+                    Err(Spanned {
+                        kind: MessageParserError::LineTooLong(1000),
+                        ..
+                    })
+                ]
+            ),
+            "{events:?}"
+        );
+    }
+
+    #[test]
+    fn test_custom_line_length_limit() {
+        let options = ParserOptions {
+            max_line_len: 2000,
+            ..ParserOptions::default()
+        };
+        let input = message_with_line(&"a".repeat(1000));
+
+        assert!(
+            MessageParser::new(input.as_bytes())
+                .with_options(options)
+                .all(|event| event.is_ok())
+        );
+        assert!(matches!(
+            MessageParser::new("HELO example.com\r\n".as_bytes())
+                .with_options(ParserOptions {
+                    max_line_len: 10,
+                    ..ParserOptions::default()
+                })
+                .next(),
+            Some(Err(Spanned {
+                kind: MessageParserError::LineTooLong(16),
+                ..
+            }))
+        ));
     }
 
     #[test]
@@ -239,21 +1128,25 @@ mod tests {
         let table = vec![
             (
                 "MAIL FROM: <test@example.com>",
-                Some(EmailAddress::new_unchecked("test@example.com")),
+                Some(EmailAddress::new_unchecked("test@example.com").into()),
             ),
             (
                 "MAIL FROM:<test@example.com>",
-                Some(EmailAddress::new_unchecked("test@example.com")),
+                Some(EmailAddress::new_unchecked("test@example.com").into()),
             ),
             ("MAIL FROM: <>", None),
             ("MAIL FROM:<>", None),
             (
                 "MAIL FROM: <test+tag@example.com>",
-                Some(EmailAddress::new_unchecked("test+tag@example.com")),
+                Some(EmailAddress::new_unchecked("test+tag@example.com").into()),
             ),
             (
                 "MAIL FROM: <test@example.com> param1=ignored",
-                Some(EmailAddress::new_unchecked("test@example.com")),
+                Some(EmailAddress::new_unchecked("test@example.com").into()),
+            ),
+            (
+                "MAIL FROM: <test@example.com> SIZE=100 BODY=8BITMIME",
+                Some(EmailAddress::new_unchecked("test@example.com").into()),
             ),
         ];
 
@@ -263,4 +1156,607 @@ mod tests {
             assert_event(MessageParserEvent::From(expected), actual);
         }
     }
+
+    #[test]
+    fn test_parse_rcpt_to() {
+        let address: MailboxAddress = EmailAddress::new_unchecked("user@example.com").into();
+        let params = |notify: Vec<DsnNotify>, orcpt: Option<&str>| RcptToParams {
+            address: address.clone(),
+            notify,
+            orcpt: orcpt.map(str::to_string),
+        };
+        let table = [
+            ("<user@example.com>", params(vec![], None)),
+            (" <user@example.com>  ", params(vec![], None)),
+            ("<user@example.com> SIZE=100", params(vec![], None)),
+            (
+                "<user@example.com> NOTIFY=SUCCESS ORCPT=rfc822;user@example.com",
+                params(vec![DsnNotify::Success], Some("rfc822;user@example.com")),
+            ),
+            (
+                "<user@example.com> notify=failure,Delay,success",
+                params(
+                    vec![DsnNotify::Failure, DsnNotify::Delay, DsnNotify::Success],
+                    None,
+                ),
+            ),
+            (
+                "<user@example.com> NOTIFY=NEVER",
+                params(vec![DsnNotify::Never], None),
+            ),
+            (
+                "<user@example.com> orcpt=RFC822;other@example.com",
+                params(vec![], Some("RFC822;other@example.com")),
+            ),
+        ];
+        let utf8 = RcptToParams {
+            address: MailboxAddress::Utf8("пользователь@пример.рф".to_string()),
+            notify: Vec::new(),
+            orcpt: None,
+        };
+        assert_eq!(parse_rcpt_to("<пользователь@пример.рф>"), Ok(utf8));
+
+        for (argument, expected) in table {
+            assert_eq!(parse_rcpt_to(argument), Ok(expected), "{argument}");
+        }
+    }
+
+    #[test]
+    fn test_parse_rcpt_to_errors() {
+        let table = [
+            ("", RcptToError::MissingPath),
+            ("user@example.com", RcptToError::MissingPath),
+            ("<user@example.com", RcptToError::MissingPath),
+            (
+                "<>",
+                RcptToError::InvalidAddress(email_address::Error::MissingSeparator),
+            ),
+            (
+                "<user@example.com> NOTIFY=SOMETIMES",
+                RcptToError::InvalidNotify("SOMETIMES".to_string()),
+            ),
+            (
+                "<user@example.com> NOTIFY=NEVER,SUCCESS",
+                RcptToError::InvalidNotify("NEVER,SUCCESS".to_string()),
+            ),
+            (
+                "<user@example.com> NOTIFY",
+                RcptToError::InvalidNotify(String::new()),
+            ),
+            (
+                "<user@example.com> NOTIFY=DELAY NOTIFY=FAILURE",
+                RcptToError::DuplicateParameter("NOTIFY".to_string()),
+            ),
+            (
+                "<user@example.com> ORCPT=user@example.com",
+                RcptToError::InvalidOrcpt("user@example.com".to_string()),
+            ),
+            (
+                "<user@example.com> ORCPT=;user@example.com",
+                RcptToError::InvalidOrcpt(";user@example.com".to_string()),
+            ),
+        ];
+
+        for (argument, expected) in table {
+            assert_eq!(parse_rcpt_to(argument), Err(expected), "{argument}");
+        }
+    }
+
+    fn parse_headers(headers: &str) -> Vec<Result<MessageParserEvent, MessageParserError>> {
+        let input = format!(
+            "HELO example.com\r\nMAIL FROM: <test@example.com>\r\nRCPT TO: <test@example.com>\r\nDATA\r\n{headers}\r\nHello, world!\r\n.\r\n"
+        );
+        MessageParser::new(input.as_bytes())
+            .map(|event| event.map_err(|err| err.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_headers() {
+        let header = |name: &str, value: &str| (name.to_string(), value.to_string());
+        let expected = vec![
+            header("Subject", "Hello"),
+            header("To", "a@example.com, b@example.com"),
+            header("X-Last", "folded\tagain"),
+        ];
+
+        let events = parse_headers(
+            "Subject: Hello\r\nTo: a@example.com,\r\n b@example.com\r\nX-Last: folded\r\n\tagain\r\n",
+        );
+        let events: Vec<_> = events.into_iter().map(Result::unwrap).collect();
+
+        let emitted: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                MessageParserEvent::Header(name, value) => Some((name.clone(), value.clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(emitted, expected);
+        assert!(matches!(events[2], MessageParserEvent::Header(..)));
+        assert_eq!(
+            events.last(),
+            Some(&MessageParserEvent::Done(Message { headers: expected })),
+        );
+    }
+
+    #[test]
+    fn test_header_before_end_of_data() {
+        let input = "HELO example.com\r\nMAIL FROM: <test@example.com>\r\nRCPT TO: <test@example.com>\r\nDATA\r\nSubject: Hi\r\n.\r\n";
+        let events: Vec<_> = MessageParser::new(input.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(
+            events[2..],
+            [
+                MessageParserEvent::Header("Subject".to_string(), "Hi".to_string()),
+                MessageParserEvent::Body(vec!["Subject: Hi".to_string()]),
+                MessageParserEvent::Done(Message {
+                    headers: vec![("Subject".to_string(), "Hi".to_string())],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_too_many_headers() {
+        let headers: String = (0..200)
+            .map(|i| format!("X-Header-{i}: value\r\n"))
+            .collect();
+
+        let events = parse_headers(&headers);
+
+        assert!(matches!(
+            events.last(),
+            Some(Err(MessageParserError::HeadersTooLarge))
+        ));
+        assert!(
+            !events
+                .iter()
+                .any(|event| matches!(event, Ok(MessageParserEvent::Body(_))))
+        );
+    }
+
+    #[test]
+    fn test_header_too_large() {
+        // Folded, so that no single line exceeds the line length limit.
+        let headers = format!(
+            "X-Large: a\r\n{}",
+            format!(" {}\r\n", "a".repeat(900)).repeat(200)
+        );
+
+        let events = parse_headers(&headers);
+
+        assert!(matches!(
+            events.last(),
+            Some(Err(MessageParserError::HeadersTooLarge))
+        ));
+    }
+
+    #[test]
+    fn test_custom_header_limits() {
+        let input = "HELO example.com\r\nMAIL FROM: <test@example.com>\r\nRCPT TO: <test@example.com>\r\nDATA\r\nA: 1\r\nB: 2\r\n\r\nHello\r\n.\r\n";
+        let limits = HeaderLimits {
+            max_count: 1,
+            ..Default::default()
+        };
+
+        let events: Vec<_> = MessageParser::new(input.as_bytes())
+            .with_header_limits(limits)
+            .collect();
+
+        assert!(matches!(
+            events.last(),
+            Some(Err(Spanned {
+                kind: MessageParserError::HeadersTooLarge,
+                ..
+            }))
+        ));
+        assert!(MessageParser::new(input.as_bytes()).all(|event| event.is_ok()));
+    }
+
+    #[test]
+    fn test_error_display() {
+        let address_error = EmailAddress::from_str("not-an-address").unwrap_err();
+        let table = [
+            (
+                MessageParserError::InvalidMailFrom(MailFromError::InvalidAddress(
+                    address_error.clone(),
+                )),
+                format!("invalid MAIL FROM argument: invalid address: {address_error}"),
+            ),
+            (
+                MessageParserError::InvalidMailFrom(MailFromError::InvalidBody(
+                    "BINARYMIME".to_string(),
+                )),
+                "invalid MAIL FROM argument: invalid BODY value: BINARYMIME".to_string(),
+            ),
+            (
+                MessageParserError::InvalidRcptTo(RcptToError::InvalidAddress(
+                    address_error.clone(),
+                )),
+                format!("invalid RCPT TO argument: invalid address: {address_error}"),
+            ),
+            (
+                MessageParserError::InvalidRcptTo(RcptToError::MissingPath),
+                "invalid RCPT TO argument: missing <address>".to_string(),
+            ),
+            (
+                MessageParserError::UnrecognizedCommand("QUIT".to_string()),
+                "unrecognized command: QUIT".to_string(),
+            ),
+            (
+                MessageParserError::HeadersTooLarge,
+                "the message headers exceed the allowed size".to_string(),
+            ),
+        ];
+
+        for (err, expected) in table {
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_error_source() {
+        use std::error::Error;
+
+        let address_error = EmailAddress::from_str("not-an-address").unwrap_err();
+        let err =
+            MessageParserError::InvalidRcptTo(RcptToError::InvalidAddress(address_error.clone()));
+        let source = err.source().and_then(Error::source).unwrap();
+        assert_eq!(source.to_string(), address_error.to_string());
+
+        let err = MessageParserError::IO(std::io::Error::other("reset"));
+        assert_eq!(err.source().unwrap().to_string(), "reset");
+        assert!(MessageParserError::UnexpectedEnd.source().is_none());
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let err = MessageParser::new("HELO example.com\r\nQUIT\r\n".as_bytes())
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!((err.line, err.offset), (2, 18));
+        assert_eq!(err.to_string(), "line 2: unrecognized command: QUIT");
+
+        // The third header, on line 7, is one more than allowed.
+        let input = "HELO example.com\r\nMAIL FROM: <test@example.com>\r\nRCPT TO: <test@example.com>\r\nDATA\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\nHello\r\n.\r\n";
+        let limits = HeaderLimits {
+            max_count: 2,
+            ..Default::default()
+        };
+        let err = MessageParser::new(input.as_bytes())
+            .with_header_limits(limits)
+            .find_map(Result::err)
+            .unwrap();
+        assert!(matches!(err.kind, MessageParserError::HeadersTooLarge));
+        assert_eq!(err.line, 7);
+        assert_eq!(&input[err.offset..], "C: 3\r\n\r\nHello\r\n.\r\n");
+
+        // An input ending mid-transaction is reported past its last line.
+        let input = "HELO example.com\r\nMAIL FROM: <test@example.com>";
+        let err = MessageParser::new(input.as_bytes())
+            .find_map(Result::err)
+            .unwrap();
+        assert!(matches!(err.kind, MessageParserError::UnexpectedEnd));
+        assert_eq!((err.line, err.offset), (3, input.len()));
+    }
+
+    #[test]
+    fn test_parse_mail_from() {
+        let address = Some(EmailAddress::new_unchecked("a@b.c").into());
+        let params = |address: Option<MailboxAddress>| MailFromParams {
+            address,
+            size: None,
+            body: None,
+            smtputf8: false,
+        };
+        let table = [
+            ("<a@b.c>", params(address.clone())),
+            (" <a@b.c>  ", params(address.clone())),
+            ("<>", params(None)),
+            ("<a@b.c> X-UNKNOWN=1 FLAG", params(address.clone())),
+            (
+                "<a@b.c> SIZE=1024",
+                MailFromParams {
+                    size: Some(1024),
+                    ..params(address.clone())
+                },
+            ),
+            (
+                "<a@b.c> body=8bitmime",
+                MailFromParams {
+                    body: Some(BodyType::EightBitMime),
+                    ..params(address.clone())
+                },
+            ),
+            (
+                "<> BODY=7BIT",
+                MailFromParams {
+                    body: Some(BodyType::SevenBit),
+                    ..params(None)
+                },
+            ),
+            (
+                "<a@b.c> SMTPUTF8 BODY=8BITMIME Size=0",
+                MailFromParams {
+                    size: Some(0),
+                    body: Some(BodyType::EightBitMime),
+                    smtputf8: true,
+                    ..params(address.clone())
+                },
+            ),
+            (
+                "<用户@例子.广告> SMTPUTF8",
+                MailFromParams {
+                    smtputf8: true,
+                    ..params(Some(MailboxAddress::Utf8("用户@例子.广告".to_string())))
+                },
+            ),
+        ];
+
+        for (argument, expected) in table {
+            assert_eq!(parse_mail_from(argument), Ok(expected), "{argument}");
+        }
+    }
+
+    #[test]
+    fn test_parse_mail_from_errors() {
+        let table = [
+            ("", MailFromError::MissingPath),
+            ("a@b.c", MailFromError::MissingPath),
+            ("<a@b.c", MailFromError::MissingPath),
+            (
+                "<not-an-address>",
+                MailFromError::InvalidAddress(email_address::Error::MissingSeparator),
+            ),
+            (
+                "<a@b.c> SIZE=big",
+                MailFromError::InvalidSize("big".to_string()),
+            ),
+            ("<a@b.c> SIZE", MailFromError::InvalidSize(String::new())),
+            (
+                "<a@b.c> SIZE=-1",
+                MailFromError::InvalidSize("-1".to_string()),
+            ),
+            (
+                "<a@b.c> BODY=BINARYMIME",
+                MailFromError::InvalidBody("BINARYMIME".to_string()),
+            ),
+            (
+                "<a@b.c> SMTPUTF8=yes",
+                MailFromError::InvalidSmtpUtf8("yes".to_string()),
+            ),
+            (
+                "<a@b.c> size=1 SIZE=2",
+                MailFromError::DuplicateParameter("SIZE".to_string()),
+            ),
+            (
+                "<a@b.c> SMTPUTF8 SMTPUTF8",
+                MailFromError::DuplicateParameter("SMTPUTF8".to_string()),
+            ),
+        ];
+
+        for (argument, expected) in table {
+            assert_eq!(parse_mail_from(argument), Err(expected), "{argument}");
+        }
+    }
+
+    #[test]
+    fn test_strip_command() {
+        assert_eq!(
+            strip_command("mail from:<a@b.c>", "MAIL FROM:"),
+            Some("<a@b.c>")
+        );
+        assert_eq!(strip_command("HELO", "HELO"), Some(""));
+        assert_eq!(strip_command("HEL", "HELO"), None);
+        assert_eq!(strip_command("HÉLO foo", "HELO"), None);
+        assert_eq!(strip_command("MAIL FRÖM:<a@b.c>", "MAIL FROM:"), None);
+    }
+
+    #[test]
+    fn test_multibyte_commands_are_unrecognized() {
+        let preceding = [
+            "HELO example.com",
+            "MAIL FROM: <test@example.com>",
+            "RCPT TO: <test@example.com>",
+        ];
+        // Each command is sent after the given number of `preceding` lines.
+        let table = [
+            (0, "HÉLO foo"),
+            (0, "HEL€"),
+            (1, "MAIL FRÖM: <test@example.com>"),
+            (1, "MAIL FROM€"),
+            (2, "RCPT TÖ: <test@example.com>"),
+            (2, "RCPT T€"),
+            (3, "DATÄ"),
+        ];
+
+        for (state, command) in table {
+            let input = [&preceding[..state], &[command]].concat().join("\r\n");
+            let event = MessageParser::new(input.as_bytes()).find(Result::is_err);
+            assert!(
+                matches!(
+                    event,
+                    Some(Err(Spanned {
+                        kind: MessageParserError::UnrecognizedCommand(_),
+                        ..
+                    }))
+                ),
+                "{command}: {event:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_permissive() {
+        let input = "HELO example.com\r\nXFOO bar\r\nMAIL FROM: <test@example.com>\r\n";
+
+        let events: Vec<_> = MessageParser::permissive(input.as_bytes())
+            .map(Result::unwrap)
+            .take(2)
+            .collect();
+        assert_eq!(
+            events,
+            [
+                MessageParserEvent::Unknown("XFOO bar".to_string()),
+                MessageParserEvent::From(Some(
+                    EmailAddress::new_unchecked("test@example.com").into()
+                )),
+            ]
+        );
+
+        let event = MessageParser::new(input.as_bytes()).next();
+        assert!(matches!(
+            event,
+            Some(Err(Spanned { kind: MessageParserError::UnrecognizedCommand(line), .. })) if line == "XFOO bar"
+        ));
+    }
+
+    /// The seeds of the fuzzing corpus, by name.
+    fn corpus() -> Vec<(String, Vec<u8>)> {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fuzz/corpus/message_parser");
+        let mut seeds: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                name.starts_with("seed-")
+                    .then(|| (name, std::fs::read(entry.path()).unwrap()))
+            })
+            .collect();
+        seeds.sort();
+        seeds
+    }
+
+    #[test]
+    fn test_options_on_corpus() {
+        // Each seed's strict error, which lenient parsing gets past to finish the message.
+        type IsExpected = fn(&MessageParserError) -> bool;
+        let divergent: [(&str, IsExpected); 5] = [
+            ("seed-bare-lf", |err| {
+                matches!(err, MessageParserError::BareLineFeed)
+            }),
+            (
+                "seed-lowercase-commands",
+                |err| matches!(err, MessageParserError::LowercaseCommand(line) if line.starts_with("mail")),
+            ),
+            ("seed-missing-helo", |err| {
+                matches!(err, MessageParserError::MissingHelo)
+            }),
+            // Its HELO is not valid UTF-8, so the transaction starts at MAIL FROM.
+            ("seed-non-utf8", |err| {
+                matches!(err, MessageParserError::MissingHelo)
+            }),
+            (
+                "seed-space-before-colon",
+                |err| matches!(err, MessageParserError::SpaceBeforeColon(name) if name == "Subject"),
+            ),
+        ];
+
+        let seeds = corpus();
+        for (name, input) in &seeds {
+            let lenient: Vec<_> = MessageParser::new(input.as_slice())
+                .with_options(ParserOptions::lenient())
+                .collect();
+            let strict: Vec<_> = MessageParser::new(input.as_slice())
+                .with_options(ParserOptions::strict())
+                .collect();
+
+            match divergent.iter().find(|(seed, _)| seed == name) {
+                Some((_, is_expected)) => {
+                    assert!(
+                        !lenient
+                            .iter()
+                            .any(|event| event.as_ref().is_err_and(|err| is_expected(&err.kind))),
+                        "{name}: {lenient:?}"
+                    );
+                    assert!(
+                        matches!(lenient.last(), Some(Ok(MessageParserEvent::Done(_)))),
+                        "{name}: {lenient:?}"
+                    );
+                    assert!(
+                        strict
+                            .iter()
+                            .any(|event| event.as_ref().is_err_and(|err| is_expected(&err.kind))),
+                        "{name}: {strict:?}"
+                    );
+                }
+                None => assert_eq!(
+                    format!("{lenient:?}"),
+                    format!("{strict:?}"),
+                    "{name} should parse the same in both modes"
+                ),
+            }
+        }
+        for (seed, _) in divergent {
+            assert!(seeds.iter().any(|(name, _)| name == seed), "missing {seed}");
+        }
+    }
+
+    #[test]
+    fn test_default_options() {
+        let input = |first: &str| {
+            format!(
+                "{first}\r\nMAIL FROM: <test@example.com>\r\nRCPT TO: <test@example.com>\r\ndata\nSubject : Hi\r\n\r\n.\r\n"
+            )
+        };
+
+        // The default tolerates everything but a missing HELO, as the parser always has.
+        let events: Vec<_> = MessageParser::new(input("HELO example.com").as_bytes()).collect();
+        assert!(events.iter().all(Result::is_ok), "{events:?}");
+        assert!(events.iter().any(|event| matches!(
+            event,
+            Ok(MessageParserEvent::Header(name, value)) if name == "Subject" && value == "Hi"
+        )));
+
+        let events: Vec<_> =
+            MessageParser::new(input("MAIL FROM: <test@example.com>").as_bytes()).collect();
+        assert!(
+            matches!(
+                events.first(),
+                Some(Err(Spanned {
+                    kind: MessageParserError::MissingHelo,
+                    line: 1,
+                    ..
+                }))
+            ),
+            "{events:?}"
+        );
+    }
+
+    #[test]
+    fn test_random_utf8_does_not_panic() {
+        let prefixes = ["HELO", "EHLO", "MAIL FROM:", "RCPT TO:", "DATA", ".", ""];
+        let chars = [
+            'a', 'Z', ' ', ':', '<', '>', '@', '.', 'é', 'É', '€', '😀', '\u{301}',
+        ];
+        // A fixed seed keeps the inputs the same on every run.
+        let mut rng = fastrand::Rng::with_seed(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..1000 {
+            let input: String = (0..rng.usize(..8))
+                .map(|_| {
+                    let prefix = prefixes[rng.usize(..prefixes.len())];
+                    let suffix: String = (0..rng.usize(..12))
+                        .map(|_| chars[rng.usize(..chars.len())])
+                        .collect();
+                    format!("{prefix}{suffix}\r\n")
+                })
+                .collect();
+            let _: Vec<_> = MessageParser::new(input.as_bytes()).collect();
+        }
+    }
+
+    #[test]
+    fn test_random_bytes_do_not_panic() {
+        let mut rng = fastrand::Rng::with_seed(0x9e37_79b9_7f4a_7c15);
+
+        for _ in 0..1000 {
+            let mut input = b"HELO example.com\r\n".to_vec();
+            input.extend((0..rng.usize(..64)).map(|_| rng.u8(..)));
+            let _: Vec<_> = MessageParser::new(input.as_slice()).collect();
+        }
+    }
 }
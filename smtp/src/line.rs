@@ -0,0 +1,252 @@
+//! Splitting a transaction into lines, bounded by the limit RFC 5321 section 4.5.3.1.6 puts on
+//! their length so a client cannot make the parser buffer one endless line.
+
+use crate::{MessageParserError, ParserOptions, Spanned};
+use std::io::{self, BufRead};
+
+/// The longest text line RFC 5321 allows, in octets, not counting its CRLF.
+pub const MAX_LINE_LENGTH: usize = 998;
+
+/// Splits a whole message into its lines, without their line breaks, accepting CRLF or a bare
+/// LF. Unlike [`LineSplitter`], lines are kept whole and need not be UTF-8.
+pub fn split_lines(bytes: &[u8]) -> Vec<Vec<u8>> {
+    let mut lines: Vec<Vec<u8>> = bytes
+        .split(|&byte| byte == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line).to_vec())
+        .collect();
+    // The final line break leaves an empty line after it.
+    if lines.last().is_some_and(Vec::is_empty) {
+        lines.pop();
+    }
+    lines
+}
+
+/// Collects the bytes of one line at a time, keeping no more of a line than the limit allows.
+pub(crate) struct LineSplitter {
+    max_line_length: usize,
+    /// Whether lines ending with a bare LF fail with [`MessageParserError::BareLineFeed`].
+    strict_crlf: bool,
+    line: Vec<u8>,
+    /// The length of the line so far, including bytes that were not kept.
+    length: usize,
+    ends_with_cr: bool,
+    /// The number of the line being read, counting from 1.
+    line_number: usize,
+    /// The byte offset of the start of the line being read.
+    offset: usize,
+    /// The position of the line last returned, or of the end of the input once it was reached.
+    position: (usize, usize),
+}
+
+impl LineSplitter {
+    pub(crate) fn new(max_line_length: usize) -> Self {
+        Self {
+            max_line_length,
+            strict_crlf: false,
+            line: Vec::new(),
+            length: 0,
+            ends_with_cr: false,
+            line_number: 1,
+            offset: 0,
+            position: (1, 0),
+        }
+    }
+
+    /// Applies the line settings of `options`. Only meant to be called before the first line.
+    pub(crate) fn configure(&mut self, options: &ParserOptions) {
+        self.max_line_length = options.max_line_len;
+        self.strict_crlf = options.strict_crlf;
+    }
+
+    /// Attaches the position of the line last returned to `kind`.
+    pub(crate) fn span<E>(&self, kind: E) -> Spanned<E> {
+        let (line, offset) = self.position;
+        Spanned { line, offset, kind }
+    }
+
+    /// Moves the position to the line being read, for errors that are not about a line
+    /// returned, such as the input ending or failing to be read.
+    pub(crate) fn skip_to_current(&mut self) {
+        self.position = (self.line_number, self.offset);
+    }
+
+    /// Takes bytes from `available`, returning how many were consumed and the line they
+    /// completed, if any.
+    pub(crate) fn push(
+        &mut self,
+        available: &[u8],
+    ) -> (usize, Option<Result<String, MessageParserError>>) {
+        match available.iter().position(|&byte| byte == b'\n') {
+            Some(end) => {
+                self.extend(&available[..end]);
+                (end + 1, Some(self.take_line(true)))
+            }
+            None => {
+                self.extend(available);
+                (available.len(), None)
+            }
+        }
+    }
+
+    /// The last line of the input, when it does not end with a line break.
+    pub(crate) fn finish(&mut self) -> Option<Result<String, MessageParserError>> {
+        if self.length == 0 {
+            self.skip_to_current();
+            return None;
+        }
+        Some(self.take_line(false))
+    }
+
+    fn extend(&mut self, bytes: &[u8]) {
+        let Some(&last) = bytes.last() else {
+            return;
+        };
+        // One byte more than the limit is kept, for a CR that is not part of the line.
+        let room = self
+            .max_line_length
+            .saturating_add(1)
+            .saturating_sub(self.line.len());
+        self.line.extend_from_slice(&bytes[..bytes.len().min(room)]);
+        self.length += bytes.len();
+        self.ends_with_cr = last == b'\r';
+    }
+
+    /// Returns the line collected so far, `terminated` when it ended with a line break.
+    fn take_line(&mut self, terminated: bool) -> Result<String, MessageParserError> {
+        self.position = (self.line_number, self.offset);
+        self.line_number += 1;
+        self.offset += self.length + usize::from(terminated);
+
+        let ends_with_cr = self.ends_with_cr;
+        let length = self.length - usize::from(ends_with_cr);
+        let mut line = std::mem::take(&mut self.line);
+        self.length = 0;
+        self.ends_with_cr = false;
+
+        if length > self.max_line_length {
+            return Err(MessageParserError::LineTooLong(length));
+        }
+        if terminated && self.strict_crlf && !ends_with_cr {
+            return Err(MessageParserError::BareLineFeed);
+        }
+        line.truncate(length);
+        String::from_utf8(line).map_err(|_| {
+            MessageParserError::IO(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            ))
+        })
+    }
+}
+
+/// The lines of a reader, like [`BufRead::lines`], failing with
+/// [`MessageParserError::LineTooLong`] on lines longer than the limit instead of reading them
+/// into memory.
+pub(crate) struct LimitedLineReader<R: BufRead> {
+    reader: R,
+    splitter: LineSplitter,
+}
+
+impl<R: BufRead> LimitedLineReader<R> {
+    pub(crate) fn new(reader: R, max_line_length: usize) -> Self {
+        Self {
+            reader,
+            splitter: LineSplitter::new(max_line_length),
+        }
+    }
+
+    pub(crate) fn configure(&mut self, options: &ParserOptions) {
+        self.splitter.configure(options);
+    }
+
+    /// Attaches the position of the line last returned to `kind`.
+    pub(crate) fn span<E>(&self, kind: E) -> Spanned<E> {
+        self.splitter.span(kind)
+    }
+}
+
+impl<R: BufRead> Iterator for LimitedLineReader<R> {
+    type Item = Result<String, MessageParserError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let available = match self.reader.fill_buf() {
+                Ok(available) => available,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    self.splitter.skip_to_current();
+                    return Some(Err(MessageParserError::IO(err)));
+                }
+            };
+            if available.is_empty() {
+                return self.splitter.finish();
+            }
+
+            let (consumed, line) = self.splitter.push(available);
+            self.reader.consume(consumed);
+            if line.is_some() {
+                return line;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(input: &[u8], max_line_length: usize) -> Vec<Result<String, usize>> {
+        // A one-byte buffer makes every line span several reads.
+        let reader = std::io::BufReader::with_capacity(1, input);
+        LimitedLineReader::new(reader, max_line_length)
+            .map(|line| match line {
+                Ok(line) => Ok(line),
+                Err(MessageParserError::LineTooLong(length)) => Err(length),
+                Err(err) => panic!("unexpected error: {err}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lines() {
+        assert_eq!(
+            lines(b"one\r\ntwo\n\r\nlast", 10),
+            [
+                Ok("one".to_string()),
+                Ok("two".to_string()),
+                Ok(String::new()),
+                Ok("last".to_string())
+            ]
+        );
+        assert!(lines(b"", 10).is_empty());
+    }
+
+    #[test]
+    fn test_line_length_limit() {
+        assert_eq!(
+            lines(b"0123456789\r\n01234567890\r\nshort\r\n", 10),
+            [
+                Ok("0123456789".to_string()),
+                Err(11),
+                Ok("short".to_string())
+            ]
+        );
+        assert_eq!(lines(b"0123456789\r", 10), [Ok("0123456789".to_string())]);
+        assert_eq!(lines(b"01234567890", 10), [Err(11)]);
+    }
+
+    #[test]
+    fn test_split_lines() {
+        assert_eq!(
+            split_lines(b"one\r\ntwo\n\r\n\xfflast\r\n"),
+            [
+                b"one".to_vec(),
+                b"two".to_vec(),
+                Vec::new(),
+                b"\xfflast".to_vec()
+            ]
+        );
+        assert_eq!(split_lines(b"no break"), [b"no break".to_vec()]);
+        assert!(split_lines(b"").is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+//! Envelope addresses, the paths of `MAIL FROM` and `RCPT TO`.
+//!
+//! ASCII addresses are validated by `email_address`. Its character checks reject many
+//! internationalized addresses RFC 6531 allows, such as ones with combining marks or emoji, so
+//! addresses that are not ASCII get a looser check of their own and are kept as received.
+
+use email_address::{EmailAddress, Error};
+use std::fmt;
+use std::str::FromStr;
+
+/// The longest local part RFC 5321 section 4.5.3.1.1 allows, in octets.
+const MAX_LOCAL_PART_BYTES: usize = 64;
+/// The longest domain RFC 5321 section 4.5.3.1.2 allows, in octets.
+const MAX_DOMAIN_BYTES: usize = 255;
+/// The longest label of a domain, in octets.
+const MAX_LABEL_BYTES: usize = 63;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(untagged))]
+pub enum MailboxAddress {
+    Ascii(EmailAddress),
+    /// An address with characters outside ASCII, which clients may only send after declaring
+    /// `SMTPUTF8`.
+    Utf8(String),
+}
+
+impl MailboxAddress {
+    /// Parses an address without angle brackets, such as `user@example.com` or
+    /// `用户@例子.广告`.
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        if value.is_ascii() {
+            return EmailAddress::from_str(value).map(MailboxAddress::Ascii);
+        }
+
+        let (local_part, domain) = value.rsplit_once('@').ok_or(Error::MissingSeparator)?;
+        if local_part.is_empty() {
+            return Err(Error::LocalPartEmpty);
+        }
+        if local_part.len() > MAX_LOCAL_PART_BYTES {
+            return Err(Error::LocalPartTooLong);
+        }
+        if domain.is_empty() {
+            return Err(Error::DomainEmpty);
+        }
+        if domain.len() > MAX_DOMAIN_BYTES {
+            return Err(Error::DomainTooLong);
+        }
+        check_local_part(local_part)?;
+        check_domain(domain)?;
+        Ok(MailboxAddress::Utf8(value.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            MailboxAddress::Ascii(address) => address.as_str(),
+            MailboxAddress::Utf8(address) => address,
+        }
+    }
+
+    pub fn local_part(&self) -> &str {
+        self.as_str()
+            .rsplit_once('@')
+            .map_or_else(|| self.as_str(), |(local_part, _)| local_part)
+    }
+
+    pub fn domain(&self) -> &str {
+        self.as_str()
+            .rsplit_once('@')
+            .map_or("", |(_, domain)| domain)
+    }
+
+    /// Whether the address can be sent without `SMTPUTF8`.
+    pub fn is_ascii(&self) -> bool {
+        matches!(self, MailboxAddress::Ascii(_))
+    }
+}
+
+/// A non-ASCII character that may appear in an address, as RFC 6531 section 3.3 extends
+/// `atext` with any UTF-8 character. Spaces and control characters are still refused.
+fn is_utf8_text(c: char) -> bool {
+    !c.is_ascii() && !c.is_whitespace() && !c.is_control()
+}
+
+/// Checks a local part that is a dot-atom, or a quoted string such as `"first last"`.
+fn check_local_part(local_part: &str) -> Result<(), Error> {
+    if let Some(quoted) = local_part
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        return if quoted.chars().any(|c| c.is_control() || c == '"') {
+            Err(Error::InvalidCharacter)
+        } else {
+            Ok(())
+        };
+    }
+
+    let is_atext = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c);
+    for atom in local_part.split('.') {
+        if atom.is_empty() || !atom.chars().all(|c| is_atext(c) || is_utf8_text(c)) {
+            return Err(Error::InvalidCharacter);
+        }
+    }
+    Ok(())
+}
+
+/// Checks an internationalized domain name in its Unicode form, such as `例子.广告`.
+fn check_domain(domain: &str) -> Result<(), Error> {
+    for label in domain.split('.') {
+        if label.is_empty() {
+            return Err(Error::SubDomainEmpty);
+        }
+        if label.len() > MAX_LABEL_BYTES {
+            return Err(Error::SubDomainTooLong);
+        }
+        if label.starts_with('-')
+            || label.ends_with('-')
+            || !label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || is_utf8_text(c))
+        {
+            return Err(Error::InvalidCharacter);
+        }
+    }
+    Ok(())
+}
+
+impl FromStr for MailboxAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl From<EmailAddress> for MailboxAddress {
+    fn from(address: EmailAddress) -> Self {
+        MailboxAddress::Ascii(address)
+    }
+}
+
+impl fmt::Display for MailboxAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let table = [
+            ("user@example.com", true),
+            ("用户@例子.广告", false),
+            ("пользователь@пример.рф", false),
+            ("प्राप्तकर्ता@उदाहरण.भारत", false),
+            ("αποστολέας@παράδειγμα.ελ", false),
+            ("مرسل@مثال.مصر", false),
+            ("ক্ষ.দাস@example.com", false),
+            ("👍@example.com", false),
+            ("\"josé silva\"@example.com", false),
+            ("user@bücher.example", false),
+        ];
+
+        for (address, ascii) in table {
+            let parsed = MailboxAddress::parse(address).unwrap();
+            assert_eq!(parsed.as_str(), address);
+            assert_eq!(parsed.is_ascii(), ascii, "{address}");
+        }
+
+        let address = MailboxAddress::parse("प्रेषक@उदाहरण.भारत").unwrap();
+        assert_eq!(address.local_part(), "प्रेषक");
+        assert_eq!(address.domain(), "उदाहरण.भारत");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let table = [
+            ("not-an-address", Error::MissingSeparator),
+            ("用户", Error::MissingSeparator),
+            ("@例子.广告", Error::LocalPartEmpty),
+            ("用户@", Error::DomainEmpty),
+            ("用户 名@例子.广告", Error::InvalidCharacter),
+            ("用户..名@例子.广告", Error::InvalidCharacter),
+            ("用户@例子..广告", Error::SubDomainEmpty),
+            ("用户@-例子.广告", Error::InvalidCharacter),
+            ("用户@[例子]", Error::InvalidCharacter),
+        ];
+
+        for (address, expected) in table {
+            assert_eq!(MailboxAddress::parse(address), Err(expected), "{address}");
+        }
+        assert_eq!(
+            MailboxAddress::parse(&format!("{}@例子.广告", "用".repeat(22))),
+            Err(Error::LocalPartTooLong)
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use crate::line::LineSplitter;
+use crate::mailbox::MailboxAddress;
+use crate::{
+    HeaderGuard, HeaderLimits, MAX_LINE_LENGTH, MailFromParams, MessageParserError,
+    MessageParserEvent, ParserOptions, Spanned, StateMachine,
+};
+use futures_core::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::AsyncBufRead;
+
+/// The async counterpart of [`MessageParser`](crate::MessageParser), yielding the same events
+/// from a tokio reader.
+pub struct AsyncMessageParser<R: AsyncBufRead + Unpin> {
+    reader: R,
+    lines: LineSplitter,
+    machine: StateMachine,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncMessageParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            lines: LineSplitter::new(MAX_LINE_LENGTH),
+            machine: StateMachine::new(),
+        }
+    }
+
+    /// The async counterpart of [`MessageParser::with_options`](crate::MessageParser::with_options).
+    pub fn with_options(mut self, options: ParserOptions) -> Self {
+        self.lines.configure(&options);
+        self.machine.configure(options);
+        self
+    }
+
+    /// The async counterpart of [`MessageParser::new_streaming`](crate::MessageParser::new_streaming).
+    pub fn new_streaming(reader: R) -> Self {
+        let mut parser = Self::new(reader);
+        parser.machine.streaming = true;
+        parser
+    }
+
+    /// The async counterpart of [`MessageParser::permissive`](crate::MessageParser::permissive).
+    pub fn permissive(reader: R) -> Self {
+        let mut parser = Self::new(reader);
+        parser.machine.permissive = true;
+        parser
+    }
+
+    pub fn with_header_limits(mut self, limits: HeaderLimits) -> Self {
+        self.machine.headers = HeaderGuard::new(limits);
+        self
+    }
+
+    /// The sender of the transaction, once `MAIL FROM` named one. `None` for the null sender.
+    pub fn from(&self) -> Option<&MailboxAddress> {
+        self.machine.mail_from.as_ref()?.address.as_ref()
+    }
+
+    /// The async counterpart of [`MessageParser::mail_from`](crate::MessageParser::mail_from).
+    pub fn mail_from(&self) -> Option<&MailFromParams> {
+        self.machine.mail_from.as_ref()
+    }
+
+    /// The recipients of the transaction, in the order `RCPT TO` named them.
+    pub fn to(&self) -> &[MailboxAddress] {
+        &self.machine.to
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> Stream for AsyncMessageParser<R> {
+    type Item = Result<MessageParserEvent, Spanned<MessageParserError>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let event = ready!(this.poll_event(cx));
+        Poll::Ready(event.map(|event| event.map_err(|kind| this.lines.span(kind))))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncMessageParser<R> {
+    fn poll_event(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<MessageParserEvent, MessageParserError>>> {
+        if let Some(event) = self.machine.take_queued() {
+            return Poll::Ready(Some(event));
+        }
+        loop {
+            let available = match ready!(Pin::new(&mut self.reader).poll_fill_buf(cx)) {
+                Ok(available) => available,
+                Err(err) => {
+                    self.lines.skip_to_current();
+                    return Poll::Ready(Some(Err(MessageParserError::IO(err))));
+                }
+            };
+            let line = if available.is_empty() {
+                match self.lines.finish() {
+                    Some(line) => line,
+                    None => return Poll::Ready(self.machine.finish()),
+                }
+            } else {
+                let (consumed, line) = self.lines.push(available);
+                Pin::new(&mut self.reader).consume(consumed);
+                match line {
+                    Some(line) => line,
+                    None => continue,
+                }
+            };
+
+            if let Some(event) = self.machine.read(line) {
+                return Poll::Ready(Some(event));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MessageParser;
+    use std::future::poll_fn;
+
+    async fn collect<R: AsyncBufRead + Unpin>(
+        mut parser: AsyncMessageParser<R>,
+    ) -> Vec<Result<MessageParserEvent, Spanned<MessageParserError>>> {
+        let mut events = Vec::new();
+        while let Some(event) = poll_fn(|cx| Pin::new(&mut parser).poll_next(cx)).await {
+            events.push(event);
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn test_line_length_limit() {
+        let line = |length| {
+            format!(
+                "HELO example.com\r\nMAIL FROM: <test@example.com>\r\nRCPT TO: <test@example.com>\r\nDATA\r\n{}\r\n.\r\n",
+                "a".repeat(length)
+            )
+        };
+
+        let events = collect(AsyncMessageParser::new(std::io::Cursor::new(line(998)))).await;
+        assert!(events.iter().all(Result::is_ok), "{events:?}");
+
+        let events = collect(AsyncMessageParser::new(std::io::Cursor::new(line(1000)))).await;
+        assert!(
+            matches!(
+                events[..],
+                [
+                    Ok(_),
+                    Ok(_),
+                    Err(Spanned {
+                        kind: MessageParserError::LineTooLong(1000),
+                        ..
+                    })
+                ]
+            ),
+            "{events:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_matches_sync_parser() {
+        let input = "HELO example.com\r\nMAIL FROM: <sender@example.com>\r\nRCPT TO: <recipient@example.com>\r\nDATA\r\nSubject: Hello\r\n\r\n..dotted\r\nHello, world!\r\n.\r\n";
+
+        let expected: Vec<_> = MessageParser::new(input.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        let actual: Vec<_> = collect(AsyncMessageParser::new(std::io::Cursor::new(input)))
+            .await
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(actual, expected);
+        assert_eq!(expected.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_reads_from_duplex_pipe() {
+        let (mut client, server) = tokio::io::duplex(64);
+        let writer = tokio::spawn(async move {
+            let lines = [
+                "HELO example.com\r\n",
+                "MAIL FROM: <sender@example.com>\r\n",
+                "RCPT TO: <recipient@example.com>\r\n",
+                "DATA\r\n",
+                "Subject: Hello\r\n\r\n",
+                "Hello, world!\r\n",
+                ".\r\n",
+            ];
+            for line in lines {
+                tokio::io::AsyncWriteExt::write_all(&mut client, line.as_bytes())
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let events = collect(AsyncMessageParser::new(tokio::io::BufReader::new(server))).await;
+        writer.await.unwrap();
+
+        let events: Vec<_> = events.into_iter().map(Result::unwrap).collect();
+        assert_eq!(
+            events,
+            [
+                MessageParserEvent::From(Some("sender@example.com".parse().unwrap())),
+                MessageParserEvent::To("recipient@example.com".parse().unwrap()),
+                MessageParserEvent::Header("Subject".to_string(), "Hello".to_string()),
+                MessageParserEvent::Body(vec![
+                    "Subject: Hello".to_string(),
+                    String::new(),
+                    "Hello, world!".to_string(),
+                ]),
+                MessageParserEvent::Done(crate::Message {
+                    headers: vec![("Subject".to_string(), "Hello".to_string())],
+                }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_duplex_pipe_closed_mid_transaction() {
+        let (mut client, server) = tokio::io::duplex(64);
+        tokio::io::AsyncWriteExt::write_all(&mut client, b"HELO example.com\r\n")
+            .await
+            .unwrap();
+        drop(client);
+
+        let events = collect(AsyncMessageParser::new(tokio::io::BufReader::new(server))).await;
+
+        assert!(matches!(
+            events[..],
+            [Err(Spanned {
+                kind: MessageParserError::UnexpectedEnd,
+                ..
+            })]
+        ));
+    }
+}
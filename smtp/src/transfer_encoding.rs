@@ -0,0 +1,254 @@
+//! Decoding of MIME bodies: the Content-Transfer-Encodings of RFC 2045 section 6, and the
+//! charsets text is written in.
+//!
+//! Decoding never fails. Malformed escapes are kept as they are, characters outside the base64
+//! alphabet are skipped and bytes that do not decode in the charset become U+FFFD.
+
+use encoding_rs::Encoding;
+
+/// Decodes `body` according to the value of its Content-Transfer-Encoding header. `7bit`,
+/// `8bit`, `binary` and unknown encodings leave it unchanged.
+pub fn decode(encoding: &str, body: &[u8]) -> Vec<u8> {
+    let encoding = encoding.trim();
+    if encoding.eq_ignore_ascii_case("quoted-printable") {
+        decode_quoted_printable(body)
+    } else if encoding.eq_ignore_ascii_case("base64") {
+        decode_base64(body)
+    } else {
+        body.to_vec()
+    }
+}
+
+/// Decodes quoted-printable text: `=` followed by two hex digits of either case is that byte,
+/// and `=` at the end of a line is a soft line break, joining the line with the next one.
+/// Whitespace at the end of a line was likely added in transport, so it is dropped.
+pub fn decode_quoted_printable(input: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(input.len());
+    for line in input.split_inclusive(|&byte| byte == b'\n') {
+        let (content, ending) = match line.strip_suffix(b"\r\n") {
+            Some(content) => (content, &b"\r\n"[..]),
+            None => match line.strip_suffix(b"\n") {
+                Some(content) => (content, &b"\n"[..]),
+                None => (line, &b""[..]),
+            },
+        };
+        let content = content.trim_ascii_end();
+        let (content, soft_break) = match content.strip_suffix(b"=") {
+            Some(content) => (content, true),
+            None => (content, false),
+        };
+
+        let mut i = 0;
+        while i < content.len() {
+            let escaped = content
+                .get(i + 1..i + 3)
+                .filter(|_| content[i] == b'=')
+                .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+            match escaped {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 3;
+                }
+                None => {
+                    decoded.push(content[i]);
+                    i += 1;
+                }
+            }
+        }
+        if !soft_break {
+            decoded.extend_from_slice(ending);
+        }
+    }
+    decoded
+}
+
+/// Decodes base64, ignoring line breaks and any other character outside the alphabet, and
+/// stopping at the padding.
+pub fn decode_base64(input: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &c in input {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => continue,
+        };
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    decoded
+}
+
+/// Converts text in `charset`, such as `ISO-8859-1` or `Shift_JIS`, to UTF-8. Unknown charsets
+/// are read as UTF-8.
+///
+/// Charset names are looked up as the WHATWG Encoding Standard defines, so `ISO-8859-1` and
+/// `US-ASCII` are read as their superset, windows-1252.
+pub fn decode_charset(charset: &str, bytes: &[u8]) -> String {
+    let charset = charset.trim().trim_matches('"');
+    match Encoding::for_label(charset.as_bytes()) {
+        Some(encoding) => encoding.decode_without_bom_handling(bytes).0.into_owned(),
+        None => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes `input` as quoted-printable, escaping every byte that is not printable ASCII.
+    fn encode_quoted_printable(input: &[u8]) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        for line in input.split_inclusive(|&byte| byte == b'\n') {
+            let content = line.strip_suffix(b"\r\n").unwrap_or(line);
+            let mut length = 0;
+            for &byte in content {
+                let escaped = match byte {
+                    b'!'..=b'<' | b'>'..=b'~' => vec![byte],
+                    _ => format!("={byte:02X}").into_bytes(),
+                };
+                // Lines are at most 76 characters, the soft line break included.
+                if length + escaped.len() > 75 {
+                    encoded.extend_from_slice(b"=\r\n");
+                    length = 0;
+                }
+                length += escaped.len();
+                encoded.extend_from_slice(&escaped);
+            }
+            if content.len() < line.len() {
+                encoded.extend_from_slice(b"\r\n");
+            }
+        }
+        encoded
+    }
+
+    fn encode_base64(input: &[u8]) -> Vec<u8> {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut encoded = Vec::new();
+        for (i, chunk) in input.chunks(3).enumerate() {
+            // Lines of 76 characters, as RFC 2045 requires.
+            if i > 0 && i % 19 == 0 {
+                encoded.extend_from_slice(b"\r\n");
+            }
+            let bytes = [
+                chunk[0],
+                *chunk.get(1).unwrap_or(&0),
+                *chunk.get(2).unwrap_or(&0),
+            ];
+            let group = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+            for j in 0..4 {
+                if j <= chunk.len() {
+                    encoded.push(ALPHABET[(group >> (18 - 6 * j) & 63) as usize]);
+                } else {
+                    encoded.push(b'=');
+                }
+            }
+        }
+        encoded
+    }
+
+    #[test]
+    fn test_quoted_printable() {
+        let table: [(&[u8], &[u8]); 8] = [
+            (b"a=3Db", b"a=b"),
+            (b"caf=C3=A9", "café".as_bytes()),
+            (b"caf=c3=a9", "café".as_bytes()),
+            (b"soft=\r\nbreak\r\n", b"softbreak\r\n"),
+            (b"soft=\nbreak", b"softbreak"),
+            (b"trailing  \r\nspace\t\r\n", b"trailing\r\nspace\r\n"),
+            (b"soft = \r\nbreak", b"soft break"),
+            (b"under_score", b"under_score"),
+        ];
+
+        for (input, expected) in table {
+            assert_eq!(
+                decode_quoted_printable(input),
+                expected,
+                "{}",
+                String::from_utf8_lossy(input)
+            );
+        }
+    }
+
+    #[test]
+    fn test_malformed_quoted_printable_is_kept() {
+        for input in [&b"=ZZ"[..], b"100% =", b"=4", b"a == b", b"=\xff"] {
+            let expected = input.strip_suffix(b"=").unwrap_or(input);
+            assert_eq!(decode_quoted_printable(input), expected);
+        }
+    }
+
+    #[test]
+    fn test_base64() {
+        let table: [(&[u8], &[u8]); 6] = [
+            (b"SGVsbG8sIHdvcmxkIQ==", b"Hello, world!"),
+            (b"SGVsbG8sIHdvcmxkIQ", b"Hello, world!"),
+            (b"SGVs\r\nbG8s\r\nIHdv\r\ncmxkIQ==\r\n", b"Hello, world!"),
+            (b" SGVs bG8s\tIHdvcmxkIQ== ", b"Hello, world!"),
+            (b"SGV*sbG8!", b"Hello"),
+            (b"", b""),
+        ];
+
+        for (input, expected) in table {
+            assert_eq!(
+                decode_base64(input),
+                expected,
+                "{}",
+                String::from_utf8_lossy(input)
+            );
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut binary: Vec<u8> = (0..=255).collect();
+        binary.extend_from_slice("\r\nGrüße aus Köln =?\r\n".repeat(20).as_bytes());
+
+        for input in [&b""[..], b"a", b"ab", b"abc", &binary] {
+            assert_eq!(decode_base64(&encode_base64(input)), input);
+            assert_eq!(
+                decode("Quoted-Printable", &encode_quoted_printable(input)),
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(" BASE64 ", b"SGk="), b"Hi");
+        assert_eq!(decode("quoted-printable", b"H=69"), b"Hi");
+        for encoding in ["7bit", "8bit", "binary", "x-unknown"] {
+            assert_eq!(decode(encoding, b"H=69"), b"H=69");
+        }
+    }
+
+    #[test]
+    fn test_decode_charset() {
+        let table: [(&str, &[u8], &str); 7] = [
+            ("utf-8", "café".as_bytes(), "café"),
+            ("ISO-8859-1", b"Gr\xfc\xdfe aus K\xf6ln", "Grüße aus Köln"),
+            ("\"iso-8859-1\"", b"caf\xe9", "café"),
+            (
+                "Shift_JIS",
+                b"\x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd",
+                "こんにちは",
+            ),
+            ("us-ascii", b"plain", "plain"),
+            ("x-unknown", "café".as_bytes(), "café"),
+            ("utf-8", b"caf\xe9", "caf\u{fffd}"),
+        ];
+
+        for (charset, bytes, expected) in table {
+            assert_eq!(decode_charset(charset, bytes), expected, "{charset}");
+        }
+    }
+}
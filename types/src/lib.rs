@@ -2,14 +2,259 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A message's header fields in the order they appeared, duplicates included.
+///
+/// Field names are compared case-insensitively, as RFC 5322 requires. Serializes as an array
+/// of `[name, value]` pairs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(transparent)]
+pub struct Headers(Vec<(String, String)>);
+
+impl Headers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of the first field called `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The values of every field called `name`, in order.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.0
+            .iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Removes every field called `name`, returning the first one's value.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let mut removed = None;
+        self.0.retain_mut(|(key, value)| {
+            if !key.eq_ignore_ascii_case(name) {
+                return true;
+            }
+            if removed.is_none() {
+                removed = Some(std::mem::take(value));
+            }
+            false
+        });
+        removed
+    }
+
+    /// Appends a field, keeping any existing ones with the same name.
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.0.push((name.into(), value.into()));
+    }
+
+    /// Adds a field before all others, where trace fields such as `Received` go.
+    pub fn prepend(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.0.insert(0, (name.into(), value.into()));
+    }
+
+    pub fn into_vec(self) -> Vec<(String, String)> {
+        self.0
+    }
+}
+
+impl std::ops::Deref for Headers {
+    type Target = [(String, String)];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Vec<(String, String)>> for Headers {
+    fn from(headers: Vec<(String, String)>) -> Self {
+        Self(headers)
+    }
+}
+
+impl From<Headers> for Vec<(String, String)> {
+    fn from(headers: Headers) -> Self {
+        headers.0
+    }
+}
+
+impl FromIterator<(String, String)> for Headers {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl Extend<(String, String)> for Headers {
+    fn extend<I: IntoIterator<Item = (String, String)>>(&mut self, iter: I) {
+        self.0.extend(iter);
+    }
+}
+
+impl IntoIterator for Headers {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Headers {
+    type Item = &'a (String, String);
+    type IntoIter = std::slice::Iter<'a, (String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Email {
     pub id: Uuid,
     pub from: String,
     pub to: String,
+    /// Every address in the `To` header, or the envelope recipient when there is none.
+    pub to_addrs: Vec<String>,
+    /// Every address in the `Cc` header.
+    pub cc: Vec<String>,
+    /// The recipients the email was delivered to over SMTP, which are missing from the
+    /// headers for BCC deliveries. Empty for imported emails.
+    pub envelope_to: Vec<String>,
     pub subject: Option<String>,
-    pub headers: Vec<(String, String)>,
+    /// When the sender says the email was written, from its `Date` header. `None` when the
+    /// header is missing or malformed.
+    pub date: Option<DateTime<Utc>>,
+    pub message_id: Option<String>,
+    /// The `Message-ID` of the email this one replies to.
+    pub in_reply_to: Option<String>,
+    /// The first address in the `Reply-To` header.
+    pub reply_to: Option<String>,
+    pub headers: Headers,
+    pub tags: Vec<String>,
     pub body: String,
+    /// A short plain-text summary of the body, for showing emails in a list.
+    pub preview: String,
+    /// The size of the body in bytes, line breaks included.
+    pub body_bytes: u64,
+    /// The number of lines in the body.
+    pub body_lines: u64,
+    pub attachments: Vec<Attachment>,
+    pub dkim_result: Option<String>,
+    /// The `(method, result)` pair of each authentication check run on the email when it was
+    /// received, such as `("spf", "pass")`. Empty when none ran.
+    pub auth_results: Vec<(String, String)>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
+
+/// A file attached to an email, downloadable by its position in [`Email::attachments`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Attachment {
+    /// The name given by the sender, if any.
+    pub filename: Option<String>,
+    pub content_type: String,
+    /// The decoded size in bytes.
+    pub size: u64,
+    /// The `Content-ID`, without angle brackets, that an HTML body can reference as `cid:`.
+    pub content_id: Option<String>,
+}
+
+/// One page of emails, newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct EmailPage {
+    pub emails: Vec<Email>,
+    /// Pass as `cursor` to fetch the following page; `None` on the last page.
+    pub next_cursor: Option<String>,
+    /// The number of emails matching the filters across all pages.
+    pub total: u64,
+}
+
+/// A recipient address and how many stored emails were sent to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RecipientCount {
+    pub address: String,
+    pub count: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers() -> Headers {
+        Headers::from(vec![
+            ("Received".to_string(), "from a.example.com".to_string()),
+            ("Subject".to_string(), "Hello".to_string()),
+            ("received".to_string(), "from b.example.com".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_lookup() {
+        let headers = headers();
+
+        assert_eq!(headers.get("SUBJECT"), Some("Hello"));
+        assert_eq!(headers.get("RECEIVED"), Some("from a.example.com"));
+        assert_eq!(
+            headers.get_all("Received").collect::<Vec<_>>(),
+            vec!["from a.example.com", "from b.example.com"]
+        );
+        assert!(headers.contains("subject"));
+        assert!(!headers.contains("Cc"));
+        assert_eq!(headers.get("Cc"), None);
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut headers = headers();
+
+        headers.insert("X-Spam", "yes");
+        assert_eq!(headers.get("x-spam"), Some("yes"));
+        assert_eq!(headers.len(), 4);
+
+        headers.prepend("Authentication-Results", "remail; none");
+        assert_eq!(headers[0].0, "Authentication-Results");
+        assert_eq!(headers.len(), 5);
+
+        assert_eq!(
+            headers.remove("RECEIVED").as_deref(),
+            Some("from a.example.com")
+        );
+        assert!(!headers.contains("Received"));
+        assert_eq!(headers.remove("Received"), None);
+        assert_eq!(
+            headers.into_vec(),
+            vec![
+                (
+                    "Authentication-Results".to_string(),
+                    "remail; none".to_string()
+                ),
+                ("Subject".to_string(), "Hello".to_string()),
+                ("X-Spam".to_string(), "yes".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_serde() {
+        let headers = headers();
+        let json = serde_json::to_string(&headers).unwrap();
+
+        assert_eq!(
+            json,
+            r#"[["Received","from a.example.com"],["Subject","Hello"],["received","from b.example.com"]]"#
+        );
+        assert_eq!(serde_json::from_str::<Headers>(&json).unwrap(), headers);
+    }
+}
@@ -1,30 +1,280 @@
-use remail_types::Email;
+use crate::backoff::Backoff;
+use crate::config;
+use crate::debounce::sleep;
+use crate::filter::EmailFilter;
+use crate::live::EmailSubscription;
+use remail_types::{Email, EmailPage, RecipientCount};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::fmt;
+use std::time::Duration;
+use uuid::Uuid;
 
-const API_BASE_URL: &str = "http://localhost:3000";
+#[derive(Debug)]
+pub enum ApiClientError {
+    InvalidBaseUrl(String),
+    InvalidHeader(String),
+    Client(reqwest::Error),
+    /// Every attempt failed with a network error or a 5xx response.
+    RetryExhausted {
+        attempts: u32,
+        last_error: String,
+    },
+}
+
+/// The longest wait between two attempts of the same request.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+impl fmt::Display for ApiClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiClientError::InvalidBaseUrl(reason) => write!(f, "invalid base URL: {reason}"),
+            ApiClientError::InvalidHeader(reason) => write!(f, "invalid header: {reason}"),
+            ApiClientError::Client(e) => write!(f, "failed to build HTTP client: {e}"),
+            ApiClientError::RetryExhausted {
+                attempts,
+                last_error,
+            } => write!(f, "request failed after {attempts} attempts: {last_error}"),
+        }
+    }
+}
+
+impl std::error::Error for ApiClientError {}
+
+#[derive(Debug, Default)]
+pub struct ApiClientBuilder {
+    base_url: Option<String>,
+    timeout: Option<Duration>,
+    headers: Vec<(String, String)>,
+    bearer_token: Option<String>,
+    retry: Option<RetryPolicy>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RetryPolicy {
+    max_attempts: u32,
+    initial_delay: Duration,
+}
+
+impl ApiClientBuilder {
+    pub fn base_url(mut self, url: &str) -> Self {
+        self.base_url = Some(url.to_string());
+        self
+    }
+
+    // The app itself only sets a base URL, a token and retries; the rest of the builder is
+    // there for other embedders of the client.
+    #[allow(dead_code)]
+    pub fn timeout(mut self, duration: Duration) -> Self {
+        self.timeout = Some(duration);
+        self
+    }
+
+    /// Adds a header sent with every request.
+    #[allow(dead_code)]
+    pub fn default_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn bearer_token(mut self, token: &str) -> Self {
+        self.bearer_token = Some(token.to_string());
+        self
+    }
+
+    /// Retries idempotent requests, such as GETs, that fail with a network error or a 5xx
+    /// response, making at most `max_attempts` attempts and roughly doubling the wait after
+    /// each one, starting at `initial_delay`. 4xx responses are never retried, and neither
+    /// are POSTs, which could be applied twice.
+    pub fn retry(mut self, max_attempts: u32, initial_delay: Duration) -> Self {
+        self.retry = Some(RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            initial_delay,
+        });
+        self
+    }
+
+    pub fn build(self) -> Result<ApiClient, ApiClientError> {
+        let base_url = self
+            .base_url
+            .ok_or_else(|| ApiClientError::InvalidBaseUrl("no base URL set".to_string()))?;
+        let url = reqwest::Url::parse(&base_url)
+            .map_err(|e| ApiClientError::InvalidBaseUrl(format!("{base_url}: {e}")))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(ApiClientError::InvalidBaseUrl(format!(
+                "{base_url}: scheme must be http or https"
+            )));
+        }
+        if url.query().is_some() || url.fragment().is_some() {
+            return Err(ApiClientError::InvalidBaseUrl(format!(
+                "{base_url}: must not have a query or fragment"
+            )));
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::try_from(name.as_str())
+                .map_err(|e| ApiClientError::InvalidHeader(format!("{name}: {e}")))?;
+            let value = HeaderValue::try_from(value.as_str())
+                .map_err(|e| ApiClientError::InvalidHeader(format!("{name}: {e}")))?;
+            headers.append(name, value);
+        }
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .map_err(ApiClientError::Client)?;
+
+        Ok(ApiClient {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            timeout: self.timeout,
+            token: self.bearer_token,
+            retry: self.retry,
+        })
+    }
+}
 
 pub struct ApiClient {
     client: reqwest::Client,
+    base_url: String,
+    timeout: Option<Duration>,
+    token: Option<String>,
+    retry: Option<RetryPolicy>,
+}
+
+/// Whether sending `request` twice has the same effect as sending it once.
+fn is_idempotent(request: &reqwest::RequestBuilder) -> bool {
+    request
+        .try_clone()
+        .and_then(|request| request.build().ok())
+        .is_some_and(|request| request.method().is_idempotent())
 }
 
 impl Default for ApiClient {
     fn default() -> Self {
-        Self {
-            client: reqwest::Client::new(),
-        }
+        Self::new()
     }
 }
 
 impl ApiClient {
+    pub fn builder() -> ApiClientBuilder {
+        ApiClientBuilder::default()
+    }
+
+    /// A client for the configured API, see [`config::api_base_url`], using the token saved
+    /// in the settings panel.
     pub fn new() -> Self {
-        Self::default()
+        Self::with_base_url(config::api_base_url()).expect("configured API base URL must be valid")
     }
 
-    pub async fn list_emails(&self) -> Result<Vec<Email>, Box<dyn std::error::Error>> {
-        let response = self
+    /// Like [`ApiClient::new`], for the API at `url`, such as a remote capture server.
+    pub fn with_base_url(url: impl Into<String>) -> Result<Self, ApiClientError> {
+        let mut builder = Self::builder()
+            .base_url(&url.into())
+            .retry(3, Duration::from_millis(500));
+        if let Some(token) = config::load_token() {
+            builder = builder.bearer_token(&token);
+        }
+        builder.build()
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let mut request = self
             .client
-            .get(format!("{API_BASE_URL}/v1/emails"))
-            .send()
-            .await?;
+            .request(method, format!("{}{path}", self.base_url));
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Sends `request`, retrying it according to the configured retry policy when it is
+    /// idempotent.
+    ///
+    /// Responses other than 5xx, including 4xx errors, are returned as-is for the caller to
+    /// handle.
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+        let Some(policy) = self.retry.filter(|_| is_idempotent(&request)) else {
+            return Ok(request.send().await?);
+        };
+
+        let mut backoff = Backoff::new(policy.initial_delay, MAX_RETRY_DELAY);
+        let mut attempts = 0;
+        loop {
+            // Bodies are always in memory, so only a streaming body would fail to clone.
+            let Some(attempt) = request.try_clone() else {
+                return Ok(request.send().await?);
+            };
+            attempts += 1;
+
+            let last_error = match attempt.send().await {
+                Ok(response) if !response.status().is_server_error() => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    let text = response.text().await.unwrap_or_default();
+                    format!("{status}: {text}")
+                }
+                Err(e) => e.to_string(),
+            };
+
+            if attempts >= policy.max_attempts {
+                return Err(ApiClientError::RetryExhausted {
+                    attempts,
+                    last_error,
+                }
+                .into());
+            }
+            sleep(backoff.next_delay_with_jitter()).await;
+        }
+    }
+
+    fn list_emails_request(
+        &self,
+        filter: &EmailFilter,
+        cursor: Option<&str>,
+    ) -> reqwest::RequestBuilder {
+        let mut request = self
+            .request(reqwest::Method::GET, "/v1/emails/page")
+            .query(&filter.query_pairs());
+        if let Some(cursor) = cursor {
+            request = request.query(&[("cursor", cursor)]);
+        }
+        request
+    }
+
+    /// Fetches the page of emails matching `filter` that follows `cursor`, or the first page.
+    pub async fn list_emails(
+        &self,
+        filter: &EmailFilter,
+        cursor: Option<&str>,
+    ) -> Result<EmailPage, Box<dyn std::error::Error>> {
+        let response = self.send(self.list_emails_request(filter, cursor)).await?;
+
+        if response.status().is_success() {
+            let page: EmailPage = response.json().await?;
+            Ok(page)
+        } else {
+            let error_text = response.text().await?;
+            Err(format!("API error: {error_text}").into())
+        }
+    }
+
+    fn search_emails_request(&self, query: &str) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::GET, "/v1/emails")
+            .query(&[("q", query)])
+    }
+
+    /// Every email whose subject, sender, recipient or body contains `query`, newest first.
+    pub async fn search_emails(
+        &self,
+        query: &str,
+    ) -> Result<Vec<Email>, Box<dyn std::error::Error>> {
+        let response = self.send(self.search_emails_request(query)).await?;
 
         if response.status().is_success() {
             let emails: Vec<Email> = response.json().await?;
@@ -34,4 +284,603 @@ impl ApiClient {
             Err(format!("API error: {error_text}").into())
         }
     }
+
+    fn list_recipients_request(&self) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::GET, "/v1/recipients")
+    }
+
+    /// Lists every recipient address with its number of emails.
+    pub async fn list_recipients(&self) -> Result<Vec<RecipientCount>, Box<dyn std::error::Error>> {
+        let response = self.send(self.list_recipients_request()).await?;
+
+        if response.status().is_success() {
+            let recipients: Vec<RecipientCount> = response.json().await?;
+            Ok(recipients)
+        } else {
+            let error_text = response.text().await?;
+            Err(format!("API error: {error_text}").into())
+        }
+    }
+
+    fn get_email_request(&self, id: Uuid) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::GET, &format!("/v1/emails/{id}"))
+    }
+
+    pub async fn get_email(&self, id: Uuid) -> Result<Email, Box<dyn std::error::Error>> {
+        let response = self.send(self.get_email_request(id)).await?;
+
+        if response.status().is_success() {
+            let email: Email = response.json().await?;
+            Ok(email)
+        } else {
+            let error_text = response.text().await?;
+            Err(format!("API error: {error_text}").into())
+        }
+    }
+
+    fn get_email_raw_request(&self, id: Uuid) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::GET, &format!("/v1/emails/{id}/raw"))
+    }
+
+    /// The email's RFC 5322 source, as reconstructed by the server.
+    pub async fn get_email_raw(&self, id: Uuid) -> Result<String, Box<dyn std::error::Error>> {
+        let response = self.send(self.get_email_raw_request(id)).await?;
+        let status = response.status();
+
+        if status.is_success() {
+            Ok(response.text().await?)
+        } else if status == reqwest::StatusCode::NOT_FOUND {
+            Err(format!("Email {id} not found").into())
+        } else {
+            let error_text = response.text().await?;
+            Err(format!("API error ({status}): {error_text}").into())
+        }
+    }
+
+    /// Where the attachment at `index` of email `id` can be downloaded, for use in links.
+    pub fn attachment_url(&self, id: Uuid, index: usize) -> String {
+        format!("{}/v1/emails/{id}/attachments/{index}", self.base_url)
+    }
+
+    fn delete_email_request(&self, id: Uuid) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::DELETE, &format!("/v1/emails/{id}"))
+    }
+
+    pub async fn delete_email(&self, id: Uuid) -> Result<(), Box<dyn std::error::Error>> {
+        let response = self.send(self.delete_email_request(id)).await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let error_text = response.text().await?;
+            Err(format!("API error: {error_text}").into())
+        }
+    }
+
+    fn delete_emails_request(&self, ids: &[Uuid]) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::POST, "/v1/emails/bulk-delete")
+            .json(&serde_json::json!({ "ids": ids }))
+    }
+
+    /// Deletes all of `ids` in one request, returning how many emails were deleted.
+    pub async fn delete_emails(&self, ids: &[Uuid]) -> Result<u64, Box<dyn std::error::Error>> {
+        #[derive(serde::Deserialize)]
+        struct DeleteResponse {
+            deleted: u64,
+        }
+
+        let response = self.send(self.delete_emails_request(ids)).await?;
+
+        if response.status().is_success() {
+            let response: DeleteResponse = response.json().await?;
+            Ok(response.deleted)
+        } else {
+            let error_text = response.text().await?;
+            Err(format!("API error: {error_text}").into())
+        }
+    }
+
+    fn import_eml_request(&self, bytes: &[u8]) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::POST, "/v1/emails/import")
+            .header(reqwest::header::CONTENT_TYPE, "message/rfc822")
+            .body(bytes.to_vec())
+    }
+
+    /// Imports a raw RFC 5322 message, returning the stored email.
+    pub async fn import_eml(&self, bytes: &[u8]) -> Result<Email, Box<dyn std::error::Error>> {
+        let response = self.send(self.import_eml_request(bytes)).await?;
+
+        if response.status().is_success() {
+            let email: Email = response.json().await?;
+            Ok(email)
+        } else {
+            let error_text = response.text().await?;
+            Err(format!("API error: {error_text}").into())
+        }
+    }
+
+    fn send_email_request(
+        &self,
+        from: &str,
+        to: &[String],
+        subject: &str,
+        body: &str,
+    ) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::POST, "/v1/emails/send")
+            .json(&serde_json::json!({
+                "from": from,
+                "to": to,
+                "subject": subject,
+                "body": body,
+            }))
+    }
+
+    /// Sends an email through the server's relay, in one transaction addressed to all of `to`.
+    pub async fn send_email(
+        &self,
+        from: &str,
+        to: &[String],
+        subject: &str,
+        body: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let response = self
+            .send(self.send_email_request(from, to, subject, body))
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let error_text = response.text().await?;
+            Err(format!("API error: {error_text}").into())
+        }
+    }
+
+    /// Opens the live stream of newly received emails.
+    ///
+    /// `EventSource` cannot send headers, so the stream is opened without the bearer token.
+    pub fn subscribe(&self) -> Result<EmailSubscription, Box<dyn std::error::Error>> {
+        EmailSubscription::open(&format!("{}/v1/emails/stream", self.base_url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const API_BASE_URL: &str = "http://localhost:3000";
+
+    fn client() -> ApiClient {
+        ApiClient::builder().base_url(API_BASE_URL).build().unwrap()
+    }
+
+    #[test]
+    fn test_list_emails_request() {
+        let filter = EmailFilter {
+            subject_contains: Some("hello".to_string()),
+            ..Default::default()
+        };
+        let request = client()
+            .list_emails_request(&filter, Some("123_abc"))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.url().as_str(),
+            format!("{API_BASE_URL}/v1/emails/page?subject_contains=hello&cursor=123_abc")
+        );
+    }
+
+    #[test]
+    fn test_search_emails_request() {
+        let request = client()
+            .search_emails_request("reset & welcome")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.url().as_str(),
+            format!("{API_BASE_URL}/v1/emails?q=reset+%26+welcome")
+        );
+    }
+
+    #[test]
+    fn test_list_recipients_request() {
+        let request = client().list_recipients_request().build().unwrap();
+
+        assert_eq!(request.method(), reqwest::Method::GET);
+        assert_eq!(
+            request.url().as_str(),
+            format!("{API_BASE_URL}/v1/recipients")
+        );
+    }
+
+    #[test]
+    fn test_get_email_request() {
+        let id = Uuid::new_v4();
+        let request = client().get_email_request(id).build().unwrap();
+
+        assert_eq!(request.method(), reqwest::Method::GET);
+        assert_eq!(
+            request.url().as_str(),
+            format!("{API_BASE_URL}/v1/emails/{id}")
+        );
+    }
+
+    #[test]
+    fn test_attachment_url() {
+        let id = Uuid::new_v4();
+
+        assert_eq!(
+            client().attachment_url(id, 2),
+            format!("{API_BASE_URL}/v1/emails/{id}/attachments/2")
+        );
+    }
+
+    #[test]
+    fn test_delete_email_request() {
+        let id = Uuid::new_v4();
+        let request = client().delete_email_request(id).build().unwrap();
+
+        assert_eq!(request.method(), reqwest::Method::DELETE);
+        assert_eq!(
+            request.url().as_str(),
+            format!("{API_BASE_URL}/v1/emails/{id}")
+        );
+    }
+
+    #[test]
+    fn test_delete_emails_request() {
+        let ids = [Uuid::new_v4(), Uuid::new_v4()];
+        let request = client().delete_emails_request(&ids).build().unwrap();
+
+        assert_eq!(request.method(), reqwest::Method::POST);
+        assert_eq!(
+            request.url().as_str(),
+            format!("{API_BASE_URL}/v1/emails/bulk-delete")
+        );
+        let body: serde_json::Value =
+            serde_json::from_slice(request.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(body, serde_json::json!({ "ids": ids }));
+    }
+
+    #[test]
+    fn test_import_eml_request() {
+        let eml = b"Subject: Hello\r\n\r\nHello, world!\r\n";
+        let request = client().import_eml_request(eml).build().unwrap();
+
+        assert_eq!(request.method(), reqwest::Method::POST);
+        assert_eq!(
+            request.url().as_str(),
+            format!("{API_BASE_URL}/v1/emails/import")
+        );
+        assert_eq!(
+            request.headers()[reqwest::header::CONTENT_TYPE],
+            "message/rfc822"
+        );
+        assert_eq!(request.body().unwrap().as_bytes(), Some(&eml[..]));
+    }
+
+    #[test]
+    fn test_send_email_request() {
+        let to = ["a@example.com".to_string(), "b@example.com".to_string()];
+        let request = client()
+            .send_email_request("me@example.com", &to, "Hello", "Hello, world!")
+            .build()
+            .unwrap();
+
+        assert_eq!(request.method(), reqwest::Method::POST);
+        assert_eq!(
+            request.url().as_str(),
+            format!("{API_BASE_URL}/v1/emails/send")
+        );
+        let body: serde_json::Value =
+            serde_json::from_slice(request.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "from": "me@example.com",
+                "to": to,
+                "subject": "Hello",
+                "body": "Hello, world!",
+            })
+        );
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        let authenticated = ApiClient::builder()
+            .base_url(API_BASE_URL)
+            .bearer_token("secret")
+            .build()
+            .unwrap();
+        let request = authenticated
+            .delete_email_request(Uuid::new_v4())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.headers()[reqwest::header::AUTHORIZATION],
+            "Bearer secret"
+        );
+        assert!(
+            !client()
+                .delete_email_request(Uuid::new_v4())
+                .build()
+                .unwrap()
+                .headers()
+                .contains_key(reqwest::header::AUTHORIZATION)
+        );
+    }
+
+    #[test]
+    fn test_with_base_url() {
+        let client = ApiClient::with_base_url("https://staging.example.com/remail/").unwrap();
+        let request = client
+            .list_emails_request(&EmailFilter::default(), None)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.url().as_str(),
+            "https://staging.example.com/remail/v1/emails/page"
+        );
+        assert!(matches!(
+            ApiClient::with_base_url("staging.example.com"),
+            Err(ApiClientError::InvalidBaseUrl(_))
+        ));
+    }
+
+    #[test]
+    fn test_builder_requires_valid_base_url() {
+        for url in [
+            None,
+            Some("not a url"),
+            Some("/api"),
+            Some("ftp://example.com"),
+            Some("http://example.com/?key=value"),
+        ] {
+            let mut builder = ApiClient::builder();
+            if let Some(url) = url {
+                builder = builder.base_url(url);
+            }
+            assert!(
+                matches!(builder.build(), Err(ApiClientError::InvalidBaseUrl(_))),
+                "{url:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_header() {
+        let result = ApiClient::builder()
+            .base_url(API_BASE_URL)
+            .default_header("bad header", "value")
+            .build();
+        assert!(matches!(result, Err(ApiClientError::InvalidHeader(_))));
+
+        let result = ApiClient::builder()
+            .base_url(API_BASE_URL)
+            .default_header("X-Ok", "bad\nvalue")
+            .build();
+        assert!(matches!(result, Err(ApiClientError::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn test_builder_settings() {
+        let client = ApiClient::builder()
+            .base_url("https://mail.example.com/api/")
+            .timeout(Duration::from_secs(5))
+            .default_header("X-Tenant", "acme")
+            .build()
+            .unwrap();
+        let request = client.import_eml_request(b"").build().unwrap();
+
+        assert_eq!(
+            request.url().as_str(),
+            "https://mail.example.com/api/v1/emails/import"
+        );
+        assert_eq!(request.timeout(), Some(&Duration::from_secs(5)));
+    }
+
+    const EMPTY_PAGE: &str = r#"{"emails":[],"next_cursor":null,"total":0}"#;
+
+    /// Serves an empty page with each of `statuses`, see [`serve_responses`].
+    async fn serve(statuses: Vec<u16>) -> (String, std::sync::Arc<std::sync::Mutex<u32>>) {
+        serve_responses(
+            statuses
+                .into_iter()
+                .map(|status| (status, EMPTY_PAGE))
+                .collect(),
+        )
+        .await
+    }
+
+    /// Serves one canned response per connection, in order, returning the server's URL and a
+    /// count of the requests it received.
+    async fn serve_responses(
+        responses: Vec<(u16, &'static str)>,
+    ) -> (String, std::sync::Arc<std::sync::Mutex<u32>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let hits = std::sync::Arc::new(std::sync::Mutex::new(0));
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    let read = stream.read(&mut buf).await.unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..read]);
+                }
+                *counter.lock().unwrap() += 1;
+
+                let response = format!(
+                    "HTTP/1.1 {status} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, hits)
+    }
+
+    fn retrying_client(url: &str, max_attempts: u32) -> ApiClient {
+        ApiClient::builder()
+            .base_url(url)
+            .retry(max_attempts, Duration::from_millis(10))
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors() {
+        let (url, hits) = serve(vec![503, 500, 200]).await;
+
+        let page = retrying_client(&url, 3)
+            .list_emails(&EmailFilter::default(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(page.total, 0);
+        assert_eq!(*hits.lock().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_success() {
+        let recipients = r#"[{"address":"a@example.com","count":2}]"#;
+        let (url, hits) =
+            serve_responses(vec![(502, "Bad Gateway"), (503, ""), (200, recipients)]).await;
+
+        let recipients = retrying_client(&url, 3).list_recipients().await.unwrap();
+
+        assert_eq!(
+            recipients,
+            [RecipientCount {
+                address: "a@example.com".to_string(),
+                count: 2,
+            }]
+        );
+        assert_eq!(*hits.lock().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_posts_are_not_retried() {
+        let (url, hits) = serve_responses(vec![(503, "busy"), (200, "")]).await;
+
+        let error = retrying_client(&url, 3)
+            .send_email("a@example.com", &["b@example.com".to_string()], "Hi", "")
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "API error: busy");
+        assert_eq!(*hits.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_exhausted() {
+        let (url, hits) = serve(vec![500, 500, 500]).await;
+
+        let error = retrying_client(&url, 3)
+            .list_emails(&EmailFilter::default(), None)
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(
+                error.downcast_ref::<ApiClientError>(),
+                Some(ApiClientError::RetryExhausted { attempts: 3, .. })
+            ),
+            "{error}"
+        );
+        assert_eq!(*hits.lock().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_retried() {
+        let (url, hits) = serve(vec![404, 200]).await;
+
+        let error = retrying_client(&url, 3)
+            .delete_email(Uuid::new_v4())
+            .await
+            .unwrap_err();
+
+        assert!(error.downcast_ref::<ApiClientError>().is_none(), "{error}");
+        assert_eq!(*hits.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_get_email_raw_request() {
+        let id = Uuid::new_v4();
+        let request = client().get_email_raw_request(id).build().unwrap();
+        assert_eq!(request.method(), reqwest::Method::GET);
+        assert_eq!(
+            request.url().as_str(),
+            format!("{API_BASE_URL}/v1/emails/{id}/raw")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_email_raw() {
+        let raw = "Subject: Hi\r\n\r\nHello\r\n";
+        let (url, hits) = serve_responses(vec![(503, ""), (200, raw)]).await;
+
+        let source = retrying_client(&url, 3)
+            .get_email_raw(Uuid::new_v4())
+            .await
+            .unwrap();
+
+        assert_eq!(source, raw);
+        assert_eq!(*hits.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_email_raw_not_found() {
+        let id = Uuid::new_v4();
+        let (url, _) = serve_responses(vec![(404, "Not Found")]).await;
+
+        let error = retrying_client(&url, 3)
+            .get_email_raw(id)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), format!("Email {id} not found"));
+    }
+
+    #[tokio::test]
+    async fn test_get_email_raw_client_error() {
+        let (url, _) = serve_responses(vec![(400, "bad id")]).await;
+
+        let error = retrying_client(&url, 3)
+            .get_email_raw(Uuid::new_v4())
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "API error (400 Bad Request): bad id");
+    }
+
+    #[tokio::test]
+    async fn test_retries_network_errors() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let error = retrying_client(&url, 2)
+            .list_emails(&EmailFilter::default(), None)
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(
+                error.downcast_ref::<ApiClientError>(),
+                Some(ApiClientError::RetryExhausted { attempts: 2, .. })
+            ),
+            "{error}"
+        );
+    }
 }
@@ -0,0 +1,111 @@
+use remail_types::Attachment;
+
+/// The name to show and download an attachment as, numbering unnamed ones from 1.
+pub fn display_name(attachment: &Attachment, index: usize) -> String {
+    attachment
+        .filename
+        .clone()
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| format!("attachment-{}.bin", index + 1))
+}
+
+/// A size in bytes as a short human-readable string, e.g. "1.5 KB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+pub fn icon(content_type: &str) -> &'static str {
+    match content_type.split('/').next().unwrap_or_default() {
+        "image" => "🖼",
+        "audio" => "🎵",
+        "video" => "🎞",
+        "text" => "📄",
+        _ if content_type.ends_with("/pdf") => "📕",
+        _ if content_type.contains("zip") => "🗜",
+        _ => "📎",
+    }
+}
+
+/// Points the `cid:` references in an HTML body at the matching attachments' download URLs.
+pub fn rewrite_cid_urls(
+    html: &str,
+    attachments: &[Attachment],
+    url_for: impl Fn(usize) -> String,
+) -> String {
+    let mut html = html.to_string();
+    for (index, attachment) in attachments.iter().enumerate() {
+        if let Some(content_id) = &attachment.content_id {
+            html = html.replace(&format!("cid:{content_id}"), &url_for(index));
+        }
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attachment(filename: Option<&str>, content_id: Option<&str>) -> Attachment {
+        Attachment {
+            filename: filename.map(str::to_string),
+            content_type: "image/png".to_string(),
+            size: 10,
+            content_id: content_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KB");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn test_display_name_falls_back_to_numbered_name() {
+        assert_eq!(
+            display_name(&attachment(Some("logo.png"), None), 0),
+            "logo.png"
+        );
+        assert_eq!(display_name(&attachment(None, None), 0), "attachment-1.bin");
+        assert_eq!(
+            display_name(&attachment(Some(" "), None), 2),
+            "attachment-3.bin"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_cid_urls() {
+        let attachments = [
+            attachment(Some("report.pdf"), None),
+            attachment(None, Some("logo@example.com")),
+        ];
+        let html = r#"<img src="cid:logo@example.com"><img src="cid:missing@example.com">"#;
+
+        assert_eq!(
+            rewrite_cid_urls(html, &attachments, |index| format!("/attachments/{index}")),
+            r#"<img src="/attachments/1"><img src="cid:missing@example.com">"#
+        );
+    }
+
+    #[test]
+    fn test_icon() {
+        assert_eq!(icon("image/png"), "🖼");
+        assert_eq!(icon("application/pdf"), "📕");
+        assert_eq!(icon("application/octet-stream"), "📎");
+    }
+}
@@ -0,0 +1,74 @@
+//! Badges for the authentication checks maild ran on an email, such as DKIM and SPF, from
+//! `Email::auth_results`.
+
+/// How a check's result reads at a glance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail,
+    /// Nothing to check, such as an unsigned message, or no answer, such as a DNS timeout.
+    Neutral,
+}
+
+impl Verdict {
+    /// Reads a result keyword of RFC 8601, such as `pass` or `softfail`.
+    pub fn of(result: &str) -> Self {
+        match result.to_ascii_lowercase().as_str() {
+            "pass" => Verdict::Pass,
+            "fail" | "softfail" | "permerror" => Verdict::Fail,
+            _ => Verdict::Neutral,
+        }
+    }
+
+    pub fn icon(self) -> &'static str {
+        match self {
+            Verdict::Pass => "✓",
+            Verdict::Fail => "✗",
+            Verdict::Neutral => "–",
+        }
+    }
+
+    pub fn badge_class(self) -> &'static str {
+        match self {
+            Verdict::Pass => "bg-green-100 text-green-800 dark:bg-green-900 dark:text-green-200",
+            Verdict::Fail => "bg-red-100 text-red-800 dark:bg-red-900 dark:text-red-200",
+            Verdict::Neutral => "bg-gray-100 text-gray-700 dark:bg-gray-700 dark:text-gray-300",
+        }
+    }
+}
+
+/// The text of a badge, such as `DKIM ✓`, with the full result left to its tooltip.
+pub fn label(method: &str, result: &str) -> String {
+    format!("{} {}", method.to_uppercase(), Verdict::of(result).icon())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict() {
+        let table = [
+            ("pass", Verdict::Pass),
+            ("PASS", Verdict::Pass),
+            ("fail", Verdict::Fail),
+            ("softfail", Verdict::Fail),
+            ("permerror", Verdict::Fail),
+            ("temperror", Verdict::Neutral),
+            ("none", Verdict::Neutral),
+            ("neutral", Verdict::Neutral),
+            ("unknown", Verdict::Neutral),
+        ];
+
+        for (result, expected) in table {
+            assert_eq!(Verdict::of(result), expected, "{result}");
+        }
+    }
+
+    #[test]
+    fn test_label() {
+        assert_eq!(label("dkim", "pass"), "DKIM ✓");
+        assert_eq!(label("spf", "softfail"), "SPF ✗");
+        assert_eq!(label("dkim", "none"), "DKIM –");
+    }
+}
@@ -0,0 +1,92 @@
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Exponential backoff: each delay doubles the previous one, up to `max`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            attempt: 0,
+        }
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self
+            .initial
+            .saturating_mul(2u32.saturating_pow(self.attempt))
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+        delay
+    }
+
+    /// Like [`Backoff::next_delay`], picking a random delay in the upper half of it, so clients
+    /// that failed together do not all retry at the same moment.
+    pub fn next_delay_with_jitter(&mut self) -> Duration {
+        jitter(self.next_delay(), random_fraction())
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// Scales `delay` into its upper half by `fraction`, which is in `[0, 1)`.
+fn jitter(delay: Duration, fraction: f64) -> Duration {
+    delay.mul_f64(0.5 + fraction / 2.0)
+}
+
+/// A random number in `[0, 1)`, taken from the first 48 bits of a v4 UUID, which are random.
+fn random_fraction() -> f64 {
+    (Uuid::new_v4().as_u128() >> 80) as f64 / (1u64 << 48) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doubles_up_to_max() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(30));
+        let delays: Vec<u64> = (0..7).map(|_| backoff.next_delay().as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 30, 30]);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(30));
+        backoff.next_delay();
+        backoff.next_delay();
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_jitter() {
+        let delay = Duration::from_secs(8);
+        assert_eq!(jitter(delay, 0.0), Duration::from_secs(4));
+        assert_eq!(jitter(delay, 0.5), Duration::from_secs(6));
+
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(30));
+        for expected in [1, 2, 4, 8] {
+            let delay = backoff.next_delay_with_jitter();
+            let expected = Duration::from_secs(expected);
+            assert!(delay >= expected / 2 && delay < expected, "{delay:?}");
+        }
+    }
+
+    #[test]
+    fn test_does_not_overflow() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(30));
+        for _ in 0..100 {
+            assert!(backoff.next_delay() <= Duration::from_secs(30));
+        }
+    }
+}
@@ -0,0 +1,121 @@
+/// The HTML version of an email's body, if it has one.
+///
+/// A `text/html` body is returned as-is; for a multipart body the first `text/html` part is
+/// used. Transfer encodings are not decoded.
+pub fn html_body(headers: &[(String, String)], body: &str) -> Option<String> {
+    let content_type = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("Content-Type"))
+        .map(|(_, value)| value.as_str())?;
+    html_in(content_type, body)
+}
+
+/// Stops `html` from loading images other than inline `data:` ones, wherever they are
+/// referenced, so opening an email does not tell its sender.
+pub fn block_remote_images(html: &str) -> String {
+    // A `<meta>` policy only covers what comes after it, so it goes before everything else.
+    format!(r#"<meta http-equiv="Content-Security-Policy" content="img-src data:">{html}"#)
+}
+
+fn html_in(content_type: &str, body: &str) -> Option<String> {
+    let media_type = content_type.split(';').next()?.trim().to_lowercase();
+    if media_type == "text/html" {
+        return Some(body.to_string());
+    }
+    if !media_type.starts_with("multipart/") {
+        return None;
+    }
+
+    let delimiter = format!("--{}", boundary(content_type)?);
+    body.split(delimiter.as_str())
+        // The preamble comes before the first delimiter and the epilogue after the last one.
+        .skip(1)
+        .take_while(|part| !part.starts_with("--"))
+        .find_map(|part| {
+            let part = part.trim_start_matches(['\r', '\n']);
+            let (part_headers, part_body) = part
+                .split_once("\r\n\r\n")
+                .or_else(|| part.split_once("\n\n"))?;
+            let part_type = part_headers.lines().find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.trim()
+                    .eq_ignore_ascii_case("Content-Type")
+                    .then(|| value.trim())
+            })?;
+            html_in(part_type, part_body.trim_end_matches(['\r', '\n']))
+        })
+}
+
+fn boundary(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content_type(value: &str) -> Vec<(String, String)> {
+        vec![("Content-Type".to_string(), value.to_string())]
+    }
+
+    #[test]
+    fn test_block_remote_images() {
+        let html = r#"<img src="https://tracker.example.com/pixel.gif">"#;
+
+        assert_eq!(
+            block_remote_images(html),
+            r#"<meta http-equiv="Content-Security-Policy" content="img-src data:"><img src="https://tracker.example.com/pixel.gif">"#
+        );
+    }
+
+    #[test]
+    fn test_html_body() {
+        assert_eq!(
+            html_body(&content_type("text/html; charset=utf-8"), "<p>Hi</p>"),
+            Some("<p>Hi</p>".to_string())
+        );
+    }
+
+    #[test]
+    fn test_plain_text_has_no_html() {
+        assert_eq!(html_body(&content_type("text/plain"), "Hi"), None);
+        assert_eq!(html_body(&[], "<p>Hi</p>"), None);
+    }
+
+    #[test]
+    fn test_multipart_alternative() {
+        let body = "preamble\r\n\
+            --b1\r\n\
+            Content-Type: text/plain\r\n\
+            \r\n\
+            Hi\r\n\
+            --b1\r\n\
+            Content-Type: text/html; charset=utf-8\r\n\
+            \r\n\
+            <p>Hi</p>\r\n\
+            --b1--\r\n";
+
+        assert_eq!(
+            html_body(
+                &content_type("multipart/alternative; boundary=\"b1\""),
+                body
+            ),
+            Some("<p>Hi</p>".to_string())
+        );
+    }
+
+    #[test]
+    fn test_multipart_without_html() {
+        let body = "--b1\nContent-Type: text/plain\n\nHi\n--b1--\n";
+
+        assert_eq!(
+            html_body(&content_type("multipart/mixed; boundary=b1"), body),
+            None
+        );
+    }
+}
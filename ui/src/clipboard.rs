@@ -0,0 +1,34 @@
+use dioxus::prelude::*;
+
+/// Copies `text` to the system clipboard, returning whether it succeeded.
+///
+/// When the Clipboard API is missing or refuses, for instance without permission or outside a
+/// secure context, the text is copied by selecting it in a hidden `<textarea>` instead.
+pub async fn copy(text: &str) -> bool {
+    let script = format!(
+        r#"
+        const text = {};
+        try {{
+            await navigator.clipboard.writeText(text);
+            return true;
+        }} catch (_) {{
+            const textarea = document.createElement("textarea");
+            textarea.value = text;
+            textarea.setAttribute("readonly", "");
+            textarea.style.position = "fixed";
+            textarea.style.opacity = "0";
+            document.body.appendChild(textarea);
+            textarea.select();
+            const copied = document.execCommand("copy");
+            textarea.remove();
+            return copied;
+        }}
+        "#,
+        serde_json::to_string(text).unwrap_or_default()
+    );
+    document::eval(&script)
+        .await
+        .ok()
+        .and_then(|result| result.as_bool())
+        .unwrap_or(false)
+}
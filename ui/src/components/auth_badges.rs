@@ -0,0 +1,23 @@
+use crate::auth::{Verdict, label};
+use dioxus::prelude::*;
+
+/// One badge per authentication check, showing whether it passed. Renders nothing when no
+/// check ran.
+#[component]
+pub fn AuthBadges(results: Vec<(String, String)>) -> Element {
+    rsx! {
+        if !results.is_empty() {
+            span {
+                class: "inline-flex flex-wrap gap-1",
+                for (index, (method, result)) in results.iter().enumerate() {
+                    span {
+                        key: "{index}",
+                        class: "px-2 py-0.5 text-xs font-medium rounded-full {Verdict::of(result).badge_class()}",
+                        title: "{method}={result}",
+                        "{label(method, result)}"
+                    }
+                }
+            }
+        }
+    }
+}
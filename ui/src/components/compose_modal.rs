@@ -0,0 +1,109 @@
+use crate::api::ApiClient;
+use crate::toast::use_toast;
+use dioxus::prelude::*;
+
+const INPUT_CLASS: &str =
+    "w-full border border-gray-300 dark:border-gray-600 dark:bg-gray-900 rounded px-3 py-2 mb-3";
+
+/// A dialog for writing an email and sending it through the server's relay, prefilled with a
+/// test message.
+#[component]
+pub fn ComposeModal(on_close: EventHandler<()>) -> Element {
+    let mut from = use_signal(|| "sender@example.com".to_string());
+    let mut to = use_signal(|| "recipient@example.com".to_string());
+    let mut subject = use_signal(|| "Test email".to_string());
+    let mut body = use_signal(|| "Hello from remail!".to_string());
+    let mut sending = use_signal(|| false);
+    let mut toasts = use_toast();
+
+    let send = move |_: MouseEvent| {
+        // Recipients are separated by commas, as in a `To` header.
+        let recipients: Vec<String> = to
+            .read()
+            .split(',')
+            .map(str::trim)
+            .filter(|address| !address.is_empty())
+            .map(str::to_string)
+            .collect();
+        let (from, subject, body) = (from(), subject(), body());
+        sending.set(true);
+        spawn(async move {
+            let result = ApiClient::new()
+                .send_email(&from, &recipients, &subject, &body)
+                .await;
+            sending.set(false);
+            match result {
+                Ok(()) => {
+                    toasts.success("Email sent");
+                    on_close.call(());
+                }
+                Err(e) => toasts.error(format!("Failed to send: {e}")),
+            }
+        });
+    };
+
+    rsx! {
+        div {
+            class: "fixed inset-0 z-50 flex items-center justify-center bg-black/50",
+            onclick: move |_| on_close.call(()),
+            onkeydown: move |event| {
+                if event.key() == Key::Escape {
+                    on_close.call(());
+                }
+            },
+            div {
+                class: "bg-white dark:bg-gray-800 rounded-lg shadow-xl p-6 w-full max-w-lg",
+                role: "dialog",
+                "aria-modal": "true",
+                "aria-label": "Compose email",
+                onclick: move |event| event.stop_propagation(),
+                h2 { class: "text-lg font-semibold mb-4", "Compose email" }
+                label { class: "block text-sm mb-1", "From" }
+                input {
+                    class: INPUT_CLASS,
+                    r#type: "email",
+                    value: "{from}",
+                    oninput: move |event| from.set(event.value()),
+                }
+                label { class: "block text-sm mb-1", "To" }
+                input {
+                    class: INPUT_CLASS,
+                    autofocus: true,
+                    value: "{to}",
+                    oninput: move |event| to.set(event.value()),
+                }
+                label { class: "block text-sm mb-1", "Subject" }
+                input {
+                    class: INPUT_CLASS,
+                    value: "{subject}",
+                    oninput: move |event| subject.set(event.value()),
+                }
+                label { class: "block text-sm mb-1", "Body" }
+                textarea {
+                    class: INPUT_CLASS,
+                    rows: "6",
+                    value: "{body}",
+                    oninput: move |event| body.set(event.value()),
+                }
+                div {
+                    class: "flex justify-end gap-3",
+                    button {
+                        class: "px-3 py-2 text-sm text-gray-600 dark:text-gray-400 hover:underline",
+                        onclick: move |_| on_close.call(()),
+                        "Cancel"
+                    }
+                    button {
+                        class: "px-3 py-2 text-sm text-white bg-blue-600 rounded hover:bg-blue-700 disabled:opacity-50",
+                        disabled: sending(),
+                        onclick: send,
+                        if sending() {
+                            "Sending..."
+                        } else {
+                            "Send"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
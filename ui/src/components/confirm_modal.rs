@@ -0,0 +1,43 @@
+use dioxus::prelude::*;
+
+/// A dialog asking the user to confirm a destructive action before it happens.
+#[component]
+pub fn ConfirmModal(
+    message: String,
+    on_confirm: EventHandler<()>,
+    on_cancel: EventHandler<()>,
+) -> Element {
+    rsx! {
+        div {
+            class: "fixed inset-0 z-50 flex items-center justify-center bg-black/50",
+            onclick: move |_| on_cancel.call(()),
+            onkeydown: move |event| {
+                if event.key() == Key::Escape {
+                    on_cancel.call(());
+                }
+            },
+            div {
+                class: "bg-white dark:bg-gray-800 rounded-lg shadow-xl p-6 w-full max-w-sm",
+                role: "dialog",
+                "aria-modal": "true",
+                // Clicks inside the dialog should not reach the backdrop and cancel it.
+                onclick: move |event| event.stop_propagation(),
+                p { class: "text-gray-900 dark:text-gray-100 mb-6", "{message}" }
+                div {
+                    class: "flex justify-end gap-3",
+                    button {
+                        class: "px-3 py-2 text-sm text-gray-600 dark:text-gray-400 hover:underline",
+                        onclick: move |_| on_cancel.call(()),
+                        "Cancel"
+                    }
+                    button {
+                        class: "px-3 py-2 text-sm text-white bg-red-600 rounded hover:bg-red-700",
+                        autofocus: true,
+                        onclick: move |_| on_confirm.call(()),
+                        "Delete"
+                    }
+                }
+            }
+        }
+    }
+}
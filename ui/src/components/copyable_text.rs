@@ -0,0 +1,41 @@
+use crate::clipboard;
+use crate::debounce::sleep;
+use dioxus::prelude::*;
+use std::time::Duration;
+
+/// How long "Copied!" replaces the copy icon.
+const COPIED_DURATION: Duration = Duration::from_millis(1500);
+
+/// Text that copies itself to the clipboard when clicked, with a copy icon shown on hover.
+#[component]
+pub fn CopyableText(text: String) -> Element {
+    let mut copied = use_signal(|| false);
+    let copy_text = text.clone();
+
+    rsx! {
+        span {
+            class: "group inline-flex items-center gap-1 cursor-pointer hover:underline",
+            title: "Copy to clipboard",
+            onclick: move |_| {
+                let text = copy_text.clone();
+                async move {
+                    if clipboard::copy(&text).await {
+                        copied.set(true);
+                        sleep(COPIED_DURATION).await;
+                        copied.set(false);
+                    }
+                }
+            },
+            "{text}"
+            if copied() {
+                span { class: "text-xs text-green-600 dark:text-green-400", "Copied!" }
+            } else {
+                span {
+                    class: "text-xs opacity-0 group-hover:opacity-100 transition-opacity",
+                    "aria-hidden": "true",
+                    "📋"
+                }
+            }
+        }
+    }
+}
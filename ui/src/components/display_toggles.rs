@@ -0,0 +1,44 @@
+use crate::preferences::{Density, Theme};
+use dioxus::prelude::*;
+
+/// Theme and density switches, writing to the preferences provided by `App`. The sun/moon
+/// button overrides the system theme; the select can go back to following it.
+#[component]
+pub fn DisplayToggles() -> Element {
+    let mut theme: Signal<Theme> = use_context();
+    let mut density: Signal<Density> = use_context();
+    let dark_mode: Memo<bool> = use_context();
+
+    rsx! {
+        div {
+            class: "flex items-center gap-3",
+            button {
+                class: "text-lg leading-none",
+                title: if dark_mode() { "Switch to light mode" } else { "Switch to dark mode" },
+                onclick: move |_| theme.set(Theme::overriding(dark_mode())),
+                if dark_mode() { "☀️" } else { "🌙" }
+            }
+            select {
+                class: "text-sm border border-gray-300 dark:border-gray-600 rounded px-2 py-1",
+                title: "Theme",
+                value: "{theme}",
+                onchange: move |event| {
+                    if let Ok(selected) = event.value().parse() {
+                        theme.set(selected);
+                    }
+                },
+                for choice in Theme::ALL {
+                    option { value: "{choice}", "Theme: {choice}" }
+                }
+            }
+            button {
+                class: "text-sm text-blue-600 dark:text-blue-400 hover:underline",
+                onclick: move |_| density.set(density().toggled()),
+                match density() {
+                    Density::Comfortable => "Compact view",
+                    Density::Compact => "Comfortable view",
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use crate::Route;
+use crate::attachments::format_size;
+use crate::components::{AuthBadges, CopyableText};
+use crate::format_subject;
+use crate::preferences::Density;
+use crate::time::{format_exact, format_relative};
+use chrono::{DateTime, Utc};
+use dioxus::prelude::*;
+use remail_types::Email;
+
+#[component]
+pub fn EmailCard(
+    email: Email,
+    highlighted: bool,
+    selected: bool,
+    /// Whether the keyboard selection is on this email.
+    active: bool,
+    read: bool,
+    now: DateTime<Utc>,
+    on_select: EventHandler<bool>,
+    on_delete: EventHandler<()>,
+    on_focus: EventHandler<()>,
+) -> Element {
+    let density: Signal<Density> = use_context();
+    let padding = density().row_padding();
+    let ring = if active { "ring-2 ring-blue-500" } else { "" };
+
+    rsx! {
+        div {
+            tabindex: 0,
+            class: if highlighted {
+                "bg-yellow-50 dark:bg-yellow-900 border border-yellow-300 dark:border-yellow-700 rounded-lg {padding} shadow-sm transition-colors duration-1000 focus:outline-none focus:ring-2 focus:ring-blue-400 {ring}"
+            } else {
+                "bg-white dark:bg-gray-800 border border-gray-200 dark:border-gray-700 rounded-lg {padding} shadow-sm transition-colors duration-1000 focus:outline-none focus:ring-2 focus:ring-blue-400 {ring}"
+            },
+            onfocus: move |_| on_focus.call(()),
+            div {
+                class: "flex justify-between items-start mb-2",
+                div {
+                    class: "flex items-center gap-3",
+                    input {
+                        r#type: "checkbox",
+                        checked: selected,
+                        onchange: move |event| on_select.call(event.checked()),
+                    }
+                    Link {
+                        class: if read {
+                            "text-xl font-normal text-gray-700 dark:text-gray-300 hover:underline"
+                        } else {
+                            "text-xl font-semibold text-gray-900 dark:text-gray-100 hover:underline"
+                        },
+                        to: Route::EmailDetail { id: email.id.to_string() },
+                        "{format_subject(&email.subject)}"
+                    }
+                }
+                div {
+                    class: "flex items-center gap-3",
+                    AuthBadges { results: email.auth_results.clone() }
+                    span {
+                        class: "text-sm text-gray-500 dark:text-gray-400",
+                        title: "{email.body_lines} lines",
+                        "{format_size(email.body_bytes)}"
+                    }
+                    span {
+                        class: "text-sm text-gray-500 dark:text-gray-400",
+                        title: format_exact(&email.created_at),
+                        "{format_relative(&email.created_at, &now)}"
+                    }
+                    button {
+                        class: "text-sm text-red-600 hover:underline",
+                        title: "Delete email",
+                        "aria-label": "Delete email",
+                        onclick: move |_| on_delete.call(()),
+                        "🗑"
+                    }
+                }
+            }
+            div {
+                class: "text-sm text-gray-600 dark:text-gray-400 mb-2",
+                "From: "
+                CopyableText { text: email.from.clone() }
+            }
+            div {
+                class: "text-sm text-gray-600 dark:text-gray-400 mb-3",
+                "To: {email.to}"
+            }
+            div {
+                class: "text-gray-700 dark:text-gray-300 {density().body_clamp()}",
+                "{email.preview}"
+            }
+        }
+    }
+}
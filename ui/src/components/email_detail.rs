@@ -0,0 +1,363 @@
+use crate::Route;
+use crate::api::ApiClient;
+use crate::body::{block_remote_images, html_body};
+use crate::components::{AuthBadges, ConfirmModal, CopyableText, HeadersTable};
+use crate::filter::EmailFilter;
+use crate::format_subject;
+use crate::toast::use_toast;
+use crate::{attachments, clipboard, download, headers, json};
+use dioxus::prelude::*;
+use remail_types::Email;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// The ways an email can be shown on its detail page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ViewMode {
+    Rendered,
+    PlainText,
+    Headers,
+    Raw,
+    Json,
+}
+
+impl ViewMode {
+    const ALL: [ViewMode; 5] = [
+        ViewMode::Rendered,
+        ViewMode::PlainText,
+        ViewMode::Headers,
+        ViewMode::Raw,
+        ViewMode::Json,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            ViewMode::Rendered => "Rendered",
+            ViewMode::PlainText => "Plain text",
+            ViewMode::Headers => "Headers",
+            ViewMode::Raw => "Raw source",
+            ViewMode::Json => "JSON",
+        }
+    }
+}
+
+/// A single email, looked up by the id in the route.
+#[component]
+pub fn EmailDetail(id: String) -> Element {
+    let email = use_resource(use_reactive((&id,), |(id,)| async move {
+        let id: Uuid = id.parse().map_err(|_| format!("Invalid email id: {id}"))?;
+        ApiClient::new()
+            .get_email(id)
+            .await
+            .map_err(|e| format!("Failed to load email: {e}"))
+    }));
+    let mut load_images = use_signal(|| false);
+    let mut view = use_signal(|| ViewMode::Rendered);
+    // Raw sources are only fetched when their tab is first opened, then kept for the session.
+    let mut raw_sources = use_signal(HashMap::<Uuid, Result<String, String>>::new);
+    let email_json = use_memo(move || match &*email.read() {
+        Some(Ok(email)) => serde_json::to_string_pretty(email).unwrap_or_default(),
+        _ => String::new(),
+    });
+    let mut read_emails: Signal<HashSet<Uuid>> = use_context();
+    use_effect(move || {
+        if let Some(Ok(email)) = &*email.read() {
+            read_emails.write().insert(email.id);
+        }
+    });
+    let mut confirming_delete = use_signal(|| false);
+    let mut toasts = use_toast();
+    let navigator = navigator();
+    let client = ApiClient::new();
+
+    let mut delete = move |id: Uuid| {
+        confirming_delete.set(false);
+        spawn(async move {
+            match ApiClient::new().delete_email(id).await {
+                Ok(()) => {
+                    toasts.success("Email deleted");
+                    navigator.push(Route::Home {
+                        filter: EmailFilter::default(),
+                    });
+                }
+                Err(e) => toasts.error(format!("Failed to delete: {e}")),
+            }
+        });
+    };
+
+    let mut select_view = move |mode: ViewMode| {
+        view.set(mode);
+        let email_id = match &*email.peek() {
+            Some(Ok(email)) => email.id,
+            _ => return,
+        };
+        if mode == ViewMode::Raw && !raw_sources.peek().contains_key(&email_id) {
+            spawn(async move {
+                let source = ApiClient::new()
+                    .get_email_raw(email_id)
+                    .await
+                    .map_err(|e| format!("Failed to load raw source: {e}"));
+                raw_sources.write().insert(email_id, source);
+            });
+        }
+    };
+
+    rsx! {
+        div {
+            class: "container mx-auto px-4 py-8",
+            Link {
+                class: "text-sm text-blue-600 dark:text-blue-400 hover:underline",
+                to: Route::Home { filter: EmailFilter::default() },
+                "← Back to emails"
+            }
+            match &*email.read() {
+                None => rsx! {
+                    div { class: "text-center py-8", "Loading email..." }
+                },
+                Some(Err(err)) => rsx! {
+                    div {
+                        class: "bg-red-100 dark:bg-red-900 border border-red-400 text-red-700 dark:text-red-200 px-4 py-3 rounded mb-4",
+                        "Error: {err}"
+                    }
+                },
+                Some(Ok(email)) => rsx! {
+                    div {
+                        class: "flex justify-between items-start gap-4 mt-4 mb-2",
+                        h1 {
+                            class: "text-2xl font-bold",
+                            "{format_subject(&email.subject)}"
+                        }
+                        button {
+                            class: "text-sm text-red-600 hover:underline",
+                            title: "Delete email",
+                            onclick: move |_| confirming_delete.set(true),
+                            "🗑 Delete"
+                        }
+                    }
+                    if confirming_delete() {
+                        ConfirmModal {
+                            message: "Delete this email?",
+                            on_confirm: {
+                                let id = email.id;
+                                move |_| delete(id)
+                            },
+                            on_cancel: move |_| confirming_delete.set(false),
+                        }
+                    }
+                    dl {
+                        class: "grid grid-cols-[auto_1fr] gap-x-4 text-sm text-gray-600 dark:text-gray-400 mb-6",
+                        dt { class: "font-medium", "From" }
+                        dd { CopyableText { text: email.from.clone() } }
+                        dt { class: "font-medium", "To" }
+                        dd { CopyableText { text: email.to.clone() } }
+                        if !email.cc.is_empty() {
+                            dt { class: "font-medium", "Cc" }
+                            dd {
+                                class: "flex flex-wrap gap-x-3",
+                                for address in email.cc.iter().cloned() {
+                                    CopyableText { key: "{address}", text: address }
+                                }
+                            }
+                        }
+                        if !bcc_recipients(email).is_empty() {
+                            dt { class: "font-medium", "Delivered to" }
+                            dd {
+                                class: "flex flex-wrap gap-x-3",
+                                for recipient in bcc_recipients(email) {
+                                    span {
+                                        key: "{recipient}",
+                                        CopyableText { text: recipient.to_string() }
+                                        " (BCC)"
+                                    }
+                                }
+                            }
+                        }
+                        dt { class: "font-medium", "Received" }
+                        dd { "{email.created_at.to_rfc3339()}" }
+                        dt { class: "font-medium", "Size" }
+                        dd { "{attachments::format_size(email.body_bytes)}, {email.body_lines} lines" }
+                        if !email.auth_results.is_empty() {
+                            dt { class: "font-medium", "Authentication" }
+                            dd { AuthBadges { results: email.auth_results.clone() } }
+                        }
+                    }
+                    if !email.attachments.is_empty() {
+                        div {
+                            class: "flex flex-wrap gap-2 mb-6",
+                            for (index, attachment) in email.attachments.iter().enumerate() {
+                                a {
+                                    key: "{index}",
+                                    class: "inline-flex items-center gap-2 px-3 py-1 text-sm border border-gray-300 dark:border-gray-600 rounded-full hover:bg-gray-100",
+                                    href: client.attachment_url(email.id, index),
+                                    download: attachments::display_name(attachment, index),
+                                    span { "{attachments::icon(&attachment.content_type)}" }
+                                    span { "{attachments::display_name(attachment, index)}" }
+                                    span {
+                                        class: "text-gray-500 dark:text-gray-400",
+                                        "{attachments::format_size(attachment.size)}"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    div {
+                        class: "flex flex-wrap gap-1 border-b border-gray-200 dark:border-gray-700 mb-4",
+                        for mode in ViewMode::ALL {
+                            button {
+                                key: "{mode.label()}",
+                                class: if view() == mode {
+                                    "px-3 py-2 text-sm border-b-2 border-blue-600 text-blue-600 dark:text-blue-400"
+                                } else {
+                                    "px-3 py-2 text-sm text-gray-600 dark:text-gray-400 hover:text-gray-900 dark:hover:text-gray-100"
+                                },
+                                onclick: move |_| select_view(mode),
+                                "{mode.label()}"
+                            }
+                        }
+                    }
+                    match view() {
+                        ViewMode::Rendered => match html_body(&email.headers, &email.body) {
+                            Some(html) => rsx! {
+                                SourceActions {
+                                    filename: "{email.id}.html",
+                                    content_type: "text/html",
+                                    text: html.clone(),
+                                }
+                                label {
+                                    class: "flex items-center gap-2 text-sm text-gray-600 dark:text-gray-400 mb-2",
+                                    input {
+                                        r#type: "checkbox",
+                                        checked: load_images(),
+                                        onchange: move |event| load_images.set(event.checked()),
+                                    }
+                                    "Load images"
+                                }
+                                // The sandbox keeps scripts in captured mail from running.
+                                iframe {
+                                    class: "w-full h-96 border border-gray-200 dark:border-gray-700 rounded mb-6",
+                                    "sandbox": "",
+                                    srcdoc: if load_images() {
+                                        attachments::rewrite_cid_urls(&html, &email.attachments, |index| {
+                                            client.attachment_url(email.id, index)
+                                        })
+                                    } else {
+                                        block_remote_images(&html)
+                                    },
+                                }
+                            },
+                            None => rsx! {
+                                SourceActions {
+                                    filename: "{email.id}.txt",
+                                    content_type: "text/plain",
+                                    text: email.body.clone(),
+                                }
+                                pre { class: SOURCE_CLASS, "{email.body}" }
+                            },
+                        },
+                        ViewMode::PlainText => rsx! {
+                            SourceActions {
+                                filename: "{email.id}.txt",
+                                content_type: "text/plain",
+                                text: email.body.clone(),
+                            }
+                            pre { class: SOURCE_CLASS, "{email.body}" }
+                        },
+                        ViewMode::Headers => rsx! {
+                            SourceActions {
+                                filename: "{email.id}-headers.txt",
+                                content_type: "text/plain",
+                                text: headers::to_text(&email.headers),
+                            }
+                            HeadersTable { headers: email.headers.to_vec() }
+                        },
+                        ViewMode::Raw => match raw_sources.read().get(&email.id) {
+                            None => rsx! {
+                                div { class: "text-center py-8", "Loading raw source..." }
+                            },
+                            Some(Err(err)) => rsx! {
+                                div {
+                                    class: "bg-red-100 dark:bg-red-900 border border-red-400 text-red-700 dark:text-red-200 px-4 py-3 rounded mb-4",
+                                    "Error: {err}"
+                                }
+                            },
+                            Some(Ok(source)) => rsx! {
+                                SourceActions {
+                                    filename: "{email.id}.eml",
+                                    content_type: "message/rfc822",
+                                    text: source.clone(),
+                                }
+                                pre { class: SOURCE_CLASS, "{source}" }
+                            },
+                        },
+                        ViewMode::Json => rsx! {
+                            SourceActions {
+                                filename: "{email.id}.json",
+                                content_type: "application/json",
+                                text: email_json(),
+                            }
+                            pre {
+                                class: SOURCE_CLASS,
+                                for (index, (kind, text)) in json::highlight(&email_json.read()).into_iter().enumerate() {
+                                    span { key: "{index}", class: kind.class(), "{text}" }
+                                }
+                            }
+                        },
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// The envelope recipients the headers do not name, because they were sent a BCC.
+fn bcc_recipients(email: &Email) -> Vec<&str> {
+    email
+        .envelope_to
+        .iter()
+        .filter(|recipient| {
+            !email
+                .to_addrs
+                .iter()
+                .chain(&email.cc)
+                .any(|address| address.eq_ignore_ascii_case(recipient))
+        })
+        .map(String::as_str)
+        .collect()
+}
+
+const SOURCE_CLASS: &str = "bg-gray-50 dark:bg-gray-800 border border-gray-200 dark:border-gray-700 rounded p-4 text-sm font-mono whitespace-pre-wrap break-all mb-6";
+
+/// Copy and download buttons for the text shown in the current view.
+#[component]
+fn SourceActions(filename: String, content_type: String, text: String) -> Element {
+    let mut copied = use_signal(|| false);
+    let copy_text = text.clone();
+
+    rsx! {
+        div {
+            class: "flex justify-end gap-3 mb-2",
+            button {
+                class: "text-sm text-blue-600 dark:text-blue-400 hover:underline",
+                onclick: move |_| {
+                    let text = copy_text.clone();
+                    async move {
+                        copied.set(clipboard::copy(&text).await);
+                    }
+                },
+                if copied() { "Copied!" } else { "Copy" }
+            }
+            button {
+                class: "text-sm text-blue-600 dark:text-blue-400 hover:underline",
+                onclick: move |_| {
+                    let filename = filename.clone();
+                    let content_type = content_type.clone();
+                    let text = text.clone();
+                    async move {
+                        download::save(&filename, &content_type, &text).await;
+                    }
+                },
+                "Download"
+            }
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use dioxus::prelude::*;
+
+/// A centered placeholder for a list with nothing to show, with an optional call to action
+/// below the text.
+#[component]
+pub fn EmptyState(title: String, subtitle: String, action: Option<Element>) -> Element {
+    rsx! {
+        div {
+            class: "flex flex-col items-center text-center py-16 text-gray-600 dark:text-gray-400",
+            svg {
+                class: "w-16 h-16 mb-4 text-gray-300 dark:text-gray-600",
+                "aria-hidden": "true",
+                view_box: "0 0 24 24",
+                fill: "none",
+                stroke: "currentColor",
+                stroke_width: "1.5",
+                stroke_linecap: "round",
+                stroke_linejoin: "round",
+                rect { x: "3", y: "5", width: "18", height: "14", rx: "2" }
+                path { d: "m3 7 9 6 9-6" }
+            }
+            p {
+                class: "text-lg font-medium text-gray-900 dark:text-gray-100 mb-2",
+                "{title}"
+            }
+            p { "{subtitle}" }
+            if let Some(action) = action {
+                div { class: "mt-6", {action} }
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::debounce::{Debouncer, sleep};
+use crate::filter::EmailFilter;
+use dioxus::prelude::*;
+use std::time::Duration;
+
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+fn non_empty(value: String) -> Option<String> {
+    if value.is_empty() { None } else { Some(value) }
+}
+
+/// The id of the search box, for focusing it from elsewhere.
+pub const SEARCH_INPUT_ID: &str = "email-search";
+
+/// Search box and sender/recipient dropdowns shown above the email list.
+#[component]
+pub fn FilterBar(
+    filter: EmailFilter,
+    senders: Vec<String>,
+    recipients: Vec<String>,
+    result_count: u64,
+    on_change: EventHandler<EmailFilter>,
+) -> Element {
+    let mut search = use_signal(|| filter.q.clone().unwrap_or_default());
+    let mut debouncer = use_signal(Debouncer::default);
+
+    let search_filter = filter.clone();
+    let to_filter = filter.clone();
+    let from_filter = filter.clone();
+
+    rsx! {
+        div {
+            class: "flex flex-wrap items-center gap-3 mb-6",
+            input {
+                id: SEARCH_INPUT_ID,
+                class: "flex-1 min-w-48 border border-gray-300 dark:border-gray-600 rounded px-3 py-2",
+                r#type: "search",
+                placeholder: "Search emails...",
+                value: "{search}",
+                oninput: move |event| {
+                    let value = event.value();
+                    search.set(value.clone());
+                    let generation = debouncer.write().bump();
+                    let filter = search_filter.clone();
+                    spawn(async move {
+                        sleep(SEARCH_DEBOUNCE).await;
+                        if debouncer.peek().is_current(generation) {
+                            on_change.call(EmailFilter {
+                                q: non_empty(value),
+                                ..filter
+                            });
+                        }
+                    });
+                },
+            }
+            select {
+                class: "border border-gray-300 dark:border-gray-600 rounded px-3 py-2",
+                value: filter.to.clone().unwrap_or_default(),
+                onchange: move |event| {
+                    on_change.call(EmailFilter {
+                        to: non_empty(event.value()),
+                        ..to_filter.clone()
+                    });
+                },
+                option { value: "", "All recipients" }
+                for recipient in recipients {
+                    option { value: "{recipient}", "{recipient}" }
+                }
+            }
+            select {
+                class: "border border-gray-300 dark:border-gray-600 rounded px-3 py-2",
+                value: filter.from.clone().unwrap_or_default(),
+                onchange: move |event| {
+                    on_change.call(EmailFilter {
+                        from: non_empty(event.value()),
+                        ..from_filter.clone()
+                    });
+                },
+                option { value: "", "All senders" }
+                for sender in senders {
+                    option { value: "{sender}", "{sender}" }
+                }
+            }
+            span {
+                class: "text-sm text-gray-500 dark:text-gray-400",
+                "{result_count} results"
+            }
+            if !filter.is_empty() {
+                button {
+                    class: "text-sm text-blue-600 dark:text-blue-400 hover:underline",
+                    onclick: move |_| {
+                        search.set(String::new());
+                        debouncer.write().bump();
+                        on_change.call(EmailFilter::default());
+                    },
+                    "Clear filters"
+                }
+            }
+        }
+    }
+}
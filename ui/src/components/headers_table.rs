@@ -0,0 +1,81 @@
+use crate::{clipboard, headers};
+use dioxus::prelude::*;
+
+/// The raw headers of an email, with the ones useful for debugging pinned to the top.
+#[component]
+pub fn HeadersTable(headers: Vec<(String, String)>) -> Element {
+    let mut name_filter = use_signal(String::new);
+    let mut copied = use_signal(|| Option::<String>::None);
+
+    let arranged = headers::arrange(&headers);
+    let visible = headers::filter_by_name(&arranged, &name_filter.read());
+    let all_text = headers::to_text(&visible);
+
+    rsx! {
+        div {
+            class: "flex items-center gap-3 mb-3",
+            input {
+                class: "flex-1 border border-gray-300 dark:border-gray-600 rounded px-3 py-2 text-sm",
+                r#type: "search",
+                placeholder: "Filter headers by name...",
+                value: "{name_filter}",
+                oninput: move |event| name_filter.set(event.value()),
+            }
+            button {
+                class: "text-sm text-blue-600 dark:text-blue-400 hover:underline",
+                onclick: move |_| {
+                    let text = all_text.clone();
+                    async move {
+                        if clipboard::copy(&text).await {
+                            copied.set(Some("all".to_string()));
+                        }
+                    }
+                },
+                if copied.read().as_deref() == Some("all") { "Copied!" } else { "Copy all as text" }
+            }
+        }
+        table {
+            class: "w-full text-sm border border-gray-200 dark:border-gray-700",
+            tbody {
+                for (index, (name, value)) in visible.into_iter().enumerate() {
+                    tr {
+                        key: "{index}-{name}",
+                        class: if headers::is_pinned(&name) { "bg-blue-50 dark:bg-blue-900 align-top" } else { "align-top" },
+                        th {
+                            class: "text-left font-medium text-gray-700 dark:text-gray-300 px-3 py-2 whitespace-nowrap",
+                            "{name}"
+                        }
+                        td {
+                            class: "px-3 py-2 font-mono text-xs text-gray-800 dark:text-gray-200 whitespace-pre-wrap break-all",
+                            "{value}"
+                        }
+                        td {
+                            class: "px-3 py-2 text-right",
+                            button {
+                                class: "text-xs text-blue-600 dark:text-blue-400 hover:underline",
+                                onclick: {
+                                    let line = headers::to_line(&name, &value);
+                                    let row = format!("{index}-{name}");
+                                    move |_| {
+                                        let line = line.clone();
+                                        let row = row.clone();
+                                        async move {
+                                            if clipboard::copy(&line).await {
+                                                copied.set(Some(row));
+                                            }
+                                        }
+                                    }
+                                },
+                                if copied.read().as_deref() == Some(format!("{index}-{name}").as_str()) {
+                                    "Copied!"
+                                } else {
+                                    "Copy"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use crate::mailbox;
+use dioxus::prelude::*;
+use remail_types::RecipientCount;
+
+const TAB: &str =
+    "px-3 py-1 text-sm rounded-full border border-gray-300 dark:border-gray-600 hover:bg-gray-100";
+const ACTIVE_TAB: &str =
+    "px-3 py-1 text-sm rounded-full border border-blue-600 bg-blue-600 text-white";
+
+/// One tab per recipient address, plus "All", each showing its number of emails.
+#[component]
+pub fn MailboxTabs(
+    recipients: Vec<RecipientCount>,
+    selected: Option<String>,
+    on_select: EventHandler<Option<String>>,
+) -> Element {
+    rsx! {
+        nav {
+            class: "flex flex-wrap gap-2 mb-6",
+            button {
+                class: if selected.is_none() { ACTIVE_TAB } else { TAB },
+                onclick: move |_| on_select.call(None),
+                "All ({mailbox::total(&recipients)})"
+            }
+            for recipient in recipients.iter().cloned() {
+                button {
+                    key: "{recipient.address}",
+                    class: if selected.as_deref() == Some(recipient.address.as_str()) {
+                        ACTIVE_TAB
+                    } else {
+                        TAB
+                    },
+                    onclick: {
+                        let address = recipient.address.clone();
+                        move |_| on_select.call(Some(address.clone()))
+                    },
+                    "{recipient.address} ({recipient.count})"
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,33 @@
+mod auth_badges;
+mod compose_modal;
+mod confirm_modal;
+mod copyable_text;
+mod display_toggles;
+mod email_card;
+mod email_detail;
+mod empty_state;
+mod filter_bar;
+mod headers_table;
+mod mailbox_tabs;
+mod settings_panel;
+mod shortcuts_help;
+mod skeleton;
+mod toasts;
+mod virtual_list;
+
+pub use auth_badges::AuthBadges;
+pub use compose_modal::ComposeModal;
+pub use confirm_modal::ConfirmModal;
+pub use copyable_text::CopyableText;
+pub use display_toggles::DisplayToggles;
+pub use email_card::EmailCard;
+pub use email_detail::EmailDetail;
+pub use empty_state::EmptyState;
+pub use filter_bar::{FilterBar, SEARCH_INPUT_ID};
+pub use headers_table::HeadersTable;
+pub use mailbox_tabs::MailboxTabs;
+pub use settings_panel::SettingsPanel;
+pub use shortcuts_help::ShortcutsHelp;
+pub use skeleton::SkeletonCard;
+pub use toasts::ToastOverlay;
+pub use virtual_list::{VirtualList, scroll_into_view};
@@ -0,0 +1,50 @@
+use crate::config;
+use dioxus::prelude::*;
+
+/// Lets the user set the bearer token sent to an authenticated API.
+#[component]
+pub fn SettingsPanel(on_close: EventHandler<()>) -> Element {
+    let mut token = use_signal(|| config::load_token().unwrap_or_default());
+
+    rsx! {
+        div {
+            class: "bg-gray-50 dark:bg-gray-800 border border-gray-200 dark:border-gray-700 rounded-lg p-4 mb-6",
+            div {
+                class: "text-sm text-gray-600 dark:text-gray-400 mb-2",
+                "API: {config::api_base_url()}"
+            }
+            label {
+                class: "block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1",
+                "Bearer token"
+            }
+            div {
+                class: "flex gap-3",
+                input {
+                    class: "flex-1 border border-gray-300 dark:border-gray-600 rounded px-3 py-2",
+                    r#type: "password",
+                    placeholder: "Leave empty for an unauthenticated API",
+                    value: "{token}",
+                    oninput: move |event| token.set(event.value()),
+                }
+                button {
+                    class: "px-3 py-2 text-sm text-white bg-blue-600 rounded hover:bg-blue-700",
+                    onclick: move |_| {
+                        let value = token.read().trim().to_string();
+                        config::save_token(Some(value.as_str()).filter(|value| !value.is_empty()));
+                        on_close.call(());
+                    },
+                    "Save"
+                }
+                button {
+                    class: "px-3 py-2 text-sm text-gray-600 dark:text-gray-400 hover:underline",
+                    onclick: move |_| {
+                        config::save_token(None);
+                        token.set(String::new());
+                        on_close.call(());
+                    },
+                    "Clear"
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::shortcuts::Shortcut;
+use dioxus::prelude::*;
+
+/// Lists the keyboard shortcuts of the email list.
+#[component]
+pub fn ShortcutsHelp(on_close: EventHandler<()>) -> Element {
+    rsx! {
+        div {
+            class: "fixed inset-0 z-50 flex items-center justify-center bg-black/50",
+            onclick: move |_| on_close.call(()),
+            div {
+                class: "bg-white dark:bg-gray-800 rounded-lg shadow-xl p-6 w-full max-w-md",
+                role: "dialog",
+                "aria-modal": "true",
+                "aria-label": "Keyboard shortcuts",
+                onclick: move |event| event.stop_propagation(),
+                h2 { class: "text-lg font-semibold mb-4", "Keyboard shortcuts" }
+                table {
+                    class: "w-full text-sm",
+                    tbody {
+                        for shortcut in Shortcut::ALL {
+                            tr {
+                                key: "{shortcut.label()}",
+                                td {
+                                    class: "py-1 pr-4 whitespace-nowrap",
+                                    kbd {
+                                        class: "font-mono px-2 py-0.5 border border-gray-300 dark:border-gray-600 rounded",
+                                        "{shortcut.label()}"
+                                    }
+                                }
+                                td { class: "py-1 text-gray-700 dark:text-gray-300", "{shortcut.description()}" }
+                            }
+                        }
+                    }
+                }
+                div {
+                    class: "flex justify-end mt-4",
+                    button {
+                        class: "px-3 py-2 text-sm text-gray-600 dark:text-gray-400 hover:underline",
+                        autofocus: true,
+                        onclick: move |_| on_close.call(()),
+                        "Close"
+                    }
+                }
+            }
+        }
+    }
+}
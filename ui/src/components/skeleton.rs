@@ -0,0 +1,26 @@
+use crate::preferences::Density;
+use dioxus::prelude::*;
+
+/// A placeholder shaped like an [`EmailCard`](super::EmailCard), shown while the inbox loads.
+///
+/// Setting `fading` fades the card out, so the loaded list does not pop in abruptly.
+#[component]
+pub fn SkeletonCard(fading: bool) -> Element {
+    let density: Signal<Density> = use_context();
+    let padding = density().row_padding();
+    let opacity = if fading { "opacity-0" } else { "opacity-100" };
+
+    rsx! {
+        div {
+            class: "border border-gray-200 dark:border-gray-700 rounded-lg {padding} transition-opacity duration-200 {opacity}",
+            "aria-hidden": "true",
+            div {
+                class: "animate-pulse",
+                div { class: "h-6 w-1/3 bg-gradient-to-r from-gray-200 to-gray-300 dark:from-gray-700 dark:to-gray-600 rounded mb-3" }
+                div { class: "h-4 w-1/4 bg-gradient-to-r from-gray-200 to-gray-300 dark:from-gray-700 dark:to-gray-600 rounded mb-2" }
+                div { class: "h-4 w-1/5 bg-gradient-to-r from-gray-200 to-gray-300 dark:from-gray-700 dark:to-gray-600 rounded mb-3" }
+                div { class: "h-4 w-2/3 bg-gradient-to-r from-gray-200 to-gray-300 dark:from-gray-700 dark:to-gray-600 rounded" }
+            }
+        }
+    }
+}
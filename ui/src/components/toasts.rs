@@ -0,0 +1,56 @@
+use crate::debounce::sleep;
+use crate::toast::{TOAST_DURATION, Toast, use_toast};
+use dioxus::prelude::*;
+
+/// Stacks the notifications shown through [`use_toast`] in the bottom right corner.
+#[component]
+pub fn ToastOverlay() -> Element {
+    let mut toasts = use_toast();
+
+    rsx! {
+        div {
+            class: "fixed bottom-4 right-4 z-50 flex flex-col items-end gap-2",
+            "aria-live": "polite",
+            for toast in toasts.toasts() {
+                ToastItem {
+                    key: "{toast.id}",
+                    toast: toast.clone(),
+                    on_dismiss: move |_| toasts.dismiss(toast.id),
+                }
+            }
+        }
+    }
+}
+
+/// One notification, which slides in from the right and dismisses itself after
+/// [`TOAST_DURATION`].
+#[component]
+fn ToastItem(toast: Toast, on_dismiss: EventHandler<()>) -> Element {
+    let mut shown = use_signal(|| false);
+    // Tied to this item, so the timer outlives the page that raised the notification.
+    use_future(move || async move {
+        sleep(TOAST_DURATION).await;
+        on_dismiss.call(());
+    });
+    let position = if shown() {
+        "translate-x-0 opacity-100"
+    } else {
+        "translate-x-full opacity-0"
+    };
+
+    rsx! {
+        div {
+            class: "flex items-center gap-3 px-4 py-3 rounded shadow-lg transition-all duration-300 {toast.kind.class()} {position}",
+            role: "status",
+            onmounted: move |_| shown.set(true),
+            span { "{toast.message}" }
+            button {
+                class: "text-lg leading-none opacity-75 hover:opacity-100",
+                title: "Dismiss",
+                "aria-label": "Dismiss notification",
+                onclick: move |_| on_dismiss.call(()),
+                "×"
+            }
+        }
+    }
+}
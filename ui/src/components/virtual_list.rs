@@ -0,0 +1,163 @@
+use dioxus::prelude::*;
+use std::ops::Range;
+
+/// The viewport height assumed until the browser reports the real one.
+const DEFAULT_VIEWPORT_HEIGHT: f64 = 1000.0;
+
+/// The indices of the items, each `item_height` tall, that overlap a viewport of
+/// `viewport_height` starting `offset` pixels below the top of the list.
+pub fn visible_range(
+    len: usize,
+    item_height: f64,
+    offset: f64,
+    viewport_height: f64,
+) -> Range<usize> {
+    if len == 0 || item_height <= 0.0 {
+        return 0..0;
+    }
+    let first = (offset.max(0.0) / item_height).floor() as usize;
+    let end = ((offset + viewport_height).max(0.0) / item_height).ceil() as usize;
+    first.min(len)..end.min(len)
+}
+
+/// Scrolls the window so the item at `index` of the list `id` is in view, if it is not already.
+pub fn scroll_into_view(id: &str, index: usize, item_height: f64) {
+    document::eval(&format!(
+        "const list = document.getElementById({});
+        if (list) {{
+            const top = list.getBoundingClientRect().top + window.scrollY + {index} * {item_height};
+            if (top < window.scrollY || top + {item_height} > window.scrollY + window.innerHeight) {{
+                window.scrollTo({{ top: top - (window.innerHeight - {item_height}) / 2, behavior: 'smooth' }});
+            }}
+        }}",
+        serde_json::to_string(id).unwrap_or_default()
+    ));
+}
+
+/// A list that only renders the items scrolled into the window, so long lists stay fast.
+///
+/// Every item is laid out `item_height` pixels tall; `id` must be unique on the page, since
+/// the list finds its own position through it.
+#[component]
+pub fn VirtualList<T: Clone + PartialEq + 'static>(
+    id: String,
+    items: Vec<T>,
+    item_height: f64,
+    render: Callback<T, Element>,
+) -> Element {
+    // How far the window has scrolled past the top of the list, and the window's height.
+    let mut viewport = use_signal(|| (0.0, DEFAULT_VIEWPORT_HEIGHT));
+
+    use_future({
+        let id = id.clone();
+        move || {
+            let id = id.clone();
+            async move {
+                let mut reports = document::eval(&format!(
+                    "const report = () => {{
+                        const list = document.getElementById({});
+                        if (!list) {{
+                            window.removeEventListener('scroll', report);
+                            window.removeEventListener('resize', report);
+                            return;
+                        }}
+                        dioxus.send([-list.getBoundingClientRect().top, window.innerHeight]);
+                    }};
+                    window.addEventListener('scroll', report, {{ passive: true }});
+                    window.addEventListener('resize', report);
+                    report();",
+                    serde_json::to_string(&id).unwrap_or_default()
+                ));
+                while let Ok(report) = reports.recv::<(f64, f64)>().await {
+                    viewport.set(report);
+                }
+            }
+        }
+    });
+
+    let (offset, viewport_height) = viewport();
+    let range = visible_range(items.len(), item_height, offset, viewport_height);
+    let total_height = items.len() as f64 * item_height;
+
+    rsx! {
+        div {
+            id,
+            class: "relative",
+            style: "height: {total_height}px",
+            for index in range {
+                div {
+                    key: "{index}",
+                    class: "absolute inset-x-0 overflow-hidden",
+                    style: "top: {index as f64 * item_height}px; height: {item_height}px",
+                    {render(items[index].clone())}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_email;
+    use remail_types::Email;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_visible_range() {
+        assert_eq!(visible_range(100, 50.0, 0.0, 200.0), 0..4);
+        assert_eq!(visible_range(100, 50.0, 125.0, 200.0), 2..7);
+        assert_eq!(visible_range(5, 50.0, 100.0, 1000.0), 2..5);
+    }
+
+    #[test]
+    fn test_visible_range_before_and_after_list() {
+        // Above the list, only the part of the viewport that reaches into it counts.
+        assert_eq!(visible_range(100, 50.0, -150.0, 200.0), 0..1);
+        assert_eq!(visible_range(100, 50.0, -300.0, 200.0), 0..0);
+        assert_eq!(visible_range(100, 50.0, 10_000.0, 200.0), 100..100);
+        assert_eq!(visible_range(0, 50.0, 0.0, 200.0), 0..0);
+        assert_eq!(visible_range(10, 0.0, 0.0, 200.0), 0..0);
+    }
+
+    #[test]
+    fn test_renders_a_window_of_a_large_list() {
+        let (item_height, viewport_height) = (120.0, 900.0);
+
+        let mut offset = 0.0;
+        while offset < 10_000.0 * item_height {
+            let range = visible_range(10_000, item_height, offset, viewport_height);
+            assert!(
+                (range.len() as f64) < viewport_height / item_height + 2.0,
+                "{range:?} at {offset}"
+            );
+            assert!(!range.is_empty(), "nothing visible at {offset}");
+            offset += 37.5;
+        }
+    }
+
+    #[test]
+    fn test_renders_only_the_visible_emails() {
+        static RENDERED: AtomicUsize = AtomicUsize::new(0);
+
+        fn app() -> Element {
+            rsx! {
+                VirtualList {
+                    id: "emails",
+                    items: (0..10_000).map(|_| test_email("Hello")).collect::<Vec<_>>(),
+                    item_height: 100.0,
+                    render: move |email: Email| {
+                        RENDERED.fetch_add(1, Ordering::SeqCst);
+                        rsx! { "{email.id}" }
+                    },
+                }
+            }
+        }
+
+        let mut dom = VirtualDom::new(app);
+        dom.rebuild_in_place();
+
+        // The default viewport fits ten of the emails.
+        assert_eq!(RENDERED.load(Ordering::SeqCst), 10);
+    }
+}
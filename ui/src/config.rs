@@ -0,0 +1,214 @@
+/// The base URL used when neither a compile-time nor a runtime URL is configured, for when the
+/// UI is served by the API binary itself.
+pub const SAME_ORIGIN_API_PATH: &str = "/api";
+
+/// Where maild listens unless the UI is built with `REMAIL_SMTP_ADDRESS`.
+const DEFAULT_SMTP_ADDRESS: &str = "localhost:2525";
+
+/// Name of the `<meta>` tag and `window` property a deployment can use to point the UI at its
+/// API.
+#[cfg(target_arch = "wasm32")]
+const RUNTIME_API_URL_KEY: &str = "REMAIL_API_URL";
+#[cfg(target_arch = "wasm32")]
+const TOKEN_STORAGE_KEY: &str = "remail_api_token";
+
+/// Outside the browser there is no page origin, so relative URLs resolve against the API's
+/// default local address.
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_ORIGIN: &str = "http://localhost:3000";
+
+/// Picks the API base URL from the first non-empty source: the compile-time `REMAIL_API_URL`,
+/// the runtime value injected into the page, then [`SAME_ORIGIN_API_PATH`]. Relative URLs are
+/// resolved against `origin`.
+pub fn resolve_api_base_url(
+    compile_time: Option<&str>,
+    runtime: Option<String>,
+    origin: Option<&str>,
+) -> String {
+    let base = compile_time
+        .map(str::to_string)
+        .into_iter()
+        .chain(runtime)
+        .map(|url| url.trim().to_string())
+        .find(|url| !url.is_empty())
+        .unwrap_or_else(|| SAME_ORIGIN_API_PATH.to_string());
+    let base = base.trim_end_matches('/');
+
+    match origin {
+        Some(origin) if base.starts_with('/') => {
+            format!("{}{base}", origin.trim_end_matches('/'))
+        }
+        _ => base.to_string(),
+    }
+}
+
+pub fn api_base_url() -> String {
+    resolve_api_base_url(
+        option_env!("REMAIL_API_URL"),
+        runtime_api_url(),
+        origin().as_deref(),
+    )
+}
+
+/// The SMTP address to point applications at, shown when the inbox is empty.
+pub fn smtp_address() -> &'static str {
+    option_env!("REMAIL_SMTP_ADDRESS")
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .unwrap_or(DEFAULT_SMTP_ADDRESS)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn runtime_api_url() -> Option<String> {
+    let window = web_sys::window()?;
+    let from_meta = window
+        .document()
+        .and_then(|document| {
+            document
+                .query_selector(&format!("meta[name=\"{RUNTIME_API_URL_KEY}\"]"))
+                .ok()
+                .flatten()
+        })
+        .and_then(|meta| meta.get_attribute("content"));
+
+    from_meta.or_else(|| {
+        js_sys::Reflect::get(&window, &RUNTIME_API_URL_KEY.into())
+            .ok()?
+            .as_string()
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn runtime_api_url() -> Option<String> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+fn origin() -> Option<String> {
+    web_sys::window()?.location().origin().ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn origin() -> Option<String> {
+    Some(DEFAULT_ORIGIN.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+/// The bearer token sent with API requests, if one was saved in the settings panel.
+#[cfg(target_arch = "wasm32")]
+pub fn load_token() -> Option<String> {
+    local_storage()?
+        .get_item(TOKEN_STORAGE_KEY)
+        .ok()
+        .flatten()
+        .filter(|token| !token.is_empty())
+}
+
+/// Saves the bearer token, or forgets it when `token` is `None`.
+#[cfg(target_arch = "wasm32")]
+pub fn save_token(token: Option<&str>) {
+    let Some(storage) = local_storage() else {
+        return;
+    };
+    let result = match token {
+        Some(token) => storage.set_item(TOKEN_STORAGE_KEY, token),
+        None => storage.remove_item(TOKEN_STORAGE_KEY),
+    };
+    if result.is_err() {
+        dioxus::logger::tracing::warn!("Failed to update the stored API token");
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_token() -> Option<String> {
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_token(_token: Option<&str>) {}
+
+/// A display preference saved under `key`, see [`crate::preferences`].
+#[cfg(target_arch = "wasm32")]
+pub fn load_preference(key: &str) -> Option<String> {
+    local_storage()?.get_item(key).ok().flatten()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_preference(key: &str, value: &str) {
+    let saved = local_storage().is_some_and(|storage| storage.set_item(key, value).is_ok());
+    if !saved {
+        dioxus::logger::tracing::warn!("Failed to save the {key} preference");
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_preference(_key: &str) -> Option<String> {
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_preference(_key: &str, _value: &str) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGIN: Option<&str> = Some("https://mail.example.com");
+
+    #[test]
+    fn test_compile_time_url_wins() {
+        assert_eq!(
+            resolve_api_base_url(
+                Some("https://api.example.com/"),
+                Some("https://runtime.example.com".to_string()),
+                ORIGIN
+            ),
+            "https://api.example.com"
+        );
+    }
+
+    #[test]
+    fn test_runtime_url_without_compile_time() {
+        assert_eq!(
+            resolve_api_base_url(
+                None,
+                Some("https://runtime.example.com".to_string()),
+                ORIGIN
+            ),
+            "https://runtime.example.com"
+        );
+        assert_eq!(
+            resolve_api_base_url(
+                Some(""),
+                Some("https://runtime.example.com".to_string()),
+                ORIGIN
+            ),
+            "https://runtime.example.com"
+        );
+    }
+
+    #[test]
+    fn test_same_origin_default() {
+        assert_eq!(
+            resolve_api_base_url(None, None, ORIGIN),
+            "https://mail.example.com/api"
+        );
+        assert_eq!(
+            resolve_api_base_url(None, Some(" ".to_string()), ORIGIN),
+            "https://mail.example.com/api"
+        );
+        assert_eq!(resolve_api_base_url(None, None, None), "/api");
+    }
+
+    #[test]
+    fn test_relative_runtime_url() {
+        assert_eq!(
+            resolve_api_base_url(None, Some("/remail/api/".to_string()), ORIGIN),
+            "https://mail.example.com/remail/api"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+/// Tracks a burst of changes so that only the last one is acted upon.
+///
+/// Every change calls [`Debouncer::bump`] and waits; once the wait is over the change is only
+/// applied if no newer change bumped the generation in the meantime.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Debouncer {
+    generation: u64,
+}
+
+impl Debouncer {
+    pub fn bump(&mut self) -> u64 {
+        self.generation += 1;
+        self.generation
+    }
+
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.generation == generation
+    }
+}
+
+pub async fn sleep(duration: Duration) {
+    #[cfg(target_arch = "wasm32")]
+    gloo_timers::future::sleep(duration).await;
+    #[cfg(not(target_arch = "wasm32"))]
+    tokio::time::sleep(duration).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_only_latest_generation_is_current() {
+        let mut debouncer = Debouncer::default();
+        let first = debouncer.bump();
+        let second = debouncer.bump();
+
+        assert!(!debouncer.is_current(first));
+        assert!(debouncer.is_current(second));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_burst_of_changes_fires_once() {
+        let debouncer = Arc::new(Mutex::new(Debouncer::default()));
+        let fired = Arc::new(Mutex::new(Vec::new()));
+
+        let mut tasks = Vec::new();
+        for (delay, value) in [(0, "h"), (100, "he"), (200, "hel")] {
+            let debouncer = debouncer.clone();
+            let fired = fired.clone();
+            tasks.push(tokio::spawn(async move {
+                sleep(Duration::from_millis(delay)).await;
+                let generation = debouncer.lock().unwrap().bump();
+                sleep(Duration::from_millis(300)).await;
+                if debouncer.lock().unwrap().is_current(generation) {
+                    fired.lock().unwrap().push(value);
+                }
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(*fired.lock().unwrap(), vec!["hel"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_clearing_within_delay_cancels_search() {
+        let debouncer = Arc::new(Mutex::new(Debouncer::default()));
+        let fired = Arc::new(Mutex::new(Vec::new()));
+
+        let type_query = |delay: u64, query: &'static str| {
+            let debouncer = debouncer.clone();
+            let fired = fired.clone();
+            tokio::spawn(async move {
+                sleep(Duration::from_millis(delay)).await;
+                let generation = debouncer.lock().unwrap().bump();
+                sleep(Duration::from_millis(300)).await;
+                if debouncer.lock().unwrap().is_current(generation) {
+                    fired.lock().unwrap().push(query);
+                }
+            })
+        };
+
+        let search = type_query(0, "invoice");
+        let clear = type_query(200, "");
+        search.await.unwrap();
+        assert!(fired.lock().unwrap().is_empty());
+
+        clear.await.unwrap();
+        assert_eq!(*fired.lock().unwrap(), vec![""]);
+    }
+}
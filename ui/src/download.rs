@@ -0,0 +1,17 @@
+use dioxus::prelude::*;
+
+/// Offers `text` to the user as a file download named `filename`.
+pub async fn save(filename: &str, content_type: &str, text: &str) {
+    let script = format!(
+        "const url = URL.createObjectURL(new Blob([{}], {{ type: {} }}));
+        const link = document.createElement('a');
+        link.href = url;
+        link.download = {};
+        link.click();
+        URL.revokeObjectURL(url);",
+        serde_json::to_string(text).unwrap_or_default(),
+        serde_json::to_string(content_type).unwrap_or_default(),
+        serde_json::to_string(filename).unwrap_or_default(),
+    );
+    let _ = document::eval(&script).await;
+}
@@ -0,0 +1,154 @@
+use remail_types::Email;
+use std::fmt;
+
+/// Filters applied to the email list, mirrored in the page's query string so they survive a
+/// refresh.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EmailFilter {
+    /// Free-text search across the subject, sender, recipient and body.
+    pub q: Option<String>,
+    pub subject_contains: Option<String>,
+    pub to: Option<String>,
+    pub from: Option<String>,
+}
+
+impl EmailFilter {
+    pub fn is_empty(&self) -> bool {
+        self.query_pairs().is_empty()
+    }
+
+    /// Applies the filter client-side, the same way the API does, to emails received live.
+    pub fn matches(&self, email: &Email) -> bool {
+        let subject_matches = self.subject_contains.as_deref().is_none_or(|needle| {
+            email
+                .subject
+                .as_deref()
+                .unwrap_or("")
+                .to_lowercase()
+                .contains(&needle.to_lowercase())
+        });
+        let query_matches = self.q.as_deref().is_none_or(|query| {
+            let query = query.to_lowercase();
+            [
+                email.subject.as_deref().unwrap_or(""),
+                &email.from,
+                &email.to,
+                &email.body,
+            ]
+            .iter()
+            .any(|field| field.to_lowercase().contains(&query))
+        });
+        subject_matches
+            && query_matches
+            && self.to.as_deref().is_none_or(|to| email.to == to)
+            && self.from.as_deref().is_none_or(|from| email.from == from)
+    }
+
+    /// The non-empty filters as `(parameter, value)` pairs, in the names the API expects.
+    pub fn query_pairs(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("q", &self.q),
+            ("subject_contains", &self.subject_contains),
+            ("to", &self.to),
+            ("from", &self.from),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| {
+            value
+                .as_deref()
+                .filter(|value| !value.is_empty())
+                .map(|value| (name, value))
+        })
+        .collect()
+    }
+}
+
+impl From<&str> for EmailFilter {
+    fn from(query: &str) -> Self {
+        let mut filter = Self::default();
+        let query = query.strip_prefix('?').unwrap_or(query);
+        for (name, value) in form_urlencoded::parse(query.as_bytes()) {
+            if value.is_empty() {
+                continue;
+            }
+            let value = Some(value.into_owned());
+            match name.as_ref() {
+                "q" => filter.q = value,
+                "subject_contains" => filter.subject_contains = value,
+                "to" => filter.to = value,
+                "from" => filter.from = value,
+                _ => {}
+            }
+        }
+        filter
+    }
+}
+
+impl fmt::Display for EmailFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let query = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(self.query_pairs())
+            .finish();
+        write!(f, "{query}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_email;
+
+    #[test]
+    fn test_query_string_round_trip() {
+        let filter = EmailFilter {
+            q: Some("reset".to_string()),
+            subject_contains: Some("welcome & hello".to_string()),
+            to: Some("test+tag@example.com".to_string()),
+            from: None,
+        };
+
+        let query = filter.to_string();
+        assert_eq!(
+            query,
+            "q=reset&subject_contains=welcome+%26+hello&to=test%2Btag%40example.com"
+        );
+        assert_eq!(EmailFilter::from(query.as_str()), filter);
+    }
+
+    #[test]
+    fn test_empty_filter() {
+        assert_eq!(EmailFilter::default().to_string(), "");
+        assert_eq!(EmailFilter::from(""), EmailFilter::default());
+        assert!(EmailFilter::from("?to=&from=").is_empty());
+    }
+
+    #[test]
+    fn test_matches() {
+        let email = Email {
+            from: "sender@example.com".to_string(),
+            ..test_email("Welcome to Remail")
+        };
+
+        assert!(EmailFilter::default().matches(&email));
+        assert!(
+            EmailFilter::from("subject_contains=welcome&to=recipient%40example.com")
+                .matches(&email)
+        );
+        assert!(!EmailFilter::from("subject_contains=goodbye").matches(&email));
+        assert!(!EmailFilter::from("from=other%40example.com").matches(&email));
+        assert!(EmailFilter::from("q=SENDER%40").matches(&email));
+        assert!(EmailFilter::from("q=remail").matches(&email));
+        assert!(!EmailFilter::from("q=invoice").matches(&email));
+    }
+
+    #[test]
+    fn test_ignores_unknown_parameters() {
+        assert_eq!(
+            EmailFilter::from("?from=a%40example.com&page=2"),
+            EmailFilter {
+                from: Some("a@example.com".to_string()),
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,123 @@
+/// Headers worth seeing first when debugging delivery, shown in this order above the rest.
+const PINNED: &[&str] = &["Message-ID", "Date", "Content-Type", "DKIM-Signature"];
+
+fn pin_rank(name: &str) -> usize {
+    PINNED
+        .iter()
+        .position(|pinned| pinned.eq_ignore_ascii_case(name))
+        .unwrap_or(PINNED.len())
+}
+
+/// Moves the [`PINNED`] headers to the top, keeping the original order otherwise, including
+/// between repeated headers of the same name.
+pub fn arrange(headers: &[(String, String)]) -> Vec<(String, String)> {
+    let mut arranged = headers.to_vec();
+    // The sort is stable, so headers with the same rank keep their relative order.
+    arranged.sort_by_key(|(name, _)| pin_rank(name));
+    arranged
+}
+
+pub fn is_pinned(name: &str) -> bool {
+    pin_rank(name) < PINNED.len()
+}
+
+/// The headers whose name contains `needle`, ignoring case. An empty needle keeps them all.
+pub fn filter_by_name(headers: &[(String, String)], needle: &str) -> Vec<(String, String)> {
+    let needle = needle.trim().to_lowercase();
+    headers
+        .iter()
+        .filter(|(name, _)| name.to_lowercase().contains(&needle))
+        .cloned()
+        .collect()
+}
+
+/// One `Name: value` line, folding multi-line values onto indented continuation lines.
+pub fn to_line(name: &str, value: &str) -> String {
+    format!("{name}: {}", value.lines().collect::<Vec<_>>().join("\n "))
+}
+
+/// The headers as they would appear in the message, one per line.
+pub fn to_text(headers: &[(String, String)]) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| to_line(name, value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn names(headers: &[(String, String)]) -> Vec<&str> {
+        headers.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_arrange_pins_known_headers() {
+        let arranged = arrange(&headers(&[
+            ("Received", "first"),
+            ("From", "alice@example.com"),
+            ("DKIM-Signature", "v=1"),
+            ("Received", "second"),
+            ("date", "Mon, 4 Aug 2025 12:00:00 +0000"),
+            ("Message-ID", "<1@example.com>"),
+        ]));
+
+        assert_eq!(
+            names(&arranged),
+            [
+                "Message-ID",
+                "date",
+                "DKIM-Signature",
+                "Received",
+                "From",
+                "Received"
+            ]
+        );
+        assert_eq!(arranged[3].1, "first");
+        assert_eq!(arranged[5].1, "second");
+    }
+
+    #[test]
+    fn test_is_pinned() {
+        assert!(is_pinned("content-type"));
+        assert!(!is_pinned("Content-Transfer-Encoding"));
+    }
+
+    #[test]
+    fn test_filter_by_name() {
+        let all = headers(&[
+            ("List-Unsubscribe", "<mailto:u@example.com>"),
+            ("X-Tracking-Id", "42"),
+            ("List-Id", "news"),
+        ]);
+
+        assert_eq!(
+            names(&filter_by_name(&all, " list ")),
+            ["List-Unsubscribe", "List-Id"]
+        );
+        assert_eq!(filter_by_name(&all, ""), all);
+        assert!(filter_by_name(&all, "subject").is_empty());
+    }
+
+    #[test]
+    fn test_to_text_folds_long_values() {
+        let text = to_text(&headers(&[
+            ("Subject", "Hello"),
+            ("Received", "from a.example.com\nby b.example.org"),
+        ]));
+
+        assert_eq!(
+            text,
+            "Subject: Hello\nReceived: from a.example.com\n by b.example.org"
+        );
+    }
+}
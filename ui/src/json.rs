@@ -0,0 +1,149 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Key,
+    String,
+    Number,
+    /// `true`, `false` or `null`.
+    Literal,
+    /// Punctuation and whitespace.
+    Plain,
+}
+
+impl TokenKind {
+    pub fn class(self) -> &'static str {
+        match self {
+            TokenKind::Key => "text-purple-700 dark:text-purple-300",
+            TokenKind::String => "text-green-700 dark:text-green-300",
+            TokenKind::Number => "text-blue-700 dark:text-blue-300",
+            TokenKind::Literal => "text-orange-700 dark:text-orange-300",
+            TokenKind::Plain => "",
+        }
+    }
+}
+
+/// Splits pretty-printed JSON into tokens for syntax highlighting. Joining the token texts
+/// gives back the input, so malformed JSON is still shown in full.
+pub fn highlight(json: &str) -> Vec<(TokenKind, String)> {
+    let mut tokens: Vec<(TokenKind, String)> = Vec::new();
+    let mut rest = json;
+    while let Some(c) = rest.chars().next() {
+        let (kind, len) = match c {
+            '"' => {
+                let len = string_len(rest);
+                let is_key = rest[len..].trim_start().starts_with(':');
+                (
+                    if is_key {
+                        TokenKind::Key
+                    } else {
+                        TokenKind::String
+                    },
+                    len,
+                )
+            }
+            '-' | '0'..='9' => (
+                TokenKind::Number,
+                rest.find(|c: char| !matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+                    .unwrap_or(rest.len()),
+            ),
+            'a'..='z' => (
+                TokenKind::Literal,
+                rest.find(|c: char| !c.is_ascii_lowercase())
+                    .unwrap_or(rest.len()),
+            ),
+            _ => (TokenKind::Plain, c.len_utf8()),
+        };
+        let (text, tail) = rest.split_at(len);
+        match tokens.last_mut() {
+            Some((TokenKind::Plain, last)) if kind == TokenKind::Plain => last.push_str(text),
+            _ => tokens.push((kind, text.to_string())),
+        }
+        rest = tail;
+    }
+    tokens
+}
+
+/// The length of the string literal at the start of `text`, including both quotes, or all of
+/// `text` if the string is never closed.
+fn string_len(text: &str) -> usize {
+    let mut escaped = false;
+    for (index, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return index + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(kind: TokenKind, text: &str) -> (TokenKind, String) {
+        (kind, text.to_string())
+    }
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            highlight(r#"{"id": "a", "size": -1.5e3, "seen": true, "cc": null}"#),
+            vec![
+                token(TokenKind::Plain, "{"),
+                token(TokenKind::Key, r#""id""#),
+                token(TokenKind::Plain, ": "),
+                token(TokenKind::String, r#""a""#),
+                token(TokenKind::Plain, ", "),
+                token(TokenKind::Key, r#""size""#),
+                token(TokenKind::Plain, ": "),
+                token(TokenKind::Number, "-1.5e3"),
+                token(TokenKind::Plain, ", "),
+                token(TokenKind::Key, r#""seen""#),
+                token(TokenKind::Plain, ": "),
+                token(TokenKind::Literal, "true"),
+                token(TokenKind::Plain, ", "),
+                token(TokenKind::Key, r#""cc""#),
+                token(TokenKind::Plain, ": "),
+                token(TokenKind::Literal, "null"),
+                token(TokenKind::Plain, "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_highlight_escaped_quotes() {
+        assert_eq!(
+            highlight(r#"["say \"hi\"", "ü"]"#),
+            vec![
+                token(TokenKind::Plain, "["),
+                token(TokenKind::String, r#""say \"hi\"""#),
+                token(TokenKind::Plain, ", "),
+                token(TokenKind::String, r#""ü""#),
+                token(TokenKind::Plain, "]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_highlight_round_trips() {
+        let email = serde_json::json!({
+            "subject": "Hello: \"world\"",
+            "headers": [["To", "bob@example.com"]],
+            "size": 42,
+        });
+        let pretty = serde_json::to_string_pretty(&email).unwrap();
+        let joined: String = highlight(&pretty)
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect();
+        assert_eq!(joined, pretty);
+
+        let unterminated = r#"{"subject": "never closed"#;
+        let joined: String = highlight(unterminated)
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect();
+        assert_eq!(joined, unterminated);
+    }
+}
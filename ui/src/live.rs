@@ -0,0 +1,145 @@
+use remail_types::Email;
+use uuid::Uuid;
+
+/// Only the web target receives events to decode.
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn decode_email_event(data: &str) -> Result<Email, serde_json::Error> {
+    serde_json::from_str(data)
+}
+
+/// Prepends the `incoming` emails that are not already listed, keeping their order, and
+/// returns the ids that were added.
+pub fn prepend_new(emails: &mut Vec<Email>, incoming: Vec<Email>) -> Vec<Uuid> {
+    let mut new_emails: Vec<Email> = Vec::new();
+    for email in incoming {
+        let known = emails
+            .iter()
+            .chain(new_emails.iter())
+            .any(|e| e.id == email.id);
+        if !known {
+            new_emails.push(email);
+        }
+    }
+
+    let ids = new_emails.iter().map(|email| email.id).collect();
+    emails.splice(0..0, new_emails);
+    ids
+}
+
+/// How new emails currently reach the page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LiveStatus {
+    /// Opening, or reopening after a drop, the live stream.
+    Connecting,
+    Live,
+    /// The live stream is unavailable, so the list is refreshed periodically.
+    Polling,
+}
+
+impl LiveStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            LiveStatus::Connecting => "Connecting to live updates...",
+            LiveStatus::Live => "Live",
+            LiveStatus::Polling => "Live updates unavailable, refreshing every 30s",
+        }
+    }
+}
+
+/// The notification shown when a new email arrives.
+pub fn new_email_notice(email: &Email) -> String {
+    format!("New email from {}", email.from)
+}
+
+/// A live feed of new emails from the API's `/v1/emails/stream` server-sent events.
+#[cfg(target_arch = "wasm32")]
+pub struct EmailSubscription {
+    // Dropping the source closes the connection.
+    _source: gloo_net::eventsource::futures::EventSource,
+    events: gloo_net::eventsource::futures::EventSourceSubscription,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl EmailSubscription {
+    pub fn open(url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut source = gloo_net::eventsource::futures::EventSource::new(url)?;
+        let events = source.subscribe("email")?;
+        Ok(Self {
+            _source: source,
+            events,
+        })
+    }
+
+    /// Waits for the next email, returning `None` once the stream drops.
+    pub async fn next(&mut self) -> Option<Email> {
+        use futures::StreamExt;
+
+        loop {
+            let (_, message) = self.events.next().await?.ok()?;
+            let Some(data) = message.data().as_string() else {
+                continue;
+            };
+            match decode_email_event(&data) {
+                Ok(email) => return Some(email),
+                Err(e) => dioxus::logger::tracing::warn!("Ignoring malformed email event: {e}"),
+            }
+        }
+    }
+}
+
+/// Server-sent events are only wired up for the web target; elsewhere opening a subscription
+/// fails so callers fall back to polling.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct EmailSubscription;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl EmailSubscription {
+    pub fn open(_url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("live updates are only supported on the web target".into())
+    }
+
+    pub async fn next(&mut self) -> Option<Email> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_email;
+
+    #[test]
+    fn test_prepend_new() {
+        let old = test_email("old");
+        let mut emails = vec![old.clone()];
+        let first = test_email("first");
+        let second = test_email("second");
+
+        let added = prepend_new(
+            &mut emails,
+            vec![first.clone(), second.clone(), old.clone(), first.clone()],
+        );
+
+        assert_eq!(added, vec![first.id, second.id]);
+        let ids: Vec<Uuid> = emails.iter().map(|email| email.id).collect();
+        assert_eq!(ids, vec![first.id, second.id, old.id]);
+    }
+
+    #[test]
+    fn test_decode_email_event() {
+        let email = test_email("hello");
+        let decoded = decode_email_event(&serde_json::to_string(&email).unwrap()).unwrap();
+        assert_eq!(decoded.id, email.id);
+        assert_eq!(decoded.subject.as_deref(), Some("hello"));
+
+        assert!(decode_email_event("not json").is_err());
+    }
+
+    #[test]
+    fn test_new_email_notice() {
+        assert_eq!(
+            new_email_notice(&test_email("Hello")),
+            "New email from sender@example.com"
+        );
+    }
+}
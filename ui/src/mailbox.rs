@@ -0,0 +1,65 @@
+use remail_types::{Email, RecipientCount};
+
+/// Counts a newly received `email` under its recipient, keeping the recipients in the API's
+/// order: the most frequent first, ties ordered by address.
+pub fn record(recipients: &mut Vec<RecipientCount>, email: &Email) {
+    match recipients
+        .iter_mut()
+        .find(|recipient| recipient.address == email.to)
+    {
+        Some(recipient) => recipient.count += 1,
+        None => recipients.push(RecipientCount {
+            address: email.to.clone(),
+            count: 1,
+        }),
+    }
+    recipients.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.address.cmp(&b.address))
+    });
+}
+
+/// The number of emails across all recipients, shown on the "All" tab.
+pub fn total(recipients: &[RecipientCount]) -> u64 {
+    recipients.iter().map(|recipient| recipient.count).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_email;
+
+    fn email_to(to: &str) -> Email {
+        Email {
+            to: to.to_string(),
+            ..test_email("Hello")
+        }
+    }
+
+    fn counts(recipients: &[RecipientCount]) -> Vec<(&str, u64)> {
+        recipients
+            .iter()
+            .map(|recipient| (recipient.address.as_str(), recipient.count))
+            .collect()
+    }
+
+    #[test]
+    fn test_record() {
+        let mut recipients = Vec::new();
+        for to in ["carol@example.com", "bob@example.com", "alice@example.com"] {
+            record(&mut recipients, &email_to(to));
+        }
+        record(&mut recipients, &email_to("bob@example.com"));
+
+        assert_eq!(
+            counts(&recipients),
+            [
+                ("bob@example.com", 2),
+                ("alice@example.com", 1),
+                ("carol@example.com", 1)
+            ]
+        );
+        assert_eq!(total(&recipients), 4);
+    }
+}
@@ -1,22 +1,132 @@
 use dioxus::prelude::*;
 mod api;
+mod attachments;
+mod auth;
+mod backoff;
+mod body;
+mod clipboard;
+mod components;
+mod config;
+mod debounce;
+mod download;
+mod filter;
+mod headers;
+mod json;
+mod live;
+mod mailbox;
+mod optimistic;
+mod pagination;
+mod preferences;
+mod read;
+mod retry;
+mod shortcuts;
+mod time;
+mod toast;
 
 use api::ApiClient;
-use remail_types::Email;
+use backoff::Backoff;
+use components::{
+    ComposeModal, ConfirmModal, DisplayToggles, EmailCard, EmailDetail, EmptyState, FilterBar,
+    MailboxTabs, SEARCH_INPUT_ID, SettingsPanel, ShortcutsHelp, SkeletonCard, ToastOverlay,
+    VirtualList, scroll_into_view,
+};
+use debounce::{Debouncer, sleep};
+use filter::EmailFilter;
+use live::LiveStatus;
+use pagination::Pagination;
+use preferences::{Density, Theme, use_preference};
+use remail_types::{Email, EmailPage, RecipientCount};
+use retry::{AutoRetry, LoadState};
+use shortcuts::Shortcut;
+use std::collections::HashSet;
+use std::time::Duration;
+use toast::{ToastHandle, use_toast};
+use uuid::Uuid;
+
+/// How often to poll for new emails when the live stream is unavailable.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// How long newly arrived emails stay highlighted.
+const HIGHLIGHT_DURATION: Duration = Duration::from_secs(3);
+/// How long the "New email from ..." notification stays on screen.
+const NOTICE_DURATION: Duration = Duration::from_secs(5);
+/// How many placeholder rows stand in for the inbox while it loads.
+const SKELETON_ROWS: usize = 5;
+/// The shortest time the placeholder rows stay up, so a fast load does not flash them.
+const MIN_SKELETON_DURATION: Duration = Duration::from_millis(200);
+/// How long the placeholder rows take to fade out once the inbox has loaded.
+const SKELETON_FADE_DURATION: Duration = Duration::from_millis(200);
+/// The id of the email list, used to scroll to the row selected from the keyboard.
+const EMAIL_LIST_ID: &str = "email-list";
+/// How often relative timestamps ("3m ago") are recomputed.
+const CLOCK_TICK: Duration = Duration::from_secs(30);
 
 fn format_subject(subject: &Option<String>) -> &str {
     subject.as_deref().unwrap_or("(no subject)")
 }
 
-fn format_date(datetime: &chrono::DateTime<chrono::Utc>) -> String {
-    datetime.format("%Y-%m-%d %H:%M").to_string()
+/// Distinct values in first-seen order, always including `selected` so an active filter stays
+/// visible in its dropdown.
+fn distinct<'a>(
+    values: impl Iterator<Item = &'a String>,
+    selected: &'a Option<String>,
+) -> Vec<String> {
+    let mut distinct: Vec<String> = Vec::new();
+    for value in values.chain(selected.iter()) {
+        if !distinct.contains(value) {
+            distinct.push(value.clone());
+        }
+    }
+    distinct
+}
+
+/// An email with `subject` and nothing else of note, for tests to adjust.
+#[cfg(test)]
+pub(crate) fn test_email(subject: &str) -> Email {
+    Email {
+        id: Uuid::new_v4(),
+        from: "sender@example.com".to_string(),
+        to: "recipient@example.com".to_string(),
+        to_addrs: Vec::new(),
+        cc: Vec::new(),
+        envelope_to: Vec::new(),
+        subject: Some(subject.to_string()),
+        date: None,
+        message_id: None,
+        in_reply_to: None,
+        reply_to: None,
+        headers: remail_types::Headers::new(),
+        tags: Vec::new(),
+        body: String::new(),
+        preview: String::new(),
+        body_bytes: 0,
+        body_lines: 0,
+        attachments: Vec::new(),
+        dkim_result: None,
+        auth_results: Vec::new(),
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    }
 }
 
 #[derive(Debug, Clone, Routable, PartialEq)]
 #[rustfmt::skip]
 enum Route {
-    #[route("/")]
-    Home {},
+    #[route("/?:..filter")]
+    Home { filter: EmailFilter },
+    #[route("/mailbox/:address?:..filter")]
+    Mailbox { address: String, filter: EmailFilter },
+    #[route("/emails/:id")]
+    EmailDetail { id: String },
+}
+
+impl Route {
+    /// The page showing `filter`, which is a recipient's mailbox when it filters by recipient.
+    fn for_filter(mut filter: EmailFilter) -> Self {
+        match filter.to.take() {
+            Some(address) => Route::Mailbox { address, filter },
+            None => Route::Home { filter },
+        }
+    }
 }
 
 const FAVICON: Asset = asset!("/assets/favicon.ico");
@@ -24,96 +134,663 @@ const MAIN_CSS: Asset = asset!("/assets/main.css");
 const TAILWIND_CSS: Asset = asset!("/assets/tailwind.css");
 
 fn main() {
+    // Applied before the first render so a dark theme does not flash light on load.
+    preferences::apply_dark_mode(
+        preferences::load::<Theme>().is_dark(preferences::system_prefers_dark()),
+    );
     dioxus::launch(App);
 }
 
 #[component]
 fn App() -> Element {
+    let theme = use_preference::<Theme>();
+    let density = use_preference::<Density>();
+    let system_prefers_dark = preferences::use_system_prefers_dark();
+    let dark_mode = use_memo(move || theme().is_dark(system_prefers_dark()));
+    use_context_provider(|| theme);
+    use_context_provider(|| density);
+    use_context_provider(|| dark_mode);
+    let read_emails = use_signal(read::load);
+    use_effect(move || read::save(&read_emails.read()));
+    use_context_provider(|| read_emails);
+    let toasts = use_signal(Vec::new);
+    use_context_provider(|| ToastHandle::new(toasts));
+    use_effect(move || preferences::apply_dark_mode(dark_mode()));
+
     rsx! {
         document::Link { rel: "icon", href: FAVICON }
         document::Link { rel: "stylesheet", href: MAIN_CSS } document::Link { rel: "stylesheet", href: TAILWIND_CSS }
         Router::<Route> {}
+        ToastOverlay {}
+    }
+}
+
+/// The emails sent to one recipient address.
+#[component]
+fn Mailbox(address: String, filter: EmailFilter) -> Element {
+    rsx! {
+        Home {
+            filter: EmailFilter {
+                to: Some(address),
+                ..filter
+            },
+        }
     }
 }
 
 /// Home page
 #[component]
-fn Home() -> Element {
+fn Home(filter: EmailFilter) -> Element {
     let emails = use_signal(Vec::<Email>::new);
-    let loading = use_signal(|| false);
-    let error = use_signal(|| Option::<String>::None);
+    let retry = use_signal(AutoRetry::default);
+    let skeleton_fading = use_signal(|| false);
+    let mut loading_more = use_signal(|| false);
+    let pagination = use_signal(Pagination::default);
+    let mut current_filter = use_signal(|| filter.clone());
+    let mut paused = use_signal(|| false);
+    let mut pending = use_signal(Vec::<Email>::new);
+    let mut highlighted = use_signal(HashSet::<Uuid>::new);
+    let mut selected = use_signal(HashSet::<Uuid>::new);
+    let mut focused = use_signal(|| Option::<Uuid>::None);
+    let mut toasts = use_toast();
+    let mut confirming_delete = use_signal(|| Option::<HashSet<Uuid>>::None);
+    let mut show_settings = use_signal(|| false);
+    let mut now = use_signal(chrono::Utc::now);
+    let mut recipients = use_signal(Vec::<RecipientCount>::new);
+    let mut live_status = use_signal(|| LiveStatus::Connecting);
+    let mut notice = use_signal(|| Option::<String>::None);
+    let mut notice_debouncer = use_signal(Debouncer::default);
+    let density: Signal<Density> = use_context();
+    let mut read_emails: Signal<HashSet<Uuid>> = use_context();
+    let mut selected_index = use_signal(|| Option::<usize>::None);
+    let mut show_shortcuts = use_signal(|| false);
+    let mut show_compose = use_signal(|| false);
+    let navigator = navigator();
+
+    use_future(move || async move {
+        loop {
+            sleep(CLOCK_TICK).await;
+            now.set(chrono::Utc::now());
+        }
+    });
+
+    let refresh_recipients = move || {
+        spawn(async move {
+            match ApiClient::new().list_recipients().await {
+                Ok(latest) => recipients.set(latest),
+                Err(e) => dioxus::logger::tracing::warn!("Failed to load recipients: {e}"),
+            }
+        });
+    };
+
+    use_hook(refresh_recipients);
+
+    // Loads the first page for `filter`, retrying with backoff until it succeeds or a newer
+    // load supersedes it.
+    let load = move |filter: EmailFilter| {
+        let mut emails = emails;
+        let mut retry = retry;
+        let mut pagination = pagination;
+        let mut skeleton_fading = skeleton_fading;
+        spawn(async move {
+            let mut generation = retry.write().restart();
+            loop {
+                let started = chrono::Utc::now();
+                let client = ApiClient::new();
+                let result = match filter.q.as_deref() {
+                    Some(query) => client.search_emails(query).await.map(|emails| {
+                        // Search results arrive in one go; the remaining filters narrow them down.
+                        let emails: Vec<Email> = emails
+                            .into_iter()
+                            .filter(|email| filter.matches(email))
+                            .collect();
+                        EmailPage {
+                            total: emails.len() as u64,
+                            emails,
+                            next_cursor: None,
+                        }
+                    }),
+                    None => client.list_emails(&filter, None).await,
+                };
+                match result {
+                    Ok(page) => {
+                        let shown = (chrono::Utc::now() - started).to_std().unwrap_or_default();
+                        sleep(MIN_SKELETON_DURATION.saturating_sub(shown)).await;
+                        if !retry.peek().is_current(generation) {
+                            return;
+                        }
+                        skeleton_fading.set(true);
+                        sleep(SKELETON_FADE_DURATION).await;
+                        skeleton_fading.set(false);
+                        if retry.write().succeed(generation) {
+                            pagination.set(Pagination::first_page(&mut emails.write(), page));
+                        }
+                        return;
+                    }
+                    Err(e) => {
+                        let error = format!("Failed to load emails: {e}");
+                        let Some(delay) = retry.write().fail(generation, error) else {
+                            return;
+                        };
+                        sleep(delay).await;
+                        if !retry.peek().is_current(generation) {
+                            return;
+                        }
+                        generation = retry.write().start();
+                    }
+                }
+            }
+        });
+    };
 
-    use_effect(move || {
+    use_effect(use_reactive((&filter,), move |(filter,)| {
+        current_filter.set(filter.clone());
+        pending.write().clear();
+        selected_index.set(None);
+        load(filter);
+    }));
+
+    let mut show_new = move |incoming: Vec<Email>| {
         let mut emails = emails;
-        let mut loading = loading;
-        let mut error = error;
+        let mut pagination = pagination;
+        let added = live::prepend_new(&mut emails.write(), incoming);
+        pagination.write().adjust_total(added.len(), 0);
+        highlighted.write().extend(added.iter().copied());
+        spawn(async move {
+            sleep(HIGHLIGHT_DURATION).await;
+            highlighted.write().retain(|id| !added.contains(id));
+        });
+    };
+
+    let mut receive = move |incoming: Vec<Email>| {
+        let filter = current_filter.peek().clone();
+        let incoming: Vec<Email> = incoming
+            .into_iter()
+            .filter(|email| filter.matches(email))
+            .collect();
+        if *paused.peek() {
+            live::prepend_new(&mut pending.write(), incoming);
+        } else {
+            show_new(incoming);
+        }
+    };
 
+    let mut announce = move |email: &Email| {
+        notice.set(Some(live::new_email_notice(email)));
+        // A later notice replaces this one and is the one to clear it.
+        let generation = notice_debouncer.write().bump();
         spawn(async move {
-            loading.set(true);
-            error.set(None);
+            sleep(NOTICE_DURATION).await;
+            if notice_debouncer.peek().is_current(generation) {
+                notice.set(None);
+            }
+        });
+    };
+
+    use_future(move || async move {
+        let client = ApiClient::new();
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(30));
+        loop {
+            live_status.set(LiveStatus::Connecting);
+            match client.subscribe() {
+                Ok(mut subscription) => {
+                    live_status.set(LiveStatus::Live);
+                    while let Some(email) = subscription.next().await {
+                        backoff.reset();
+                        mailbox::record(&mut recipients.write(), &email);
+                        announce(&email);
+                        receive(vec![email]);
+                    }
+                    live_status.set(LiveStatus::Connecting);
+                    sleep(backoff.next_delay()).await;
+                }
+                Err(_) => {
+                    live_status.set(LiveStatus::Polling);
+                    sleep(POLL_INTERVAL).await;
+                    let filter = current_filter.peek().clone();
+                    if let Ok(latest) = client.list_emails(&filter, None).await {
+                        receive(latest.emails);
+                    }
+                    refresh_recipients();
+                }
+            }
+        }
+    });
+
+    let load_more = move |_: MouseEvent| {
+        let mut emails = emails;
+        let mut pagination = pagination;
+        spawn(async move {
+            let Some(cursor) = pagination.peek().next_cursor.clone() else {
+                return;
+            };
+            let filter = current_filter.peek().clone();
+
+            loading_more.set(true);
+            match ApiClient::new().list_emails(&filter, Some(&cursor)).await {
+                // A page for a filter that has since changed belongs to a stale listing.
+                Ok(page) if *current_filter.peek() == filter => {
+                    pagination.write().append(&mut emails.write(), page);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    loading_more.set(false);
+                    toasts.error(format!("Failed to load more emails: {e}"));
+                    return;
+                }
+            }
+            loading_more.set(false);
+        });
+    };
+
+    // Asks for confirmation first; the deletion itself happens in `confirm_delete`.
+    let mut delete = move |ids: HashSet<Uuid>| {
+        if !ids.is_empty() {
+            confirming_delete.set(Some(ids));
+        }
+    };
+
+    let mut confirm_delete = move |ids: HashSet<Uuid>| {
+        let mut emails = emails;
+        let mut pagination = pagination;
+        confirming_delete.set(None);
+        spawn(async move {
+            let removed = optimistic::remove(&mut emails.write(), &ids);
+            selected.write().retain(|id| !ids.contains(id));
 
             let client = ApiClient::new();
-            match client.list_emails().await {
-                Ok(emails_data) => {
-                    emails.set(emails_data);
+            let ids: Vec<Uuid> = ids.into_iter().collect();
+            let result = match ids.as_slice() {
+                [id] => client.delete_email(*id).await,
+                ids => client.delete_emails(ids).await.map(|_| ()),
+            };
+
+            match result {
+                Ok(()) => {
+                    pagination.write().adjust_total(0, ids.len());
+                    refresh_recipients();
+                    toasts.success(match ids.len() {
+                        1 => "Email deleted".to_string(),
+                        count => format!("{count} emails deleted"),
+                    });
                 }
                 Err(e) => {
-                    error.set(Some(format!("Failed to load emails: {e}")));
+                    optimistic::restore(&mut emails.write(), removed);
+                    toasts.error(format!("Failed to delete: {e}"));
                 }
             }
-            loading.set(false);
         });
+    };
+
+    let mut on_shortcut = move |shortcut: Shortcut| {
+        let current = selected_index
+            .peek()
+            .and_then(|index| emails.peek().get(index).map(|email| email.id));
+        match shortcut {
+            Shortcut::Down | Shortcut::Up => {
+                let step = if shortcut == Shortcut::Down { 1 } else { -1 };
+                let index =
+                    shortcuts::move_selection(*selected_index.peek(), emails.peek().len(), step);
+                selected_index.set(index);
+                if let Some(index) = index {
+                    scroll_into_view(EMAIL_LIST_ID, index, density.peek().row_height());
+                }
+            }
+            Shortcut::Open => {
+                if let Some(id) = current {
+                    read_emails.write().insert(id);
+                    navigator.push(Route::EmailDetail { id: id.to_string() });
+                }
+            }
+            Shortcut::Delete => {
+                if let Some(id) = current {
+                    delete(HashSet::from([id]));
+                }
+            }
+            Shortcut::MarkRead => {
+                if let Some(id) = current {
+                    read_emails.write().insert(id);
+                }
+            }
+            Shortcut::MarkUnread => {
+                if let Some(id) = current {
+                    read_emails.write().remove(&id);
+                }
+            }
+            Shortcut::Search => {
+                document::eval(&format!(
+                    "document.getElementById({})?.focus();",
+                    serde_json::to_string(SEARCH_INPUT_ID).unwrap_or_default()
+                ));
+            }
+            Shortcut::Help => show_shortcuts.set(true),
+        }
+    };
+
+    use_future(move || async move {
+        let keys: Vec<&str> = Shortcut::ALL
+            .iter()
+            .flat_map(|shortcut| shortcut.keys())
+            .copied()
+            .collect();
+        let mut presses = document::eval(&format!(
+            "const keys = {};
+            window.remailShortcuts = (event) => {{
+                if (event.ctrlKey || event.metaKey || event.altKey || !keys.includes(event.key)) return;
+                // Typing in a form field, or pressing Enter on a link or button, is not a shortcut.
+                if (event.target.closest('input, textarea, select, [contenteditable]')) return;
+                if (event.key === 'Enter' && event.target.closest('a, button')) return;
+                event.preventDefault();
+                dioxus.send(event.key);
+            }};
+            document.addEventListener('keydown', window.remailShortcuts);",
+            serde_json::to_string(&keys).unwrap_or_default()
+        ));
+        while let Ok(key) = presses.recv::<String>().await {
+            if let Some(shortcut) = Shortcut::from_key(&key) {
+                on_shortcut(shortcut);
+            }
+        }
     });
 
+    use_drop(|| {
+        document::eval(
+            "document.removeEventListener('keydown', window.remailShortcuts); delete window.remailShortcuts;",
+        );
+    });
+
+    let senders = distinct(emails().iter().map(|email| &email.from), &filter.from);
+    let to_addresses = distinct(emails().iter().map(|email| &email.to), &filter.to);
+
     rsx! {
         div {
             class: "container mx-auto px-4 py-8",
-            h1 {
-                class: "text-3xl font-bold mb-8",
-                "Email List"
+            div {
+                class: "flex justify-between items-center mb-8",
+                h1 {
+                    class: "text-3xl font-bold",
+                    "Email List"
+                }
+                div {
+                    class: "flex items-center gap-4",
+                    DisplayToggles {}
+                    button {
+                        class: "text-sm text-blue-600 dark:text-blue-400 hover:underline",
+                        onclick: move |_| show_settings.toggle(),
+                        "Settings"
+                    }
+                }
             }
 
-            if loading() {
-                div {
-                    class: "text-center py-8",
-                    "Loading emails..."
+            if show_settings() {
+                SettingsPanel {
+                    on_close: move |_| show_settings.set(false),
                 }
-            } else if let Some(err) = error() {
-                div {
-                    class: "bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded mb-4",
-                    "Error: {err}"
+            }
+
+            MailboxTabs {
+                recipients: recipients(),
+                selected: filter.to.clone(),
+                on_select: {
+                    let filter = filter.clone();
+                    move |to| {
+                        navigator.push(Route::for_filter(EmailFilter {
+                            to,
+                            ..filter.clone()
+                        }));
+                    }
+                },
+            }
+
+            FilterBar {
+                filter: filter.clone(),
+                senders,
+                recipients: to_addresses,
+                result_count: pagination.read().total,
+                on_change: move |filter| {
+                    navigator.replace(Route::for_filter(filter));
+                },
+            }
+
+            div {
+                class: "flex justify-end gap-4 mb-4",
+                span {
+                    class: "mr-auto text-sm text-gray-500 dark:text-gray-400",
+                    "Showing {emails.read().len()} of {pagination.read().total} emails"
                 }
-            } else {
-                div {
-                    class: "space-y-4",
-                    for email in emails().iter() {
-                        div {
-                            class: "bg-white border border-gray-200 rounded-lg p-6 shadow-sm",
-                            div {
-                                class: "flex justify-between items-start mb-2",
-                                h2 {
-                                    class: "text-xl font-semibold text-gray-900",
-                                    "{format_subject(&email.subject)}"
-                                }
-                                span {
-                                    class: "text-sm text-gray-500",
-                                    "{format_date(&email.created_at)}"
+                if !selected.read().is_empty() {
+                    button {
+                        class: "text-sm text-red-600 hover:underline",
+                        onclick: move |_| delete(selected()),
+                        "Delete selected ({selected.read().len()})"
+                    }
+                }
+                if !emails.read().is_empty() {
+                    button {
+                        class: "text-sm text-red-600 hover:underline",
+                        onclick: move |_| {
+                            delete(emails.read().iter().map(|email| email.id).collect());
+                        },
+                        "Delete all"
+                    }
+                }
+                button {
+                    class: "text-sm text-blue-600 dark:text-blue-400 hover:underline",
+                    title: "Keyboard shortcuts (?)",
+                    onclick: move |_| show_shortcuts.set(true),
+                    "Shortcuts"
+                }
+                label {
+                    class: "text-sm text-blue-600 dark:text-blue-400 hover:underline cursor-pointer",
+                    "Import .eml"
+                    input {
+                        r#type: "file",
+                        accept: ".eml,message/rfc822",
+                        class: "hidden",
+                        onchange: move |event| async move {
+                            let Some(files) = event.files() else {
+                                return;
+                            };
+                            for name in files.files() {
+                                let Some(bytes) = files.read_file(&name).await else {
+                                    continue;
+                                };
+                                match ApiClient::new().import_eml(&bytes).await {
+                                    Ok(email) => {
+                                        mailbox::record(&mut recipients.write(), &email);
+                                        if current_filter.peek().matches(&email) {
+                                            show_new(vec![email]);
+                                        }
+                                        toasts.success(format!("Imported {name}"));
+                                    }
+                                    Err(e) => toasts.error(format!("Failed to import {name}: {e}")),
                                 }
                             }
-                            div {
-                                class: "text-sm text-gray-600 mb-2",
-                                "From: {email.from}"
-                            }
-                            div {
-                                class: "text-sm text-gray-600 mb-3",
-                                "To: {email.to}"
+                        },
+                    }
+                }
+                span {
+                    class: "text-sm text-gray-500 dark:text-gray-400",
+                    "{live_status().label()}"
+                }
+                button {
+                    class: "text-sm text-blue-600 dark:text-blue-400 hover:underline",
+                    onclick: move |_| {
+                        if paused() {
+                            paused.set(false);
+                            let incoming = std::mem::take(&mut *pending.write());
+                            show_new(incoming);
+                        } else {
+                            paused.set(true);
+                        }
+                    },
+                    if paused() {
+                        "Resume live updates ({pending.read().len()} new)"
+                    } else {
+                        "Pause live updates"
+                    }
+                }
+            }
+
+            match retry.read().state().clone() {
+                LoadState::Loading => rsx! {
+                    div {
+                        class: "space-y-4",
+                        "aria-busy": "true",
+                        "aria-label": "Loading emails",
+                        for index in 0..SKELETON_ROWS {
+                            SkeletonCard { key: "{index}", fading: skeleton_fading() }
+                        }
+                    }
+                },
+                LoadState::Failed { error, attempts, retry_in } => rsx! {
+                    div {
+                        class: "bg-red-100 dark:bg-red-900 border border-red-400 text-red-700 dark:text-red-200 px-4 py-3 rounded mb-4",
+                        div { class: "font-medium", "Error: {error}" }
+                        div {
+                            class: "text-sm mt-1",
+                            "Attempt {attempts} failed, retrying in {retry_in.as_secs()}s. API: {config::api_base_url()}"
+                        }
+                        button {
+                            class: "mt-3 px-3 py-1 text-sm text-white bg-red-600 rounded hover:bg-red-700",
+                            onclick: move |_| load(current_filter.peek().clone()),
+                            "Retry now"
+                        }
+                    }
+                },
+                LoadState::Loaded if emails.read().is_empty() => rsx! {
+                    if let Some(query) = &filter.q {
+                        EmptyState {
+                            title: format!("No results for \"{query}\""),
+                            subtitle: "Try a different search or clear the filters".to_string(),
+                        }
+                    } else if filter.is_empty() {
+                        EmptyState {
+                            title: "No emails yet".to_string(),
+                            subtitle: "Emails you receive will appear here".to_string(),
+                            action: rsx! {
+                                button {
+                                    class: "px-4 py-2 text-sm text-white bg-blue-600 rounded hover:bg-blue-700",
+                                    onclick: move |_| show_compose.set(true),
+                                    "Send a test email"
+                                }
+                            },
+                        }
+                    } else {
+                        EmptyState {
+                            title: "No emails match these filters".to_string(),
+                            subtitle: "Try removing some of them".to_string(),
+                        }
+                    }
+                    p {
+                        class: "text-center text-xs text-gray-600 dark:text-gray-400",
+                        "SMTP: "
+                        code { class: "font-mono", "{config::smtp_address()}" }
+                        " · API: {config::api_base_url()}"
+                    }
+                },
+                LoadState::Loaded => rsx! {
+                    div {
+                        onkeydown: move |event| {
+                            if event.key() == Key::Delete {
+                                let ids = if selected.read().is_empty() {
+                                    focused().into_iter().collect()
+                                } else {
+                                    selected()
+                                };
+                                delete(ids);
                             }
-                            div {
-                                class: "text-gray-700 line-clamp-3",
-                                "{email.body}"
+                        },
+                        VirtualList {
+                            id: EMAIL_LIST_ID,
+                            items: emails(),
+                            item_height: density().row_height(),
+                            render: move |email: Email| {
+                                let active = selected_index()
+                                    .and_then(|index| emails.read().get(index).map(|email| email.id))
+                                    == Some(email.id);
+                                rsx! {
+                                    div {
+                                        class: "pb-4 h-full",
+                                        EmailCard {
+                                            highlighted: highlighted.read().contains(&email.id),
+                                            selected: selected.read().contains(&email.id),
+                                            active,
+                                            read: read_emails.read().contains(&email.id),
+                                            now: now(),
+                                            on_select: move |checked| {
+                                                if checked {
+                                                    selected.write().insert(email.id);
+                                                } else {
+                                                    selected.write().remove(&email.id);
+                                                }
+                                            },
+                                            on_delete: move |_| delete(HashSet::from([email.id])),
+                                            on_focus: move |_| focused.set(Some(email.id)),
+                                            email,
+                                        }
+                                    }
+                                }
+                            },
+                        }
+                    }
+
+                    if pagination.read().has_more() {
+                        div {
+                            class: "text-center mt-6",
+                            button {
+                                class: "px-4 py-2 text-sm text-blue-600 dark:text-blue-400 border border-blue-600 rounded hover:bg-blue-50 dark:hover:bg-blue-900 disabled:opacity-50",
+                                disabled: loading_more(),
+                                onclick: load_more,
+                                if loading_more() {
+                                    "Loading..."
+                                } else {
+                                    "Load more"
+                                }
                             }
                         }
+                    } else if !emails.read().is_empty() {
+                        div {
+                            class: "text-center text-sm text-gray-500 dark:text-gray-400 mt-6",
+                            "All emails loaded"
+                        }
                     }
+                },
+            }
+
+            button {
+                class: "fixed bottom-4 left-4 px-3 py-2 text-sm bg-white dark:bg-gray-800 border border-gray-300 dark:border-gray-600 rounded shadow",
+                title: "Scroll to top",
+                onclick: move |_| {
+                    document::eval("window.scrollTo({ top: 0, behavior: 'smooth' });");
+                },
+                "↑ Top"
+            }
+
+            if let Some(message) = notice() {
+                div {
+                    class: "fixed bottom-20 right-4 bg-blue-600 text-white px-4 py-3 rounded shadow-lg",
+                    "{message}"
+                }
+            }
+
+            if show_compose() {
+                ComposeModal { on_close: move |_| show_compose.set(false) }
+            }
+
+            if show_shortcuts() {
+                ShortcutsHelp { on_close: move |_| show_shortcuts.set(false) }
+            }
+
+            if let Some(ids) = confirming_delete() {
+                ConfirmModal {
+                    message: if ids.len() == 1 {
+                        "Delete this email?".to_string()
+                    } else {
+                        format!("Delete {} emails?", ids.len())
+                    },
+                    on_confirm: move |_| confirm_delete(ids.clone()),
+                    on_cancel: move |_| confirming_delete.set(None),
                 }
             }
         }
@@ -0,0 +1,87 @@
+use remail_types::Email;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Emails removed from a list ahead of the server confirming it, remembered with their
+/// positions so a failed request can put them back where they were.
+#[derive(Debug, Default)]
+pub struct Removed(Vec<(usize, Email)>);
+
+pub fn remove(emails: &mut Vec<Email>, ids: &HashSet<Uuid>) -> Removed {
+    let mut removed = Vec::new();
+    let mut index = 0;
+    emails.retain(|email| {
+        let keep = !ids.contains(&email.id);
+        if !keep {
+            removed.push((index, email.clone()));
+        }
+        index += 1;
+        keep
+    });
+    Removed(removed)
+}
+
+pub fn restore(emails: &mut Vec<Email>, removed: Removed) {
+    // Positions are ascending, so inserting in order rebuilds the original list.
+    for (index, email) in removed.0 {
+        if emails.iter().any(|e| e.id == email.id) {
+            continue;
+        }
+        emails.insert(index.min(emails.len()), email);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_email;
+
+    fn subjects(emails: &[Email]) -> Vec<&str> {
+        emails
+            .iter()
+            .map(|email| email.subject.as_deref().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_remove_and_rollback() {
+        let original = vec![
+            test_email("a"),
+            test_email("b"),
+            test_email("c"),
+            test_email("d"),
+        ];
+        let mut emails = original.clone();
+
+        let ids = HashSet::from([original[1].id, original[3].id]);
+        let removed = remove(&mut emails, &ids);
+        assert_eq!(subjects(&emails), vec!["a", "c"]);
+
+        restore(&mut emails, removed);
+        assert_eq!(subjects(&emails), vec!["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_rollback_after_list_changed() {
+        let original = vec![test_email("a"), test_email("b"), test_email("c")];
+        let mut emails = original.clone();
+
+        let removed = remove(&mut emails, &HashSet::from([original[2].id]));
+        emails.remove(0);
+        restore(&mut emails, removed);
+
+        assert_eq!(subjects(&emails), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_rollback_skips_emails_already_back() {
+        let original = vec![test_email("a"), test_email("b")];
+        let mut emails = original.clone();
+
+        let removed = remove(&mut emails, &HashSet::from([original[0].id]));
+        emails.insert(0, original[0].clone());
+        restore(&mut emails, removed);
+
+        assert_eq!(subjects(&emails), vec!["a", "b"]);
+    }
+}
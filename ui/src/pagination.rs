@@ -0,0 +1,105 @@
+use remail_types::{Email, EmailPage};
+
+/// Where the loaded list ends and how many emails match the current filter.
+///
+/// The API pages by a keyset cursor rather than an offset, so emails prepended from the live
+/// stream do not shift the pages that are still to be loaded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pagination {
+    pub next_cursor: Option<String>,
+    pub total: u64,
+}
+
+impl Pagination {
+    /// Replaces `emails` with the first page of a new listing.
+    pub fn first_page(emails: &mut Vec<Email>, page: EmailPage) -> Self {
+        *emails = page.emails;
+        Self {
+            next_cursor: page.next_cursor,
+            total: page.total,
+        }
+    }
+
+    /// Appends a following page, skipping emails that are already listed because they were
+    /// prepended from the live stream before their page was loaded.
+    pub fn append(&mut self, emails: &mut Vec<Email>, page: EmailPage) {
+        for email in page.emails {
+            if !emails.iter().any(|e| e.id == email.id) {
+                emails.push(email);
+            }
+        }
+        self.next_cursor = page.next_cursor;
+        self.total = page.total;
+    }
+
+    /// Accounts for emails added to or removed from the top of the list since the last page.
+    pub fn adjust_total(&mut self, added: usize, removed: usize) {
+        self.total = (self.total + added as u64).saturating_sub(removed as u64);
+    }
+
+    pub fn has_more(&self) -> bool {
+        self.next_cursor.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_email;
+
+    fn page(emails: Vec<Email>, next_cursor: Option<&str>, total: u64) -> EmailPage {
+        EmailPage {
+            emails,
+            next_cursor: next_cursor.map(str::to_string),
+            total,
+        }
+    }
+
+    fn subjects(emails: &[Email]) -> Vec<&str> {
+        emails
+            .iter()
+            .map(|email| email.subject.as_deref().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_cursor_bookkeeping() {
+        let mut emails = vec![test_email("stale")];
+
+        let mut pagination = Pagination::first_page(
+            &mut emails,
+            page(vec![test_email("a"), test_email("b")], Some("1"), 3),
+        );
+        assert_eq!(subjects(&emails), vec!["a", "b"]);
+        assert_eq!(pagination.next_cursor.as_deref(), Some("1"));
+        assert!(pagination.has_more());
+
+        pagination.append(&mut emails, page(vec![test_email("c")], None, 3));
+        assert_eq!(subjects(&emails), vec!["a", "b", "c"]);
+        assert_eq!(pagination.total, 3);
+        assert!(!pagination.has_more());
+    }
+
+    #[test]
+    fn test_append_skips_prepended_emails() {
+        let live = test_email("live");
+        let mut emails = Vec::new();
+        let mut pagination =
+            Pagination::first_page(&mut emails, page(vec![test_email("a")], Some("1"), 2));
+
+        emails.insert(0, live.clone());
+        pagination.adjust_total(1, 0);
+        assert_eq!(pagination.total, 3);
+
+        pagination.append(&mut emails, page(vec![live, test_email("b")], None, 3));
+        assert_eq!(subjects(&emails), vec!["live", "a", "b"]);
+        assert_eq!(pagination.total, 3);
+    }
+
+    #[test]
+    fn test_adjust_total_saturates() {
+        let mut pagination = Pagination::default();
+        pagination.adjust_total(0, 2);
+        assert_eq!(pagination.total, 0);
+    }
+}
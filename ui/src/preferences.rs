@@ -0,0 +1,238 @@
+use crate::config;
+use dioxus::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+
+/// A display setting remembered across visits under its own storage key.
+pub trait Preference: Copy + Default + PartialEq + FromStr + fmt::Display + 'static {
+    const STORAGE_KEY: &'static str;
+}
+
+/// The saved value of `T`, falling back to its default when nothing valid was saved.
+pub fn load<T: Preference>() -> T {
+    parse(config::load_preference(T::STORAGE_KEY).as_deref())
+}
+
+fn parse<T: Preference>(stored: Option<&str>) -> T {
+    stored
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_default()
+}
+
+/// A signal holding the preference `T`, initialized from storage and saved whenever it changes.
+pub fn use_preference<T: Preference>() -> Signal<T> {
+    let preference = use_signal(load::<T>);
+    use_effect(move || config::save_preference(T::STORAGE_KEY, &preference().to_string()));
+    preference
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Theme {
+    Light,
+    Dark,
+    /// Follows the operating system's color scheme.
+    #[default]
+    System,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Light, Theme::Dark, Theme::System];
+
+    pub fn is_dark(self, system_prefers_dark: bool) -> bool {
+        match self {
+            Theme::Light => false,
+            Theme::Dark => true,
+            Theme::System => system_prefers_dark,
+        }
+    }
+
+    /// The explicit theme that flips the current appearance, overriding the system's.
+    pub fn overriding(currently_dark: bool) -> Self {
+        if currently_dark {
+            Theme::Light
+        } else {
+            Theme::Dark
+        }
+    }
+}
+
+impl Preference for Theme {
+    const STORAGE_KEY: &'static str = "remail_theme";
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::System => "system",
+        })
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "light" => Ok(Theme::Light),
+            "dark" => Ok(Theme::Dark),
+            "system" => Ok(Theme::System),
+            other => Err(format!("unknown theme {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Density {
+    #[default]
+    Comfortable,
+    Compact,
+}
+
+impl Density {
+    pub fn toggled(self) -> Self {
+        match self {
+            Density::Comfortable => Density::Compact,
+            Density::Compact => Density::Comfortable,
+        }
+    }
+
+    /// Padding of each row in the email list.
+    pub fn row_padding(self) -> &'static str {
+        match self {
+            Density::Comfortable => "p-6",
+            Density::Compact => "px-4 py-2",
+        }
+    }
+
+    /// The height in pixels each row takes in the email list, including the gap below it.
+    pub fn row_height(self) -> f64 {
+        match self {
+            Density::Comfortable => 232.0,
+            Density::Compact => 128.0,
+        }
+    }
+
+    /// How many lines of the body preview each row shows.
+    pub fn body_clamp(self) -> &'static str {
+        match self {
+            Density::Comfortable => "line-clamp-3",
+            Density::Compact => "line-clamp-1",
+        }
+    }
+}
+
+impl Preference for Density {
+    const STORAGE_KEY: &'static str = "remail_density";
+}
+
+impl fmt::Display for Density {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Density::Comfortable => "comfortable",
+            Density::Compact => "compact",
+        })
+    }
+}
+
+impl FromStr for Density {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "comfortable" => Ok(Density::Comfortable),
+            "compact" => Ok(Density::Compact),
+            other => Err(format!("unknown density {other}")),
+        }
+    }
+}
+
+/// The media query matching when the operating system uses a dark color scheme.
+const DARK_SCHEME_QUERY: &str = "(prefers-color-scheme: dark)";
+
+#[cfg(target_arch = "wasm32")]
+pub fn system_prefers_dark() -> bool {
+    web_sys::window()
+        .and_then(|window| window.match_media(DARK_SCHEME_QUERY).ok().flatten())
+        .is_some_and(|query| query.matches())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn system_prefers_dark() -> bool {
+    false
+}
+
+/// A signal tracking [`system_prefers_dark`], updated when the operating system's color
+/// scheme changes while the page is open.
+pub fn use_system_prefers_dark() -> Signal<bool> {
+    let mut prefers_dark = use_signal(system_prefers_dark);
+    use_future(move || async move {
+        let mut changes = document::eval(&format!(
+            "window.matchMedia({}).addEventListener('change', (event) => dioxus.send(event.matches));",
+            serde_json::to_string(DARK_SCHEME_QUERY).unwrap_or_default()
+        ));
+        while let Ok(matches) = changes.recv::<bool>().await {
+            prefers_dark.set(matches);
+        }
+    });
+    prefers_dark
+}
+
+/// Toggles the `dark` class on the root element, which Tailwind's `dark:` variants key off.
+#[cfg(target_arch = "wasm32")]
+pub fn apply_dark_mode(dark: bool) {
+    let root = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.document_element());
+    if let Some(root) = root
+        && root.class_list().toggle_with_force("dark", dark).is_err()
+    {
+        dioxus::logger::tracing::warn!("Failed to apply dark mode");
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn apply_dark_mode(_dark: bool) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for theme in Theme::ALL {
+            assert_eq!(parse::<Theme>(Some(&theme.to_string())), theme);
+        }
+        for density in [Density::Comfortable, Density::Compact] {
+            assert_eq!(parse::<Density>(Some(&density.to_string())), density);
+        }
+    }
+
+    #[test]
+    fn test_missing_or_unknown_values_use_defaults() {
+        assert_eq!(parse::<Theme>(None), Theme::System);
+        assert_eq!(parse::<Theme>(Some("sepia")), Theme::System);
+        assert_eq!(parse::<Density>(Some("")), Density::Comfortable);
+    }
+
+    #[test]
+    fn test_system_theme_follows_system_preference() {
+        assert!(Theme::System.is_dark(true));
+        assert!(!Theme::System.is_dark(false));
+        assert!(Theme::Dark.is_dark(false));
+        assert!(!Theme::Light.is_dark(true));
+    }
+
+    #[test]
+    fn test_theme_override() {
+        assert_eq!(Theme::overriding(true), Theme::Light);
+        assert_eq!(Theme::overriding(false), Theme::Dark);
+    }
+
+    #[test]
+    fn test_density_toggles() {
+        assert_eq!(Density::Comfortable.toggled(), Density::Compact);
+        assert_eq!(Density::Compact.toggled(), Density::Comfortable);
+    }
+}
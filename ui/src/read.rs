@@ -0,0 +1,52 @@
+use crate::config;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Where the ids of read emails are remembered. The server has no read state, so this is
+/// per browser.
+const STORAGE_KEY: &str = "remail_read";
+
+/// The ids of the emails marked as read.
+pub fn load() -> HashSet<Uuid> {
+    parse(config::load_preference(STORAGE_KEY).as_deref())
+}
+
+pub fn save(read: &HashSet<Uuid>) {
+    config::save_preference(STORAGE_KEY, &serialize(read));
+}
+
+fn parse(stored: Option<&str>) -> HashSet<Uuid> {
+    stored
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|id| id.trim().parse().ok())
+        .collect()
+}
+
+fn serialize(read: &HashSet<Uuid>) -> String {
+    read.iter()
+        .map(Uuid::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let read: HashSet<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        assert_eq!(parse(Some(&serialize(&read))), read);
+    }
+
+    #[test]
+    fn test_parse_skips_invalid_ids() {
+        let id = Uuid::new_v4();
+        assert_eq!(
+            parse(Some(&format!("{id},not-an-id,"))),
+            HashSet::from([id])
+        );
+        assert!(parse(None).is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::backoff::Backoff;
+use std::time::Duration;
+
+/// The longest wait between two automatic attempts to load the inbox.
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    /// The last attempt failed; the next one starts automatically after `retry_in`.
+    Failed {
+        error: String,
+        attempts: u32,
+        retry_in: Duration,
+    },
+}
+
+/// Tracks loading the inbox, retrying failed attempts with exponential backoff until one
+/// succeeds.
+///
+/// Every attempt gets a generation; results and retry timers from an attempt that has since
+/// been superseded, by a manual retry or a new filter, are ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoRetry {
+    state: LoadState,
+    backoff: Backoff,
+    attempts: u32,
+    generation: u64,
+}
+
+impl Default for AutoRetry {
+    fn default() -> Self {
+        Self {
+            state: LoadState::Loading,
+            backoff: Backoff::new(Duration::from_secs(1), MAX_RETRY_DELAY),
+            attempts: 0,
+            generation: 0,
+        }
+    }
+}
+
+impl AutoRetry {
+    pub fn state(&self) -> &LoadState {
+        &self.state
+    }
+
+    /// Starts a fresh round of attempts, forgetting earlier failures.
+    pub fn restart(&mut self) -> u64 {
+        self.backoff.reset();
+        self.attempts = 0;
+        self.start()
+    }
+
+    /// Starts the next attempt of the current round.
+    pub fn start(&mut self) -> u64 {
+        self.generation += 1;
+        self.state = LoadState::Loading;
+        self.generation
+    }
+
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.generation == generation
+    }
+
+    /// Records that attempt `generation` succeeded, returning whether it is still current.
+    pub fn succeed(&mut self, generation: u64) -> bool {
+        if !self.is_current(generation) {
+            return false;
+        }
+        self.backoff.reset();
+        self.attempts = 0;
+        self.state = LoadState::Loaded;
+        true
+    }
+
+    /// Records that attempt `generation` failed, returning how long to wait before retrying,
+    /// or `None` if the attempt is no longer current.
+    pub fn fail(&mut self, generation: u64, error: String) -> Option<Duration> {
+        if !self.is_current(generation) {
+            return None;
+        }
+        self.attempts += 1;
+        let retry_in = self.backoff.next_delay();
+        self.state = LoadState::Failed {
+            error,
+            attempts: self.attempts,
+            retry_in,
+        };
+        Some(retry_in)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fail(retry: &mut AutoRetry) -> Option<Duration> {
+        let generation = retry.start();
+        retry.fail(generation, "connection refused".to_string())
+    }
+
+    #[test]
+    fn test_starts_loading() {
+        assert_eq!(AutoRetry::default().state(), &LoadState::Loading);
+    }
+
+    #[test]
+    fn test_backs_off_up_to_max() {
+        let mut retry = AutoRetry::default();
+        let delays: Vec<u64> = (0..7)
+            .map(|_| fail(&mut retry).unwrap().as_secs())
+            .collect();
+
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 30, 30]);
+        assert_eq!(
+            retry.state(),
+            &LoadState::Failed {
+                error: "connection refused".to_string(),
+                attempts: 7,
+                retry_in: MAX_RETRY_DELAY,
+            }
+        );
+    }
+
+    #[test]
+    fn test_success_stops_and_resets() {
+        let mut retry = AutoRetry::default();
+        fail(&mut retry);
+        fail(&mut retry);
+
+        let generation = retry.start();
+        assert!(retry.succeed(generation));
+        assert_eq!(retry.state(), &LoadState::Loaded);
+
+        assert_eq!(fail(&mut retry), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_restart_forgets_failures() {
+        let mut retry = AutoRetry::default();
+        fail(&mut retry);
+        fail(&mut retry);
+
+        let generation = retry.restart();
+        assert_eq!(retry.state(), &LoadState::Loading);
+        assert_eq!(
+            retry.fail(generation, "timeout".to_string()),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_ignores_superseded_attempts() {
+        let mut retry = AutoRetry::default();
+        let stale = retry.start();
+        let current = retry.restart();
+
+        assert!(!retry.is_current(stale));
+        assert_eq!(retry.fail(stale, "late".to_string()), None);
+        assert!(!retry.succeed(stale));
+        assert_eq!(retry.state(), &LoadState::Loading);
+
+        assert!(retry.succeed(current));
+        assert_eq!(retry.state(), &LoadState::Loaded);
+    }
+}
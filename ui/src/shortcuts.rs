@@ -0,0 +1,125 @@
+/// A keyboard shortcut on the email list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shortcut {
+    Down,
+    Up,
+    Open,
+    Delete,
+    MarkRead,
+    MarkUnread,
+    Search,
+    Help,
+}
+
+impl Shortcut {
+    pub const ALL: [Shortcut; 8] = [
+        Shortcut::Down,
+        Shortcut::Up,
+        Shortcut::Open,
+        Shortcut::Delete,
+        Shortcut::MarkRead,
+        Shortcut::MarkUnread,
+        Shortcut::Search,
+        Shortcut::Help,
+    ];
+
+    /// The shortcut bound to `key`, as named by `KeyboardEvent.key`.
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|shortcut| shortcut.keys().contains(&key))
+    }
+
+    pub fn keys(self) -> &'static [&'static str] {
+        match self {
+            Shortcut::Down => &["j", "ArrowDown"],
+            Shortcut::Up => &["k", "ArrowUp"],
+            Shortcut::Open => &["Enter"],
+            Shortcut::Delete => &["d"],
+            Shortcut::MarkRead => &["r"],
+            Shortcut::MarkUnread => &["u"],
+            Shortcut::Search => &["/"],
+            Shortcut::Help => &["?"],
+        }
+    }
+
+    /// The keys as shown in the help, e.g. `j / ↓`.
+    pub fn label(self) -> String {
+        self.keys()
+            .iter()
+            .map(|key| match *key {
+                "ArrowDown" => "↓",
+                "ArrowUp" => "↑",
+                key => key,
+            })
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Shortcut::Down => "Select the next email",
+            Shortcut::Up => "Select the previous email",
+            Shortcut::Open => "Open the selected email",
+            Shortcut::Delete => "Delete the selected email",
+            Shortcut::MarkRead => "Mark the selected email as read",
+            Shortcut::MarkUnread => "Mark the selected email as unread",
+            Shortcut::Search => "Focus the search box",
+            Shortcut::Help => "Show these shortcuts",
+        }
+    }
+}
+
+/// Moves the selection in a list of `len` items by `step`, staying within the list. With no
+/// selection yet, moving down selects the first item and moving up the last.
+pub fn move_selection(current: Option<usize>, len: usize, step: isize) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    Some(match current {
+        Some(index) => index.saturating_add_signed(step).min(len - 1),
+        None if step < 0 => len - 1,
+        None => 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_key() {
+        assert_eq!(Shortcut::from_key("j"), Some(Shortcut::Down));
+        assert_eq!(Shortcut::from_key("ArrowUp"), Some(Shortcut::Up));
+        assert_eq!(Shortcut::from_key("?"), Some(Shortcut::Help));
+        assert_eq!(Shortcut::from_key("J"), None);
+        assert_eq!(Shortcut::from_key("x"), None);
+    }
+
+    #[test]
+    fn test_keys_are_unique() {
+        for shortcut in Shortcut::ALL {
+            for key in shortcut.keys() {
+                assert_eq!(Shortcut::from_key(key), Some(shortcut), "{key}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_label() {
+        assert_eq!(Shortcut::Down.label(), "j / ↓");
+        assert_eq!(Shortcut::Open.label(), "Enter");
+    }
+
+    #[test]
+    fn test_move_selection() {
+        assert_eq!(move_selection(None, 3, 1), Some(0));
+        assert_eq!(move_selection(None, 3, -1), Some(2));
+        assert_eq!(move_selection(Some(0), 3, 1), Some(1));
+        assert_eq!(move_selection(Some(2), 3, 1), Some(2));
+        assert_eq!(move_selection(Some(0), 3, -1), Some(0));
+        // The list may have shrunk since the selection was made.
+        assert_eq!(move_selection(Some(5), 3, -1), Some(2));
+        assert_eq!(move_selection(Some(0), 0, 1), None);
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, Local, TimeDelta, Utc};
+
+/// How long a timestamp keeps a relative label before it is shown as a local date instead.
+const RELATIVE_CUTOFF: TimeDelta = TimeDelta::days(7);
+/// How far in the future a timestamp may be, due to clock skew, and still read as "just now".
+const SKEW_TOLERANCE: TimeDelta = TimeDelta::minutes(1);
+
+/// Describes `datetime` relative to `now`, e.g. "just now", "3m ago" or "2h ago".
+///
+/// Timestamps older than a week, or too far in the future to be explained by clock skew, are
+/// shown as an absolute date in the local timezone.
+pub fn format_relative(datetime: &DateTime<Utc>, now: &DateTime<Utc>) -> String {
+    let elapsed = *now - *datetime;
+    if elapsed < -SKEW_TOLERANCE || elapsed >= RELATIVE_CUTOFF {
+        return format_local(datetime);
+    }
+
+    if elapsed < TimeDelta::minutes(1) {
+        "just now".to_string()
+    } else if elapsed < TimeDelta::hours(1) {
+        format!("{}m ago", elapsed.num_minutes())
+    } else if elapsed < TimeDelta::days(1) {
+        format!("{}h ago", elapsed.num_hours())
+    } else {
+        format!("{}d ago", elapsed.num_days())
+    }
+}
+
+/// The full timestamp, for tooltips.
+pub fn format_exact(datetime: &DateTime<Utc>) -> String {
+    datetime.to_rfc3339()
+}
+
+fn format_local(datetime: &DateTime<Utc>) -> String {
+    datetime
+        .with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2025-08-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn ago(delta: TimeDelta) -> String {
+        format_relative(&(now() - delta), &now())
+    }
+
+    #[test]
+    fn test_under_a_minute_is_just_now() {
+        assert_eq!(ago(TimeDelta::zero()), "just now");
+        assert_eq!(ago(TimeDelta::seconds(59)), "just now");
+    }
+
+    #[test]
+    fn test_minutes() {
+        assert_eq!(ago(TimeDelta::seconds(60)), "1m ago");
+        assert_eq!(ago(TimeDelta::minutes(3)), "3m ago");
+        assert_eq!(ago(TimeDelta::seconds(3599)), "59m ago");
+    }
+
+    #[test]
+    fn test_hours() {
+        assert_eq!(ago(TimeDelta::minutes(60)), "1h ago");
+        assert_eq!(ago(TimeDelta::hours(2)), "2h ago");
+        assert_eq!(ago(TimeDelta::hours(24) - TimeDelta::seconds(1)), "23h ago");
+    }
+
+    #[test]
+    fn test_days() {
+        assert_eq!(ago(TimeDelta::hours(24)), "1d ago");
+        assert_eq!(ago(RELATIVE_CUTOFF - TimeDelta::seconds(1)), "6d ago");
+    }
+
+    #[test]
+    fn test_older_than_a_week_is_absolute_local_time() {
+        let datetime = now() - RELATIVE_CUTOFF;
+
+        assert_eq!(format_relative(&datetime, &now()), format_local(&datetime));
+    }
+
+    #[test]
+    fn test_small_future_skew_is_just_now() {
+        assert_eq!(ago(-TimeDelta::seconds(30)), "just now");
+        assert_eq!(ago(-SKEW_TOLERANCE), "just now");
+    }
+
+    #[test]
+    fn test_far_future_is_absolute_local_time() {
+        let datetime = now() + TimeDelta::hours(3);
+
+        assert_eq!(format_relative(&datetime, &now()), format_local(&datetime));
+    }
+
+    #[test]
+    fn test_format_exact_is_rfc3339() {
+        assert_eq!(format_exact(&now()), "2025-08-10T12:00:00+00:00");
+    }
+}
@@ -0,0 +1,114 @@
+use chrono::{DateTime, Utc};
+use dioxus::prelude::*;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How long a notification stays on screen unless dismissed first.
+pub const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Success,
+    Error,
+    // Nothing shows neutral notices yet.
+    #[allow(dead_code)]
+    Info,
+}
+
+impl ToastKind {
+    /// The Tailwind classes colouring a notification of this kind.
+    pub fn class(self) -> &'static str {
+        match self {
+            ToastKind::Success => "bg-green-600 text-white",
+            ToastKind::Error => "bg-red-600 text-white",
+            ToastKind::Info => "bg-blue-600 text-white",
+        }
+    }
+}
+
+/// A transient notification.
+///
+/// `created_at` is wall-clock time rather than an `Instant`, which is unavailable in the
+/// browser.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Toast {
+    pub id: Uuid,
+    pub message: String,
+    pub kind: ToastKind,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Toast {
+    pub fn new(kind: ToastKind, message: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            message: message.into(),
+            kind,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Shows notifications from any component; provided by `App`, which renders them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToastHandle {
+    toasts: Signal<Vec<Toast>>,
+}
+
+impl ToastHandle {
+    pub fn new(toasts: Signal<Vec<Toast>>) -> Self {
+        Self { toasts }
+    }
+
+    /// The notifications on screen, oldest first.
+    pub fn toasts(&self) -> Vec<Toast> {
+        self.toasts.read().clone()
+    }
+
+    pub fn success(&mut self, message: impl Into<String>) {
+        self.show(ToastKind::Success, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.show(ToastKind::Error, message);
+    }
+
+    #[allow(dead_code)]
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.show(ToastKind::Info, message);
+    }
+
+    pub fn show(&mut self, kind: ToastKind, message: impl Into<String>) {
+        self.toasts.write().push(Toast::new(kind, message));
+    }
+
+    pub fn dismiss(&mut self, id: Uuid) {
+        dismiss(&mut self.toasts.write(), id);
+    }
+}
+
+pub fn use_toast() -> ToastHandle {
+    use_context()
+}
+
+fn dismiss(toasts: &mut Vec<Toast>, id: Uuid) {
+    toasts.retain(|toast| toast.id != id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dismiss_removes_only_that_toast() {
+        let first = Toast::new(ToastKind::Success, "Email deleted");
+        let second = Toast::new(ToastKind::Error, "Failed to delete");
+        let mut toasts = vec![first.clone(), second.clone()];
+
+        dismiss(&mut toasts, first.id);
+        assert_eq!(toasts, vec![second.clone()]);
+
+        dismiss(&mut toasts, first.id);
+        assert_eq!(toasts, vec![second]);
+    }
+}
@@ -1,6 +1,7 @@
 /** @type {import('tailwindcss').Config} */
 module.exports = {
   mode: "all",
+  darkMode: "class",
   content: ["./src/**/*.{rs,html,css}", "./dist/**/*.html"],
   theme: {
     extend: {},